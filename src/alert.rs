@@ -0,0 +1,325 @@
+//! Threshold alerts of the [SelectorType::Number](crate::structure::SelectorType::Number)
+//! values, set with `alert`, `alert_above` and `alert_below` of the selectors, and the
+//! alerts of the anomalies flagged with `anomaly` (see [crate::anomaly]).
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::anomaly::Anomaly;
+use crate::scalper::ResourceResult;
+use crate::structure::{AlertRule, Resource};
+
+/// A value which crossed the threshold of its selector, or an anomaly
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Alert {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub param: Option<String>,
+    /// Name of the value in the output
+    pub name: String,
+    pub value: f64,
+    /// Serialized as `"rule": "< 100"` or `"anomaly": {...}`
+    #[serde(flatten)]
+    pub trigger: Trigger,
+}
+
+/// What raised the [Alert]
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// The value matches the threshold
+    Rule(AlertRule),
+    /// The value is far from its previous values
+    Anomaly(Anomaly),
+    /// The value changed from the stored one, by the `change(...)` rule
+    Change { rule: AlertRule, from: f64 },
+}
+
+impl Alert {
+    /// It checks if the alert is about the same value as the other one
+    pub fn same_value(&self, other: &Alert) -> bool {
+        let same_trigger = match (&self.trigger, &other.trigger) {
+            (Trigger::Rule(rule), Trigger::Rule(other)) => rule == other,
+            (Trigger::Anomaly(_), Trigger::Anomaly(_)) => true,
+            (Trigger::Change { rule, .. }, Trigger::Change { rule: other, .. }) => rule == other,
+            _ => false,
+        };
+        self.param == other.param && self.name == other.name && same_trigger
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.param {
+            Some(param) => write!(f, "{} [{}]", self.name, param)?,
+            None => write!(f, "{}", self.name)?,
+        }
+        match &self.trigger {
+            Trigger::Rule(rule) => write!(f, " = {} is {} ({})", self.value, rule, self.url),
+            Trigger::Anomaly(anomaly) => write!(
+                f,
+                " = {} is an anomaly, {} ({})",
+                self.value, anomaly, self.url
+            ),
+            Trigger::Change { rule, from } => write!(
+                f,
+                " = {} is {}, from {} ({})",
+                self.value, rule, from, self.url
+            ),
+        }
+    }
+}
+
+/// It checks the values against the alert rules of their selectors
+///
+/// Arguments:
+///
+/// - `resources`: The scraped resources, with the parameters applied.
+/// - `results`: The [ResourceResult] of each resource, in the same order.
+///
+/// Returns:
+///
+/// An [Alert] for each matched rule, and for each anomaly of the selectors with
+/// `anomaly.alert`. The values which failed to parse are not checked.
+pub fn evaluate(resources: &[Resource], results: &[ResourceResult]) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    for (resource, result) in resources.iter().zip(results) {
+        let Ok(values) = &result.values else {
+            continue;
+        };
+        // There is a value for each selector, in the same order
+        for (selector, value) in resource.selectors.iter().zip(values) {
+            let Some(number) = value.value.as_f64() else {
+                continue;
+            };
+            let mut triggers: Vec<Trigger> = selector
+                .alert_rules()
+                .into_iter()
+                // The changes are checked against the history by [evaluate_changes]
+                .filter(|rule| rule.change.is_none() && rule.matches(number))
+                .map(Trigger::Rule)
+                .collect();
+            if let (Some(rule), Some(anomaly)) = (&selector.anomaly, &value.anomaly) {
+                if rule.alert {
+                    triggers.push(Trigger::Anomaly(anomaly.clone()));
+                }
+            }
+            for trigger in triggers {
+                alerts.push(Alert {
+                    url: result.url.clone(),
+                    param: result.param.clone(),
+                    name: value.name.clone(),
+                    value: number,
+                    trigger,
+                });
+            }
+        }
+    }
+
+    alerts
+}
+
+/// It checks the changes of the values against the `change(...)` alert rules of their
+/// selectors
+///
+/// Arguments:
+///
+/// - `resources`: The scraped resources, with the parameters applied.
+/// - `results`: The [ResourceResult] of each resource, in the same order.
+/// - `stored`: It returns the stored value by the URL and the parameter of the resource, the
+///   name of the value and the period: the newest one at least as old as the period.
+///
+/// Returns:
+///
+/// An [Alert] for each matched rule. The values without a stored value are not checked.
+pub fn evaluate_changes(
+    resources: &[Resource],
+    results: &[ResourceResult],
+    mut stored: impl FnMut(&str, Option<&str>, &str, Duration) -> Option<f64>,
+) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    for (resource, result) in resources.iter().zip(results) {
+        let Ok(values) = &result.values else {
+            continue;
+        };
+        for value in values {
+            let Some(number) = value.value.as_f64() else {
+                continue;
+            };
+            // The rule can be on any selector, it refers to the value by its name
+            let rules = resource.selectors.iter().flat_map(|s| s.alert_rules());
+            for rule in rules {
+                let Some(change) = rule.change.as_ref().filter(|c| c.name == value.name) else {
+                    continue;
+                };
+                let param = result.param.as_deref();
+                let Some(from) = stored(&result.url, param, &value.name, change.period) else {
+                    continue;
+                };
+                if change
+                    .between(from, number)
+                    .is_some_and(|c| rule.matches(c))
+                {
+                    alerts.push(Alert {
+                        url: result.url.clone(),
+                        param: result.param.clone(),
+                        name: value.name.clone(),
+                        value: number,
+                        trigger: Trigger::Change { rule, from },
+                    });
+                }
+            }
+        }
+    }
+
+    alerts
+}
+
+/// It returns the alerts which were not triggered by the previous run, i.e. the values
+/// which have just crossed their thresholds
+pub fn crossed<'a>(alerts: &'a [Alert], previous: &[Alert]) -> Vec<&'a Alert> {
+    alerts
+        .iter()
+        .filter(|alert| !previous.iter().any(|old| old.same_value(alert)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalper::ParsedValue;
+    use crate::structure::{AnomalyRule, Selector, SelectorType};
+    use serde_json::{json, Value};
+
+    fn result(values: Vec<(&str, Value)>) -> ResourceResult {
+        ResourceResult {
+            url: "https://example.com".to_string(),
+            param: None,
+            final_url: None,
+            accept_language: None,
+            language: None,
+            values: Ok(values
+                .into_iter()
+                .map(|(name, value)| ParsedValue {
+                    name: name.to_string(),
+                    value,
+                    error: None,
+                    stale: None,
+                    anomaly: None,
+                })
+                .collect()),
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut price = Selector::new("h1".to_string(), "price".to_string(), SelectorType::Number);
+        price.alert = Some("< 100".parse().unwrap());
+        price.alert_above = Some(200.0);
+        let title = Selector::new("h2".to_string(), "title".to_string(), SelectorType::String);
+        let resources = vec![Resource::new(
+            "https://example.com".to_string(),
+            vec![price, title],
+        )];
+
+        let alerts = evaluate(
+            &resources,
+            &[result(vec![("price", json!(95.5)), ("title", json!("x"))])],
+        );
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].to_string(),
+            "price = 95.5 is < 100 (https://example.com)"
+        );
+
+        assert!(evaluate(&resources, &[result(vec![("price", json!(150))])]).is_empty());
+        assert!(evaluate(&resources, &[result(vec![("price", Value::Null)])]).is_empty());
+
+        let higher = evaluate(&resources, &[result(vec![("price", json!(250))])]);
+        assert_eq!(crossed(&higher, &alerts).len(), 1);
+        assert!(crossed(&alerts, &alerts).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_anomaly() {
+        let mut price = Selector::new("h1".to_string(), "price".to_string(), SelectorType::Number);
+        price.anomaly = Some(AnomalyRule {
+            window: 20,
+            z_score: None,
+            jump: Some(50.0),
+            alert: true,
+        });
+        let resources = vec![Resource::new(
+            "https://example.com".to_string(),
+            vec![price],
+        )];
+        let mut spike = result(vec![("price", json!(74000))]);
+        if let Ok(values) = &mut spike.values {
+            values[0].anomaly = Some(Anomaly {
+                median: 7.4,
+                change: Some(999900.0),
+                z_score: None,
+            });
+        }
+
+        let alerts = evaluate(&resources, &[spike]);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].to_string(),
+            "price = 74000 is an anomaly, +999900% from the median 7.4 (https://example.com)"
+        );
+        assert_eq!(
+            serde_json::to_value(&alerts[0]).unwrap()["anomaly"]["median"],
+            json!(7.4)
+        );
+        assert!(evaluate(&resources, &[result(vec![("price", json!(7.5))])]).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_changes() {
+        let mut price = Selector::new("h1".to_string(), "price".to_string(), SelectorType::Number);
+        price.alert = Some("change(price, 24h) > 5%".parse().unwrap());
+        let resources = vec![Resource::new(
+            "https://example.com".to_string(),
+            vec![price],
+        )];
+        let current = [result(vec![("price", json!(106))])];
+        // The change rules are not thresholds of the value itself
+        assert!(evaluate(&resources, &current).is_empty());
+
+        let mut asked = Vec::new();
+        let alerts = evaluate_changes(&resources, &current, |url, param, name, period| {
+            asked.push((
+                url.to_string(),
+                param.is_none(),
+                name.to_string(),
+                period.as_secs(),
+            ));
+            Some(100.0)
+        });
+        assert_eq!(
+            asked,
+            [(
+                "https://example.com".to_string(),
+                true,
+                "price".to_string(),
+                24 * 60 * 60
+            )]
+        );
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].to_string(),
+            "price = 106 is change(price, 1d) > 5%, from 100 (https://example.com)"
+        );
+        assert_eq!(
+            serde_json::to_value(&alerts[0]).unwrap()["change"],
+            json!({"rule": "change(price, 1d) > 5%", "from": 100.0})
+        );
+
+        assert!(evaluate_changes(&resources, &current, |_, _, _, _| Some(104.0)).is_empty());
+        assert!(evaluate_changes(&resources, &current, |_, _, _, _| None).is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+//! Anomalies of the [SelectorType::Number](crate::structure::SelectorType::Number) values,
+//! set with `anomaly` of the selectors. A value is compared to its previous values, e.g.
+//! from the history of the runs, so a suspicious spike stands out from the usual twitches.
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::scalper::ResourceResult;
+use crate::structure::{AnomalyRule, Resource};
+
+/// Fewest previous values to tell an anomaly from a usual value
+pub const MIN_HISTORY: usize = 3;
+
+/// How far the value is from its previous values
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Anomaly {
+    /// Median of the previous values
+    pub median: f64,
+    /// Change from the median in percent, not set if the median is 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<f64>,
+    /// z-score against the previous values, not set if they are all the same
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub z_score: Option<f64>,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.change {
+            Some(change) => write!(f, "{:+}% from the median {}", change, self.median)?,
+            None => write!(f, "the median is {}", self.median)?,
+        }
+        if let Some(z_score) = self.z_score {
+            write!(f, ", z-score {}", z_score)?;
+        }
+        Ok(())
+    }
+}
+
+/// It checks the value against its previous values
+///
+/// Arguments:
+///
+/// - `rule`: The [AnomalyRule] of the selector.
+/// - `value`: The parsed value.
+/// - `history`: The previous values, in any order.
+///
+/// Returns:
+///
+/// The [Anomaly] if the value crosses a limit of the rule. `None` for the usual values,
+/// and if there are fewer than [MIN_HISTORY] previous values.
+pub fn detect(rule: &AnomalyRule, value: f64, history: &[f64]) -> Option<Anomaly> {
+    if history.len() < MIN_HISTORY {
+        return None;
+    }
+
+    let mut sorted = history.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    let median = match sorted.len() % 2 {
+        0 => (sorted[middle - 1] + sorted[middle]) / 2.0,
+        _ => sorted[middle],
+    };
+    let change = (median != 0.0).then(|| round((value - median) / median.abs() * 100.0));
+
+    let count = history.len() as f64;
+    let mean = history.iter().sum::<f64>() / count;
+    let deviation = (history.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count).sqrt();
+    let z_score = (deviation > 0.0).then(|| round((value - mean) / deviation));
+
+    let jumped = match (rule.jump, change) {
+        (Some(jump), Some(change)) => change.abs() > jump,
+        _ => false,
+    };
+    let spiked = match (rule.z_score, z_score) {
+        (Some(limit), Some(z_score)) => z_score.abs() > limit,
+        // Any change of a constant series is infinitely far from it
+        (Some(_), None) => value != mean,
+        (None, _) => false,
+    };
+
+    (jumped || spiked).then_some(Anomaly {
+        median,
+        change,
+        z_score,
+    })
+}
+
+/// It flags the anomalies of the values of the selectors with `anomaly`
+///
+/// Arguments:
+///
+/// - `resources`: The scraped resources, with the parameters applied.
+/// - `results`: The [ResourceResult] of each resource, in the same order.
+/// - `history`: It returns the previous values of a value by the URL and the parameter of the
+///   resource and the name of the value, at most the given number of the newest ones.
+///
+/// The values which failed to parse are not checked.
+pub fn flag(
+    resources: &[Resource],
+    results: &mut [ResourceResult],
+    mut history: impl FnMut(&str, Option<&str>, &str, usize) -> Vec<f64>,
+) {
+    for (resource, result) in resources.iter().zip(results.iter_mut()) {
+        let Ok(values) = &mut result.values else {
+            continue;
+        };
+        // There is a value for each selector, in the same order
+        for (selector, value) in resource.selectors.iter().zip(values.iter_mut()) {
+            let (Some(rule), Some(number)) = (&selector.anomaly, value.value.as_f64()) else {
+                continue;
+            };
+            let previous = history(
+                &result.url,
+                result.param.as_deref(),
+                &value.name,
+                rule.window,
+            );
+            value.anomaly = detect(rule, number, &previous);
+        }
+    }
+}
+
+/// It rounds the number to 2 decimal places for the output
+fn round(number: f64) -> f64 {
+    (number * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalper::ParsedValue;
+    use crate::structure::{Selector, SelectorType};
+    use serde_json::json;
+
+    fn rule(z_score: Option<f64>, jump: Option<f64>) -> AnomalyRule {
+        AnomalyRule {
+            window: 20,
+            z_score,
+            jump,
+            alert: false,
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let history = [7.2, 7.4, 7.5, 7.3, 7.4];
+        let jump = rule(None, Some(50.0));
+        assert_eq!(detect(&jump, 7.6, &history), None);
+        let anomaly = detect(&jump, 74000.0, &history).unwrap();
+        assert_eq!(anomaly.median, 7.4);
+        assert_eq!(anomaly.change, Some(999900.0));
+        assert_eq!(
+            anomaly.to_string(),
+            format!(
+                "+999900% from the median 7.4, z-score {}",
+                anomaly.z_score.unwrap()
+            )
+        );
+
+        let z_score = rule(Some(3.0), None);
+        assert_eq!(detect(&z_score, 7.45, &history), None);
+        assert!(detect(&z_score, 9.0, &history).is_some());
+        assert!(detect(&z_score, 5.0, &history).is_some());
+
+        // A constant series has no z-score, any change is an anomaly
+        let constant = [10.0, 10.0, 10.0];
+        assert_eq!(detect(&z_score, 10.0, &constant), None);
+        assert_eq!(
+            detect(&z_score, 11.0, &constant),
+            Some(Anomaly {
+                median: 10.0,
+                change: Some(10.0),
+                z_score: None,
+            })
+        );
+
+        assert_eq!(detect(&jump, 74000.0, &history[..2]), None);
+    }
+
+    #[test]
+    fn test_flag() {
+        let mut price = Selector::new("h1".to_string(), "price".to_string(), SelectorType::Number);
+        price.anomaly = Some(rule(None, Some(50.0)));
+        let title = Selector::new("h2".to_string(), "title".to_string(), SelectorType::String);
+        let resources = vec![Resource::new(
+            "https://example.com/%%".to_string(),
+            vec![price, title],
+        )];
+        let mut results = vec![ResourceResult {
+            url: "https://example.com/AAPL".to_string(),
+            param: Some("AAPL".to_string()),
+            final_url: None,
+            accept_language: None,
+            language: None,
+            values: Ok(vec![
+                ParsedValue {
+                    name: "price".to_string(),
+                    value: json!(300),
+                    error: None,
+                    stale: None,
+                    anomaly: None,
+                },
+                ParsedValue {
+                    name: "title".to_string(),
+                    value: json!("Apple"),
+                    error: None,
+                    stale: None,
+                    anomaly: None,
+                },
+            ]),
+        }];
+
+        let mut asked = Vec::new();
+        flag(&resources, &mut results, |url, param, name, window| {
+            asked.push((
+                url.to_string(),
+                param.map(str::to_string),
+                name.to_string(),
+                window,
+            ));
+            vec![100.0, 110.0, 105.0]
+        });
+        assert_eq!(
+            asked,
+            [(
+                "https://example.com/AAPL".to_string(),
+                Some("AAPL".to_string()),
+                "price".to_string(),
+                20
+            )]
+        );
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].anomaly.as_ref().unwrap().median, 105.0);
+        assert!(values[1].anomaly.is_none());
+    }
+}
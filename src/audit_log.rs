@@ -0,0 +1,150 @@
+//! Append-only audit log of the runs, one JSON line per run in the data directory
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use anyhow::Result;
+use chrono::{SecondsFormat, Utc};
+use rvp::scalper::ResourceResult;
+use serde::{Deserialize, Serialize};
+
+/// Name of the audit log inside the data directory
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+/// A single run of a config
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Entry {
+    /// UTC time of the run in RFC 3339 format
+    pub time: String,
+    /// The user who started the run
+    pub user: String,
+    /// The command which ran the config (`batch`, `watch`)
+    pub command: String,
+    pub config: String,
+    /// Parameters applied to the resources
+    pub params: Vec<String>,
+    pub resources: usize,
+    pub failed_resources: usize,
+    pub values: usize,
+    pub failed_values: usize,
+}
+
+impl Entry {
+    /// It summarizes the results of the run
+    pub fn new(command: &str, config: &str, results: &[ResourceResult]) -> Self {
+        let mut params: Vec<String> = Vec::new();
+        for param in results.iter().filter_map(|r| r.param.as_ref()) {
+            if !params.contains(param) {
+                params.push(param.clone());
+            }
+        }
+        Self {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            user: current_user(),
+            command: command.to_string(),
+            config: config.to_string(),
+            params,
+            resources: results.len(),
+            failed_resources: results.iter().filter(|r| r.values.is_err()).count(),
+            values: results
+                .iter()
+                .filter_map(|r| r.values.as_ref().ok())
+                .map(|v| v.len())
+                .sum(),
+            failed_values: results.iter().map(|r| r.failed_values().len()).sum(),
+        }
+    }
+}
+
+/// It appends the entry to the audit log in the directory, the directory is created if needed
+pub fn append(dir: &Path, entry: &Entry) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(AUDIT_FILE))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// It reads all entries of the audit log in the directory, oldest first
+pub fn read(dir: &Path) -> Result<Vec<Entry>> {
+    let path = dir.join(AUDIT_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(fs::File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(entries)
+}
+
+/// Name of the current user from the environment
+fn current_user() -> String {
+    ["USER", "USERNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|user| !user.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::{error::RvpError, scalper::ParsedValue};
+    use serde_json::Value;
+
+    #[test]
+    fn test_append_and_read() -> Result<()> {
+        let dir = std::env::temp_dir().join("rvp_test_audit_log");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(read(&dir)?.is_empty());
+
+        let results = vec![
+            ResourceResult {
+                url: "https://ok.com/AAPL".to_string(),
+                param: Some("AAPL".to_string()),
+                final_url: None,
+                accept_language: None,
+                language: None,
+                values: Ok(vec![ParsedValue {
+                    name: "price".to_string(),
+                    value: Value::Null,
+                    error: Some("invalid selector".to_string()),
+                    stale: None,
+                    anomaly: None,
+                }]),
+            },
+            ResourceResult {
+                url: "https://down.com/AAPL".to_string(),
+                param: Some("AAPL".to_string()),
+                final_url: None,
+                accept_language: None,
+                language: None,
+                values: Err(RvpError::ConfigError("timeout".to_string())),
+            },
+        ];
+        let entry = Entry::new("batch", "stock", &results);
+        assert_eq!(entry.params, vec!["AAPL"]);
+        assert_eq!(entry.resources, 2);
+        assert_eq!(entry.failed_resources, 1);
+        assert_eq!(entry.values, 1);
+        assert_eq!(entry.failed_values, 1);
+
+        append(&dir, &entry)?;
+        append(&dir, &Entry::new("watch", "weather", &[]))?;
+        let entries = read(&dir)?;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], entry);
+        assert_eq!(entries[1].config, "weather");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
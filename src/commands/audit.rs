@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use clap::Parser;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use rvp::error::RvpError;
+
+use crate::audit_log::{self, Entry};
+use crate::context::Context;
+
+/// Show the audit log of the runs: who ran which config with which params, and how it went
+///
+/// Every `batch` and `watch` run is appended to `audit.jsonl` in the data directory
+/// (`$XDG_DATA_HOME/rvp` or `~/.local/share/rvp`).
+#[derive(Parser)]
+pub struct Args {
+    /// (Optional) Show only the runs of the config with this name
+    #[arg(short, long, value_name = "NAME")]
+    config: Option<String>,
+
+    /// (Optional) Show only the runs since this date (UTC), e.g. `2024-05-01`
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    since: Option<NaiveDate>,
+
+    /// (Optional) Show only the last N runs
+    #[arg(short = 'n', long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Output the entries as JSON lines
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let Some(dir) = ctx.data_dir() else {
+        return Err(
+            RvpError::NotFoundError("Could not find the data directory!".to_string()).into(),
+        );
+    };
+    let entries = filter_entries(audit_log::read(&dir)?, &args);
+
+    if args.json {
+        for entry in entries.iter() {
+            println!("{}", serde_json::to_string(entry)?);
+        }
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!(
+            "No runs found in {}",
+            dir.join(audit_log::AUDIT_FILE).display()
+        );
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            "Time",
+            "User",
+            "Command",
+            "Config",
+            "Params",
+            "Resources",
+            "Values",
+        ]);
+    for entry in entries.iter() {
+        table.add_row(vec![
+            entry.time.clone(),
+            entry.user.clone(),
+            entry.command.clone(),
+            entry.config.clone(),
+            entry.params.join(" "),
+            summary(entry.resources, entry.failed_resources),
+            summary(entry.values, entry.failed_values),
+        ]);
+    }
+    ctx.style_table(&mut table);
+    println!("{}", table);
+
+    Ok(())
+}
+
+/// It applies the filters of the arguments, the entries are kept in the log order
+fn filter_entries(entries: Vec<Entry>, args: &Args) -> Vec<Entry> {
+    // RFC 3339 times in UTC are ordered as strings
+    let since = args.since.map(|date| date.format("%Y-%m-%d").to_string());
+    let mut entries: Vec<Entry> = entries
+        .into_iter()
+        .filter(|e| {
+            args.config
+                .as_ref()
+                .map_or(true, |config| &e.config == config)
+        })
+        .filter(|e| since.as_ref().map_or(true, |since| &e.time >= since))
+        .collect();
+
+    if let Some(limit) = args.limit {
+        let skip = entries.len().saturating_sub(limit);
+        entries.drain(..skip);
+    }
+    entries
+}
+
+/// Total count with the number of failures, e.g. `3 (1 failed)`
+fn summary(total: usize, failed: usize) -> String {
+    match failed {
+        0 => total.to_string(),
+        _ => format!("{} ({} failed)", total, failed),
+    }
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid date \"{}\", expected YYYY-MM-DD!", date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(time: &str, config: &str) -> Entry {
+        Entry {
+            time: time.to_string(),
+            user: "sam".to_string(),
+            command: "batch".to_string(),
+            config: config.to_string(),
+            params: vec![],
+            resources: 1,
+            failed_resources: 0,
+            values: 2,
+            failed_values: 1,
+        }
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let entries = vec![
+            entry("2024-04-30T10:00:00Z", "stock"),
+            entry("2024-05-01T10:00:00Z", "weather"),
+            entry("2024-05-02T10:00:00Z", "stock"),
+            entry("2024-05-03T10:00:00Z", "stock"),
+        ];
+        let args = Args::parse_from([
+            "audit",
+            "--config",
+            "stock",
+            "--since",
+            "2024-05-01",
+            "-n",
+            "1",
+        ]);
+
+        let filtered = filter_entries(entries, &args);
+        assert_eq!(filtered, vec![entry("2024-05-03T10:00:00Z", "stock")]);
+        assert_eq!(summary(2, 1), "2 (1 failed)");
+        assert!(parse_date("05/01/2024").is_err());
+    }
+}
@@ -1,21 +1,215 @@
-use std::{ffi::OsStr, path::PathBuf};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use crate::scalper::{grab, ParsedValue};
-use crate::structure::{Config, ConfigFormat, Resource};
+use crate::audit_log;
+use crate::config_cache;
+use crate::context::Context;
+use crate::exit::AlertsTriggered;
+#[cfg(feature = "history")]
+use crate::history_store;
+use crate::host_stats;
+use crate::output::{
+    generate_csv, generate_flat_json, generate_grouped_csv, generate_grouped_json,
+    generate_grouped_table, generate_json, generate_table, render_template, write_to_file,
+    ValueGroup,
+};
+use crate::paths;
+use crate::remote_config;
+use crate::report::{self, RecordingFetcher};
+use crate::signature::{sign_json, signature_header, signing_key, SIGNATURE_HEADER};
 use anyhow::{anyhow, Result};
-use clap::{value_parser, Parser};
-use comfy_table::modifiers::UTF8_ROUND_CORNERS;
-use comfy_table::presets::UTF8_FULL;
-use comfy_table::Table;
+use clap::{builder::FalseyValueParser, value_parser, Parser};
+use futures_util::future::join_all;
+use rvp::alert::{self, Alert};
+#[cfg(feature = "history")]
+use rvp::anomaly;
+use rvp::compute;
+use rvp::error::RvpError;
+use rvp::fetcher::{Fetcher, RateLimitedFetcher};
+use rvp::redact::Redactor;
+use rvp::robots::{self, RobotsFetcher};
+#[cfg(feature = "history")]
+use rvp::scalper::Stale;
+use rvp::scalper::{grab_resources_bounded, ParsedValue, ResourceResult};
+use rvp::sitemap;
+use rvp::structure::{
+    url_host, Computed, Config, ConfigFormat, OutputFormat, Params, RateLimit, Resource, Timezone,
+};
+use rvp::tuning::TunedFetcher;
 use serde::Serialize;
-use serde_json::{json, to_string_pretty};
 
 /// Parse multiple data fields from a N resources defined in a config file
 #[derive(Parser)]
+#[command(visible_alias = "b")]
 pub struct Args {
-    /// Path to the config file.
-    #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf))]
-    path: PathBuf,
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Output the data in JSON format
+    #[arg(long, env = "RVP_JSON", conflicts_with = "csv", value_parser = FalseyValueParser::new())]
+    json: bool,
+
+    /// Used with the JSON output to output a flat `name -> value` map instead of a list of
+    /// records
+    #[arg(long, env = "RVP_FLATTEN", conflicts_with = "nest", value_parser = FalseyValueParser::new())]
+    flatten: bool,
+
+    /// Group the values by resource, so the values of the selectors with the same name don't mix.
+    ///
+    /// With `--json`, the records are nested by config and resource (with its URL and parameter).
+    /// Tables and CSV get a resource column.
+    #[arg(long, env = "RVP_NEST", value_parser = FalseyValueParser::new())]
+    nest: bool,
+
+    /// Used with the JSON output to wrap it into an envelope with its HMAC-SHA256 signature.
+    ///
+    /// The secret key is read from the `RVP_SIGNING_KEY` environment variable. The signature
+    /// covers the compact JSON of the `data` field with the object keys sorted.
+    #[arg(long, env = "RVP_SIGN_OUTPUT", value_parser = FalseyValueParser::new())]
+    sign_output: bool,
+
+    /// Output the data in CSV format (`name,value` columns)
+    #[arg(long, env = "RVP_CSV", value_parser = FalseyValueParser::new())]
+    csv: bool,
+
+    /// Output the data as a table, the default unless the config or the settings set another
+    /// `output`
+    #[arg(long, env = "RVP_TABLE", conflicts_with_all = ["json", "csv"], value_parser = FalseyValueParser::new())]
+    table: bool,
+
+    /// (Optional) Render the values with the template, `{{name}}` is replaced with the value.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// --template "{{price}} USD as of {{date}}"
+    /// ```
+    ///
+    /// With `--params-file` and `--param-list` the template is rendered once per parameter,
+    /// which is available as `{{param}}`.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        env = "RVP_TEMPLATE",
+        conflicts_with_all = ["json", "csv", "nest"]
+    )]
+    template: Option<String>,
+
+    /// (Optional) Write the output to the given file instead of stdout.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// --csv --output ./stocks.csv
+    /// ```
+    ///
+    /// Missing parent directories will be created. Used instead of the `output_file` of the
+    /// config.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        env = "RVP_OUTPUT",
+        value_parser = value_parser!(PathBuf)
+    )]
+    output: Option<PathBuf>,
+
+    /// Used with `--output` (or the `output_file` of the config) to append the data to the end
+    /// of the file instead of overwriting it.
+    #[arg(long, env = "RVP_APPEND", value_parser = FalseyValueParser::new())]
+    append: bool,
+
+    /// Check that the hosts of all resources are reachable (a HEAD request to each host)
+    /// before fetching any page, and fail with the list of the unreachable ones
+    #[arg(long, env = "RVP_PREFLIGHT", value_parser = FalseyValueParser::new())]
+    preflight: bool,
+
+    #[command(flatten)]
+    webhook: WebhookArgs,
+
+    /// Used with `--store` to output the last stored values of the resources which fail in
+    /// this run instead of the errors, flagged with `"stale": true` and the `time` of their run
+    #[cfg(feature = "history")]
+    #[arg(long, env = "RVP_FALLBACK_TO_LAST", value_parser = FalseyValueParser::new())]
+    fallback_to_last: bool,
+
+    /// Used with `--webhook` (or the `webhook` of the settings) to call it only when the
+    /// `alert` rules of the selectors are matched, with the alerts as the body instead of
+    /// the results
+    #[arg(long, env = "RVP_WEBHOOK_ON_ALERT", value_parser = FalseyValueParser::new())]
+    webhook_on_alert: bool,
+}
+
+/// Arguments to deliver the results to an HTTP endpoint.
+///
+/// Shared by the commands which run a config (`batch`, `watch`).
+#[derive(clap::Args)]
+pub struct WebhookArgs {
+    /// (Optional) URL to POST the results to as JSON, grouped by resource
+    /// (the same as `--json --nest`).
+    ///
+    /// With `--template`, the rendered text is sent as `{"text": ..., "content": ...}`
+    /// instead, which is understood by the Slack and Discord webhooks.
+    #[arg(long, value_name = "URL", env = "RVP_WEBHOOK")]
+    webhook: Option<String>,
+
+    /// Used with `--webhook` to sign the body with HMAC-SHA256 in the `X-Rvp-Signature`
+    /// header (`sha256=<hex>`). The secret key is read from the `RVP_SIGNING_KEY`
+    /// environment variable.
+    #[arg(long, env = "RVP_WEBHOOK_SIGN", requires = "webhook", value_parser = FalseyValueParser::new())]
+    webhook_sign: bool,
+}
+
+/// Arguments to load a config file and pass parameters to its resources.
+///
+/// Shared by the commands which run a config (`batch`, `watch`).
+#[derive(clap::Args)]
+pub struct ConfigArgs {
+    /// Path to the config file, or its `http://` or `https://` URL.
+    ///
+    /// The configs from the URLs are downloaded every time, and their copy in the cache
+    /// directory is used when the download fails.
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        env = "RVP_CONFIG",
+        value_parser = value_parser!(PathBuf),
+        required_unless_present_any = ["name", "config_name"]
+    )]
+    path: Option<PathBuf>,
+
+    /// Name of the config file, e.g. `stock` for `stock.toml`. It's looked up in the current
+    /// directory, then in the global config directory.
+    ///
+    /// `rvp <NAME>` is a shorthand for `rvp batch --name <NAME>`.
+    #[arg(short, long, value_name = "NAME", conflicts_with = "path")]
+    name: Option<String>,
+
+    /// The same as `--name`, e.g. `rvp batch stock`.
+    #[arg(value_name = "CONFIG", conflicts_with_all = ["path", "name"])]
+    config_name: Option<String>,
+
+    /// (Optional) Expected SHA-256 of the config downloaded from the `--path` URL, in hex.
+    /// The config is not used if it differs.
+    #[arg(long, value_name = "HEX", env = "RVP_CONFIG_SHA256", requires = "path")]
+    sha256: Option<String>,
+
+    /// (Optional) Trust the config downloaded from the `--path` URL without `--sha256`.
+    ///
+    /// Otherwise such a config can't set `output_file`, `webhook`, the `auth` of the
+    /// resources, or the resources other than HTTP(S).
+    #[arg(
+        long,
+        env = "RVP_TRUST_REMOTE",
+        value_parser = FalseyValueParser::new(),
+        requires = "path",
+        conflicts_with = "sha256"
+    )]
+    trust_remote: bool,
 
     /// (Optional) Parameters to be passed to the resources separated by spaces.
     ///
@@ -32,7 +226,7 @@ pub struct Args {
     /// ```
     ///
     /// In this case, you can pass any value for the second parameter, because it will be ignored.
-    #[arg(long, num_args(0..))]
+    #[arg(long, num_args(0..), env = "RVP_PARAMS", value_delimiter = ' ')]
     params: Option<Vec<String>>,
 
     /// (Optional) Single parameter to be passed to all resources.
@@ -46,7 +240,7 @@ pub struct Args {
     /// ```
     ///
     /// This argument is useful when you want to pass the same parameter to all resources.
-    #[arg(long, conflicts_with = "params")]
+    #[arg(long, env = "RVP_ONE_PARAM", conflicts_with = "params")]
     one_param: Option<String>,
 
     /// (Optional) Used with `--params` to repeat each param for all resources. If you specify 3
@@ -61,185 +255,1416 @@ pub struct Args {
     /// ```
     /// --params param1 param2 -r
     /// ```
-    #[arg(short, long, conflicts_with = "one_param")]
+    #[arg(short, long, env = "RVP_REPEAT", conflicts_with = "one_param", value_parser = FalseyValueParser::new())]
     repeat: bool,
 
-    /// Output the data in JSON format
-    #[arg(long)]
-    json: bool,
+    /// (Optional) Value of a named `%name%` placeholder in the URLs, can be repeated.
+    ///
+    /// Named parameters can be combined with the `%%` parameters.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// --param ticker=AAPL --param date=2024-01-01
+    /// ```
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_named_param)]
+    named_params: Vec<(String, String)>,
+
+    /// (Optional) File with the parameter values, one per line. The whole config is run once
+    /// per value, the same as `--params <VALUES> --repeat`.
+    ///
+    /// Empty lines and lines starting with `#` are skipped.
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_parser = value_parser!(PathBuf),
+        conflicts_with_all = ["params", "one_param", "param_list"]
+    )]
+    params_file: Option<PathBuf>,
+
+    /// (Optional) Comma-separated parameter values, the whole config is run once per value.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// --param-list AAPL,MSFT,GOOG
+    /// ```
+    #[arg(
+        long,
+        value_name = "VALUES",
+        env = "RVP_PARAM_LIST",
+        value_delimiter = ',',
+        conflicts_with_all = ["params", "one_param"]
+    )]
+    param_list: Option<Vec<String>>,
+
+    /// (Optional) Run only the resources with the tag, of their own or of their group. Can be
+    /// repeated or comma-separated, a resource with any of the tags is run.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// --tag stocks,crypto
+    /// ```
+    #[arg(
+        long = "tag",
+        value_name = "TAG",
+        env = "RVP_TAGS",
+        value_delimiter = ','
+    )]
+    tags: Vec<String>,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let config = args.config.load(ctx).await?;
+    warn_unchecked_changes(ctx, &config);
+    let format = output_format(&args, &config, ctx);
+    let output = args.output.clone().or(config.output_file.clone());
+    if (args.flatten || args.sign_output) && format != OutputFormat::Json {
+        return Err(anyhow!(
+            "--flatten and --sign-output need --json or `output = \"json\"` in the config!"
+        ));
+    }
+    // Checked here, as `--store` is a global option which `requires` can't refer to
+    #[cfg(feature = "history")]
+    if args.fallback_to_last && ctx.store.is_none() {
+        return Err(anyhow!("--fallback-to-last needs --store!"));
+    }
+    if args.append && output.is_none() {
+        return Err(anyhow!(
+            "--append needs --output or the output_file of the config!"
+        ));
+    }
+    // Fail before scraping if the output could not be signed
+    let signing_key = match args.sign_output {
+        true => Some(signing_key()?),
+        false => None,
+    };
+    let webhook = args.webhook.prepare(&config, ctx)?;
+    if args.webhook_on_alert && webhook.is_none() {
+        return Err(anyhow!(
+            "--webhook-on-alert needs --webhook or the webhook of the config or the settings!"
+        ));
+    }
+    ctx.log(format!(
+        "Parsing {} resources of \"{}\"",
+        config.resources.len(),
+        config.name
+    ));
+    if args.preflight {
+        preflight(&config, ctx).await?;
+    }
+    let config_name = config.name.clone();
+    let config = lease_resources(config, ctx).await?;
+    let config = expand_sitemaps(config, ctx).await?;
+    let resources = config.resources.clone();
+    let computed = config.computed.clone();
+    let mut results = scrape(config, ctx).await;
+    // The previous values are read before the current run is stored
+    let previous = previous_values(ctx, &config_name, &computed, &results);
+    flag_anomalies(ctx, &config_name, &resources, &mut results);
+    let changes = change_alerts(ctx, &config_name, &resources, &results);
+    record_run(ctx, "batch", &config_name, &results);
+    // The stale values don't raise the alerts again
+    let mut alerts = alert::evaluate(&resources, &results);
+    alerts.extend(changes);
+    #[cfg(feature = "history")]
+    let results = match args.fallback_to_last {
+        true => fallback_to_last(ctx, &config_name, results),
+        false => results,
+    };
+    let mut outputs = collect_values(&results);
+    compute::append_computed(&computed, &mut outputs, previous_of(&previous, None));
+
+    // The values are grouped by resource with `--nest`, and by the parameter with
+    // `--params-file` and `--param-list`
+    let groups = if args.nest {
+        Some(("Resource", group_by_resource(&results)))
+    } else if args.config.fans_out() {
+        let mut groups = group_by_param(&results);
+        for (param, values) in groups.iter_mut() {
+            let param = Some(param.as_str()).filter(|p| !p.is_empty());
+            compute::append_computed(&computed, values, previous_of(&previous, param));
+        }
+        Some(("Param", groups))
+    } else {
+        None
+    };
+
+    let (json, csv) = match format {
+        OutputFormat::Table => (false, false),
+        OutputFormat::Json => (true, false),
+        OutputFormat::Csv => (false, true),
+    };
+    let rendered = if let Some(template) = &args.template {
+        match &groups {
+            Some((_, groups)) => render_grouped_template(template, groups)?,
+            None => render_template(template, &outputs)?,
+        }
+    } else if json && args.nest {
+        generate_nested_json(&config_name, &results, |text| ctx.redact(text).into_owned())
+    } else if let Some((column, groups)) = &groups {
+        if json {
+            generate_grouped_json(groups, args.flatten)
+        } else if csv {
+            generate_grouped_csv(&column.to_lowercase(), groups)?
+        } else {
+            let mut table = generate_grouped_table(column, groups, ctx.max_value_chars());
+            ctx.style_table(&mut table);
+            table.to_string()
+        }
+    } else if json && args.flatten {
+        generate_flat_json(&outputs)
+    } else if json {
+        generate_json(&outputs)
+    } else if csv {
+        generate_csv(&outputs)?
+    } else {
+        let mut table = generate_table(&outputs, ctx.max_value_chars());
+        ctx.style_table(&mut table);
+        table.to_string()
+    };
+    let rendered = match signing_key {
+        Some(key) => sign_json(&rendered, &key)?,
+        None => rendered,
+    };
+
+    match output {
+        Some(path) => write_to_file(&path, &rendered, args.append)?,
+        None => println!("{}", rendered),
+    }
+
+    let delivered = match &webhook {
+        Some(webhook) if args.webhook_on_alert => match alerts.is_empty() {
+            true => Ok(()),
+            false => {
+                let alerts: Vec<&Alert> = alerts.iter().collect();
+                webhook
+                    .deliver(
+                        alerts_json(&config_name, &alerts, |text| ctx.redact(text).into_owned()),
+                        ctx,
+                    )
+                    .await
+            }
+        },
+        Some(webhook) => {
+            let body = match args.template {
+                Some(_) => webhook_text(&rendered),
+                None => generate_nested_json(&config_name, &results, |text| {
+                    ctx.redact(text).into_owned()
+                }),
+            };
+            webhook.deliver(body, ctx).await
+        }
+        None => Ok(()),
+    };
+
+    // Print the errors after the output, so the successfully parsed values are not lost
+    let failed_resources = match ctx.quiet {
+        true => results.iter().filter(|r| r.values.is_err()).count(),
+        false => report_errors(&results, ctx),
+    };
+    report_alerts(&alerts, ctx);
+    delivered?;
+    if failed_resources > 0 {
+        let message = format!(
+            "{} of {} resources failed to parse!",
+            failed_resources,
+            results.len()
+        );
+        // The error of the first failed resource picks the exit code
+        return Err(match results.iter().find_map(|r| r.values.as_ref().err()) {
+            Some(err) => anyhow::Error::new(ctx.redactor().redact_error(err)).context(message),
+            None => anyhow!(message),
+        });
+    }
+    if !alerts.is_empty() {
+        return Err(AlertsTriggered(alerts.len()).into());
+    }
+
+    Ok(())
+}
+
+/// It prints the triggered alerts to stderr
+pub fn report_alerts<'a>(alerts: impl IntoIterator<Item = &'a Alert>, ctx: &Context) {
+    for alert in alerts {
+        eprintln!("Alert: {}", ctx.redact(&alert.to_string()));
+    }
+}
+
+/// It returns the output format: `--json`, `--csv` or `--table`, otherwise the `output` of
+/// the config or of the settings
+fn output_format(args: &Args, config: &Config, ctx: &Context) -> OutputFormat {
+    match (args.json, args.csv, args.table) {
+        (true, _, _) => OutputFormat::Json,
+        (_, true, _) => OutputFormat::Csv,
+        (_, _, true) => OutputFormat::Table,
+        _ => config.output.or(ctx.settings.output).unwrap_or_default(),
+    }
+}
+
+/// It generates the webhook body with the alerts, the summary is in the `text` (Slack)
+/// and `content` (Discord) fields
+pub fn alerts_json(
+    config_name: &str,
+    alerts: &[&Alert],
+    redact: impl Fn(&str) -> String,
+) -> String {
+    let alerts: Vec<Alert> = alerts
+        .iter()
+        .map(|alert| Alert {
+            url: redact(&alert.url),
+            param: alert.param.as_deref().map(&redact),
+            ..(*alert).clone()
+        })
+        .collect();
+    let lines: Vec<String> = alerts.iter().map(|alert| alert.to_string()).collect();
+    let text = format!("Alerts of \"{}\":\n{}", config_name, lines.join("\n"));
+    serde_json::json!({
+        "config": config_name,
+        "alerts": alerts,
+        "text": text,
+        "content": text,
+    })
+    .to_string()
+}
+
+/// The alerts collected by `--digest`, sent in one message once per interval instead of
+/// after every run
+pub struct Digest {
+    interval: Duration,
+    started: Instant,
+    alerts: Vec<Alert>,
+}
+
+impl Digest {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            started: Instant::now(),
+            alerts: Vec::new(),
+        }
+    }
+
+    /// It adds the alerts of a run, an alert of the same value replaces the older one.
+    ///
+    /// Returns all the collected alerts when the interval is over, `None` before it or if
+    /// there are no alerts to send.
+    pub fn collect(&mut self, alerts: Vec<Alert>, now: Instant) -> Option<Vec<Alert>> {
+        for alert in alerts {
+            self.alerts.retain(|old| !old.same_value(&alert));
+            self.alerts.push(alert);
+        }
+        if now.duration_since(self.started) < self.interval {
+            return None;
+        }
+        self.started = now;
+        self.flush()
+    }
+
+    /// It returns the collected alerts which were not sent yet, e.g. when the command stops
+    pub fn flush(&mut self) -> Option<Vec<Alert>> {
+        Some(std::mem::take(&mut self.alerts)).filter(|alerts| !alerts.is_empty())
+    }
+}
+
+/// It sends the alerts left in the digest when the command stops
+pub async fn flush_digest(
+    digest: Option<Digest>,
+    webhook: Option<&Webhook>,
+    config_name: &str,
+    ctx: &Context,
+) {
+    let alerts = digest.and_then(|mut digest| digest.flush());
+    if let (Some(webhook), Some(alerts)) = (webhook, alerts) {
+        let body = alerts_json(config_name, &alerts.iter().collect::<Vec<_>>(), |text| {
+            ctx.redact(text).into_owned()
+        });
+        if let Err(err) = webhook.deliver(body, ctx).await {
+            eprintln!("{}", ctx.redact(&err.to_string()));
+        }
+    }
+}
+
+/// It returns a future resolved on Ctrl+C or SIGTERM, so the long running commands can
+/// send the pending digest before they stop. The signals are caught from the call on.
+pub fn shutdown_signal() -> Result<impl std::future::Future<Output = ()>> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        Ok(async move {
+            tokio::select! {
+                _ = interrupt.recv() => {}
+                _ = terminate.recv() => {}
+            }
+        })
+    }
+    #[cfg(not(unix))]
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+}
+
+/// The webhook endpoint with the signing key
+pub struct Webhook {
+    url: String,
+    key: Option<Vec<u8>>,
+}
+
+impl WebhookArgs {
+    /// It reads the signing key, so a missing key fails the command before scraping.
+    /// The `webhook` of the settings is used without `--webhook`.
+    pub fn prepare(&self, config: &Config, ctx: &Context) -> Result<Option<Webhook>> {
+        let url = self.webhook.as_ref().or(config.webhook.as_ref());
+        let Some(url) = url.or(ctx.settings.webhook.as_ref()) else {
+            return Ok(None);
+        };
+        let key = match self.webhook_sign {
+            true => Some(signing_key()?),
+            false => None,
+        };
+        Ok(Some(Webhook {
+            url: url.clone(),
+            key,
+        }))
+    }
 }
 
-pub async fn command(args: Args) -> Result<()> {
-    if !args.path.exists() {
-        return Err(anyhow!("File does not exist!"));
+impl Webhook {
+    /// It POSTs the JSON body to the webhook, signed if the key is set
+    pub async fn deliver(&self, body: String, ctx: &Context) -> Result<()> {
+        let headers = match &self.key {
+            Some(key) => vec![(SIGNATURE_HEADER, signature_header(&body, key))],
+            None => vec![],
+        };
+        ctx.log(format!("Sending the results to {}", self.url));
+        match ctx.fetcher.post_json(&self.url, body, &headers).await {
+            Ok(()) => Ok(()),
+            Err(RvpError::FetchError { message, .. }) => Err(anyhow!(
+                "Webhook delivery to {} failed: {}",
+                ctx.redact(&self.url),
+                ctx.redact(&message)
+            )),
+            Err(err) => Err(ctx.redactor().redact_error(&err).into()),
+        }
+    }
+}
+
+/// It sends a HEAD request to each host of the resources at once. The outages are reported
+/// together, before any page is fetched.
+pub async fn preflight(config: &Config, ctx: &Context) -> Result<()> {
+    let mut origins: Vec<String> = Vec::new();
+    for resource in config.resources.iter() {
+        let Ok(url) = url::Url::parse(&resource.url) else {
+            continue;
+        };
+        let origin = url.origin().ascii_serialization();
+        if matches!(url.scheme(), "http" | "https") && !origins.contains(&origin) {
+            origins.push(origin);
+        }
+    }
+    ctx.log(format!(
+        "Checking the connection to {} hosts",
+        origins.len()
+    ));
+
+    let checks = join_all(origins.iter().map(|origin| async move {
+        let start = Instant::now();
+        let status = ctx.fetcher.head(origin).await;
+        (origin, status, start.elapsed())
+    }))
+    .await;
+    let mut failures: Vec<(&String, RvpError)> = Vec::new();
+    for (origin, status, elapsed) in checks {
+        match status {
+            Ok(status) => ctx.log(format!(
+                "{} responded with {} in {} ms",
+                origin,
+                status,
+                elapsed.as_millis()
+            )),
+            Err(err) => failures.push((origin, err)),
+        }
     }
 
-    let config_format = match args.path.extension().and_then(OsStr::to_str) {
-        Some("json") => ConfigFormat::Json,
-        Some("toml") => ConfigFormat::Toml,
-        _ => return Err(anyhow!("Invalid file format!")),
+    let Some((_, first)) = failures.first() else {
+        return Ok(());
     };
+    if !ctx.quiet {
+        eprintln!("Unreachable hosts:");
+        for (origin, err) in failures.iter() {
+            let message = match err {
+                RvpError::FetchError { message, .. } => message.clone(),
+                err => err.to_string(),
+            };
+            eprintln!("  • {}: {}", ctx.redact(origin), ctx.redact(&message));
+        }
+    }
+    Err(
+        anyhow::Error::new(ctx.redactor().redact_error(first)).context(format!(
+            "{} of {} hosts are unreachable, no pages were fetched!",
+            failures.len(),
+            origins.len()
+        )),
+    )
+}
+
+/// It wraps the text into the JSON message of the Slack (`text`) and Discord (`content`) webhooks
+fn webhook_text(text: &str) -> String {
+    serde_json::json!({ "text": text, "content": text }).to_string()
+}
+
+/// It renders the template once per group, the group name is available as `{{param}}`
+fn render_grouped_template(template: &str, groups: &[ValueGroup]) -> Result<String> {
+    let mut lines = Vec::with_capacity(groups.len());
+    for (param, values) in groups {
+        let mut values = values.clone();
+        values.insert(
+            0,
+            ParsedValue {
+                name: "param".to_string(),
+                value: param.clone().into(),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+        );
+        lines.push(render_template(template, &values)?);
+    }
+    Ok(lines.join("\n"))
+}
+
+impl ConfigArgs {
+    /// It reads the config file and applies the parameters to its resources
+    pub async fn load(&self, ctx: &Context) -> Result<Config> {
+        let name = self.name.as_ref().or(self.config_name.as_ref());
+        let remote = self.path.as_deref().and_then(remote_config::as_url);
+        let path = match (&self.path, name) {
+            (Some(path), _) => match remote {
+                Some(url) => remote_config::download(url, self.sha256.as_deref(), ctx).await?,
+                None => path.clone(),
+            },
+            (None, Some(name)) => paths::resolve_config(&ctx.config_search_dirs(), name)?,
+            (None, None) => unreachable!("clap requires either path or name"),
+        };
+        if !path.exists() {
+            return Err(RvpError::NotFoundError("File does not exist!".to_string()).into());
+        }
+        ctx.log(format!("Loading config {}", path.display()));
+
+        let config_format = ConfigFormat::from_path(&path)?;
+
+        let cache_dir = ctx.cache_dir().filter(|_| !ctx.no_cache);
+        let (cached, hit) = config_cache::load(cache_dir.as_deref(), &path, &config_format)?;
+        if hit {
+            ctx.log("Using the cached config, the file has not changed");
+        }
+        if !cached.problems.is_empty() {
+            ctx.log(format!(
+                "The config has {} problems, run `rvp check` for the details",
+                cached.problems.len()
+            ));
+        }
+        let mut config = cached.config;
+        ctx.set_redactor(Redactor::new(&config.redact)?);
+        config.apply_groups()?;
+        if let Some(url) = remote.filter(|_| self.sha256.is_none() && !self.trust_remote) {
+            remote_config::check_untrusted(url, &config)?;
+        }
 
-    let mut config = Config::from_file(&args.path, &config_format)?;
+        let params = match (&self.params, &self.one_param) {
+            _ if self.fans_out() => Params::Repeat(self.fan_out_values()?),
+            (Some(params), _) if self.repeat => Params::Repeat(params.clone()),
+            (Some(params), _) => Params::PerResource(params.clone()),
+            (None, Some(param)) => Params::One(param.clone()),
+            (None, None) => Params::None,
+        };
 
-    if config.needs_parameters() {
-        if args.params.is_none() && args.one_param.is_none() {
+        if config.needs_parameters() && matches!(params, Params::None) {
             return Err(anyhow!(
                 "This config needs parameters!\nMore info: rvp batch --help"
             ));
         }
+        // Before the parameters, so the fanned out resources are reported once
+        if !ctx.quiet {
+            for resource in config.resources.iter().filter(|r| r.enabled) {
+                if let Some(deprecation) = resource.deprecation() {
+                    eprintln!("Warning: {}", ctx.redact(&deprecation));
+                }
+            }
+        }
+        config.apply_params(&params)?;
+        let disabled = config.remove_disabled();
+        if disabled > 0 {
+            ctx.log(format!("Skipping {} disabled resources", disabled));
+        }
+        let untagged = config.remove_untagged(&self.tags);
+        if untagged > 0 {
+            ctx.log(format!(
+                "Skipping {} resources without the tags {}",
+                untagged,
+                self.tags.join(", ")
+            ));
+        }
+        config.apply_named_params(&self.named_params)?;
+        config.apply_timezone(ctx.utc.then_some(Timezone::UTC));
+
+        Ok(config)
+    }
+
+    /// It checks if the config is run once per value of `--params-file` or `--param-list`
+    pub fn fans_out(&self) -> bool {
+        self.params_file.is_some() || self.param_list.is_some()
+    }
+
+    /// It reads the values of `--params-file` or `--param-list`
+    fn fan_out_values(&self) -> Result<Vec<String>> {
+        let values: Vec<String> = match (&self.params_file, &self.param_list) {
+            (Some(path), _) => fs::read_to_string(path)
+                .map_err(|err| anyhow!("Failed to read {}: {}", path.display(), err))?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .collect(),
+            (None, Some(list)) => list
+                .iter()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect(),
+            (None, None) => vec![],
+        };
+        if values.is_empty() {
+            return Err(anyhow!("No parameter values to run the config with!"));
+        }
+        Ok(values)
+    }
+}
+
+/// Parse the `name=value` pair of the `--param` argument
+fn parse_named_param(param: &str) -> Result<(String, String)> {
+    match param.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(anyhow!(
+            "Invalid parameter \"{}\", expected NAME=VALUE!",
+            param
+        )),
+    }
+}
 
-        if args.params.is_some() {
-            let params = args.params.unwrap();
-            let resources_len = config.resources.len();
-            if params.is_empty() {
-                return Err(anyhow!("You need to specify at least one parameter!"));
+/// It keeps only the resources leased by this node, if the lease backend is set
+#[cfg_attr(not(feature = "lease"), allow(unused_variables))]
+pub async fn lease_resources(config: Config, ctx: &Context) -> Result<Config> {
+    #[cfg(feature = "lease")]
+    if let Some(url) = &ctx.lease_backend {
+        let mut leases = crate::lease::Leases::connect(url, ctx.lease_ttl).await?;
+        let mut config = config;
+        let mut leased = Vec::with_capacity(config.resources.len());
+        for resource in config.resources {
+            match leases.acquire(&config.name, &resource.url).await? {
+                true => leased.push(resource),
+                false => ctx.log(format!("Skipping {}, leased by another node", resource.url)),
             }
+        }
+        config.resources = leased;
+        return Ok(config);
+    }
+
+    Ok(config)
+}
+
+/// It replaces the resources with a `sitemap` by a resource for each page of the sitemap
+pub async fn expand_sitemaps(mut config: Config, ctx: &Context) -> Result<Config> {
+    if config.resources.iter().all(|r| r.sitemap.is_none()) {
+        return Ok(config);
+    }
+    let rate_limited = rate_limited_fetcher(&config, &ctx.fetcher, ctx);
+    let limited: &dyn Fetcher = match &rate_limited {
+        Some(fetcher) => fetcher,
+        None => &ctx.fetcher,
+    };
+    let robots = robots_fetcher(config.respect_robots, limited, ctx);
+    let fetcher: &dyn Fetcher = match &robots {
+        Some(fetcher) => fetcher,
+        None => limited,
+    };
+    let resources = std::mem::take(&mut config.resources);
+    config.resources = sitemap::expand(fetcher, resources).await?;
+    ctx.log(format!(
+        "Expanded the sitemaps to {} resources",
+        config.resources.len()
+    ));
+    Ok(config)
+}
+
+/// It collects the values of all successfully parsed resources
+pub fn collect_values(results: &[ResourceResult]) -> Vec<ParsedValue> {
+    results
+        .iter()
+        .filter_map(|r| r.values.as_ref().ok())
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// The latest stored values by the parameter, for the `prev_` names of the computed values
+type PreviousValues = Vec<(Option<String>, Vec<ParsedValue>)>;
 
-            if resources_len != params.len() && !args.repeat {
-                return Err(anyhow!(
-                    "The number of parameters does not match the number of resources ({})!",
-                    resources_len
-                ));
+/// It reads the latest values used by the computed values from the history of `--store`, for
+/// each parameter of the run and without the parameter. Without the store there are no
+/// previous values.
+#[cfg_attr(not(feature = "history"), allow(unused_variables))]
+fn previous_values(
+    ctx: &Context,
+    config_name: &str,
+    computed: &[Computed],
+    results: &[ResourceResult],
+) -> PreviousValues {
+    #[cfg(feature = "history")]
+    if let Some(url) = &ctx.store {
+        let names = compute::previous_names(computed);
+        if names.is_empty() {
+            return PreviousValues::new();
+        }
+        let store = match history_store::Store::open(url) {
+            Ok(store) => store,
+            Err(err) => {
+                eprintln!("Warning: failed to read the history: {}", err);
+                return PreviousValues::new();
+            }
+        };
+        let mut previous = PreviousValues::new();
+        let mut params: Vec<Option<String>> = vec![None];
+        for param in results.iter().filter_map(|r| r.param.clone()) {
+            if !params.contains(&Some(param.clone())) {
+                params.push(Some(param));
             }
+        }
+        for param in params {
+            let stored_param = param.as_deref().map(|p| ctx.redact(p).into_owned());
+            let values = names
+                .iter()
+                .filter_map(|name| {
+                    let query = history_store::Query {
+                        config: Some(config_name),
+                        selector: Some(name),
+                        param: stored_param.as_deref(),
+                        limit: 1,
+                    };
+                    let record = store.recent(&query).ok()?.pop()?;
+                    Some(ParsedValue {
+                        name: record.selector,
+                        value: serde_json::from_str(&record.value).ok()?,
+                        error: None,
+                        stale: None,
+                        anomaly: None,
+                    })
+                })
+                .collect();
+            previous.push((param, values));
+        }
+        return previous;
+    }
 
-            if args.repeat {
-                // Duplicate resources for each param and the same number of params
-                let mut duplicated_resources: Vec<Resource> = Vec::default();
-                let mut duplicated_params: Vec<String> = Vec::default();
-                for param in params.iter() {
-                    for r in config.resources.iter() {
-                        duplicated_params.push(param.clone());
-                        duplicated_resources.push(r.clone());
-                    }
-                }
-                config.resources = duplicated_resources;
+    PreviousValues::new()
+}
 
-                // Mutate duplicated resources urls with duplicated params
-                for (i, param) in duplicated_params.iter().enumerate() {
-                    config.resources[i].mut_url_with_param(param);
-                }
-            } else {
-                // Mutate resource urls with params
-                for (i, param) in params.iter().enumerate() {
-                    config.resources[i].mut_url_with_param(param);
+/// It flags the anomalies of the values against their history in `--store`. Called before
+/// the run is stored, so the values are not compared with themselves.
+#[cfg_attr(not(feature = "history"), allow(unused_variables))]
+pub fn flag_anomalies(
+    ctx: &Context,
+    config_name: &str,
+    resources: &[Resource],
+    results: &mut [ResourceResult],
+) {
+    #[cfg(feature = "history")]
+    if let Some(url) = &ctx.store {
+        let checked = resources
+            .iter()
+            .flat_map(|r| r.selectors.iter())
+            .any(|s| s.anomaly.is_some());
+        if !checked {
+            return;
+        }
+        let store = match history_store::Store::open(url) {
+            Ok(store) => store,
+            Err(err) => {
+                eprintln!("Warning: failed to read the history: {}", err);
+                return;
+            }
+        };
+        anomaly::flag(resources, results, |url, param, name, window| {
+            let url = ctx.redact(url);
+            let param = param.map(|p| ctx.redact(p).into_owned());
+            match store.numbers(config_name, &url, name, param.as_deref(), window) {
+                Ok(numbers) => numbers,
+                Err(err) => {
+                    eprintln!("Warning: failed to read the history: {}", err);
+                    Vec::new()
                 }
             }
+        });
+    }
+}
+
+/// It warns if the config has `change(...)` alert rules, but no `--store` to check them with
+pub fn warn_unchecked_changes(ctx: &Context, config: &Config) {
+    let changes = config
+        .resources
+        .iter()
+        .flat_map(|r| r.selectors.iter())
+        .any(|s| s.alert.as_ref().is_some_and(|a| a.change.is_some()));
+    // The history is not built in without the feature
+    #[cfg(feature = "history")]
+    let stored = ctx.store.is_some();
+    #[cfg(not(feature = "history"))]
+    let stored = false;
+    if changes && !stored && !ctx.quiet {
+        eprintln!("Warning: the change(...) alerts are not checked without --store");
+    }
+}
+
+/// It checks the `change(...)` alert rules against the stored values of `--store`. Without
+/// the store there are no changes to check.
+#[cfg_attr(not(feature = "history"), allow(unused_variables))]
+pub fn change_alerts(
+    ctx: &Context,
+    config_name: &str,
+    resources: &[Resource],
+    results: &[ResourceResult],
+) -> Vec<Alert> {
+    #[cfg(feature = "history")]
+    if let Some(url) = &ctx.store {
+        let checked = resources
+            .iter()
+            .flat_map(|r| r.selectors.iter())
+            .any(|s| s.alert.as_ref().is_some_and(|a| a.change.is_some()));
+        if !checked {
+            return Vec::new();
         }
+        let store = match history_store::Store::open(url) {
+            Ok(store) => store,
+            Err(err) => {
+                eprintln!("Warning: failed to read the history: {}", err);
+                return Vec::new();
+            }
+        };
+        let now = chrono::Utc::now();
+        return alert::evaluate_changes(resources, results, |url, param, name, period| {
+            let url = ctx.redact(url);
+            let param = param.map(|p| ctx.redact(p).into_owned());
+            let time = now.checked_sub_signed(chrono::Duration::from_std(period).ok()?)?;
+            match store.number_at(config_name, &url, name, param.as_deref(), time) {
+                Ok(number) => number,
+                Err(err) => {
+                    eprintln!("Warning: failed to read the history: {}", err);
+                    None
+                }
+            }
+        });
+    }
 
-        if args.one_param.is_some() {
-            let param = args.one_param.unwrap();
+    Vec::new()
+}
 
-            for resource in config.resources.iter_mut() {
-                resource.mut_url_with_param(&param);
+/// It replaces the errors of the failed resources with the last values stored for them,
+/// flagged as stale. The resources without stored values stay failed.
+#[cfg(feature = "history")]
+fn fallback_to_last(
+    ctx: &Context,
+    config_name: &str,
+    mut results: Vec<ResourceResult>,
+) -> Vec<ResourceResult> {
+    let Some(url) = &ctx.store else {
+        return results;
+    };
+    let store = match history_store::Store::open(url) {
+        Ok(store) => store,
+        Err(err) => {
+            eprintln!("Warning: failed to read the history: {}", err);
+            return results;
+        }
+    };
+    for result in results.iter_mut() {
+        let Err(err) = &result.values else {
+            continue;
+        };
+        let resource = ctx.redact(&result.url).into_owned();
+        let param = result.param.as_deref().map(|p| ctx.redact(p).into_owned());
+        let records = match store.last_values(config_name, &resource, param.as_deref()) {
+            Ok(records) if !records.is_empty() => records,
+            Ok(_) => continue,
+            Err(err) => {
+                eprintln!("Warning: failed to read the history: {}", err);
+                continue;
             }
+        };
+        if !ctx.quiet {
+            eprintln!(
+                "Warning: {}, using the stored values",
+                ctx.redact(&err.to_string())
+            );
         }
+        let values = records
+            .into_iter()
+            .map(|record| ParsedValue {
+                name: record.selector,
+                value: serde_json::from_str(&record.value).unwrap_or_default(),
+                error: None,
+                stale: Some(Stale { time: record.time }),
+                anomaly: None,
+            })
+            .collect();
+        result.values = Ok(values);
     }
+    results
+}
+
+/// It returns the previous values of the parameter
+fn previous_of<'a>(previous: &'a PreviousValues, param: Option<&str>) -> &'a [ParsedValue] {
+    previous
+        .iter()
+        .find(|(p, _)| p.as_deref() == param)
+        .map_or(&[], |(_, values)| values)
+}
 
-    // TODO: parse in a thread pool
-    let mut tasks = Vec::default();
-    for r in config.resources {
-        tasks.push(tokio::spawn(grab(r.selectors, r.url)));
+/// It groups the parsed values by the parameter applied to their resources, in the order of the
+/// parameters
+fn group_by_param(results: &[ResourceResult]) -> Vec<ValueGroup> {
+    let mut groups: Vec<ValueGroup> = Vec::new();
+    for result in results.iter() {
+        let Ok(values) = &result.values else {
+            continue;
+        };
+        let param = result.param.clone().unwrap_or_default();
+        match groups.iter_mut().find(|(p, _)| *p == param) {
+            Some((_, group)) => group.extend(values.iter().cloned()),
+            None => groups.push((param, values.clone())),
+        }
     }
+    groups
+}
 
-    let mut outputs = Vec::default();
-    for task in tasks {
-        let mut parsed = match task.await.unwrap() {
-            Ok(v) => v,
-            Err(e) => {
-                panic!("Error while processing request to one of the URLs: {}", e);
-            }
+/// It groups the parsed values by resource, labeled by the URL. The failed resources are skipped.
+fn group_by_resource(results: &[ResourceResult]) -> Vec<ValueGroup> {
+    results
+        .iter()
+        .filter_map(|r| r.values.as_ref().ok().map(|v| (r.url.clone(), v.clone())))
+        .collect()
+}
+
+/// It prints the summary of the failed resources and values to stderr,
+/// so they don't mix with the output. The secrets of the config are redacted.
+///
+/// The resources redirected to another host are warned about, as the values likely come
+/// from a login or an error page. So are the pages in another language than the
+/// `accept_language` of the resource, as the numbers are formatted differently there.
+///
+/// Returns:
+///
+/// The number of resources which failed completely
+pub fn report_errors(results: &[ResourceResult], ctx: &Context) -> usize {
+    for result in results.iter().filter(|r| r.language_mismatch()) {
+        eprintln!(
+            "Warning: {} returned a page in \"{}\", but \"{}\" is accepted",
+            ctx.redact(&result.url),
+            result.language.as_deref().unwrap_or_default(),
+            result.accept_language.as_deref().unwrap_or_default()
+        );
+    }
+    for result in results.iter() {
+        let Some(final_url) = &result.final_url else {
+            continue;
         };
-        outputs.append(&mut parsed);
+        let message = format!(
+            "{} was redirected to {}",
+            ctx.redact(&result.url),
+            ctx.redact(final_url)
+        );
+        match result.redirected_off_host() {
+            true => eprintln!("Warning: {}, another host than configured", message),
+            false => ctx.log(message),
+        }
     }
 
-    if args.json {
-        println!("{}", generate_json(&outputs));
-    } else {
-        println!("{}", generate_table(&outputs));
+    let failed_resources: Vec<(&String, &RvpError)> = results
+        .iter()
+        .filter_map(|r| r.values.as_ref().err().map(|e| (&r.url, e)))
+        .collect();
+    let failed_values: Vec<(&String, &ParsedValue)> = results
+        .iter()
+        .flat_map(|r| r.failed_values().into_iter().map(move |v| (&r.url, v)))
+        .collect();
+
+    if failed_resources.is_empty() && failed_values.is_empty() {
+        return 0;
     }
 
-    Ok(())
+    eprintln!("\nErrors:");
+    for (url, error) in failed_resources.iter() {
+        eprintln!(
+            "  • {}: {}",
+            ctx.redact(url),
+            ctx.redact(&error.to_string())
+        );
+    }
+    for (url, value) in failed_values {
+        eprintln!(
+            "  • {} \"{}\": {}",
+            ctx.redact(url),
+            value.name,
+            ctx.redact(value.error.as_deref().unwrap_or_default())
+        );
+    }
+
+    failed_resources.len()
 }
 
-/// Generate table from parsed values
-fn generate_table(parsed_values: &Vec<ParsedValue>) -> Table {
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_header(vec!["Name", "Value"]);
-    for parsed_value in parsed_values {
-        table.add_row(vec![&parsed_value.name, &parsed_value.value.to_string()]);
+/// It grabs the values of all resources of the config concurrently, up to `--concurrency` at once,
+/// keeping the requests to the same host within the rate limit (and the limits picked with
+/// `--auto-concurrency`) and the retained pages within `--max-memory`. With
+/// `--respect-robots`, the resources disallowed by `robots.txt` are skipped.
+pub async fn scrape(config: Config, ctx: &Context) -> Vec<ResourceResult> {
+    // The requests are recorded for the report as they are sent, without the waits
+    let recording = ctx.report_file.as_ref().map(|_| RecordingFetcher {
+        inner: &ctx.fetcher,
+        recorder: &ctx.requests,
+    });
+    let base: &dyn Fetcher = match &recording {
+        Some(fetcher) => fetcher,
+        None => &ctx.fetcher,
+    };
+    // The rate limit is waited for outside of the tuned fetcher, so it is not measured
+    let tuned = tuned_fetcher(&config, base, ctx);
+    let inner: &dyn Fetcher = match &tuned {
+        Some(fetcher) => fetcher,
+        None => base,
+    };
+    let rate_limited = rate_limited_fetcher(&config, inner, ctx);
+    let limited: &dyn Fetcher = match &rate_limited {
+        Some(fetcher) => fetcher,
+        None => inner,
+    };
+    // The robots.txt is fetched within the rate limit too
+    let robots = robots_fetcher(config.respect_robots, limited, ctx);
+    let (fetcher, resources): (&dyn Fetcher, _) = match &robots {
+        Some(robots) => (robots, skip_disallowed(robots, config.resources, ctx).await),
+        None => (limited, config.resources),
+    };
+
+    let concurrency = ctx.concurrency.unwrap_or(resources.len());
+    let results = grab_resources_bounded(fetcher, resources, concurrency, ctx.max_memory).await;
+
+    if let Some(tuned) = tuned {
+        match ctx.data_dir() {
+            Some(dir) => {
+                if let Err(err) = host_stats::save(&dir, &tuned.stats()) {
+                    eprintln!("Warning: failed to save the host stats: {}", err);
+                }
+            }
+            None => ctx.log("Could not find the data directory, the host stats are not saved"),
+        }
     }
+    results
+}
 
-    table
+/// It drops the resources disallowed by the `robots.txt` of their hosts, with a warning
+async fn skip_disallowed(
+    robots: &RobotsFetcher<'_>,
+    resources: Vec<Resource>,
+    ctx: &Context,
+) -> Vec<Resource> {
+    let mut allowed = Vec::with_capacity(resources.len());
+    for resource in resources {
+        match robots.is_allowed(&resource.url).await {
+            true => allowed.push(resource),
+            false => eprintln!(
+                "Warning: skipping {}, {}",
+                ctx.redact(&resource.url),
+                robots::DISALLOWED
+            ),
+        }
+    }
+    allowed
 }
 
-/// Generate json from parsed values
-fn generate_json(parsed_values: &Vec<ParsedValue>) -> String {
-    let json_str = json!(parsed_values);
-    to_string_pretty(&json_str).expect("Error while prettifying json!")
+/// It wraps the fetcher with the per-host limits picked from the stats of the previous runs,
+/// if `--auto-concurrency` is set
+fn tuned_fetcher<'a>(
+    config: &Config,
+    fetcher: &'a dyn Fetcher,
+    ctx: &Context,
+) -> Option<TunedFetcher<'a>> {
+    if !ctx.auto_concurrency {
+        return None;
+    }
+    let recorded = ctx
+        .data_dir()
+        .map(|dir| host_stats::load(&dir))
+        .unwrap_or_default();
+    let fetcher = TunedFetcher::new(fetcher, recorded);
+
+    let mut hosts: Vec<&str> = Vec::new();
+    for resource in config.resources.iter() {
+        let host = url_host(&resource.url);
+        if hosts.contains(&host) {
+            continue;
+        }
+        hosts.push(host);
+        let limits = fetcher.limits(&resource.url);
+        ctx.log(format!(
+            "Auto concurrency of {}: {} requests at once, {} ms between them",
+            host,
+            limits.concurrency,
+            limits.delay.as_millis()
+        ));
+    }
+    Some(fetcher)
 }
 
+/// It appends the run to the audit log in the data directory, the values to the history with
+/// `--store`, and writes the run report with `--report-file`.
+///
+/// The run is not failed if the log could not be written, only a warning is printed.
+pub fn record_run(ctx: &Context, command: &str, config_name: &str, results: &[ResourceResult]) {
+    if let Some(path) = &ctx.report_file {
+        if let Err(err) = report::write(path, ctx, command, config_name, results) {
+            eprintln!("Warning: failed to write the report: {}", err);
+        }
+    }
+
+    #[cfg(feature = "history")]
+    if let Some(url) = &ctx.store {
+        let stored = history_store::Store::open(url).and_then(|mut store| {
+            store.append(config_name, results, |text| ctx.redact(text).into_owned())
+        });
+        match stored {
+            Ok(count) => ctx.log(format!("Stored {} values in the history", count)),
+            Err(err) => eprintln!("Warning: failed to store the history: {}", err),
+        }
+    }
+
+    let Some(dir) = ctx.data_dir() else {
+        ctx.log("Could not find the data directory, the run is not audited");
+        return;
+    };
+    let mut entry = audit_log::Entry::new(command, config_name, results);
+    entry.params = entry
+        .params
+        .iter()
+        .map(|param| ctx.redact(param).into_owned())
+        .collect();
+    if let Err(err) = audit_log::append(&dir, &entry) {
+        eprintln!("Warning: failed to write the audit log: {}", err);
+    }
+}
+
+/// It wraps the fetcher if `--rate-limit`, the `rate_limit` of the config or of the
+/// settings is set, or a resource (e.g. of a group) limits its host
+pub fn rate_limited_fetcher<'a>(
+    config: &Config,
+    fetcher: &'a dyn Fetcher,
+    ctx: &Context,
+) -> Option<RateLimitedFetcher<'a>> {
+    let rate_limit = ctx.rate_limit.or(config.rate_limit);
+    let rate_limited = rate_limit.or(ctx.settings.rate_limit).map(|rate_limit| {
+        ctx.log(format!("Rate limit: {} per host", rate_limit));
+        RateLimitedFetcher::new(fetcher, rate_limit)
+    });
+    let mut host_limits: Vec<(&str, RateLimit)> = Vec::new();
+    for resource in config.resources.iter() {
+        let Some(rate_limit) = resource.rate_limit else {
+            continue;
+        };
+        let host_limit = (url_host(&resource.url), rate_limit);
+        if !host_limits.contains(&host_limit) {
+            host_limits.push(host_limit);
+        }
+    }
+    host_limits
+        .into_iter()
+        .fold(rate_limited, |rate_limited, (host, rate_limit)| {
+            ctx.log(format!("Rate limit of {}: {}", host, rate_limit));
+            let rate_limited =
+                rate_limited.unwrap_or_else(|| RateLimitedFetcher::per_host(fetcher));
+            Some(rate_limited.with_host_limit(host, rate_limit))
+        })
+}
+
+/// It wraps the fetcher with the `robots.txt` rules, if `--respect-robots` is set or the
+/// config respects them (`respect_robots`) without `--ignore-robots`
+pub fn robots_fetcher<'a>(
+    respect_robots: bool,
+    fetcher: &'a dyn Fetcher,
+    ctx: &Context,
+) -> Option<RobotsFetcher<'a>> {
+    ctx.robots
+        .unwrap_or(respect_robots)
+        .then(|| RobotsFetcher::new(fetcher, ctx.user_agent.as_deref()))
+}
+
+/// Records of a single resource in the nested JSON output
 #[derive(Serialize)]
-struct JsonData {
+struct JsonData<'a> {
     url: String,
-    data: Vec<ParsedValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    param: Option<String>,
+    /// The URL after the redirects, if the request was redirected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    final_url: Option<String>,
+    data: &'a [ParsedValue],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The nested JSON output: config -> resources -> records
+#[derive(Serialize)]
+struct NestedJson<'a> {
+    /// Time of the run, only in the JSON lines of `schedule`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<&'a str>,
+    config: &'a str,
+    resources: Vec<JsonData<'a>>,
+}
+
+/// It generates the JSON with the records grouped by config and resource. The secrets in the
+/// URLs, the parameters and the errors are hidden with `redact`.
+pub fn generate_nested_json(
+    config_name: &str,
+    results: &[ResourceResult],
+    redact: impl Fn(&str) -> String,
+) -> String {
+    let nested = nested_json(config_name, results, None, redact);
+    serde_json::to_string_pretty(&nested).expect("Error while prettifying json!")
+}
+
+/// Same as [generate_nested_json], but on a single line with the time of the run,
+/// for the JSON lines files
+pub fn generate_json_line(
+    config_name: &str,
+    results: &[ResourceResult],
+    time: &str,
+    redact: impl Fn(&str) -> String,
+) -> String {
+    let nested = nested_json(config_name, results, Some(time), redact);
+    serde_json::to_string(&nested).expect("Error while serializing json!")
+}
+
+fn nested_json<'a>(
+    config_name: &'a str,
+    results: &'a [ResourceResult],
+    time: Option<&'a str>,
+    redact: impl Fn(&str) -> String,
+) -> NestedJson<'a> {
+    let resources = results
+        .iter()
+        .map(|r| JsonData {
+            url: redact(&r.url),
+            param: r.param.as_deref().map(&redact),
+            final_url: r.final_url.as_deref().map(&redact),
+            data: r.values.as_deref().unwrap_or_default(),
+            error: r.values.as_ref().err().map(|e| redact(&e.to_string())),
+        })
+        .collect();
+    NestedJson {
+        time,
+        config: config_name,
+        resources,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json::{Number, Value};
+    use serde_json::Value;
 
     #[test]
-    fn test_generate_table() {
-        let parsed_values = vec![
-            ParsedValue {
-                name: "name1".to_string(),
-                value: Value::String("value1".to_string()),
-            },
-            ParsedValue {
-                name: "name2".to_string(),
-                value: Value::Number(Number::from_f64(2.2).unwrap()),
-            },
-        ];
+    fn test_output_format() {
+        let args = |flags: &[&str]| {
+            let mut argv = vec!["batch", "-p", "config.toml"];
+            argv.extend(flags);
+            Args::parse_from(argv)
+        };
+        let mut config = Config::new("test".to_string(), "".to_string(), vec![]);
+        let mut ctx = Context::default();
+        assert_eq!(
+            output_format(&args(&[]), &config, &ctx),
+            OutputFormat::Table
+        );
+        assert_eq!(
+            output_format(&args(&["--csv"]), &config, &ctx),
+            OutputFormat::Csv
+        );
 
-        let table = generate_table(&parsed_values);
+        ctx.settings.output = Some(OutputFormat::Json);
+        assert_eq!(output_format(&args(&[]), &config, &ctx), OutputFormat::Json);
+        assert_eq!(
+            output_format(&args(&["--table"]), &config, &ctx),
+            OutputFormat::Table
+        );
+        assert_eq!(
+            output_format(&args(&["--csv"]), &config, &ctx),
+            OutputFormat::Csv
+        );
 
+        config.output = Some(OutputFormat::Csv);
+        assert_eq!(output_format(&args(&[]), &config, &ctx), OutputFormat::Csv);
         assert_eq!(
-            table.to_string(),
-            "\
-            ╭───────┬──────────╮\n\
-            │ Name  ┆ Value    │\n\
-            ╞═══════╪══════════╡\n\
-            │ name1 ┆ \"value1\" │\n\
-            ├╌╌╌╌╌╌╌┼╌╌╌╌╌╌╌╌╌╌┤\n\
-            │ name2 ┆ 2.2      │\n\
-            ╰───────┴──────────╯"
+            output_format(&args(&["--json", "--flatten"]), &config, &ctx),
+            OutputFormat::Json
         );
     }
 
     #[test]
-    fn test_generate_json() {
-        let parsed_values = vec![
-            ParsedValue {
-                name: "name1".to_string(),
-                value: Value::String("value1".to_string()),
+    fn test_collect_values_and_report_errors() {
+        let results = vec![
+            ResourceResult {
+                url: "https://ok.com".to_string(),
+                param: None,
+                final_url: Some("https://ok.com/en/".to_string()),
+                accept_language: None,
+                language: None,
+                values: Ok(vec![
+                    ParsedValue {
+                        name: "price".to_string(),
+                        value: Value::from(1.5),
+                        error: None,
+                        stale: None,
+                        anomaly: None,
+                    },
+                    ParsedValue {
+                        name: "broken".to_string(),
+                        value: Value::Null,
+                        error: Some("invalid selector".to_string()),
+                        stale: None,
+                        anomaly: None,
+                    },
+                ]),
             },
-            ParsedValue {
-                name: "name2".to_string(),
-                value: Value::Number(Number::from_f64(25.6).unwrap()),
+            ResourceResult {
+                url: "https://down.com".to_string(),
+                param: None,
+                final_url: None,
+                accept_language: None,
+                language: None,
+                values: Err(RvpError::FetchError {
+                    url: "https://down.com".to_string(),
+                    message: "timeout".to_string(),
+                }),
             },
         ];
 
-        let json = generate_json(&parsed_values);
+        assert_eq!(
+            parse_named_param("date=2024-01-01=x").unwrap(),
+            ("date".to_string(), "2024-01-01=x".to_string())
+        );
+        assert!(parse_named_param("AAPL").is_err());
+        assert!(parse_named_param("=AAPL").is_err());
 
+        let groups = group_by_param(&results);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].1.len(), 2);
+        let groups = group_by_resource(&results);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, "https://ok.com");
+
+        let values = collect_values(&results);
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].name, "price");
+
+        assert_eq!(results[0].failed_values().len(), 1);
+        let ctx = Context::default();
+        assert_eq!(report_errors(&results, &ctx), 1);
+        assert_eq!(report_errors(&results[..1], &ctx), 0);
+
+        let keep = |text: &str| text.to_string();
+        let nested: Value =
+            serde_json::from_str(&generate_nested_json("stock", &results, keep)).unwrap();
+        assert_eq!(nested["config"], "stock");
+        assert_eq!(nested["resources"][0]["data"][0]["value"], 1.5);
+        assert_eq!(nested["resources"][0]["final_url"], "https://ok.com/en/");
+        assert!(nested["resources"][1].get("final_url").is_none());
+        assert_eq!(nested["resources"][1]["data"], Value::Array(vec![]));
+        assert!(nested["resources"][1]["error"].is_string());
+
+        let line = generate_json_line("stock", &results, "2024-05-01T10:00:00Z", keep);
+        assert!(!line.contains('\n'));
+        assert!(line.starts_with(r#"{"time":"2024-05-01T10:00:00Z","config":"stock","#));
+
+        // The secrets are hidden in the URLs and the errors, as the JSON goes to the webhooks
+        // and the archives
+        let redactor = rvp::redact::Redactor::new(&["(?:ok|down)\\.com".to_string()]).unwrap();
+        let redact = |text: &str| redactor.redact(text).into_owned();
+        let line = generate_json_line("stock", &results, "2024-05-01T10:00:00Z", redact);
+        assert!(
+            !line.contains("ok.com") && !line.contains("down.com"),
+            "{}",
+            line
+        );
+        assert!(line.contains(r#""final_url":"https://[REDACTED]/en/""#));
+    }
+
+    #[test]
+    fn test_digest() {
+        let alert = |value: f64| Alert {
+            url: "https://example.com".to_string(),
+            param: None,
+            name: "price".to_string(),
+            value,
+            trigger: alert::Trigger::Rule("< 100".parse().unwrap()),
+        };
+        let start = Instant::now();
+        let mut digest = Digest::new(Duration::from_secs(60));
+        digest.started = start;
+
+        assert_eq!(digest.collect(vec![alert(95.0)], start), None);
+        let later = start + Duration::from_secs(30);
+        assert_eq!(digest.collect(vec![alert(90.0)], later), None);
+        let sent = digest.collect(vec![], start + Duration::from_secs(60));
+        assert_eq!(sent, Some(vec![alert(90.0)]));
+
+        // Nothing is sent for an interval without alerts
+        assert_eq!(
+            digest.collect(vec![], start + Duration::from_secs(120)),
+            None
+        );
+
+        // The alerts of an unfinished interval are sent when the command stops
+        let later = start + Duration::from_secs(150);
+        assert_eq!(digest.collect(vec![alert(85.0)], later), None);
+        assert_eq!(digest.flush(), Some(vec![alert(85.0)]));
+        assert_eq!(digest.flush(), None);
+    }
+
+    #[tokio::test]
+    async fn test_preflight() {
+        let resource = |url: &str| Resource::new(url.to_string(), vec![]);
+        let config = Config::new(
+            "local".to_string(),
+            String::new(),
+            vec![
+                resource("file:///tmp/page.html"),
+                resource("http://127.0.0.1:1/a"),
+                resource("http://127.0.0.1:1/b"),
+            ],
+        );
+        let mut ctx = Context::default();
+        ctx.quiet = true;
+
+        // Nothing listens on the port 1, the host is checked once
+        let err = preflight(&config, &ctx).await.unwrap_err();
         assert_eq!(
-            json,
-            "[\n  {\n    \"name\": \"name1\",\n    \"value\": \"value1\"\n  },\n  \
-            {\n    \"name\": \"name2\",\n    \"value\": 25.6\n  }\n]"
+            err.to_string(),
+            "1 of 1 hosts are unreachable, no pages were fetched!"
         );
+        assert_eq!(crate::exit::code(&err), crate::exit::NETWORK);
+
+        let local = Config::new(String::new(), String::new(), vec![resource("file:///x")]);
+        assert!(preflight(&local, &ctx).await.is_ok());
     }
 }
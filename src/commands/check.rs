@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::Utc;
+use clap::{value_parser, Parser};
+use rvp::{
+    error::RvpError,
+    redact::Redactor,
+    structure::{Config, ConfigFormat},
+    validate::{duplicates, past_sunsets, validate},
+};
+
+use crate::context::Context;
+use crate::paths;
+
+/// Check the config file without fetching anything: URLs, selector syntax,
+/// unique selector names and parameter placeholders
+///
+/// The selectors repeated in the resources with the same URL and the deprecated resources
+/// are reported as warnings. The deprecated resources past their `sunset` date are problems.
+#[derive(Parser)]
+#[command(visible_aliases = ["validate", "lint"])]
+pub struct Args {
+    /// Path to the config file.
+    #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf), required_unless_present = "name")]
+    path: Option<PathBuf>,
+
+    /// Name of the config, looked up in the current directory and then in the global config
+    /// directory.
+    #[arg(short, long, value_name = "NAME", conflicts_with = "path")]
+    name: Option<String>,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let path = match (args.path, &args.name) {
+        (Some(path), _) => path,
+        (None, Some(name)) => paths::resolve_config(&ctx.config_search_dirs(), name)?,
+        (None, None) => unreachable!("clap requires either path or name"),
+    };
+    if !path.exists() {
+        return Err(RvpError::NotFoundError("File does not exist!".to_string()).into());
+    }
+    ctx.log(format!("Checking config {}", path.display()));
+
+    let config = Config::from_file(&path, &ConfigFormat::from_path(&path)?)?;
+    // An invalid pattern is reported with the other problems
+    if let Ok(redactor) = Redactor::new(&config.redact) {
+        ctx.set_redactor(redactor);
+    }
+    let mut problems = validate(&config);
+    problems.extend(past_sunsets(&config, Utc::now().date_naive()));
+    // The duplicates and the deprecations don't break the config, so they are only warnings
+    let duplicates = duplicates(&config);
+    let deprecations: Vec<String> = config
+        .resources
+        .iter()
+        .filter_map(|r| r.deprecation())
+        .map(|deprecation| ctx.redact(&deprecation).into_owned())
+        .collect();
+    if !ctx.quiet {
+        for duplicate in duplicates.iter() {
+            eprintln!("warning: {}", duplicate);
+        }
+        for deprecation in deprecations.iter() {
+            eprintln!("warning: {}", deprecation);
+        }
+    }
+    if problems.is_empty() {
+        match (duplicates.len(), deprecations.len()) {
+            (0, 0) => println!("Config \"{}\" is valid", config.name),
+            (0, n) => println!("Config \"{}\" is valid, with {} warning(s)", config.name, n),
+            (d, n) => println!(
+                "Config \"{}\" is valid, with {} warning(s). Run `rvp edit` to remove the duplicates",
+                config.name,
+                d + n
+            ),
+        }
+        return Ok(());
+    }
+
+    if !ctx.quiet {
+        for problem in problems.iter() {
+            eprintln!("- {}", problem);
+        }
+    }
+    Err(RvpError::ConfigError(format!(
+        "Found {} problem(s) in {}",
+        problems.len(),
+        path.display()
+    ))
+    .into())
+}
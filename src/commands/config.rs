@@ -0,0 +1,438 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::{value_parser, Parser, Subcommand};
+use rvp::{
+    compute::{Expr, PREVIOUS_PREFIX},
+    error::RvpError,
+    structure::{url_host, Config, ConfigFormat},
+    validate::duplicates,
+};
+
+use super::new::FormatArg;
+use crate::context::Context;
+
+/// Merge configs into one, split a config into smaller ones, or convert it to another format
+#[derive(Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: ConfigCommands,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Merge the resources of the configs into one config, to run them in one batch.
+    ///
+    /// The options of the config (e.g. `rate_limit` or `webhook`) are taken from the first
+    /// config which sets them, with a warning if the others set another value.
+    Merge {
+        /// Paths to the config files, in the order of their resources.
+        #[arg(value_name = "PATH", required = true, num_args = 2.., value_parser = value_parser!(PathBuf))]
+        paths: Vec<PathBuf>,
+
+        /// (Optional) Name of the merged config, the name of the first config by default.
+        #[arg(short, long, value_name = "NAME")]
+        name: Option<String>,
+
+        /// (Optional) Path of the config file to create, the format is detected by the extension.
+        ///
+        /// Without it, the config is printed to stdout as TOML.
+        #[arg(short, long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+        output: Option<PathBuf>,
+
+        /// Overwrite the config file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Split the config into configs with one resource each, or with the resources of one host.
+    ///
+    /// The parts are named `<name>-<n>` (or `<name>-<host>`) and keep the options of the
+    /// config, and the computed values of their own selectors.
+    Split {
+        /// Path to the config file.
+        #[arg(value_name = "PATH", value_parser = value_parser!(PathBuf))]
+        path: PathBuf,
+
+        /// A config per resource, numbered by the position of the resource.
+        #[arg(
+            long,
+            required_unless_present = "per_host",
+            conflicts_with = "per_host"
+        )]
+        per_resource: bool,
+
+        /// A config per host of the resources.
+        #[arg(long)]
+        per_host: bool,
+
+        /// (Optional) Directory to save the parts to, the directory of the config by default.
+        #[arg(short, long, value_name = "DIR", value_parser = value_parser!(PathBuf))]
+        output_dir: Option<PathBuf>,
+
+        /// Overwrite the config files if they already exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Convert the config to another format, e.g. from JSON to TOML.
+    ///
+    /// The fields keep their order. The converted config is saved next to the original one,
+    /// with the extension of the format.
+    Convert {
+        /// Path to the config file.
+        #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf))]
+        path: PathBuf,
+
+        /// Format to convert the config to.
+        #[arg(long, value_name = "FORMAT")]
+        to: FormatArg,
+
+        /// (Optional) Path of the converted config file, instead of the one next to the original.
+        #[arg(short, long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+        output: Option<PathBuf>,
+
+        /// Overwrite the config file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    match args.command {
+        ConfigCommands::Merge {
+            paths,
+            name,
+            output,
+            force,
+        } => {
+            let configs = paths
+                .iter()
+                .map(|path| load(path))
+                .collect::<Result<Vec<Config>>>()?;
+            let (mut config, warnings) = merge(configs);
+            if let Some(name) = name {
+                config.name = name;
+            }
+            if !ctx.quiet {
+                for warning in warnings.iter() {
+                    eprintln!("Warning: {}", warning);
+                }
+                for duplicate in duplicates(&config).iter() {
+                    eprintln!("Warning: {}", duplicate);
+                }
+            }
+
+            let Some(path) = output else {
+                print!("{}", config.to_toml());
+                return Ok(());
+            };
+            if path.exists() && !force {
+                return Err(anyhow!(
+                    "Config file {} already exists, use --force to overwrite it!",
+                    path.display()
+                ));
+            }
+            let path = config.save(&path, &ConfigFormat::from_path(&path)?)?;
+            println!(
+                "Merged {} resources into {}",
+                config.resources.len(),
+                path.display()
+            );
+        }
+        ConfigCommands::Split {
+            path,
+            per_resource: _,
+            per_host,
+            output_dir,
+            force,
+        } => {
+            let config = load(&path)?;
+            let config_format = ConfigFormat::from_path(&path)?;
+            let dir = match output_dir {
+                Some(dir) => dir,
+                None => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            };
+            let parts = split(&config, per_host);
+            let paths: Vec<PathBuf> = parts
+                .iter()
+                .map(|part| part.get_full_path(&dir, &config_format))
+                .collect();
+            // Nothing is written if any of the parts would overwrite a file
+            if let Some(existing) = paths.iter().find(|path| path.exists() && !force) {
+                return Err(anyhow!(
+                    "Config file {} already exists, use --force to overwrite it!",
+                    existing.display()
+                ));
+            }
+            for (part, path) in parts.iter().zip(paths.iter()) {
+                let path = part.save(path, &config_format)?;
+                println!(
+                    "Saved {} resources to {}",
+                    part.resources.len(),
+                    path.display()
+                );
+            }
+        }
+        ConfigCommands::Convert {
+            path,
+            to,
+            output,
+            force,
+        } => {
+            let config = load(&path)?;
+            let config_format: ConfigFormat = to.into();
+            let output = output.unwrap_or_else(|| path.with_extension(config_format.extension()));
+            if output == path {
+                return Err(anyhow!(
+                    "Config file {} is already in {} format!",
+                    path.display(),
+                    config_format.extension().to_uppercase()
+                ));
+            }
+            if output.exists() && !force {
+                return Err(anyhow!(
+                    "Config file {} already exists, use --force to overwrite it!",
+                    output.display()
+                ));
+            }
+            let output = config.save(&output, &config_format)?;
+            println!("Converted {} to {}", path.display(), output.display());
+        }
+    }
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<Config> {
+    if !path.exists() {
+        return Err(
+            RvpError::NotFoundError(format!("File {} does not exist!", path.display())).into(),
+        );
+    }
+    Ok(Config::from_file(path, &ConfigFormat::from_path(path)?)?)
+}
+
+/// It merges the configs into the first one, and returns the warnings about the options set
+/// differently by the configs
+fn merge(configs: Vec<Config>) -> (Config, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut configs = configs.into_iter();
+    let Some(mut merged) = configs.next() else {
+        return (Config::new(String::new(), String::new(), vec![]), warnings);
+    };
+    for config in configs {
+        let conflicts = [
+            (
+                "rate_limit",
+                pick(&mut merged.rate_limit, config.rate_limit),
+            ),
+            ("timezone", pick(&mut merged.timezone, config.timezone)),
+            (
+                "market_hours",
+                pick(&mut merged.market_hours, config.market_hours),
+            ),
+            ("output", pick(&mut merged.output, config.output)),
+            (
+                "output_file",
+                pick(&mut merged.output_file, config.output_file),
+            ),
+            ("webhook", pick(&mut merged.webhook, config.webhook)),
+        ];
+        for (option, _) in conflicts.iter().filter(|(_, conflict)| *conflict) {
+            warnings.push(format!(
+                "{} of \"{}\" is ignored, the merged config keeps the first one",
+                option, config.name
+            ));
+        }
+
+        if merged.description.is_empty() {
+            merged.description = config.description;
+        }
+        merged.respect_robots |= config.respect_robots;
+        for redact in config.redact {
+            if !merged.redact.contains(&redact) {
+                merged.redact.push(redact);
+            }
+        }
+        for computed in config.computed {
+            match merged.computed.iter().find(|c| c.name == computed.name) {
+                Some(existing) if existing.expr != computed.expr => warnings.push(format!(
+                    "computed \"{}\" of \"{}\" is ignored, the merged config keeps the first one",
+                    computed.name, config.name
+                )),
+                Some(_) => {}
+                None => merged.computed.push(computed),
+            }
+        }
+        for group in config.groups {
+            match merged.groups.iter().find(|g| g.name == group.name) {
+                Some(existing) if *existing != group => warnings.push(format!(
+                    "group \"{}\" of \"{}\" is ignored, the merged config keeps the first one",
+                    group.name, config.name
+                )),
+                Some(_) => {}
+                None => merged.groups.push(group),
+            }
+        }
+        merged.resources.extend(config.resources);
+    }
+    (merged, warnings)
+}
+
+/// It sets the option if it's not set yet, and returns whether it's already set to another value
+fn pick<T: PartialEq>(merged: &mut Option<T>, other: Option<T>) -> bool {
+    match (merged.as_ref(), other) {
+        (None, other) => {
+            *merged = other;
+            false
+        }
+        (Some(value), Some(other)) => *value != other,
+        (Some(_), None) => false,
+    }
+}
+
+/// It splits the config into the configs with one resource each, or with the resources of
+/// the same host
+fn split(config: &Config, per_host: bool) -> Vec<Config> {
+    let mut parts: Vec<(String, Config)> = Vec::new();
+    for (index, resource) in config.resources.iter().enumerate() {
+        let key = match per_host {
+            true => url_host(&resource.url).to_string(),
+            false => (index + 1).to_string(),
+        };
+        match parts.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, part)) => part.resources.push(resource.clone()),
+            None => {
+                let part = Config {
+                    name: format!("{}-{}", config.name, key),
+                    computed: vec![],
+                    resources: vec![resource.clone()],
+                    ..config.clone()
+                };
+                parts.push((key, part));
+            }
+        }
+    }
+
+    let mut parts: Vec<Config> = parts.into_iter().map(|(_, part)| part).collect();
+    for part in parts.iter_mut() {
+        // Only the computed values of the selectors in the part can be computed
+        let mut names: Vec<String> = part
+            .resources
+            .iter()
+            .flat_map(|r| r.selectors.iter().map(|s| s.name.clone()))
+            .collect();
+        for computed in config.computed.iter() {
+            let Ok(expr) = Expr::parse(&computed.expr) else {
+                continue;
+            };
+            let known = expr.names().iter().all(|name| {
+                let name = name.strip_prefix(PREVIOUS_PREFIX).unwrap_or(name);
+                names.iter().any(|n| n == name)
+            });
+            if known {
+                names.push(computed.name.clone());
+                part.computed.push(computed.clone());
+            }
+        }
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::structure::{Computed, Resource, Selector, SelectorType};
+
+    fn config(name: &str, urls: &[(&str, &str)]) -> Config {
+        let resources = urls
+            .iter()
+            .map(|(url, selector)| {
+                Resource::new(
+                    url.to_string(),
+                    vec![Selector::new(
+                        "h1".to_string(),
+                        selector.to_string(),
+                        SelectorType::Number,
+                    )],
+                )
+            })
+            .collect();
+        Config::new(name.to_string(), "".to_string(), resources)
+    }
+
+    #[test]
+    fn test_merge() -> Result<()> {
+        let mut first = config("first", &[("https://a.com", "price")]);
+        first.rate_limit = Some("2/s".parse()?);
+        let mut second = config("second", &[("https://b.com", "rate")]);
+        second.description = "Rates".to_string();
+        second.rate_limit = Some("1/s".parse()?);
+        second.webhook = Some("https://hooks.example.com".to_string());
+        second.respect_robots = true;
+        second.redact = vec!["token=([^&]+)".to_string()];
+
+        let (merged, warnings) = merge(vec![first, second]);
+        assert_eq!(merged.name, "first");
+        assert_eq!(merged.description, "Rates");
+        assert_eq!(merged.resources.len(), 2);
+        assert_eq!(merged.rate_limit, Some("2/s".parse()?));
+        assert_eq!(merged.webhook.as_deref(), Some("https://hooks.example.com"));
+        assert!(merged.respect_robots);
+        assert_eq!(merged.redact.len(), 1);
+        assert_eq!(
+            warnings,
+            vec!["rate_limit of \"second\" is ignored, the merged config keeps the first one"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split() {
+        let mut big = config(
+            "big",
+            &[
+                ("https://a.com/1", "price"),
+                ("https://b.com", "rate"),
+                ("https://a.com/2", "volume"),
+            ],
+        );
+        big.computed = vec![
+            Computed {
+                name: "change".to_string(),
+                expr: "price - prev_price".to_string(),
+            },
+            Computed {
+                name: "total".to_string(),
+                expr: "price * volume".to_string(),
+            },
+        ];
+
+        let parts = split(&big, false);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["big-1", "big-2", "big-3"]);
+        assert_eq!(parts[0].computed.len(), 1);
+        assert!(parts[1].computed.is_empty());
+
+        let parts = split(&big, true);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["big-a.com", "big-b.com"]);
+        assert_eq!(parts[0].resources.len(), 2);
+        assert_eq!(parts[0].computed.len(), 2);
+    }
+
+    #[test]
+    fn test_convert_round_trip() -> Result<()> {
+        let dir = std::env::temp_dir().join("rvp_test_config_convert");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut original = config("stock", &[("https://a.com/%%", "price")]);
+        original.rate_limit = Some("2/s".parse()?);
+        original.resources[0].selectors[0].alert_below = Some(100.0);
+
+        let json = original.save(&dir.join("stock.json"), &ConfigFormat::Json)?;
+        let converted = load(&json)?;
+        let toml = converted.save(&json.with_extension("toml"), &ConfigFormat::Toml)?;
+        assert_eq!(load(&toml)?.to_toml(), original.to_toml());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::{value_parser, Parser, Subcommand};
+use rvp::{
+    error::RvpError,
+    fetcher::{Fetcher, Request},
+    scalper::{extract, ParsedValue},
+    structure::{Config, ConfigFormat},
+};
+use scraper::Html;
+
+use crate::context::Context;
+use crate::output::{generate_table, write_to_file};
+
+/// Developer utilities to inspect how the pages are fetched and parsed.
+#[derive(Parser)]
+#[command(hide = true)]
+pub struct Args {
+    #[command(subcommand)]
+    command: DebugCommands,
+}
+
+#[derive(Subcommand)]
+enum DebugCommands {
+    /// Fetch the page and print the raw HTML as it is seen by the parser.
+    Fetch {
+        /// URL of the page to fetch.
+        #[arg(short, long, value_name = "URL")]
+        url: String,
+
+        /// (Optional) Write the HTML to the given file instead of stdout.
+        #[arg(short, long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+        output: Option<PathBuf>,
+    },
+    /// Parse a local HTML file with the selectors of the config file.
+    ///
+    /// Useful together with `debug fetch --output` to tune the selectors without hitting the site.
+    Parse {
+        /// Path to the config file.
+        #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf))]
+        path: PathBuf,
+
+        /// Path to the HTML file to parse.
+        #[arg(short, long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+        file: PathBuf,
+    },
+    /// Show the entries of the cache directory: the remote configs and the parsed configs.
+    Cache {
+        /// (Optional) Remove the cached entries instead.
+        #[arg(long)]
+        clear: bool,
+    },
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    match args.command {
+        DebugCommands::Fetch { url, output } => {
+            ctx.log(format!("Fetching {}", url));
+            let request = Request::get(&url).with_local_files();
+            let html = ctx.fetcher.fetch_document(&request).await?.text;
+            match output {
+                Some(path) => write_to_file(&path, &html, false)?,
+                None => println!("{}", html),
+            }
+        }
+        DebugCommands::Parse { path, file } => {
+            if !path.exists() {
+                return Err(RvpError::NotFoundError("File does not exist!".to_string()).into());
+            }
+            let mut config = Config::from_file(&path, &ConfigFormat::from_path(&path)?)?;
+            config.apply_groups()?;
+            let values = parse_file(&config, &fs::read_to_string(file)?);
+
+            let mut table = generate_table(&values, ctx.max_value_chars());
+            ctx.style_table(&mut table);
+            println!("{}", table);
+            for value in values.iter().filter(|v| v.error.is_some()) {
+                eprintln!(
+                    "{}: {}",
+                    value.name,
+                    value.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        DebugCommands::Cache { clear } => {
+            let Some(dir) = ctx.cache_dir() else {
+                return Err(RvpError::NotFoundError(
+                    "Could not find the cache directory!".to_string(),
+                )
+                .into());
+            };
+            let entries = cache_entries(&dir)?;
+            println!("Cache: {}", dir.display());
+            for entry in entries.iter() {
+                println!(
+                    "{}: {} files, {} bytes",
+                    entry.name, entry.files, entry.bytes
+                );
+            }
+            if clear {
+                for entry in entries.iter() {
+                    let path = dir.join(&entry.name);
+                    match path.is_dir() {
+                        true => fs::remove_dir_all(&path)?,
+                        false => fs::remove_file(&path)?,
+                    }
+                }
+                println!("Removed {} entries", entries.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A top level entry of the cache directory, e.g. `remote`
+#[derive(Debug, PartialEq)]
+struct CacheEntry {
+    name: String,
+    /// Number of the files in it, 1 for a file
+    files: usize,
+    /// Total size of the files
+    bytes: u64,
+}
+
+/// It returns the entries of the cache directory sorted by name, none if it doesn't exist
+fn cache_entries(dir: &Path) -> Result<Vec<CacheEntry>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let (mut files, mut bytes) = (0, 0);
+        let mut pending = vec![entry.path()];
+        while let Some(path) = pending.pop() {
+            match path.is_dir() {
+                true => {
+                    for child in fs::read_dir(&path)? {
+                        pending.push(child?.path());
+                    }
+                }
+                false => {
+                    files += 1;
+                    bytes += fs::metadata(&path)?.len();
+                }
+            }
+        }
+        entries.push(CacheEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            files,
+            bytes,
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Applies the selectors of every resource in the config to the same HTML document
+fn parse_file(config: &Config, html: &str) -> Vec<ParsedValue> {
+    let document = Html::parse_document(html);
+    config
+        .resources
+        .iter()
+        .flat_map(|resource| extract(&document, &resource.selectors))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::structure::{Resource, Selector, SelectorType};
+    use serde_json::Value;
+
+    #[test]
+    fn test_parse_file() {
+        let config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![Resource::new(
+                "https://example.com".to_string(),
+                vec![
+                    Selector::new("h1".to_string(), "title".to_string(), SelectorType::String),
+                    Selector::new(
+                        "#price".to_string(),
+                        "price".to_string(),
+                        SelectorType::Number,
+                    ),
+                ],
+            )],
+        );
+
+        let values = parse_file(
+            &config,
+            "<html><body><h1>Hello</h1><span id=\"price\">1.5</span></body></html>",
+        );
+
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].value, Value::String("Hello".to_string()));
+        assert_eq!(values[1].value, Value::from(1.5));
+    }
+
+    #[test]
+    fn test_cache_entries() {
+        let dir = std::env::temp_dir().join("rvp_test_debug_cache");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(cache_entries(&dir).unwrap().is_empty());
+
+        fs::create_dir_all(dir.join("remote").join("a")).unwrap();
+        fs::write(dir.join("remote").join("a").join("config.toml"), "abc").unwrap();
+        fs::write(dir.join("remote").join("b.toml"), "de").unwrap();
+        fs::create_dir_all(dir.join("configs")).unwrap();
+        assert_eq!(
+            cache_entries(&dir).unwrap(),
+            vec![
+                CacheEntry {
+                    name: "configs".to_string(),
+                    files: 0,
+                    bytes: 0,
+                },
+                CacheEntry {
+                    name: "remote".to_string(),
+                    files: 2,
+                    bytes: 5,
+                },
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::{SecondsFormat, Utc};
+use clap::{value_parser, Parser};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use rvp::error::RvpError;
+use rvp::scalper::ResourceResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::batch::{
+    expand_sitemaps, lease_resources, record_run, report_errors, scrape, ConfigArgs, WebhookArgs,
+};
+use crate::context::Context;
+use crate::output::truncate;
+use crate::paths;
+
+/// Directory of the snapshots inside the data directory
+const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Show only the values which changed since the previous run of the config
+///
+/// The values of every run are saved as a snapshot in the data directory
+/// (`$XDG_DATA_HOME/rvp/snapshots`), the next run is compared with it.
+#[derive(Parser)]
+pub struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Output the changes in JSON format
+    #[arg(long)]
+    json: bool,
+
+    /// (Optional) Path of the snapshot file, instead of `<config name>.json` in the data directory
+    #[arg(long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+    snapshot: Option<PathBuf>,
+
+    #[command(flatten)]
+    webhook: WebhookArgs,
+}
+
+/// The values of the last run of the config
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot {
+    /// UTC time of the run in RFC 3339 format
+    time: String,
+    values: Vec<SnapshotValue>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct SnapshotValue {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    param: Option<String>,
+    name: String,
+    value: Value,
+}
+
+/// A changed value, `old` is missing for the new values
+#[derive(Serialize, PartialEq, Debug)]
+struct Change {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    param: Option<String>,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<Value>,
+    new: Value,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let config = args.config.load(ctx).await?;
+    let webhook = args.webhook.prepare(&config, ctx)?;
+    let path = match (args.snapshot, ctx.data_dir()) {
+        (Some(path), _) => path,
+        (None, Some(dir)) => dir
+            .join(SNAPSHOTS_DIR)
+            .join(format!("{}.json", paths::file_name(&config.name))),
+        (None, None) => {
+            return Err(
+                RvpError::NotFoundError("Could not find the data directory!".to_string()).into(),
+            )
+        }
+    };
+
+    let config_name = config.name.clone();
+    let config = lease_resources(config, ctx).await?;
+    let config = expand_sitemaps(config, ctx).await?;
+    let results = scrape(config, ctx).await;
+    record_run(ctx, "diff", &config_name, &results);
+
+    let previous = match path.exists() {
+        true => Some(serde_json::from_str::<Snapshot>(&fs::read_to_string(
+            &path,
+        )?)?),
+        false => None,
+    };
+    let is_first = previous.is_none();
+    let current = snapshot_values(&results);
+    let changes = compare(previous.as_ref(), &current);
+    save_snapshot(&path, previous, current)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+    } else if is_first {
+        println!(
+            "Saved the first snapshot to {}, the next run shows the changes",
+            path.display()
+        );
+    } else if changes.is_empty() {
+        println!("No changes");
+    } else {
+        let mut table = generate_changes_table(&changes, ctx.max_value_chars());
+        ctx.style_table(&mut table);
+        println!("{}", table);
+    }
+
+    if let (Some(webhook), false) = (&webhook, changes.is_empty()) {
+        let body = json!({ "config": config_name, "changes": changes }).to_string();
+        webhook.deliver(body, ctx).await?;
+    }
+
+    if !ctx.quiet {
+        report_errors(&results, ctx);
+    }
+    Ok(())
+}
+
+/// It collects the parsed values, the failed ones are left out
+fn snapshot_values(results: &[ResourceResult]) -> Vec<SnapshotValue> {
+    results
+        .iter()
+        .filter_map(|r| r.values.as_ref().ok().map(|values| (r, values)))
+        .flat_map(|(r, values)| {
+            values
+                .iter()
+                .filter(|v| v.error.is_none())
+                .map(|v| SnapshotValue {
+                    param: r.param.clone(),
+                    name: v.name.clone(),
+                    value: v.value.clone(),
+                })
+        })
+        .collect()
+}
+
+/// It returns the values which differ from the snapshot. Without a snapshot there is
+/// nothing to compare with, so there are no changes.
+fn compare(previous: Option<&Snapshot>, current: &[SnapshotValue]) -> Vec<Change> {
+    let Some(previous) = previous else {
+        return vec![];
+    };
+
+    current
+        .iter()
+        .filter_map(|value| {
+            let old = previous
+                .values
+                .iter()
+                .find(|old| old.param == value.param && old.name == value.name);
+            match old {
+                Some(old) if old.value == value.value => None,
+                old => Some(Change {
+                    param: value.param.clone(),
+                    name: value.name.clone(),
+                    old: old.map(|old| old.value.clone()),
+                    new: value.value.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// It saves the current values over the previous ones, so the values which failed in this
+/// run (or belong to other parameters) are kept for the next comparison
+fn save_snapshot(
+    path: &Path,
+    previous: Option<Snapshot>,
+    current: Vec<SnapshotValue>,
+) -> Result<()> {
+    let mut snapshot = previous.unwrap_or_default();
+    for value in current {
+        let old = snapshot
+            .values
+            .iter_mut()
+            .find(|old| old.param == value.param && old.name == value.name);
+        match old {
+            Some(old) => *old = value,
+            None => snapshot.values.push(value),
+        }
+    }
+    snapshot.time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&snapshot)?)?;
+    Ok(())
+}
+
+/// Generate table of the changes with the old and the new values, truncated to `max_chars`
+fn generate_changes_table(changes: &[Change], max_chars: Option<usize>) -> Table {
+    let cell = |value: &Value| match max_chars {
+        Some(max_chars) => truncate(&value.to_string(), max_chars),
+        None => value.to_string(),
+    };
+    let with_params = changes.iter().any(|c| c.param.is_some());
+    let mut header = vec!["Name", "Old", "New"];
+    if with_params {
+        header.insert(0, "Param");
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(header);
+    for change in changes {
+        let mut row = vec![
+            change.name.clone(),
+            change.old.as_ref().map_or("(new)".to_string(), cell),
+            cell(&change.new),
+        ];
+        if with_params {
+            row.insert(0, change.param.clone().unwrap_or_default());
+        }
+        table.add_row(row);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(param: Option<&str>, name: &str, value: Value) -> SnapshotValue {
+        SnapshotValue {
+            param: param.map(str::to_string),
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_compare_and_save() -> Result<()> {
+        let current = vec![
+            value(Some("AAPL"), "price", json!(1.5)),
+            value(Some("AAPL"), "title", json!("Apple")),
+        ];
+        assert!(compare(None, &current).is_empty());
+
+        let path = std::env::temp_dir().join("rvp_test_diff/snapshot.json");
+        let _ = fs::remove_file(&path);
+        save_snapshot(&path, None, current)?;
+        let previous: Snapshot = serde_json::from_str(&fs::read_to_string(&path)?)?;
+
+        let current = vec![
+            value(Some("AAPL"), "price", json!(1.4)),
+            value(Some("AAPL"), "title", json!("Apple")),
+            value(Some("MSFT"), "price", json!(3.0)),
+        ];
+        let changes = compare(Some(&previous), &current);
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    param: Some("AAPL".to_string()),
+                    name: "price".to_string(),
+                    old: Some(json!(1.5)),
+                    new: json!(1.4),
+                },
+                Change {
+                    param: Some("MSFT".to_string()),
+                    name: "price".to_string(),
+                    old: None,
+                    new: json!(3.0),
+                },
+            ]
+        );
+
+        // The values missing in the run are kept
+        save_snapshot(&path, Some(previous), vec![value(None, "other", json!(1))])?;
+        let saved: Snapshot = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(saved.values.len(), 3);
+        Ok(())
+    }
+}
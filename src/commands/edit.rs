@@ -1,48 +1,182 @@
-use std::{ffi::OsStr, path::PathBuf};
+use std::path::PathBuf;
 
-use crate::structure::{
-    Config, ConfigFormat, Position, Resource, Selector, SelectorType, URL_PARAM_PLACEHOLDER,
-};
-use anyhow::{anyhow, Result};
-use clap::{value_parser, Parser};
+use crate::context::Context;
+use anyhow::Result;
+use clap::{value_parser, Parser, Subcommand};
 use inquire::{
     required,
     validator::Validation::{Invalid, Valid},
-    Confirm, Select, Text,
+    Confirm, MultiSelect, Select, Text,
+};
+use rvp::error::RvpError;
+use rvp::structure::{
+    Config, ConfigFormat, Position, Resource, ResourceKind, Selector, SelectorType,
+    URL_PARAM_PLACEHOLDER,
 };
+use rvp::validate::{duplicates, remove_duplicates, validate, Duplicate};
 use validator::ValidateUrl;
 
+use super::new::{ask_selector_path, parse_selector, parse_selector_type, KindArg, Preview};
+
 /// Edit config file
+///
+/// The selectors repeated in the resources with the same URL are offered for removal first.
+/// The subcommands add, remove or change the resources and the selectors without the prompts,
+/// e.g. `rvp edit add-selector -p cfg.toml -r 1 -n price -s div.price -t number`.
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
+    /// Path to the config file.
+    #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf), required = true)]
+    path: Option<PathBuf>,
+
+    /// Don't fetch the pages to preview the values matched by the selectors.
+    #[arg(long)]
+    no_preview: bool,
+
+    #[command(subcommand)]
+    command: Option<EditCommands>,
+}
+
+#[derive(Subcommand)]
+enum EditCommands {
+    /// Add a resource with the given selectors.
+    AddResource {
+        /// Path to the config file.
+        #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf))]
+        path: PathBuf,
+
+        /// URL of the resource.
+        #[arg(short, long, value_name = "URL")]
+        url: String,
+
+        /// (Optional) Response type of the resource, `html` by default.
+        #[arg(long, value_name = "KIND")]
+        kind: Option<KindArg>,
+
+        /// Selector of the resource, can be repeated. The type is `string` (default),
+        /// `number`, `boolean` or `date`.
+        ///
+        /// Example: `--selector "price=#quote > span:number"`
+        #[arg(short, long = "selector", value_name = "NAME=PATH:TYPE", value_parser = parse_selector)]
+        selectors: Vec<Selector>,
+    },
+    /// Remove the resource with its selectors.
+    RemoveResource {
+        #[command(flatten)]
+        target: ResourceTarget,
+    },
+    /// Add a selector to the resource.
+    AddSelector {
+        #[command(flatten)]
+        target: ResourceTarget,
+
+        /// Name of the selector.
+        #[arg(short, long, value_name = "NAME")]
+        name: String,
+
+        /// Path of the selector, e.g. `div.price`.
+        #[arg(short, long, value_name = "PATH")]
+        selector: String,
+
+        /// Type of the value: `string`, `number`, `boolean` or `date`.
+        #[arg(short = 't', long = "type", value_name = "TYPE", default_value = "string", value_parser = parse_selector_type)]
+        parsed_type: SelectorType,
+    },
+    /// Remove the selector from the resource.
+    RemoveSelector {
+        #[command(flatten)]
+        target: ResourceTarget,
+
+        /// Name of the selector.
+        #[arg(short, long, value_name = "NAME")]
+        name: String,
+    },
+    /// Change the path, the type or the name of the selector of the resource.
+    SetSelector {
+        #[command(flatten)]
+        target: ResourceTarget,
+
+        /// Name of the selector.
+        #[arg(short, long, value_name = "NAME")]
+        name: String,
+
+        /// (Optional) New path of the selector.
+        #[arg(short, long, value_name = "PATH")]
+        selector: Option<String>,
+
+        /// (Optional) New type of the value: `string`, `number`, `boolean` or `date`.
+        #[arg(short = 't', long = "type", value_name = "TYPE", value_parser = parse_selector_type)]
+        parsed_type: Option<SelectorType>,
+
+        /// (Optional) New name of the selector.
+        #[arg(long, value_name = "NAME")]
+        rename: Option<String>,
+    },
+    /// Disable the resource, it is kept in the config but not fetched.
+    DisableResource {
+        #[command(flatten)]
+        target: ResourceTarget,
+    },
+    /// Enable the disabled resource again.
+    EnableResource {
+        #[command(flatten)]
+        target: ResourceTarget,
+    },
+}
+
+/// The config file and its resource changed by a subcommand
+#[derive(clap::Args)]
+struct ResourceTarget {
     /// Path to the config file.
     #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf))]
     path: PathBuf,
+
+    /// URL of the resource or its position in the config, starting from 1.
+    #[arg(short, long, value_name = "RESOURCE")]
+    resource: String,
 }
 
-pub async fn command(args: Args) -> Result<()> {
-    if !args.path.exists() {
-        return Err(anyhow!("File does not exist!"));
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let path = match args.command {
+        Some(command) => return edit_with(command, ctx),
+        None => args.path.unwrap_or_default(),
+    };
+    if !path.exists() {
+        return Err(RvpError::NotFoundError("File does not exist!".to_string()).into());
     }
 
-    let config_format = match args.path.extension().and_then(OsStr::to_str) {
-        Some("json") => ConfigFormat::Json,
-        Some("toml") => ConfigFormat::Toml,
-        _ => return Err(anyhow!("Invalid file format!")),
-    };
+    let config_format = ConfigFormat::from_path(&path)?;
 
-    let mut config = Config::from_file(&args.path, &config_format)?;
+    let mut config = Config::from_file(&path, &config_format)?;
+    dedupe(&mut config)?;
 
     'resource_loop: loop {
+        // The menu is rendered from the current config state, so the changes are visible right away
         let resource =
             Select::new("Select resource to edit:", config.resources.clone()).prompt()?;
+        let Some(resource_index) = config.resources.try_position(&resource) else {
+            println!("{}", NOT_FOUND_MESSAGE);
+            continue 'resource_loop;
+        };
 
-        let actions = vec!["Edit URL", "Edit selectors", "Delete", "↩ Back", "⏹ Exit"];
+        let actions = vec![
+            "Edit URL",
+            "Change response type",
+            "Edit selectors",
+            match resource.enabled {
+                true => "Disable",
+                false => "Enable",
+            },
+            "Delete",
+            "↩ Back",
+            "⏹ Exit",
+        ];
         let action = Select::new("Select action:", actions).prompt()?;
 
         match action {
             "Edit URL" => {
-                config.resources[&resource].url = Text::new("Site URL:")
+                config.resources[resource_index].url = Text::new("Site URL:")
                     .with_validator(required!("This field is required"))
                     .with_initial_value(&resource.url)
                     .with_help_message(
@@ -54,16 +188,23 @@ pub async fn command(args: Args) -> Result<()> {
                     })
                     .prompt()?;
             }
+            "Change response type" => {
+                config.resources[resource_index].kind =
+                    Some(Select::new("Response type:", ResourceKind::list_as_vec()).prompt()?);
+            }
             "Edit selectors" => {
-                edit_selectors(&mut config, &resource)?;
+                let mut preview = Preview::new(&resource, !args.no_preview);
+                edit_selectors(&mut config.resources[resource_index], &mut preview, ctx).await?;
+            }
+            "Disable" | "Enable" => {
+                config.resources[resource_index].enabled = !resource.enabled;
             }
             "Delete" => {
                 if Confirm::new("Are you sure you want to delete this resource?")
                     .with_default(false)
                     .prompt()?
                 {
-                    let index = config.resources.position(&resource);
-                    config.resources.remove(index);
+                    config.resources.remove(resource_index);
                 }
             }
             "↩ Back" => continue 'resource_loop,
@@ -82,18 +223,266 @@ pub async fn command(args: Args) -> Result<()> {
         }
     }
 
-    match Confirm::new("Save changes?").with_default(true).prompt()? {
-        true => {
-            config.save(&config_format)?;
+    let options = vec!["Save", "Save as…", "Discard changes"];
+    match Select::new("Save changes?", options).prompt()? {
+        "Save" => {
+            check_before_save(&config, ctx)?;
+            config.save(&path, &config_format)?;
             println!("Config file saved!");
         }
-        false => println!("Changes discarded."),
+        "Save as…" => {
+            let path = Text::new("Save as:")
+                .with_validator(required!("This field is required"))
+                .with_help_message(
+                    "e.g. ./configs/stocks.toml, the format is detected by extension",
+                )
+                .with_initial_value(&path.display().to_string())
+                .with_validator(|input: &str| {
+                    match ConfigFormat::from_path(&PathBuf::from(input)) {
+                        Ok(_) => Ok(Valid),
+                        Err(e) => Ok(Invalid(e.to_string().into())),
+                    }
+                })
+                .prompt()?;
+            let path = PathBuf::from(path);
+            check_before_save(&config, ctx)?;
+            let path = config.save(&path, &ConfigFormat::from_path(&path)?)?;
+            println!("Config file saved to {}", path.display());
+        }
+        "Discard changes" => println!("Changes discarded."),
+        _ => unreachable!(),
     }
 
     Ok(())
 }
 
-fn edit_selectors(config: &mut Config, resource: &Resource) -> Result<()> {
+/// It applies the change of the subcommand to the config file and saves it
+fn edit_with(command: EditCommands, ctx: &Context) -> Result<()> {
+    let path = match &command {
+        EditCommands::AddResource { path, .. } => path.clone(),
+        EditCommands::RemoveResource { target }
+        | EditCommands::AddSelector { target, .. }
+        | EditCommands::RemoveSelector { target, .. }
+        | EditCommands::SetSelector { target, .. }
+        | EditCommands::DisableResource { target }
+        | EditCommands::EnableResource { target } => target.path.clone(),
+    };
+    if !path.exists() {
+        return Err(RvpError::NotFoundError("File does not exist!".to_string()).into());
+    }
+    let config_format = ConfigFormat::from_path(&path)?;
+    let mut config = Config::from_file(&path, &config_format)?;
+    for line in apply(&mut config, command)? {
+        println!("{}", line);
+    }
+    check_before_save(&config, ctx)?;
+    config.save(&path, &config_format)?;
+    println!("Config file saved!");
+    Ok(())
+}
+
+/// It refuses to save the config with the problems found by [validate], so an edit can't
+/// break a working config
+fn check_before_save(config: &Config, ctx: &Context) -> Result<()> {
+    let problems = validate(config);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    if !ctx.quiet {
+        for problem in problems.iter() {
+            eprintln!("- {}", problem);
+        }
+    }
+    Err(RvpError::ConfigError(format!(
+        "Found {} problem(s), the config was not saved",
+        problems.len()
+    ))
+    .into())
+}
+
+/// It changes the config as the subcommand says, and returns the changed resources
+fn apply(config: &mut Config, command: EditCommands) -> Result<Vec<String>> {
+    let changed = match command {
+        EditCommands::AddResource {
+            url,
+            kind,
+            selectors,
+            ..
+        } => {
+            if !url.contains(URL_PARAM_PLACEHOLDER) && !ValidateUrl::validate_url(&url) {
+                return Err(RvpError::ConfigError(format!("Invalid URL \"{}\"!", url)).into());
+            }
+            config.resources.push(Resource {
+                kind: kind.map(ResourceKind::from),
+                ..Resource::new(url, selectors)
+            });
+            vec![config.resources.len() - 1]
+        }
+        EditCommands::RemoveResource { target } => {
+            let indexes = find_resources(config, &target.resource)?;
+            let mut removed = Vec::new();
+            for index in indexes.into_iter().rev() {
+                removed.insert(0, format!("Removed {}", config.resources.remove(index)));
+            }
+            return Ok(removed);
+        }
+        EditCommands::AddSelector {
+            target,
+            name,
+            selector,
+            parsed_type,
+        } => {
+            let indexes = find_resources(config, &target.resource)?;
+            for &index in indexes.iter() {
+                let resource = &mut config.resources[index];
+                if resource.selectors.iter().any(|s| s.name == name) {
+                    return Err(RvpError::ConfigError(format!(
+                        "Selector \"{}\" already exists in resource #{}!",
+                        name,
+                        index + 1
+                    ))
+                    .into());
+                }
+                resource.selectors.push(Selector::new(
+                    selector.clone(),
+                    name.clone(),
+                    parsed_type.clone(),
+                ));
+            }
+            indexes
+        }
+        EditCommands::RemoveSelector { target, name } => {
+            let indexes = find_resources(config, &target.resource)?;
+            for &index in indexes.iter() {
+                let position = find_selector(&config.resources[index], index, &name)?;
+                config.resources[index].selectors.remove(position);
+            }
+            indexes
+        }
+        EditCommands::SetSelector {
+            target,
+            name,
+            selector,
+            parsed_type,
+            rename,
+        } => {
+            let indexes = find_resources(config, &target.resource)?;
+            for &index in indexes.iter() {
+                let position = find_selector(&config.resources[index], index, &name)?;
+                let current = &mut config.resources[index].selectors[position];
+                if let Some(path) = &selector {
+                    current.path = path.clone();
+                }
+                if let Some(parsed_type) = &parsed_type {
+                    current.parsed_type = parsed_type.clone();
+                }
+                if let Some(rename) = &rename {
+                    current.name = rename.clone();
+                }
+            }
+            indexes
+        }
+        EditCommands::DisableResource { target } => set_enabled(config, &target, false)?,
+        EditCommands::EnableResource { target } => set_enabled(config, &target, true)?,
+    };
+    Ok(changed
+        .into_iter()
+        .map(|index| config.resources[index].to_string())
+        .collect())
+}
+
+/// It enables or disables the resources matching the URL or the position
+fn set_enabled(config: &mut Config, target: &ResourceTarget, enabled: bool) -> Result<Vec<usize>> {
+    let indexes = find_resources(config, &target.resource)?;
+    for &index in indexes.iter() {
+        config.resources[index].enabled = enabled;
+    }
+    Ok(indexes)
+}
+
+/// It returns the position of the selector with the given name in the resource
+fn find_selector(resource: &Resource, index: usize, name: &str) -> Result<usize> {
+    resource
+        .selectors
+        .iter()
+        .position(|s| s.name == name)
+        .ok_or_else(|| {
+            RvpError::NotFoundError(format!(
+                "Selector \"{}\" was not found in resource #{}!",
+                name,
+                index + 1
+            ))
+            .into()
+        })
+}
+
+/// It returns the indexes of the resources with the given URL, or the resource at the
+/// given position starting from 1
+pub(crate) fn find_resources(config: &Config, resource: &str) -> Result<Vec<usize>> {
+    let indexes: Vec<usize> = match resource.parse::<usize>() {
+        Ok(position) if position >= 1 && position <= config.resources.len() => {
+            vec![position - 1]
+        }
+        Ok(_) => vec![],
+        Err(_) => config
+            .resources
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.url == resource)
+            .map(|(i, _)| i)
+            .collect(),
+    };
+    if indexes.is_empty() {
+        return Err(RvpError::NotFoundError(format!(
+            "Resource \"{}\" was not found in the config!",
+            resource
+        ))
+        .into());
+    }
+    Ok(indexes)
+}
+
+/// It offers to remove the selectors repeated in the resources with the same URL.
+///
+/// The selectors with the same name but another path are only reported, one of them
+/// should be renamed.
+fn dedupe(config: &mut Config) -> Result<()> {
+    let (same_path, same_name): (Vec<Duplicate>, Vec<Duplicate>) =
+        duplicates(config).into_iter().partition(|d| d.same_path);
+    for duplicate in same_name.iter() {
+        println!("Warning: {}", duplicate);
+    }
+    if same_path.is_empty() {
+        return Ok(());
+    }
+
+    let options: Vec<String> = same_path.iter().map(|d| d.to_string()).collect();
+    let chosen = MultiSelect::new(
+        &format!("Found {} duplicate selectors, remove:", options.len()),
+        options,
+    )
+    .with_help_message("The resources left without selectors are removed too")
+    .raw_prompt()?;
+
+    let chosen: Vec<Duplicate> = same_path
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| chosen.iter().any(|option| option.index == *i))
+        .map(|(_, d)| d)
+        .collect();
+    let removed = remove_duplicates(config, &chosen);
+    println!("Removed {} duplicate selectors", removed);
+    Ok(())
+}
+
+/// Shown when the chosen item can't be found in the config anymore
+const NOT_FOUND_MESSAGE: &str = "The item was not found in the config, please select it again.";
+
+async fn edit_selectors(
+    resource: &mut Resource,
+    preview: &mut Preview,
+    ctx: &Context,
+) -> Result<()> {
     'edit_selectors: loop {
         let action = Select::new(
             "Select action:",
@@ -103,28 +492,26 @@ fn edit_selectors(config: &mut Config, resource: &Resource) -> Result<()> {
 
         match action {
             "Add selector" => {
-                let path = Text::new("Selector path:")
-                    .with_validator(required!("This field is required"))
-                    .with_help_message("e.g. body > div > h1")
-                    .prompt()?;
+                let path = ask_selector_path("Selector path:", None, preview, ctx).await?;
                 let name = Text::new("Selector name:")
                     .with_validator(required!("This field is required"))
                     .with_help_message("e.g. title")
                     .prompt()?;
                 let parsed_type =
                     Select::new("Selector type:", SelectorType::list_as_vec()).prompt()?;
-                config.resources[resource]
+                resource
                     .selectors
                     .push(Selector::new(path, name, parsed_type));
             }
             "Edit selectors" => 'selectors_loop: loop {
-                let selector = Select::new(
-                    "Choose selector to edit:",
-                    // TODO: Fix bug: selector names are not updated in the cloned vector
-                    config.resources[resource].selectors.clone(),
-                )
-                .with_help_message("Choose selector to edit or delete.")
-                .prompt()?;
+                // Rendered from the current state of the resource on each iteration
+                let selector = Select::new("Choose selector to edit:", resource.selectors.clone())
+                    .with_help_message("Choose selector to edit or delete.")
+                    .prompt()?;
+                let Some(selector_index) = resource.selectors.try_position(&selector) else {
+                    println!("{}", NOT_FOUND_MESSAGE);
+                    continue 'selectors_loop;
+                };
 
                 let actions = vec![
                     "Rename",
@@ -136,9 +523,10 @@ fn edit_selectors(config: &mut Config, resource: &Resource) -> Result<()> {
                 ];
                 let action = Select::new("Select action:", actions).prompt()?;
 
+                let current = &mut resource.selectors[selector_index];
                 match action {
                     "Rename" => {
-                        config.resources[resource].selectors[&selector].name = Text::new("Name:")
+                        current.name = Text::new("Name:")
                             .with_validator(required!("This field is required"))
                             .with_help_message("e.g. title")
                             .with_initial_value(&selector.name)
@@ -146,15 +534,12 @@ fn edit_selectors(config: &mut Config, resource: &Resource) -> Result<()> {
                         break 'selectors_loop;
                     }
                     "Edit" => {
-                        config.resources[resource].selectors[&selector].path = Text::new("Path:")
-                            .with_validator(required!("This field is required"))
-                            .with_help_message("e.g. body > div > h1")
-                            .with_initial_value(&selector.path)
-                            .prompt()?;
+                        current.path =
+                            ask_selector_path("Path:", Some(&selector.path), preview, ctx).await?;
                         break 'selectors_loop;
                     }
                     "Change type" => {
-                        config.resources[resource].selectors[&selector].parsed_type =
+                        current.parsed_type =
                             Select::new("Selector type:", SelectorType::list_as_vec()).prompt()?;
                         break 'selectors_loop;
                     }
@@ -163,8 +548,7 @@ fn edit_selectors(config: &mut Config, resource: &Resource) -> Result<()> {
                             .with_default(false)
                             .prompt()?
                         {
-                            let index = config.resources[resource].selectors.position(&selector);
-                            config.resources[resource].selectors.remove(index);
+                            resource.selectors.remove(selector_index);
                             break 'selectors_loop;
                         }
                     }
@@ -190,3 +574,125 @@ fn edit_selectors(config: &mut Config, resource: &Resource) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_resources() -> Result<()> {
+        let config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![
+                Resource::new("https://a.com".to_string(), vec![]),
+                Resource::new("https://b.com".to_string(), vec![]),
+                Resource::new("https://a.com".to_string(), vec![]),
+            ],
+        );
+        assert_eq!(find_resources(&config, "2")?, vec![1]);
+        assert_eq!(find_resources(&config, "https://a.com")?, vec![0, 2]);
+        assert!(find_resources(&config, "0").is_err());
+        assert!(find_resources(&config, "4").is_err());
+        assert!(find_resources(&config, "https://c.com").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply() -> Result<()> {
+        let target = |resource: &str| ResourceTarget {
+            path: PathBuf::from("test.toml"),
+            resource: resource.to_string(),
+        };
+        let mut config = Config::new("test".to_string(), "".to_string(), vec![]);
+
+        apply(
+            &mut config,
+            EditCommands::AddResource {
+                path: PathBuf::from("test.toml"),
+                url: "https://a.com".to_string(),
+                kind: None,
+                selectors: vec![],
+            },
+        )?;
+        assert!(apply(
+            &mut config,
+            EditCommands::AddResource {
+                path: PathBuf::from("test.toml"),
+                url: "not a url".to_string(),
+                kind: None,
+                selectors: vec![],
+            },
+        )
+        .is_err());
+
+        let add_price = || EditCommands::AddSelector {
+            target: target("https://a.com"),
+            name: "price".to_string(),
+            selector: "div.price".to_string(),
+            parsed_type: SelectorType::Number,
+        };
+        apply(&mut config, add_price())?;
+        assert!(apply(&mut config, add_price()).is_err());
+        assert_eq!(config.resources[0].selectors[0].path, "div.price");
+
+        apply(
+            &mut config,
+            EditCommands::SetSelector {
+                target: target("1"),
+                name: "price".to_string(),
+                selector: Some("span.price".to_string()),
+                parsed_type: None,
+                rename: Some("cost".to_string()),
+            },
+        )?;
+        let selector = &config.resources[0].selectors[0];
+        assert_eq!(selector.name, "cost");
+        assert_eq!(selector.path, "span.price");
+        assert!(selector.parsed_type == SelectorType::Number);
+
+        let remove_cost = || EditCommands::RemoveSelector {
+            target: target("1"),
+            name: "cost".to_string(),
+        };
+        apply(&mut config, remove_cost())?;
+        assert!(config.resources[0].selectors.is_empty());
+        assert!(apply(&mut config, remove_cost()).is_err());
+
+        apply(
+            &mut config,
+            EditCommands::DisableResource {
+                target: target("1"),
+            },
+        )?;
+        assert!(!config.resources[0].enabled);
+
+        let removed = apply(
+            &mut config,
+            EditCommands::RemoveResource {
+                target: target("1"),
+            },
+        )?;
+        assert_eq!(removed.len(), 1);
+        assert!(config.resources.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_before_save() {
+        let ctx = Context::default();
+        let title = Selector::new("h1".to_string(), "title".to_string(), SelectorType::String);
+        let mut config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![Resource::new("https://a.com".to_string(), vec![title])],
+        );
+        assert!(check_before_save(&config, &ctx).is_ok());
+
+        config.resources.clear();
+        assert_eq!(
+            check_before_save(&config, &ctx).unwrap_err().to_string(),
+            "Found 1 problem(s), the config was not saved"
+        );
+    }
+}
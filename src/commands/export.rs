@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{value_parser, Parser, ValueEnum};
+use rvp::structure::{fill_env_vars, Auth, Config};
+
+use super::batch::ConfigArgs;
+use crate::context::Context;
+use crate::output::write_to_file;
+
+/// Export the requests of a config to run them outside of rvp
+#[derive(Parser)]
+pub struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Format of the export.
+    #[arg(long, value_name = "FORMAT")]
+    format: ExportFormat,
+
+    /// Hide the secrets matched by the `redact` patterns of the config, e.g. to share the
+    /// script with the site owners. The redacted requests won't work as is.
+    #[arg(long)]
+    redact: bool,
+
+    /// (Optional) Write the export to the given file instead of stdout.
+    ///
+    /// Missing parent directories will be created.
+    #[arg(short, long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// Shell script with a curl command per resource
+    Curl,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let config = args.config.load(ctx).await?;
+    let exported = match args.format {
+        ExportFormat::Curl => curl_script(&config, ctx),
+    };
+    let exported = match args.redact {
+        true => ctx.redact(&exported).into_owned(),
+        false => exported,
+    };
+
+    match args.output {
+        Some(path) => write_to_file(&path, &exported, false)?,
+        None => println!("{}", exported),
+    }
+    Ok(())
+}
+
+/// It generates a shell script with the curl command equivalent to the request of each resource
+fn curl_script(config: &Config, ctx: &Context) -> String {
+    let mut script = format!("#!/bin/sh\n# Requests of the \"{}\" config\n", config.name);
+
+    for (i, resource) in config.resources.iter().enumerate() {
+        let selectors: Vec<&str> = resource.selectors.iter().map(|s| s.name.as_str()).collect();
+        script.push_str(&format!(
+            "\n# Resource #{} ({}): {}\n",
+            i + 1,
+            resource.kind(),
+            selectors.join(", ")
+        ));
+        if resource.render() {
+            script.push_str("# Rendered with a headless browser by rvp, curl gets the page before its scripts run\n");
+        }
+
+        let mut command = vec!["curl".to_string(), "-sS".to_string(), "-L".to_string()];
+        if let Some(timeout) = ctx.timeout {
+            command.push(format!("--max-time {}", timeout.as_secs()));
+        }
+        if let Some(user_agent) = &ctx.user_agent {
+            command.push(format!("-A {}", shell_quote(user_agent)));
+        }
+        let request = resource.request();
+        if !request.method.is_default() {
+            command.push(format!("-X {}", request.method));
+        }
+        for (name, value) in request.headers.iter() {
+            command.push(format!(
+                "-H {}",
+                shell_quote(&format!("{}: {}", name, value))
+            ));
+        }
+        match &request.auth {
+            Some(Auth::Basic { user, pass }) => command.push(format!(
+                "-u {}",
+                shell_quote_env(&format!("{}:{}", user, pass))
+            )),
+            Some(Auth::Bearer { token }) => command.push(format!(
+                "-H {}",
+                shell_quote_env(&format!("Authorization: Bearer {}", token))
+            )),
+            None => {}
+        }
+        if let Some(body) = &request.body {
+            command.push(format!("--data-raw {}", shell_quote(body)));
+        }
+        command.push(shell_quote(&request.url));
+        script.push_str(&command.join(" "));
+        script.push('\n');
+    }
+
+    script
+}
+
+/// It quotes the argument for the POSIX shell
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Same as [shell_quote], but the `${NAME}` references are expanded by the shell, so the
+/// secrets are read from the environment when the script runs
+fn shell_quote_env(arg: &str) -> String {
+    fill_env_vars(&shell_quote(arg), |name| {
+        Some(format!("'\"${{{}}}\"'", name))
+    })
+    .expect("all variables have a value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::fetcher::Method;
+    use rvp::structure::{Resource, ResourceKind, Selector, SelectorType};
+    use std::time::Duration;
+
+    #[test]
+    fn test_curl_script() {
+        let selector = Selector::new("h1".to_string(), "title".to_string(), SelectorType::String);
+        let mut resource = Resource::new("https://example.com/?q=it's".to_string(), vec![selector]);
+        resource.accept_language = Some("en-US".to_string());
+        let mut graphql = Resource::new("https://example.com/graphql".to_string(), vec![]);
+        graphql.kind = Some(ResourceKind::Json);
+        graphql.method = Some(Method::Post);
+        graphql.body = Some(r#"{"query": "{ price }"}"#.to_string());
+        graphql.content_type = Some("application/json".to_string());
+        graphql.auth = Some(Auth::Bearer {
+            token: "${API_TOKEN}".to_string(),
+        });
+        let config = Config::new("pages".to_string(), "".to_string(), vec![resource, graphql]);
+        let mut ctx = Context::default();
+        ctx.timeout = Some(Duration::from_secs(10));
+        ctx.user_agent = Some("rvp/1.0".to_string());
+
+        let script = curl_script(&config, &ctx);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("# Resource #1 (HTML): title\n"));
+        assert!(script.contains(
+            "curl -sS -L --max-time 10 -A 'rvp/1.0' -H 'Accept-Language: en-US' 'https://example.com/?q=it'\\''s'\n"
+        ));
+        assert!(script.contains(
+            "curl -sS -L --max-time 10 -A 'rvp/1.0' -X POST -H 'Content-Type: application/json' -H 'Authorization: Bearer '\"${API_TOKEN}\"'' --data-raw '{\"query\": \"{ price }\"}' 'https://example.com/graphql'\n"
+        ));
+    }
+}
@@ -1,12 +1,21 @@
-use anyhow::Result;
-use clap::Parser;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::{value_parser, Parser};
+use url::Url;
 
 use validator::Validate;
 
-use crate::scalper;
+use super::batch::robots_fetcher;
+use crate::context::Context;
+use crate::output::{render_template, write_to_file};
+use rvp::fetcher::decode;
+use rvp::scalper::{self, ParsedValue};
 
 /// Simply grab one value from a web page.
 #[derive(Parser, Validate)]
+#[command(visible_alias = "g")]
 pub struct Args {
     /// Selector path to grab from the page.
     ///
@@ -18,23 +27,84 @@ pub struct Args {
     #[validate(length(min = 1, message = "should not be empty!"))]
     selector: String,
 
-    /// URL to web page to grab from.
+    /// URL to web page to grab from, a path to a saved page, or `-` to read the page from stdin.
     ///
-    /// Example: `-f="https://example.com"`
+    /// Example: `-f="https://example.com"`, `-f=./page.html` or `curl -s https://example.com | rvp grab -s="h1" -f=-`
     #[arg(short, long, value_name = "URL")]
-    #[validate(url(message = "must be a valid URL!"))]
     from: String,
+
+    /// (Optional) Render the value with the template, `{{value}}` is replaced with the value.
+    ///
+    /// Example: `--template "{{value}} USD"`
+    #[arg(long, value_name = "TEMPLATE")]
+    template: Option<String>,
+
+    /// (Optional) Write the value to the given file instead of stdout.
+    ///
+    /// Missing parent directories will be created.
+    #[arg(short, long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+    output: Option<PathBuf>,
+
+    /// Used with `--output` to append the value to the end of the file instead of overwriting it.
+    #[arg(long, requires = "output")]
+    append: bool,
 }
 
-pub async fn command(args: Args) -> Result<()> {
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
     args.validate()?;
 
-    let value = scalper::grab_one(&args.selector, &args.from).await?;
+    let value = match args.from.as_str() {
+        "-" => {
+            ctx.log("Reading the page from stdin");
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            scalper::grab_one_from_html(&decode(&bytes, None), &args.selector)?
+        }
+        from => {
+            let url = page_url(from)?;
+            ctx.log(format!("Fetching {}", url));
+            match robots_fetcher(false, &ctx.fetcher, ctx) {
+                Some(robots) => scalper::grab_one(&robots, &args.selector, &url).await?,
+                None => scalper::grab_one(&ctx.fetcher, &args.selector, &url).await?,
+            }
+        }
+    };
+    let value = match &args.template {
+        Some(template) => render_template(
+            template,
+            &[ParsedValue {
+                name: "value".to_string(),
+                value: value.into(),
+                error: None,
+                stale: None,
+                anomaly: None,
+            }],
+        )?,
+        None => value,
+    };
 
-    println!("{}", value);
+    match args.output {
+        Some(path) => write_to_file(&path, &value, args.append)?,
+        None => println!("{}", value),
+    }
     Ok(())
 }
 
+/// It returns the URL of the page, the `file://` URL for a path to a saved page
+pub(crate) fn page_url(from: &str) -> Result<String> {
+    // A single letter scheme is the drive of a Windows path
+    if let Ok(url) = Url::parse(from) {
+        if url.scheme().len() > 1 {
+            return Ok(from.to_string());
+        }
+    }
+    let path = std::fs::canonicalize(from)
+        .map_err(|err| anyhow!("\"{}\" is neither a valid URL nor a file: {}", from, err))?;
+    Url::from_file_path(&path)
+        .map(String::from)
+        .map_err(|_| anyhow!("invalid file path \"{}\"", path.display()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,8 +114,11 @@ mod tests {
         let args = Args {
             selector: "body > div > h1".to_string(),
             from: "http://example.com".to_string(),
+            template: None,
+            output: None,
+            append: false,
         };
-        command(args).await
+        command(args, &Context::default()).await
     }
 
     #[tokio::test]
@@ -53,20 +126,39 @@ mod tests {
         let args = Args {
             selector: "#search > div".to_string(),
             from: "invalid-url".to_string(),
+            template: None,
+            output: None,
+            append: false,
         };
-        command(args)
+        command(args, &Context::default())
             .await
             .expect_err("should fail with invalid URL!");
         Ok(())
     }
 
+    #[test]
+    fn test_page_url() -> Result<()> {
+        assert_eq!(page_url("https://example.com")?, "https://example.com");
+        assert_eq!(page_url("file:///tmp/page.html")?, "file:///tmp/page.html");
+        let path = std::env::current_dir()?.join("Cargo.toml");
+        assert_eq!(
+            page_url("Cargo.toml")?,
+            Url::from_file_path(path).unwrap().as_str()
+        );
+        assert!(page_url("missing.html").is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_command_with_empty_selector() -> Result<()> {
         let args = Args {
             selector: "".to_string(),
             from: "http://example.com".to_string(),
+            template: None,
+            output: None,
+            append: false,
         };
-        command(args)
+        command(args, &Context::default())
             .await
             .expect_err("should fail with empty selector!");
         Ok(())
@@ -0,0 +1,82 @@
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+
+use crate::context::Context;
+use crate::history_store::{Query, Store};
+
+/// Show the recent values stored with `--store`, newest first
+///
+/// Every `batch` and `watch` run with `--store sqlite://history.db` appends its values
+/// to the history, so the trends of the scraped numbers can be tracked.
+#[derive(Parser)]
+pub struct Args {
+    /// (Optional) Show only the values of the config with this name
+    #[arg(short, long, value_name = "NAME")]
+    config: Option<String>,
+
+    /// (Optional) Show only the values of the selector with this name
+    #[arg(short, long, value_name = "NAME")]
+    selector: Option<String>,
+
+    /// (Optional) Show only the values grabbed with this parameter
+    #[arg(long, value_name = "PARAM")]
+    param: Option<String>,
+
+    /// Maximum number of values to show
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 20)]
+    limit: usize,
+
+    /// Output the values as JSON lines
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let Some(url) = &ctx.store else {
+        return Err(anyhow!(
+            "Set the history store with --store, e.g. --store sqlite://history.db"
+        ));
+    };
+    let store = Store::open(url)?;
+    let records = store.recent(&Query {
+        config: args.config.as_deref(),
+        selector: args.selector.as_deref(),
+        param: args.param.as_deref(),
+        limit: args.limit,
+    })?;
+
+    if args.json {
+        for record in records.iter() {
+            println!("{}", serde_json::to_string(record)?);
+        }
+        return Ok(());
+    }
+    if records.is_empty() {
+        println!("No values found in {}", url);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Time", "Config", "Param", "Selector", "Value"]);
+    for record in records {
+        let value = match record.error {
+            Some(error) => format!("{} ({})", record.value, error),
+            None => record.value,
+        };
+        table.add_row(vec![
+            record.time,
+            record.config,
+            record.param.unwrap_or_default(),
+            record.selector,
+            value,
+        ]);
+    }
+    ctx.style_table(&mut table);
+    println!("{}", table);
+
+    Ok(())
+}
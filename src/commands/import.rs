@@ -0,0 +1,105 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::{value_parser, Parser, ValueEnum};
+use rvp::{
+    error::RvpError,
+    import::{from_har, from_picker, from_scrapy, from_yaml},
+    structure::ConfigFormat,
+};
+
+use crate::context::Context;
+
+/// Import selector definitions made by other tools as a config file
+#[derive(Parser)]
+pub struct Args {
+    /// Path to the file to import.
+    #[arg(value_name = "FILE", value_parser = value_parser!(PathBuf))]
+    file: PathBuf,
+
+    /// Format of the imported file.
+    #[arg(long, value_name = "FORMAT")]
+    format: ImportFormat,
+
+    /// (Optional) Name of the config, overrides the name found in the file.
+    #[arg(short, long, value_name = "NAME")]
+    name: Option<String>,
+
+    /// (Optional) Path of the config file to create, the format is detected by the extension.
+    ///
+    /// If neither `--output` nor `--global` is set, the config is printed to stdout as TOML.
+    #[arg(short, long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+    output: Option<PathBuf>,
+
+    /// (Optional) Save the config as TOML in the global config directory, named after the config.
+    #[arg(short, long, conflicts_with = "output")]
+    global: bool,
+
+    /// Overwrite the config file if it already exists
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    /// JSON interchange format of the picked elements, e.g. from a browser extension
+    RvpPicker,
+    /// Scrapy spider (`.py`): the `name`, the `start_urls` and the `response.css(...)` calls
+    Scrapy,
+    /// "url + css list" YAML: a list of pages with the `url` and the `css` selectors
+    Yaml,
+    /// HAR capture of a browsing session (`.har`), saved from the network tab of the devtools:
+    /// a resource per visited page
+    Har,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    ctx.log(format!("Importing {}", args.file.display()));
+    let data = fs::read_to_string(&args.file)?;
+    let imported = match args.format {
+        ImportFormat::RvpPicker => from_picker(&data)?,
+        ImportFormat::Scrapy => from_scrapy(&data)?,
+        ImportFormat::Yaml => from_yaml(&data)?,
+        ImportFormat::Har => from_har(&data)?,
+    };
+    if !ctx.quiet {
+        for skipped in imported.skipped.iter() {
+            eprintln!("Skipped {}", skipped);
+        }
+    }
+    let mut config = imported.config;
+    if let Some(name) = args.name {
+        config.name = name;
+    }
+
+    let path = match (args.output, args.global) {
+        (Some(path), _) => path,
+        (None, true) => match ctx.config_dir() {
+            Some(dir) => config.get_full_path(&dir, &ConfigFormat::Toml),
+            None => {
+                return Err(RvpError::NotFoundError(
+                    "Could not find the global config directory!".to_string(),
+                )
+                .into())
+            }
+        },
+        (None, false) => {
+            print!("{}", config.to_toml());
+            return Ok(());
+        }
+    };
+    if path.exists() && !args.force {
+        return Err(anyhow!(
+            "Config file {} already exists, use --force to overwrite it!",
+            path.display()
+        ));
+    }
+
+    let path = config.save(&path, &ConfigFormat::from_path(&path)?)?;
+    println!(
+        "Imported {} resources into {}",
+        config.resources.len(),
+        path.display()
+    );
+    Ok(())
+}
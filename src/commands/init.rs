@@ -0,0 +1,128 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use clap::Parser;
+use inquire::{
+    validator::Validation::{Invalid, Valid},
+    Confirm, Select, Text,
+};
+use rvp::{
+    error::RvpError,
+    structure::{ConfigFormat, RateLimit},
+};
+use validator::ValidateUrl;
+
+use crate::context::Context;
+use crate::settings::{Settings, SETTINGS_FILE};
+
+/// Set up rvp: create its directories and write the global settings
+///
+/// The settings are the defaults of all commands: the format of the new configs, the
+/// `User-Agent`, the rate limit and the `robots.txt` politeness, and the webhook to send the
+/// results to. The command line options and the configs take precedence over them.
+#[derive(Parser)]
+pub struct Args {
+    /// Don't ask anything, keep the current settings or write the defaults.
+    #[arg(long)]
+    defaults: bool,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let Some(config_dir) = ctx.config_dir() else {
+        return Err(RvpError::NotFoundError(
+            "Could not find the global config directory!".to_string(),
+        )
+        .into());
+    };
+    let dirs: Vec<(&str, PathBuf)> = [
+        ("Configs", Some(config_dir.clone())),
+        ("Data", ctx.data_dir()),
+        ("Cache", ctx.cache_dir()),
+    ]
+    .into_iter()
+    .filter_map(|(name, dir)| Some((name, dir?)))
+    .collect();
+    for (name, dir) in dirs.iter() {
+        match dir.is_dir() {
+            true => println!("{}: {}", name, dir.display()),
+            false => {
+                fs::create_dir_all(dir)?;
+                println!("{}: {} (created)", name, dir.display());
+            }
+        }
+    }
+
+    let settings = match args.defaults {
+        true => ctx.settings.clone(),
+        false => ask_settings(&ctx.settings)?,
+    };
+    settings.save(&config_dir)?;
+    println!(
+        "Settings saved to {}",
+        config_dir.join(SETTINGS_FILE).display()
+    );
+    println!("Done! Create your first config with `rvp new --global`.");
+    Ok(())
+}
+
+/// It asks for each setting, starting from the current ones
+fn ask_settings(current: &Settings) -> Result<Settings> {
+    let options = vec!["TOML", "JSON"];
+    let cursor = match current.format {
+        Some(ConfigFormat::Json) => 1,
+        _ => 0,
+    };
+    let format = match Select::new("Format of the new configs:", options)
+        .with_starting_cursor(cursor)
+        .prompt()?
+    {
+        "JSON" => ConfigFormat::Json,
+        _ => ConfigFormat::Toml,
+    };
+
+    let user_agent = Text::new("User-Agent of the requests:")
+        .with_help_message("(Optional) Leave empty for the default one")
+        .with_initial_value(current.user_agent.as_deref().unwrap_or_default())
+        .prompt()?;
+
+    let rate_limit = Text::new("Maximum requests to the same host:")
+        .with_help_message("(Optional) e.g. 2/s, 30/m or 100/h, leave empty for no limit")
+        .with_initial_value(
+            &current
+                .rate_limit
+                .map(|r| r.to_string())
+                .unwrap_or_default(),
+        )
+        .with_validator(|input: &str| {
+            match input.trim().is_empty() || input.trim().parse::<RateLimit>().is_ok() {
+                true => Ok(Valid),
+                false => Ok(Invalid("must be e.g. 2/s, 30/m or 100/h!".into())),
+            }
+        })
+        .prompt()?;
+
+    let respect_robots = Confirm::new("Skip the pages disallowed by robots.txt?")
+        .with_default(current.respect_robots)
+        .prompt()?;
+
+    let webhook = Text::new("Webhook to send the results to:")
+        .with_help_message("(Optional) e.g. a Slack or Discord webhook, leave empty for none")
+        .with_initial_value(current.webhook.as_deref().unwrap_or_default())
+        .with_validator(|input: &str| {
+            match input.trim().is_empty() || ValidateUrl::validate_url(input.trim()) {
+                true => Ok(Valid),
+                false => Ok(Invalid("must be a valid URL!".into())),
+            }
+        })
+        .prompt()?;
+
+    let optional = |value: String| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+    Ok(Settings {
+        format: Some(format),
+        user_agent: optional(user_agent),
+        rate_limit: optional(rate_limit).map(|r| r.parse()).transpose()?,
+        respect_robots,
+        webhook: optional(webhook),
+        ..current.clone()
+    })
+}
@@ -0,0 +1,285 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::{value_parser, Parser};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use rvp::{
+    error::RvpError,
+    redact::Redactor,
+    structure::{Config, ConfigFormat, Resource, ResourceKind, Selector},
+};
+
+use crate::context::Context;
+use crate::paths;
+use crate::settings::SETTINGS_FILE;
+
+/// Show the resources and the selectors of a config as a tree, or the configs in a directory
+///
+/// Nothing is fetched. Without any option, the configs of the global config directory
+/// are listed.
+#[derive(Parser)]
+#[command(visible_alias = "ls")]
+pub struct Args {
+    /// Path to the config file to show.
+    #[arg(short, long, value_name = "PATH", value_parser = value_parser!(PathBuf), conflicts_with_all = ["name", "dir"])]
+    path: Option<PathBuf>,
+
+    /// Name of the config to show, looked up in the current directory and then in the global
+    /// config directory.
+    #[arg(short, long, value_name = "NAME", conflicts_with = "dir")]
+    name: Option<String>,
+
+    /// (Optional) Directory to list the configs of, the global config directory by default.
+    #[arg(short, long, value_name = "DIR", value_parser = value_parser!(PathBuf))]
+    dir: Option<PathBuf>,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let global_dir = || {
+        ctx.config_dir().ok_or_else(|| {
+            RvpError::NotFoundError("Could not find the global config directory!".to_string())
+        })
+    };
+    let path = match (args.path, &args.name) {
+        (Some(path), _) => path,
+        (None, Some(name)) => paths::resolve_config(&ctx.config_search_dirs(), name)?,
+        (None, None) => {
+            let dir = match args.dir {
+                Some(dir) => dir,
+                None => global_dir()?,
+            };
+            return list_configs(&dir, ctx);
+        }
+    };
+    if !path.exists() {
+        return Err(RvpError::NotFoundError("File does not exist!".to_string()).into());
+    }
+
+    let config = Config::from_file(&path, &ConfigFormat::from_path(&path)?)?;
+    let redactor = Redactor::new(&config.redact)?;
+    println!("{}", config_tree(&config, &redactor));
+    Ok(())
+}
+
+/// It prints a table of the configs in the directory, the files which are not configs
+/// and the settings file are skipped
+fn list_configs(dir: &Path, ctx: &Context) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(RvpError::NotFoundError(format!(
+            "Directory {} does not exist!",
+            dir.display()
+        ))
+        .into());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file() && ConfigFormat::from_path(path).is_ok())
+        .filter(|path| !path.ends_with(SETTINGS_FILE))
+        .collect();
+    files.sort();
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            "File",
+            "Name",
+            "Description",
+            "Resources",
+            "Selectors",
+            "Parameters",
+        ]);
+    let mut count = 0;
+    for path in files.iter() {
+        let config = match Config::from_file(path, &ConfigFormat::from_path(path)?) {
+            Ok(config) => config,
+            Err(err) => {
+                ctx.log(format!("Skipping {}: {}", path.display(), err));
+                continue;
+            }
+        };
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        let selectors: usize = config.resources.iter().map(|r| r.selectors.len()).sum();
+        let mut params: Vec<String> = Vec::new();
+        for name in config.resources.iter().flat_map(placeholders) {
+            if !params.contains(&name) {
+                params.push(name);
+            }
+        }
+        table.add_row(vec![
+            file.to_string(),
+            config.name.clone(),
+            config.description.clone(),
+            config.resources.len().to_string(),
+            selectors.to_string(),
+            params.join(", "),
+        ]);
+        count += 1;
+    }
+
+    if count == 0 {
+        println!("No configs found in {}", dir.display());
+        return Ok(());
+    }
+    ctx.style_table(&mut table);
+    println!("{}", table);
+    Ok(())
+}
+
+/// It renders the resources of the config and their selectors as a tree, the secrets in the
+/// URLs are redacted
+fn config_tree(config: &Config, redactor: &Redactor) -> String {
+    let mut tree = match config.description.is_empty() {
+        true => config.name.clone(),
+        false => format!("{} - {}", config.name, config.description),
+    };
+    for (i, resource) in config.resources.iter().enumerate() {
+        let last = i + 1 == config.resources.len();
+        tree.push_str(&format!(
+            "\n{}{}",
+            branch(last),
+            resource_label(resource, redactor)
+        ));
+        selectors_tree(&mut tree, &resource.selectors, indent(last));
+    }
+    tree
+}
+
+/// It appends the selectors and their children to the tree
+fn selectors_tree(tree: &mut String, selectors: &[Selector], prefix: &str) {
+    for (i, selector) in selectors.iter().enumerate() {
+        let last = i + 1 == selectors.len();
+        tree.push_str(&format!(
+            "\n{}{}{}",
+            prefix,
+            branch(last),
+            selector_label(selector)
+        ));
+        let prefix = format!("{}{}", prefix, indent(last));
+        selectors_tree(tree, &selector.children, &prefix);
+    }
+}
+
+fn branch(last: bool) -> &'static str {
+    match last {
+        true => "└── ",
+        false => "├── ",
+    }
+}
+
+fn indent(last: bool) -> &'static str {
+    match last {
+        true => "    ",
+        false => "│   ",
+    }
+}
+
+/// The URL of the resource with its kind and the parameters it needs, e.g.
+/// `https://api.com/%% (JSON, needs %%)`
+fn resource_label(resource: &Resource, redactor: &Redactor) -> String {
+    let mut details: Vec<String> = Vec::new();
+    if resource.kind() != ResourceKind::Html {
+        details.push(resource.kind().to_string());
+    }
+    if resource.render() {
+        details.push("render".to_string());
+    }
+    if resource.sitemap.is_some() {
+        details.push("sitemap".to_string());
+    }
+    if !resource.enabled {
+        details.push("disabled".to_string());
+    }
+    let params = placeholders(resource);
+    if !params.is_empty() {
+        details.push(format!("needs {}", params.join(", ")));
+    }
+    let url = redactor.redact(&resource.url);
+    match details.is_empty() {
+        true => url.into_owned(),
+        false => format!("{} ({})", url, details.join(", ")),
+    }
+}
+
+/// The parameter placeholders the resource needs, `%%` and the named `%name%` ones
+fn placeholders(resource: &Resource) -> Vec<String> {
+    let mut params: Vec<String> = match resource.needs_parameter() {
+        true => vec!["%%".to_string()],
+        false => vec![],
+    };
+    params.extend(resource.named_params().iter().map(|n| format!("%{}%", n)));
+    params
+}
+
+/// The name, the path and the type of the selector, e.g. `price: #quote > span (Number, list)`
+fn selector_label(selector: &Selector) -> String {
+    let mut details = vec![selector.parsed_type.to_string()];
+    for (flag, name) in [
+        (selector.list, "list"),
+        (selector.required, "required"),
+        (selector.follow, "follow"),
+    ] {
+        if flag {
+            details.push(name.to_string());
+        }
+    }
+    format!(
+        "{}: {} ({})",
+        selector.name,
+        selector.path,
+        details.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::structure::SelectorType;
+
+    #[test]
+    fn test_config_tree() -> Result<()> {
+        let mut link = Selector::new(
+            "a.item".to_string(),
+            "items".to_string(),
+            SelectorType::String,
+        );
+        link.follow = true;
+        link.children = vec![Selector::new(
+            "h1".to_string(),
+            "title".to_string(),
+            SelectorType::String,
+        )];
+        let price = Selector::new(
+            "#quote > span".to_string(),
+            "price".to_string(),
+            SelectorType::Number,
+        );
+        let mut api = Resource::new("https://api.com/%ticker%?key=secret".to_string(), vec![]);
+        api.kind = Some(ResourceKind::Json);
+        api.enabled = false;
+        let config = Config::new(
+            "stock".to_string(),
+            "Stock quotes".to_string(),
+            vec![
+                Resource::new("https://shop.com/%%".to_string(), vec![price, link]),
+                api,
+            ],
+        );
+
+        let redactor = Redactor::new(&["key=([^&]+)".to_string()])?;
+        assert_eq!(
+            config_tree(&config, &redactor),
+            "stock - Stock quotes
+├── https://shop.com/%% (needs %%)
+│   ├── price: #quote > span (Number)
+│   └── items: a.item (String, follow)
+│       └── title: h1 (String)
+└── https://api.com/%ticker%?key=[REDACTED] (JSON, disabled, needs %ticker%)"
+        );
+        Ok(())
+    }
+}
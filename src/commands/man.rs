@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::{value_parser, Command, CommandFactory, Parser};
+use clap_mangen::Man;
+
+use crate::context::Context;
+
+/// Generate man pages for rvp and all its commands
+#[derive(Parser)]
+pub struct Args {
+    /// (Optional) Directory to write the man pages to (`rvp.1`, `rvp-batch.1`, ...).
+    ///
+    /// If not provided, the main page is printed to stdout:
+    ///
+    /// ```
+    /// rvp man | man -l -
+    /// ```
+    #[arg(short, long, value_name = "DIR", value_parser = value_parser!(PathBuf))]
+    output_dir: Option<PathBuf>,
+}
+
+/// Reference of the config file format, appended to the main page
+const CONFIG_FILE_SECTION: &str = r#"A config file is a TOML (.toml) or JSON (.json) file, the format is detected by the extension.
+
+name = "stock"                      # name of the config
+description = "Stock information"   # optional description
+rate_limit = "2/s"                  # optional: max requests per host (s, m, h)
+redact = ["token=([^&]+)"]          # optional: secrets to hide in logs and errors
+timezone = "America/New_York"       # optional: timezone of the dates in the output
+respect_robots = true               # optional: skip the URLs disallowed by robots.txt
+output = "json"                     # optional: table, json or csv output of batch
+output_file = "stock.json"          # optional: file to write the output of batch to
+webhook = "https://example.com/hook" # optional: URL to POST the results to
+
+[market_hours]                      # optional: watch skips the runs outside of them
+days = ["Mon", "Tue", "Wed"]        # trading days, Mon to Fri by default
+open = "09:30"                      # optional: opening time in the timezone
+close = "16:00"                     # optional: closing time in the timezone
+holidays = ["2024-12-25"]           # optional: dates when the market is closed
+
+[[computed]]                        # optional: a value computed from the parsed ones
+name = "change"                     # name of the value in the output
+expr = "price - prev_price"         # arithmetic of the values, prev_ is the previous run
+
+[[groups]]                          # optional: settings shared by the resources
+name = "nasdaq"                     # name of the group, used by the resources
+kind = "Json"                       # optional: the same settings as of the resources,
+accept_language = "en-US"           # kind, render, key_template, accept_language,
+                                    # refetch_on_miss, method, content_type, auth,
+                                    # rate_limit and schedule; the headers are
+                                    # merged and the tags joined
+enabled = false                     # optional: park all resources of the group
+
+[defaults.selector]                 # optional: fields of all selectors, unless set
+parsed_type = "Number"              # parsed_type, regex, regex_group, transforms,
+required = true                     # required, truthy_values, date_format and
+                                    # out_of_range
+
+[[resources]]                       # a web page to grab the values from
+url = "https://example.com/%%"      # %% and %name% are replaced with the params
+group = "nasdaq"                    # optional: inherit the settings of the group,
+                                    # unless the resource sets them
+kind = "Json"                       # optional: Html (default), Json or Feed
+render = true                       # optional: render with headless Chromium
+key_template = "{param}_{selector}" # optional: output names of the values
+next_page = "a.next"                # optional: link to the next page to follow
+                                    # (JSON path of the URL for Json)
+max_pages = 5                       # optional: pages to grab with next_page (10)
+accept_language = "en-US,en;q=0.9"  # optional: Accept-Language header, warns when
+                                    # the page is in another language
+refetch_on_miss = true              # optional: fetch again bypassing the caches
+                                    # when a required value is empty
+method = "POST"                     # optional: GET (default), POST, PUT or PATCH
+body = '{"symbol": "%%"}'           # optional: body of the request, %% and %name%
+                                    # are replaced in it too (JSON-escaped
+                                    # with a JSON content_type, percent-encoded
+                                    # with a form one)
+content_type = "application/json"   # optional: Content-Type header of the body
+headers = { Referer = "https://example.com" } # optional: extra headers, the
+                                    # credential ones are sent only to the same origin
+rate_limit = "10/m"                 # optional: stricter rate limit of the host
+schedule = "@hourly"                # optional: own cron of the resource in
+                                    # `rvp schedule`, instead of --cron
+tags = ["stocks"]                   # optional: with --tag, only the resources
+                                    # with any of the tags run
+enabled = false                     # optional: park the resource, it is not fetched
+deprecated = true                   # optional: warn that the resource is retired
+deprecation_note = "use the API"    # optional: shown with the deprecation warning
+sunset = "2025-06-30"               # optional: `rvp check` fails after this date
+
+[resources.sitemap]                 # optional: url is a sitemap.xml, the selectors
+                                    # are grabbed from each page listed in it
+filter = "/product/"                # optional: regex the page URLs must match
+limit = 100                         # optional: maximum number of pages (100)
+
+[resources.auth.bearer]             # optional: Authorization header of the requests,
+token = "${API_TOKEN}"              # ${NAME} is read from the environment; or
+                                    # [resources.auth.basic] with user and pass
+
+[[resources.selectors]]             # a value to grab from the page
+path = "body > div > h1"            # CSS selector, or JSON path for Json and Feed;
+                                    # %% and %name% are replaced here too
+name = "title"                      # name of the value in the output
+parsed_type = "String"              # String, Number, Boolean or Date
+regex = "([0-9.]+)"                 # optional: regex applied to the text
+regex_group = 1                     # optional: capture group of the regex
+transforms = ["trim", 'strip_prefix:"$"'] # optional: cleanups after the regex:
+                                    # trim, lowercase, uppercase, strip_prefix:"x",
+                                    # strip_suffix:"x", replace:"a"->"b", split:","[0]
+list = true                         # optional: all matches as an array, joined
+                                    # across the pages of next_page
+required = true                     # optional: an empty value is an error
+truthy_values = ["yes"]             # optional: true values for Boolean
+date_format = "%d.%m.%Y"            # optional: chrono format for Date
+alert = "< 100"                     # optional: alert threshold of a Number
+                                    # (<, <=, >, >=, ==, !=) or of its change with
+                                    # --store, e.g. change(title, 24h) > 5%;
+                                    # batch exits with 3
+alert_above = 200                   # optional: shorthand for alert = "> 200"
+alert_below = 100                   # optional: shorthand for alert = "< 100"
+min = 1                             # optional: smallest sane value of a Number
+max = 10000                         # optional: largest sane value of a Number
+out_of_range = "drop"               # optional: flag (error) or drop the values
+                                    # outside of min and max (flag)
+follow = true                       # optional: grab the children from each page
+                                    # linked by the matches (href)
+max_links = 20                      # optional: links followed by follow (20)
+
+[resources.selectors.anomaly]       # optional: flag the numbers far from their
+                                    # previous values in the history of --store
+window = 20                         # optional: previous values compared with (20)
+z_score = 3                         # optional: largest z-score of a usual value
+jump = 50                           # optional: largest change from the median, %
+alert = true                        # optional: report the anomalies as alerts
+
+[[resources.selectors.children]]    # a value to grab from each followed page,
+path = "h1"                         # the same fields as the selectors
+name = "name"
+parsed_type = "String""#;
+
+pub async fn command(args: Args, _ctx: &Context) -> Result<()> {
+    let mut cmd = crate::Cli::command().disable_help_subcommand(true);
+    cmd.build();
+
+    match args.output_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)?;
+            generate(cmd, &dir, true)?;
+            println!("Man pages saved to {}", dir.display());
+        }
+        None => render(cmd, &mut io::stdout(), true)?,
+    }
+    Ok(())
+}
+
+/// It writes the pages of the command and its visible subcommands to the directory
+fn generate(cmd: Command, dir: &Path, is_root: bool) -> Result<()> {
+    for subcommand in cmd.get_subcommands().filter(|s| !s.is_hide_set()).cloned() {
+        generate(subcommand, dir, false)?;
+    }
+
+    let path = dir.join(Man::new(cmd.clone()).get_filename());
+    let mut file = fs::File::create(path)?;
+    render(cmd, &mut file, is_root)
+}
+
+/// It renders the man page, the main page gets the config file reference
+fn render(cmd: Command, w: &mut dyn Write, is_root: bool) -> Result<()> {
+    Man::new(cmd).render(w)?;
+    if is_root {
+        writeln!(w, ".SH \"CONFIG FILE\"\n.nf")?;
+        for line in CONFIG_FILE_SECTION.lines() {
+            writeln!(w, "\\&{}", line.replace('\\', "\\e"))?;
+        }
+        writeln!(w, ".fi")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut cmd = crate::Cli::command();
+        cmd.build();
+
+        let mut page = Vec::new();
+        render(cmd, &mut page, true).unwrap();
+        let page = String::from_utf8(page).unwrap();
+
+        assert!(page.contains(".SH \"CONFIG FILE\""));
+        assert!(page.contains("batch"));
+        assert!(!page.contains("debug"));
+    }
+}
@@ -1,4 +1,24 @@
+pub mod audit;
 pub mod batch;
+pub mod check;
+pub mod config;
+pub mod debug;
+pub mod diff;
 pub mod edit;
+pub mod export;
 pub mod grab;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod import;
+pub mod init;
+pub mod list;
+pub mod man;
 pub mod new;
+pub mod output_schema;
+pub mod run;
+pub mod schedule;
+pub mod suggest;
+pub mod test;
+pub mod watch;
+#[cfg(feature = "worker")]
+pub mod worker;
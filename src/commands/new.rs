@@ -1,9 +1,20 @@
-use crate::structure::{
-    Config, ConfigFormat, Resource, Selector, SelectorType, URL_PARAM_PLACEHOLDER,
+use crate::context::Context;
+use anyhow::{anyhow, Result};
+use clap::{value_parser, Parser, ValueEnum};
+use rvp::error::RvpError;
+use rvp::fetcher::Fetcher;
+use rvp::scalper::{extract, extract_json};
+use rvp::structure::{
+    Config, ConfigFormat, Resource, ResourceKind, Selector, SelectorType, URL_PARAM_PLACEHOLDER,
+};
+use rvp::validate::validate;
+use scraper::Html;
+use serde_json::Value;
+use std::{
+    env,
+    ffi::OsStr,
+    path::{Path, PathBuf},
 };
-use anyhow::Result;
-use clap::Parser;
-use rand::distributions::{Alphanumeric, DistString};
 use validator::ValidateUrl;
 
 use inquire::{
@@ -13,57 +24,240 @@ use inquire::{
 };
 
 /// Create new config file to grab multiple values from a web page at once.
+///
+/// With `--url` and `--selector`, the config is created without the dialog, e.g. in scripts.
 #[derive(Parser)]
 pub struct Args {
     /// Name of the config file to create.
     ///
     /// (*Optional*) If not provided, the default name will be used.
-    #[arg(short, long, value_name = "NAME")]
+    #[arg(short, long, value_name = "NAME", conflicts_with = "output")]
     name: Option<String>,
+
+    /// (Optional) Directory to save the config file in. Defaults to the current directory.
+    ///
+    /// Missing directories will be created.
+    #[arg(long, value_name = "DIR", value_parser = value_parser!(PathBuf))]
+    output_dir: Option<PathBuf>,
+
+    /// (Optional) Save the config file in the global config directory
+    /// (`$XDG_CONFIG_HOME/rvp` or `~/.config/rvp`, can be changed with `--config-dir`).
+    #[arg(short, long, conflicts_with = "output_dir")]
+    global: bool,
+
+    /// (Optional) Exact path of the config file to create, e.g. `./configs/stocks.toml`.
+    ///
+    /// The format is detected by the file extension.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        value_parser = value_parser!(PathBuf),
+        conflicts_with_all = ["output_dir", "global"]
+    )]
+    output: Option<PathBuf>,
+
+    /// Don't fetch the pages to preview the values matched by the selectors.
+    #[arg(long)]
+    no_preview: bool,
+
+    /// (Optional) Format of the config file, asked for by default.
+    #[arg(long, value_name = "FORMAT", conflicts_with = "output")]
+    format: Option<FormatArg>,
+
+    /// (Optional) URL of the resource. Creates the config without the dialog, together with
+    /// the `--selector`s.
+    #[arg(short, long, value_name = "URL", requires = "selectors")]
+    url: Option<String>,
+
+    /// Selector of the `--url` resource, can be repeated. The type is `string` (default),
+    /// `number`, `boolean` or `date`.
+    ///
+    /// Example: `--selector "price=#quote > span:number"`
+    #[arg(
+        short,
+        long = "selector",
+        value_name = "NAME=PATH:TYPE",
+        value_parser = parse_selector,
+        requires = "url"
+    )]
+    selectors: Vec<Selector>,
+
+    /// (Optional) Response type of the `--url` resource, `html` by default.
+    #[arg(long, value_name = "KIND", requires = "url")]
+    kind: Option<KindArg>,
+
+    /// (Optional) Description of the config, used with `--url`.
+    #[arg(short, long, value_name = "TEXT", requires = "url")]
+    description: Option<String>,
+
+    /// Used with `--url` to overwrite the config file if it already exists.
+    #[arg(long, requires = "url")]
+    force: bool,
 }
 
-pub async fn command(args: Args) -> Result<()> {
-    let name = args.name.unwrap_or("default".to_string());
+/// Format of a config file given on the command line
+#[derive(Clone, Copy, ValueEnum)]
+pub(super) enum FormatArg {
+    Toml,
+    Json,
+}
 
-    let options = vec!["TOML", "JSON"];
-    let format = Select::new("Save configuration in:", options).prompt()?;
+impl From<FormatArg> for ConfigFormat {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Toml => ConfigFormat::Toml,
+            FormatArg::Json => ConfigFormat::Json,
+        }
+    }
+}
 
-    println!(
-        "Creating new config file {}.{}",
-        name,
-        format.to_lowercase()
-    );
+/// Response type of a resource given on the command line
+#[derive(Clone, Copy, ValueEnum)]
+pub(super) enum KindArg {
+    Html,
+    Json,
+    Feed,
+}
 
-    let description = Text::new("Config description:")
-        .with_help_message("(Optional) Create a helpful description for this config file")
-        .with_default("")
-        .prompt()?;
+impl From<KindArg> for ResourceKind {
+    fn from(kind: KindArg) -> Self {
+        match kind {
+            KindArg::Html => ResourceKind::Html,
+            KindArg::Json => ResourceKind::Json,
+            KindArg::Feed => ResourceKind::Feed,
+        }
+    }
+}
 
-    let resources = add_resources()?;
+/// Number of the matched values shown by the preview of a selector
+const PREVIEW_VALUES: usize = 5;
 
-    let mut config = Config::new(name, description, resources);
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let dir = match (&args.output_dir, args.global) {
+        (Some(dir), _) => dir.clone(),
+        (None, true) => match ctx.config_dir() {
+            Some(dir) => dir,
+            None => {
+                return Err(RvpError::NotFoundError(
+                    "Could not find the global config directory!".to_string(),
+                )
+                .into())
+            }
+        },
+        (None, false) => env::current_dir()?,
+    };
 
-    let cf = match format {
-        "TOML" => ConfigFormat::Toml,
-        "JSON" => ConfigFormat::Json,
-        _ => unreachable!(),
+    let (name, cf) = match &args.output {
+        Some(output) => {
+            let cf = ConfigFormat::from_path(output)?;
+            let name = output
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .unwrap_or("default")
+                .to_string();
+            (name, cf)
+        }
+        None if args.format.is_some() || args.url.is_some() => {
+            let cf = match args.format {
+                Some(format) => format.into(),
+                None => ctx.settings.format.clone().unwrap_or(ConfigFormat::Toml),
+            };
+            (args.name.clone().unwrap_or("default".to_string()), cf)
+        }
+        None => {
+            let options = vec!["TOML", "JSON"];
+            // The format of the settings is preselected
+            let cursor = match ctx.settings.format {
+                Some(ConfigFormat::Json) => 1,
+                _ => 0,
+            };
+            let format = Select::new("Save configuration in:", options)
+                .with_starting_cursor(cursor)
+                .prompt()?;
+            let cf = match format {
+                "TOML" => ConfigFormat::Toml,
+                "JSON" => ConfigFormat::Json,
+                _ => unreachable!(),
+            };
+            (args.name.clone().unwrap_or("default".to_string()), cf)
+        }
     };
-    let path = config.get_full_path(&cf);
-    if path.exists() {
-        let overwrite = Confirm::new("Config file already exists. Overwrite?")
-            .with_default(false)
-            .prompt()?;
-        if !overwrite {
-            // Generate a random name for the config file
-            config.name = format!(
-                "{}_{}",
-                config.name,
-                Alphanumeric.sample_string(&mut rand::thread_rng(), 8)
-            );
+
+    let mut config = Config::new(name, "".to_string(), vec![]);
+
+    // Resolve the name conflicts before the user spends time on the dialog
+    let mut path = match &args.output {
+        Some(output) => output.clone(),
+        None => config.get_full_path(&dir, &cf),
+    };
+    if let Some(url) = &args.url {
+        if path.exists() && !args.force {
+            return Err(anyhow!(
+                "Config file {} already exists, use --force to overwrite it!",
+                path.display()
+            ));
+        }
+        let kind = args.kind.map(ResourceKind::from);
+        config.description = args.description.clone().unwrap_or_default();
+        config.resources = vec![Resource {
+            kind,
+            ..Resource::new(url.clone(), args.selectors.clone())
+        }];
+        let problems = validate(&config);
+        if !problems.is_empty() {
+            if !ctx.quiet {
+                for problem in problems.iter() {
+                    eprintln!("- {}", problem);
+                }
+            }
+            return Err(RvpError::ConfigError(format!(
+                "Found {} problem(s), the config was not created",
+                problems.len()
+            ))
+            .into());
         }
+        let path = config.save(&path, &cf)?;
+        println!("Config file saved to {}", path.display());
+        return Ok(());
     }
 
-    let path = config.save(&cf)?;
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or(dir);
+    while path.exists() {
+        let options = vec!["Overwrite", "Choose another name", "Cancel"];
+        let choice = Select::new(
+            &format!("Config file {} already exists:", path.display()),
+            options,
+        )
+        .prompt()?;
+
+        match choice {
+            "Overwrite" => break,
+            "Choose another name" => {
+                config.name = Text::new("Config name:")
+                    .with_validator(required!("This field is required"))
+                    .with_initial_value(&config.name)
+                    .prompt()?;
+                path = config.get_full_path(&dir, &cf);
+            }
+            "Cancel" => {
+                println!("Config file was not created.");
+                return Ok(());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    println!("Creating new config file {}", path.display());
+
+    config.description = Text::new("Config description:")
+        .with_help_message("(Optional) Create a helpful description for this config file")
+        .with_default("")
+        .prompt()?;
+
+    config.resources = add_resources(!args.no_preview, ctx).await?;
+
+    let path = config.save(&path, &cf)?;
 
     println!("Config file saved to {}", path.display());
     println!("Done! Don't worry, you can edit the config file later.");
@@ -72,14 +266,11 @@ pub async fn command(args: Args) -> Result<()> {
 }
 
 /// Create list of selectors from user input
-fn add_selectors() -> Result<Vec<Selector>> {
+async fn add_selectors(preview: &mut Preview, ctx: &Context) -> Result<Vec<Selector>> {
     let mut selectors: Vec<Selector> = Vec::new();
 
     'selector_loop: loop {
-        let path = Text::new("Selector path:")
-            .with_validator(required!("This field is required"))
-            .with_help_message("e.g. body > div > h1")
-            .prompt()?;
+        let path = ask_selector_path("Selector path:", None, preview, ctx).await?;
         let name = Text::new("Selector name:")
             .with_validator(required!("This field is required"))
             .with_help_message("e.g. title")
@@ -99,8 +290,195 @@ fn add_selectors() -> Result<Vec<Selector>> {
     Ok(selectors)
 }
 
+/// It parses the `name=path:type` of `--selector`. The type is taken after the last `:`
+/// only if it's a known type, so the paths with pseudo-classes (`li:first-child`) work.
+pub(super) fn parse_selector(value: &str) -> Result<Selector> {
+    let invalid = || anyhow!("Invalid selector \"{}\", expected NAME=PATH:TYPE!", value);
+    let (name, rest) = value.split_once('=').ok_or_else(invalid)?;
+    let parsed_type = rest
+        .rsplit_once(':')
+        .and_then(|(path, suffix)| Some((path, parse_selector_type(suffix).ok()?)));
+    let (path, parsed_type) = parsed_type.unwrap_or((rest, SelectorType::String));
+    if name.trim().is_empty() || path.trim().is_empty() {
+        return Err(invalid());
+    }
+    Ok(Selector::new(
+        path.trim().to_string(),
+        name.trim().to_string(),
+        parsed_type,
+    ))
+}
+
+/// It parses the selector type given on the command line, case-insensitive
+pub(super) fn parse_selector_type(value: &str) -> Result<SelectorType> {
+    SelectorType::list_as_vec()
+        .into_iter()
+        .find(|t| t.to_string().eq_ignore_ascii_case(value.trim()))
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid selector type \"{}\", expected string, number, boolean or date!",
+                value
+            )
+        })
+}
+
+/// It returns the example of the selector path for the resource kind
+pub fn selector_path_example(kind: ResourceKind) -> &'static str {
+    match kind {
+        ResourceKind::Html => "e.g. body > div > h1",
+        ResourceKind::Json => "e.g. $.data[0].price or /data/0/price",
+        ResourceKind::Feed => "e.g. $.items[0].title, or $.items[*].title for a list",
+    }
+}
+
+/// It asks for the selector path and previews its matches on the page of the resource,
+/// until the user accepts the path
+pub async fn ask_selector_path(
+    message: &str,
+    initial: Option<&str>,
+    preview: &mut Preview,
+    ctx: &Context,
+) -> Result<String> {
+    let mut path = initial.unwrap_or_default().to_string();
+    loop {
+        path = Text::new(message)
+            .with_validator(required!("This field is required"))
+            .with_help_message(selector_path_example(preview.resource.kind()))
+            .with_initial_value(&path)
+            .prompt()?;
+        let matched = match preview.values(&path, ctx).await {
+            Some(Ok(values)) => values,
+            Some(Err(err)) => {
+                println!("The selector doesn't work: {}", err);
+                vec![]
+            }
+            None => return Ok(path),
+        };
+        match matched.len() {
+            0 => println!("No matches on the page"),
+            n => {
+                println!(
+                    "{} match(es), the first one is grabbed unless it's a list:",
+                    n
+                );
+                for value in matched.iter().take(PREVIEW_VALUES) {
+                    println!("  • {}", value);
+                }
+            }
+        }
+        if Confirm::new("Use this selector?")
+            .with_default(!matched.is_empty())
+            .prompt()?
+        {
+            return Ok(path);
+        }
+    }
+}
+
+/// The page of a resource to preview the values matched by its selectors, fetched on the
+/// first preview
+pub struct Preview {
+    resource: Resource,
+    enabled: bool,
+    /// The text of the page, `Some(None)` if it could not be fetched
+    page: Option<Option<String>>,
+}
+
+impl Preview {
+    pub fn new(resource: &Resource, enabled: bool) -> Self {
+        Self {
+            resource: resource.clone(),
+            enabled,
+            page: None,
+        }
+    }
+
+    /// It returns the values matched by the selector path as text, `None` if the preview is
+    /// disabled or the page could not be fetched
+    async fn values(&mut self, path: &str, ctx: &Context) -> Option<Result<Vec<String>>> {
+        if !self.enabled {
+            return None;
+        }
+        if self.page.is_none() {
+            self.page = Some(self.fetch(ctx).await);
+        }
+        let page = self.page.clone().flatten()?;
+
+        let mut selector = Selector::new(
+            path.to_string(),
+            "preview".to_string(),
+            SelectorType::String,
+        );
+        selector.list = true;
+        let selectors = [selector];
+        let values = match self.resource.kind() {
+            ResourceKind::Html => extract(&Html::parse_document(&page), &selectors),
+            ResourceKind::Json => match serde_json::from_str(&page) {
+                Ok(document) => extract_json(&document, &selectors),
+                Err(err) => return Some(Err(err.into())),
+            },
+            ResourceKind::Feed => match rvp::feed::parse(&self.resource.url, &page) {
+                Ok(document) => extract_json(&document, &selectors),
+                Err(err) => return Some(Err(err.into())),
+            },
+        };
+        let value = values.into_iter().next()?;
+        if let Some(err) = value.error {
+            return Some(Err(anyhow::anyhow!(err)));
+        }
+        let items = match value.value {
+            Value::Array(items) => items,
+            value => vec![value],
+        };
+        Some(Ok(items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(text) => text,
+                item => item.to_string(),
+            })
+            .collect()))
+    }
+
+    /// It fetches the page, asking for the value of the URL parameters first. `None` if the
+    /// value is not given or the page could not be fetched, with a warning.
+    async fn fetch(&mut self, ctx: &Context) -> Option<String> {
+        let named: Vec<String> = self
+            .resource
+            .named_params()
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        if self.resource.needs_parameter() || !named.is_empty() {
+            let param = Text::new("Parameter value for the preview:")
+                .with_help_message(
+                    "e.g. AAPL, used for all placeholders. Leave empty to skip the preview",
+                )
+                .prompt()
+                .ok()?;
+            if param.trim().is_empty() {
+                return None;
+            }
+            self.resource.mut_url_with_param(&param);
+            let named: Vec<(String, String)> = named
+                .iter()
+                .map(|name| (name.clone(), param.clone()))
+                .collect();
+            self.resource.mut_url_with_named_params(&named).ok()?;
+        }
+        ctx.log(format!("Fetching {}", self.resource.url));
+        // With the method, the body, the headers and the auth of the resource
+        match ctx.fetcher.fetch_document(&self.resource.request()).await {
+            Ok(document) => Some(document.text),
+            Err(err) => {
+                println!("Warning: the preview is not available, {}", err);
+                None
+            }
+        }
+    }
+}
+
 /// Create list of resources from user input
-fn add_resources() -> Result<Vec<Resource>> {
+async fn add_resources(preview: bool, ctx: &Context) -> Result<Vec<Resource>> {
     let mut resources: Vec<Resource> = Vec::new();
 
     'resource_loop: loop {
@@ -120,8 +498,17 @@ fn add_resources() -> Result<Vec<Resource>> {
             })
             .prompt()?;
 
-        let selectors = add_selectors()?;
-        resources.push(Resource::new(url, selectors));
+        let kind = Select::new("Response type:", ResourceKind::list_as_vec())
+            .with_help_message("JSON for the API endpoints, the selectors are JSON paths then")
+            .prompt()?;
+
+        // HTML is the default, it is not written to the config
+        let mut resource = Resource {
+            kind: (kind != ResourceKind::Html).then_some(kind),
+            ..Resource::new(url, vec![])
+        };
+        resource.selectors = add_selectors(&mut Preview::new(&resource, preview), ctx).await?;
+        resources.push(resource);
 
         println!("New Resource added!");
 
@@ -136,3 +523,68 @@ fn add_resources() -> Result<Vec<Resource>> {
 
     Ok(resources)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selector() -> Result<()> {
+        let price = parse_selector("price=#quote > span:number")?;
+        assert_eq!(price.name, "price");
+        assert_eq!(price.path, "#quote > span");
+        assert!(price.parsed_type == SelectorType::Number);
+
+        let first = parse_selector("first = li:first-child")?;
+        assert_eq!(first.name, "first");
+        assert_eq!(first.path, "li:first-child");
+        assert!(first.parsed_type == SelectorType::String);
+
+        let date = parse_selector("date=$.updated:Date")?;
+        assert!(date.parsed_type == SelectorType::Date);
+
+        assert!(parse_selector("h1").is_err());
+        assert!(parse_selector("=h1").is_err());
+        assert!(parse_selector("title=:number").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preview_values() -> Result<()> {
+        let ctx = Context::default();
+        let resource = Resource::new("https://example.com".to_string(), vec![]);
+        let mut preview = Preview::new(&resource, true);
+        preview.page = Some(Some(
+            "<ul><li>1.5</li><li>2.5</li></ul><h1>Title</h1>".to_string(),
+        ));
+        assert_eq!(
+            preview.values("li", &ctx).await.transpose()?,
+            Some(vec!["1.5".to_string(), "2.5".to_string()])
+        );
+        assert_eq!(preview.values("h2", &ctx).await.transpose()?, Some(vec![]));
+        assert!(preview
+            .values("li >", &ctx)
+            .await
+            .is_some_and(|r| r.is_err()));
+
+        let mut api = Resource::new("https://example.com/api".to_string(), vec![]);
+        api.kind = Some(ResourceKind::Json);
+        let mut preview = Preview::new(&api, true);
+        preview.page = Some(Some(
+            r#"{"items": [{"price": 1}, {"price": 2}]}"#.to_string(),
+        ));
+        assert_eq!(
+            preview.values("$.items[*].price", &ctx).await.transpose()?,
+            Some(vec!["1".to_string(), "2".to_string()])
+        );
+
+        // No page, no preview
+        assert!(Preview::new(&resource, false)
+            .values("li", &ctx)
+            .await
+            .is_none());
+        preview.page = Some(None);
+        assert!(preview.values("$.items", &ctx).await.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use serde_json::{json, Value};
+
+use crate::context::Context;
+use crate::signature::ALGORITHM;
+
+/// Print the schema of the JSON output of `batch`, to generate the types of its consumers
+///
+/// Every shape of the output is one of the `anyOf` variants: the list of the values
+/// (`--json`), the flat map (`--flatten`), the values grouped by the parameter
+/// (`--params-file`, `--param-list`), the values nested by resource (`--nest`, `schedule`),
+/// and the signed envelope of any of them (`--sign-output`).
+#[derive(Parser)]
+pub struct Args {
+    /// Format of the schema
+    #[arg(long, value_name = "FORMAT", default_value = "json")]
+    format: SchemaFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SchemaFormat {
+    /// JSON Schema (draft 2020-12)
+    Json,
+}
+
+pub async fn command(args: Args, _ctx: &Context) -> Result<()> {
+    match args.format {
+        SchemaFormat::Json => println!("{}", serde_json::to_string_pretty(&output_schema())?),
+    }
+    Ok(())
+}
+
+/// It returns the JSON Schema of the `batch` output.
+///
+/// The `value` of a value is `null` when it could not be parsed, with the reason in its
+/// `error`, and the `data` of a resource is empty when the whole resource failed, with the
+/// reason in the `error` of the resource.
+fn output_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://github.com/samgozman/rvp/output.schema.json",
+        "title": "rvp batch output",
+        "anyOf": [
+            { "$ref": "#/$defs/values" },
+            { "$ref": "#/$defs/flat" },
+            { "$ref": "#/$defs/grouped" },
+            { "$ref": "#/$defs/nested" },
+            { "$ref": "#/$defs/signed" },
+        ],
+        "$defs": {
+            "value": {
+                "description": "A parsed value, `null` with the `error` if it could not be parsed",
+                "type": ["string", "number", "boolean", "array", "object", "null"],
+            },
+            "parsed": {
+                "description": "A named value of a selector or of a computed value",
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "value": { "$ref": "#/$defs/value" },
+                    "error": {
+                        "description": "Why the value could not be parsed, only on failure",
+                        "type": "string",
+                    },
+                    "stale": {
+                        "description": "The value is from a previous run, `--fallback-to-last`",
+                        "const": true,
+                    },
+                    "time": {
+                        "description": "UTC time of the run of the stale value",
+                        "type": "string",
+                        "format": "date-time",
+                    },
+                    "anomaly": {
+                        "description": "The number is far from its previous values, `anomaly`",
+                        "type": "object",
+                        "properties": {
+                            "median": { "type": "number" },
+                            "change": {
+                                "description": "Change from the median in percent",
+                                "type": "number",
+                            },
+                            "z_score": { "type": "number" },
+                        },
+                        "required": ["median"],
+                    },
+                },
+                "required": ["name", "value"],
+                "additionalProperties": false,
+            },
+            "values": {
+                "description": "The values of all resources, `--json`",
+                "type": "array",
+                "items": { "$ref": "#/$defs/parsed" },
+            },
+            "flat": {
+                "description": "The `name -> value` map, `--json --flatten`",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/$defs/value" },
+            },
+            "grouped": {
+                "description": "The values by the parameter, `--params-file` and `--param-list`",
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "param": { "type": "string" },
+                        "data": {
+                            "oneOf": [
+                                { "$ref": "#/$defs/values" },
+                                { "$ref": "#/$defs/flat" },
+                            ],
+                        },
+                    },
+                    "required": ["param", "data"],
+                    "additionalProperties": false,
+                },
+            },
+            "resource": {
+                "description": "The values of a resource with its details",
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string" },
+                    "param": { "type": "string" },
+                    "final_url": {
+                        "description": "The URL after the redirects, only if redirected",
+                        "type": "string",
+                    },
+                    "data": { "$ref": "#/$defs/values" },
+                    "error": {
+                        "description": "Why the resource failed, the `data` is empty then",
+                        "type": "string",
+                    },
+                },
+                "required": ["url", "data"],
+                "additionalProperties": false,
+            },
+            "nested": {
+                "description": "The values by resource, `--json --nest` and the lines of `schedule`",
+                "type": "object",
+                "properties": {
+                    "time": {
+                        "description": "UTC time of the run, only in the lines of `schedule`",
+                        "type": "string",
+                        "format": "date-time",
+                    },
+                    "config": { "type": "string" },
+                    "resources": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/resource" },
+                    },
+                },
+                "required": ["config", "resources"],
+                "additionalProperties": false,
+            },
+            "signed": {
+                "description": "Any of the outputs with its signature, `--sign-output`",
+                "type": "object",
+                "properties": {
+                    "data": {
+                        "anyOf": [
+                            { "$ref": "#/$defs/values" },
+                            { "$ref": "#/$defs/flat" },
+                            { "$ref": "#/$defs/grouped" },
+                            { "$ref": "#/$defs/nested" },
+                        ],
+                    },
+                    "signature": {
+                        "type": "object",
+                        "properties": {
+                            "algorithm": { "const": ALGORITHM },
+                            "value": {
+                                "description": "Hex of the HMAC of the compact `data` with the sorted keys",
+                                "type": "string",
+                            },
+                        },
+                        "required": ["algorithm", "value"],
+                        "additionalProperties": false,
+                    },
+                },
+                "required": ["data", "signature"],
+                "additionalProperties": false,
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::batch::generate_nested_json;
+    use crate::output::{generate_flat_json, generate_grouped_json, generate_json};
+    use crate::signature::sign_json;
+    use rvp::error::RvpError;
+    use rvp::scalper::{ParsedValue, ResourceResult};
+
+    /// It checks the value with the subset of JSON Schema used by [output_schema]
+    fn conforms(value: &Value, schema: &Value, root: &Value) -> bool {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/$defs/");
+            return conforms(value, &root["$defs"][name], root);
+        }
+        if let Some(variants) = schema["oneOf"].as_array() {
+            let matching = variants.iter().filter(|s| conforms(value, s, root));
+            return matching.count() == 1;
+        }
+        if let Some(variants) = schema["anyOf"].as_array() {
+            return variants.iter().any(|s| conforms(value, s, root));
+        }
+        if let Some(constant) = schema.get("const") {
+            return value == constant;
+        }
+        let type_name = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        let types = match &schema["type"] {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => vec![type_name],
+        };
+        if !types.contains(&type_name) {
+            return false;
+        }
+        match value {
+            Value::Array(items) => items
+                .iter()
+                .all(|item| conforms(item, &schema["items"], root)),
+            Value::Object(object) => {
+                let required = schema["required"].as_array().cloned().unwrap_or_default();
+                let has_required = required
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .all(|key| object.contains_key(key));
+                has_required
+                    && object.iter().all(|(key, item)| {
+                        match (
+                            schema["properties"].get(key),
+                            &schema["additionalProperties"],
+                        ) {
+                            (Some(property), _) => conforms(item, property, root),
+                            (None, Value::Bool(false)) => false,
+                            (None, Value::Null) => true,
+                            (None, additional) => conforms(item, additional, root),
+                        }
+                    })
+            }
+            _ => true,
+        }
+    }
+
+    #[test]
+    fn test_output_schema() {
+        let values = vec![
+            ParsedValue {
+                name: "price".to_string(),
+                value: json!(1.5),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "title".to_string(),
+                value: Value::Null,
+                error: Some("no element".to_string()),
+                stale: None,
+                anomaly: None,
+            },
+        ];
+        let results = vec![
+            ResourceResult {
+                url: "https://ok.com".to_string(),
+                param: Some("AAPL".to_string()),
+                final_url: Some("https://ok.com/en/".to_string()),
+                accept_language: None,
+                language: None,
+                values: Ok(values.clone()),
+            },
+            ResourceResult {
+                url: "https://failed.com".to_string(),
+                param: None,
+                final_url: None,
+                accept_language: None,
+                language: None,
+                values: Err(RvpError::FetchError {
+                    url: "https://failed.com".to_string(),
+                    message: "timeout".to_string(),
+                }),
+            },
+        ];
+        let groups = vec![("AAPL".to_string(), values.clone())];
+
+        let schema = output_schema();
+        let outputs = [
+            generate_json(&values),
+            generate_flat_json(&values),
+            generate_grouped_json(&groups, false),
+            generate_grouped_json(&groups, true),
+            generate_nested_json("stock", &results, |text| text.to_string()),
+            sign_json(&generate_json(&values), b"key").unwrap(),
+        ];
+        for output in outputs {
+            let output: Value = serde_json::from_str(&output).unwrap();
+            assert!(conforms(&output, &schema, &schema), "{}", output);
+        }
+
+        let without_value = json!([{"name": "price"}]);
+        assert!(!conforms(&without_value, &schema, &schema));
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{value_parser, Parser};
+use rvp::{
+    fetcher::{Fetcher, RateLimitedFetcher},
+    scalper::{grab_resources, ParsedValue},
+    structure::Resource,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    fs::File,
+    io::{self, AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader},
+};
+
+use super::batch::robots_fetcher;
+use crate::context::Context;
+
+/// Run ad-hoc jobs described as JSON lines, one result line per job
+///
+/// Each line is a resource with an optional `id`, which is copied to the result:
+///
+/// ```
+/// {"id": 1, "url": "https://example.com", "selectors": [{"path": "h1", "name": "title", "parsed_type": "String"}]}
+/// ```
+#[derive(Parser)]
+pub struct Args {
+    /// Path to the file with the jobs, `-` to read them from stdin.
+    ///
+    /// The jobs are processed as they come, so rvp can be driven as a long-lived worker over a pipe.
+    #[arg(long, value_name = "FILE", value_parser = value_parser!(PathBuf))]
+    jobs: PathBuf,
+}
+
+/// A single job: the resource to grab and its identifier
+#[derive(Deserialize)]
+struct Job {
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(flatten)]
+    resource: Resource,
+}
+
+/// The result line of a job
+#[derive(Serialize)]
+pub struct JobResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    /// Line number of the job, set only if the job could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    data: Vec<ParsedValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let input: Box<dyn AsyncBufRead + Unpin> = match args.jobs.to_str() {
+        Some("-") => Box::new(BufReader::new(io::stdin())),
+        _ => Box::new(BufReader::new(File::open(&args.jobs).await?)),
+    };
+
+    // The rate limit is shared by all jobs
+    let rate_limited = ctx
+        .rate_limit
+        .map(|rate_limit| RateLimitedFetcher::new(&ctx.fetcher, rate_limit));
+    let limited: &dyn Fetcher = match &rate_limited {
+        Some(fetcher) => fetcher,
+        None => &ctx.fetcher,
+    };
+    let robots = robots_fetcher(false, limited, ctx);
+    let fetcher: &dyn Fetcher = match &robots {
+        Some(fetcher) => fetcher,
+        None => limited,
+    };
+
+    let mut stdout = io::stdout();
+    let mut lines = input.lines();
+    let mut line_number = 0;
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = run_job(fetcher, &line, line_number, true, true).await;
+        let mut output = serde_json::to_string(&result)?;
+        output.push('\n');
+        stdout.write_all(output.as_bytes()).await?;
+        stdout.flush().await?;
+    }
+
+    Ok(())
+}
+
+/// It parses the job from the line and grabs its values.
+///
+/// The `file://` URLs are read only with `local_files`, i.e. for the jobs of the local user,
+/// the others may fetch only the HTTP(S) URLs. The resources with `render = true` are grabbed
+/// only with `render`.
+pub async fn run_job(
+    fetcher: &dyn Fetcher,
+    line: &str,
+    line_number: usize,
+    local_files: bool,
+    render: bool,
+) -> JobResult {
+    let job: Job = match serde_json::from_str(line) {
+        Ok(job) => job,
+        Err(err) => {
+            return JobResult {
+                id: None,
+                line: Some(line_number),
+                url: None,
+                data: vec![],
+                error: Some(format!("invalid job: {}", err)),
+            }
+        }
+    };
+    let is_web_url = url::Url::parse(&job.resource.url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+    let rejection = match (is_web_url, job.resource.render()) {
+        (false, _) if !local_files => Some("the jobs can fetch only the HTTP(S) URLs"),
+        (_, true) if !render => Some("the jobs can't render the pages"),
+        _ => None,
+    };
+    if let Some(rejection) = rejection {
+        return JobResult {
+            id: job.id,
+            line: None,
+            url: Some(job.resource.url),
+            data: vec![],
+            error: Some(rejection.to_string()),
+        };
+    }
+
+    let mut results = grab_resources(fetcher, vec![job.resource]).await;
+    let result = results.remove(0);
+    let (data, error) = match result.values {
+        Ok(values) => (values, None),
+        Err(err) => (vec![], Some(err.to_string())),
+    };
+
+    JobResult {
+        id: job.id,
+        line: None,
+        url: Some(result.url),
+        data,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct StaticFetcher;
+
+    #[async_trait]
+    impl Fetcher for StaticFetcher {
+        async fn fetch(&self, _url: &str) -> rvp::error::Result<String> {
+            Ok("<html><body><h1>Example</h1></body></html>".to_string())
+        }
+
+        async fn render(&self, url: &str) -> rvp::error::Result<String> {
+            self.fetch(url).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_job() {
+        let line = r#"{"id": "a1", "url": "https://example.com", "selectors": [{"path": "h1", "name": "title", "parsed_type": "String"}]}"#;
+        let result =
+            serde_json::to_value(run_job(&StaticFetcher, line, 1, true, true).await).unwrap();
+        assert_eq!(result["id"], "a1");
+        assert_eq!(result["data"][0]["value"], "Example");
+        assert!(result.get("error").is_none());
+
+        let result =
+            serde_json::to_value(run_job(&StaticFetcher, "{}", 2, true, true).await).unwrap();
+        assert_eq!(result["line"], 2);
+        assert!(result["error"].is_string());
+
+        // The jobs from the network fetch only the web pages, and render them only if allowed
+        let job = |line: String, render: bool| async move {
+            let result = run_job(&StaticFetcher, &line, 3, false, render).await;
+            serde_json::to_value(result).unwrap()["error"].clone()
+        };
+        for url in [
+            "file:///etc/passwd",
+            "FILE:///etc/passwd",
+            "view-source:file:///etc/passwd",
+            "chrome://settings",
+        ] {
+            let line = format!(r#"{{"url": "{}", "selectors": []}}"#, url);
+            assert_eq!(
+                job(line, false).await,
+                "the jobs can fetch only the HTTP(S) URLs"
+            );
+        }
+        let rendered = r#"{"url": "https://example.com", "render": true, "selectors": []}"#;
+        assert_eq!(
+            job(rendered.to_string(), false).await,
+            "the jobs can't render the pages"
+        );
+        assert!(job(rendered.to_string(), true).await.is_null());
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use clap::{builder::FalseyValueParser, value_parser, Parser};
+use rvp::alert::{self, Alert};
+use rvp::cron::Schedule;
+use rvp::structure::{Config, Timezone};
+
+use super::batch::{
+    alerts_json, change_alerts, expand_sitemaps, flag_anomalies, flush_digest, generate_json_line,
+    generate_nested_json, lease_resources, record_run, report_alerts, report_errors, scrape,
+    shutdown_signal, warn_unchecked_changes, ConfigArgs, Digest, WebhookArgs,
+};
+use super::watch::{format_time, parse_interval};
+use crate::context::Context;
+use crate::output::write_to_file;
+
+/// Run a config on a cron schedule in the foreground, without the system cron
+///
+/// Each run is written as a JSON line with the time and the values grouped by resource,
+/// the same as `batch --json --nest`.
+/// The triggered alerts are printed to stderr.
+#[derive(Parser)]
+pub struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Cron expression of the runs: `minute hour day-of-month month day-of-week`.
+    ///
+    /// The times are in the `timezone` of the config, UTC by default. The resources with
+    /// their own `schedule` (or of their group) are run on it instead.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// --cron "*/15 9-16 * * mon-fri"
+    /// ```
+    #[arg(long, value_name = "EXPR", env = "RVP_CRON", value_parser = parse_schedule)]
+    cron: Schedule,
+
+    /// (Optional) Append the JSON lines to the given file instead of printing them to stdout.
+    ///
+    /// Missing parent directories will be created.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        env = "RVP_OUTPUT",
+        value_parser = value_parser!(PathBuf)
+    )]
+    output: Option<PathBuf>,
+
+    /// Run even when the market is closed according to the `market_hours` of the config
+    #[arg(long, env = "RVP_IGNORE_MARKET_HOURS", value_parser = FalseyValueParser::new())]
+    ignore_market_hours: bool,
+
+    #[command(flatten)]
+    webhook: WebhookArgs,
+
+    /// Used with `--webhook` (or the `webhook` of the settings) to call it only when a value
+    /// crosses the threshold of its `alert` rule, with the new alerts of the run in one
+    /// message instead of the results
+    #[arg(long, env = "RVP_WEBHOOK_ON_ALERT", value_parser = FalseyValueParser::new())]
+    webhook_on_alert: bool,
+
+    /// (Optional) Used with `--webhook-on-alert` to send the new alerts of all runs in one
+    /// message once per interval instead of after every run, e.g. `24h`. The alerts left
+    /// are sent when the schedule is stopped.
+    #[arg(
+        long,
+        value_name = "INTERVAL",
+        env = "RVP_DIGEST",
+        requires = "webhook_on_alert",
+        value_parser = parse_interval
+    )]
+    digest: Option<Duration>,
+
+    /// (Optional) Serve `/healthz` and `/readyz` on the address for the liveness and
+    /// readiness probes, e.g. `0.0.0.0:8080`.
+    ///
+    /// `/healthz` also returns the status of the last run of the config, and fails once the
+    /// next run is late by more than the period of the cron and a minute.
+    #[cfg(feature = "health")]
+    #[arg(long, value_name = "ADDR", env = "RVP_HEALTH")]
+    health: Option<std::net::SocketAddr>,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let config = args.config.load(ctx).await?;
+    warn_unchecked_changes(ctx, &config);
+    let webhook = args.webhook.prepare(&config, ctx)?;
+    if args.webhook_on_alert && webhook.is_none() {
+        return Err(anyhow!(
+            "--webhook-on-alert needs --webhook or the webhook of the config or the settings!"
+        ));
+    }
+    let timezone = config.timezone.unwrap_or(Timezone::UTC);
+    let display_timezone = match ctx.utc {
+        true => Timezone::UTC,
+        false => timezone,
+    };
+
+    #[cfg(feature = "health")]
+    let health = match args.health {
+        Some(address) => Some(crate::health::Health::serve(address).await?),
+        None => None,
+    };
+
+    let mut digest = args.digest.map(Digest::new);
+    let mut previous_alerts: Vec<Alert> = Vec::new();
+    let redact = |text: &str| ctx.redact(text).into_owned();
+    let schedules = schedules(&config, &args.cron);
+    let mut after = Utc::now();
+    let shutdown = shutdown_signal()?;
+    tokio::pin!(shutdown);
+    loop {
+        let Some(next) = next_run(&schedules, &after.with_timezone(&timezone.0)) else {
+            return Err(anyhow!(
+                "Cron expression \"{}\" never matches a date!",
+                args.cron
+            ));
+        };
+        let next = next.with_timezone(&Utc);
+        if !ctx.quiet {
+            eprintln!(
+                "Next run of \"{}\" at {}. Press Ctrl+C to stop.",
+                config.name,
+                format_time(next, display_timezone)
+            );
+        }
+        #[cfg(feature = "health")]
+        if let Some(health) = &health {
+            // The period until the following run, e.g. a night for `* 9-16 * * *`
+            let period = next_run(&schedules, &next.with_timezone(&timezone.0))
+                .and_then(|following| (following.with_timezone(&Utc) - next).to_std().ok())
+                .unwrap_or_default();
+            health.expect(next, period);
+        }
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        // A signal during a run stops the schedule after it
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = &mut shutdown => break,
+        }
+        let due = due_resources(
+            &config,
+            &args.cron,
+            &after.with_timezone(&timezone.0),
+            &next.with_timezone(&timezone.0),
+        );
+        // A run longer than the interval skips the missed runs instead of catching up
+        after = next.max(Utc::now());
+
+        if !args.ignore_market_hours && !config.is_market_open(next) {
+            ctx.log("Market is closed, the run is skipped");
+            continue;
+        }
+        if due.resources.len() < config.resources.len() {
+            ctx.log(format!(
+                "Running {} of {} resources, the others have their own schedule",
+                due.resources.len(),
+                config.resources.len()
+            ));
+        }
+
+        let leased = match lease_resources(due, ctx).await {
+            Ok(leased) => expand_sitemaps(leased, ctx).await,
+            Err(err) => Err(err),
+        };
+        let leased = match leased {
+            Ok(leased) => leased,
+            Err(err) => {
+                // Keep running, the backend or the sitemap might be back on the next run
+                eprintln!("{}", ctx.redact(&format!("{:?}", err)));
+                continue;
+            }
+        };
+        let resources = leased.resources.clone();
+        let mut results = scrape(leased, ctx).await;
+        flag_anomalies(ctx, &config.name, &resources, &mut results);
+        let changes = change_alerts(ctx, &config.name, &resources, &results);
+        record_run(ctx, "schedule", &config.name, &results);
+        #[cfg(feature = "health")]
+        if let Some(health) = &health {
+            health.record(&config.name, &results);
+        }
+        let mut alerts = alert::evaluate(&resources, &results);
+        alerts.extend(changes);
+
+        let time = next.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let line = generate_json_line(&config.name, &results, &time, redact);
+        match &args.output {
+            // Keep running when the disk is full, the next run might be written
+            Some(path) => {
+                if let Err(err) = write_to_file(path, &line, true) {
+                    eprintln!("Failed to write {}: {}", path.display(), err);
+                }
+            }
+            None => println!("{}", line),
+        }
+        if !ctx.quiet {
+            report_errors(&results, ctx);
+        }
+        report_alerts(&alerts, ctx);
+
+        // The alerts which were already triggered by the previous run are not sent again
+        let crossed = alert::crossed(&alerts, &previous_alerts);
+        let body = match (args.webhook_on_alert, &mut digest) {
+            (true, Some(digest)) => {
+                let crossed = crossed.into_iter().cloned().collect();
+                digest.collect(crossed, Instant::now()).map(|alerts| {
+                    alerts_json(&config.name, &alerts.iter().collect::<Vec<_>>(), redact)
+                })
+            }
+            (true, None) if crossed.is_empty() => None,
+            (true, None) => Some(alerts_json(&config.name, &crossed, redact)),
+            (false, _) => Some(generate_nested_json(&config.name, &results, redact)),
+        };
+        if let (Some(webhook), Some(body)) = (&webhook, body) {
+            // Keep running, the endpoint might be back on the next run
+            if let Err(err) = webhook.deliver(body, ctx).await {
+                eprintln!("{}", ctx.redact(&err.to_string()));
+            }
+        }
+        // The alerts of the resources which were not run are kept for their next run
+        previous_alerts.retain(|alert| !resources.iter().any(|r| r.url == alert.url));
+        previous_alerts.extend(alerts);
+    }
+
+    flush_digest(digest, webhook.as_ref(), &config.name, ctx).await;
+    Ok(())
+}
+
+/// It returns the cron of the command and the own schedules of the resources, without the
+/// duplicates
+fn schedules<'a>(config: &'a Config, cron: &'a Schedule) -> Vec<&'a Schedule> {
+    let mut schedules = vec![cron];
+    for schedule in config.resources.iter().filter_map(|r| r.schedule.as_ref()) {
+        if !schedules.contains(&schedule) {
+            schedules.push(schedule);
+        }
+    }
+    schedules
+}
+
+/// It returns the first time after the given one matching any of the schedules
+fn next_run<Tz: TimeZone>(schedules: &[&Schedule], after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    schedules
+        .iter()
+        .filter_map(|schedule| schedule.next_after(after))
+        .min()
+}
+
+/// It returns the config with only the resources due at the `next` run after the `after`
+/// time: the ones with their own schedule matching it, and the others if the `cron` does
+fn due_resources<Tz: TimeZone>(
+    config: &Config,
+    cron: &Schedule,
+    after: &DateTime<Tz>,
+    next: &DateTime<Tz>,
+) -> Config {
+    let mut due = config.clone();
+    due.resources.retain(|resource| {
+        let schedule = resource.schedule.as_ref().unwrap_or(cron);
+        schedule.next_after(after).as_ref() == Some(next)
+    });
+    due
+}
+
+/// Parse the cron expression of `--cron`
+fn parse_schedule(expression: &str) -> Result<Schedule> {
+    Ok(expression.parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::structure::Resource;
+
+    #[test]
+    fn test_due_resources() {
+        let resource = |url: &str, schedule: Option<&str>| Resource {
+            schedule: schedule.map(|s| s.parse().unwrap()),
+            ..Resource::new(url.to_string(), vec![])
+        };
+        let config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![
+                resource("https://a.com", None),
+                resource("https://b.com", Some("*/5 * * * *")),
+                resource("https://c.com", Some("0 * * * *")),
+            ],
+        );
+        let cron: Schedule = "*/15 * * * *".parse().unwrap();
+        let schedules = schedules(&config, &cron);
+        assert_eq!(schedules.len(), 3);
+
+        let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        let urls = |after: &str| {
+            let next = next_run(&schedules, &at(after)).unwrap();
+            let due = due_resources(&config, &cron, &at(after), &next);
+            let urls: Vec<String> = due.resources.into_iter().map(|r| r.url).collect();
+            (next.to_rfc3339(), urls)
+        };
+        assert_eq!(
+            urls("2024-05-01T10:01:00Z"),
+            (
+                "2024-05-01T10:05:00+00:00".to_string(),
+                vec!["https://b.com".to_string()]
+            )
+        );
+        assert_eq!(
+            urls("2024-05-01T10:10:00Z"),
+            (
+                "2024-05-01T10:15:00+00:00".to_string(),
+                vec!["https://a.com".to_string(), "https://b.com".to_string()]
+            )
+        );
+        assert_eq!(urls("2024-05-01T10:55:00Z").1.len(), 3);
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::{self, Read};
+
+use anyhow::Result;
+use clap::Parser;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use rvp::{
+    error::RvpError,
+    fetcher::{decode, Fetcher, Request},
+    suggest::suggest,
+};
+
+use super::grab::page_url;
+use crate::context::Context;
+use crate::output::truncate;
+
+/// Suggest the CSS selectors for the value, found on the page by its text
+///
+/// The selectors are ranked from the most stable ones: the naming attributes (e.g. `itemprop`),
+/// the ids, the classes, and the positions of the elements last. Use one of them in
+/// `rvp grab -s` or in a config.
+#[derive(Parser)]
+pub struct Args {
+    /// URL to web page to search, a path to a saved page, or `-` to read the page from stdin.
+    #[arg(short, long, value_name = "URL")]
+    from: String,
+
+    /// Text of the value on the page, e.g. `--contains "123.45"`. The case and the spaces
+    /// are ignored.
+    #[arg(short, long, value_name = "TEXT")]
+    contains: String,
+
+    /// Maximum number of the suggestions to show.
+    #[arg(short, long, value_name = "N", default_value_t = 10)]
+    limit: usize,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let html = match args.from.as_str() {
+        "-" => {
+            ctx.log("Reading the page from stdin");
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            decode(&bytes, None)
+        }
+        from => {
+            let url = page_url(from)?;
+            ctx.log(format!("Fetching {}", url));
+            let request = Request::get(&url).with_local_files();
+            ctx.fetcher.fetch_document(&request).await?.text
+        }
+    };
+
+    let suggestions = suggest(&html, &args.contains);
+    if suggestions.is_empty() {
+        return Err(
+            RvpError::NotFoundError(format!("No element contains \"{}\"!", args.contains)).into(),
+        );
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["#", "Selector", "Matches", "Text"]);
+    for (i, suggestion) in suggestions.iter().take(args.limit).enumerate() {
+        let text = match ctx.max_value_chars() {
+            Some(max_chars) => truncate(&suggestion.text, max_chars),
+            None => suggestion.text.clone(),
+        };
+        table.add_row(vec![
+            (i + 1).to_string(),
+            suggestion.selector.clone(),
+            suggestion.matches.to_string(),
+            text,
+        ]);
+    }
+    ctx.style_table(&mut table);
+    println!("{}", table);
+    if suggestions.iter().take(args.limit).any(|s| s.matches > 1) {
+        println!("The selectors with more than one match grab the first element only");
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use clap::{builder::RangedU64ValueParser, Parser};
+use rvp::{
+    fetcher::Fetcher,
+    scalper::{probe_resources, probe_stability},
+};
+
+use super::batch::{expand_sitemaps, rate_limited_fetcher, robots_fetcher, ConfigArgs};
+use crate::context::Context;
+use crate::output::{generate_probe_table, generate_stability_table};
+
+/// Pause between the probes of `--stability` when the config has no rate limit
+const STABILITY_PAUSE: Duration = Duration::from_secs(1);
+
+/// Fetch the resources of the config and check every selector against the live pages
+///
+/// Prints a pass/fail table with the text found by each selector and the converted value.
+/// Useful to find out which selectors broke after a site changed its markup.
+/// With `--stability N`, the flaky selectors are found before they pollute the history.
+#[derive(Parser)]
+pub struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// (Optional) Probe every resource N times and report the selectors with varying
+    /// match counts or values, and the ones failing only sometimes.
+    ///
+    /// The probes respect the rate limit, without one they are a second apart.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(2..)
+    )]
+    stability: Option<usize>,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let config = args.config.load(ctx).await?;
+    // The selectors are checked against the pages of the sitemaps, not the sitemaps themselves
+    let config = expand_sitemaps(config, ctx).await?;
+    ctx.log(format!(
+        "Testing {} resources of \"{}\"",
+        config.resources.len(),
+        config.name
+    ));
+
+    let rate_limited = rate_limited_fetcher(&config, &ctx.fetcher, ctx);
+    let limited: &dyn Fetcher = match &rate_limited {
+        Some(fetcher) => fetcher,
+        None => &ctx.fetcher,
+    };
+    let robots = robots_fetcher(config.respect_robots, limited, ctx);
+    let fetcher: &dyn Fetcher = match &robots {
+        Some(fetcher) => fetcher,
+        None => limited,
+    };
+    let concurrency = ctx.concurrency.unwrap_or(config.resources.len());
+
+    if let Some(probes) = args.stability {
+        let mut rounds = Vec::with_capacity(probes);
+        for i in 0..probes {
+            if i > 0 && rate_limited.is_none() {
+                tokio::time::sleep(STABILITY_PAUSE).await;
+            }
+            ctx.log(format!("Probe {} of {}", i + 1, probes));
+            rounds.push(probe_resources(fetcher, config.resources.clone(), concurrency).await);
+        }
+
+        let summary = probe_stability(&config.resources, &rounds);
+        let mut table = generate_stability_table(&summary, &ctx.redactor());
+        ctx.style_table(&mut table);
+        println!("{}", table);
+
+        let unstable = summary
+            .iter()
+            .filter(|s| s.is_flaky() || s.is_broken())
+            .count();
+        if unstable > 0 {
+            return Err(anyhow!(
+                "{} of {} selectors are flaky or broken!",
+                unstable,
+                summary.len()
+            ));
+        }
+        println!(
+            "All {} selectors are stable over {} probes",
+            summary.len(),
+            probes
+        );
+        return Ok(());
+    }
+
+    let results = probe_resources(fetcher, config.resources, concurrency).await;
+
+    let mut table = generate_probe_table(&results, &ctx.redactor());
+    ctx.style_table(&mut table);
+    println!("{}", table);
+
+    let mut total = 0;
+    let mut failed = 0;
+    for result in results.iter() {
+        match &result.probes {
+            Ok(probes) => {
+                total += probes.len();
+                failed += probes
+                    .iter()
+                    .filter(|p| !p.matched || p.value.is_err())
+                    .count();
+            }
+            Err(_) => {
+                total += 1;
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} of {} checks failed!", failed, total));
+    }
+
+    println!("All {} checks passed", total);
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use clap::{builder::FalseyValueParser, Parser};
+
+use crate::context::Context;
+use crate::output::generate_table_with_changes;
+use rvp::alert::{self, Alert};
+use rvp::compute;
+use rvp::scalper::ParsedValue;
+use rvp::structure::Timezone;
+
+use super::batch::{
+    alerts_json, change_alerts, collect_values, expand_sitemaps, flag_anomalies, flush_digest,
+    generate_nested_json, lease_resources, record_run, report_alerts, report_errors, scrape,
+    shutdown_signal, warn_unchecked_changes, ConfigArgs, Digest, WebhookArgs,
+};
+
+/// Re-run a config periodically and print the refreshed values
+#[derive(Parser)]
+#[command(visible_alias = "w")]
+pub struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Interval between the runs.
+    ///
+    /// Supports `s`, `m` and `h` suffixes. Plain numbers are treated as seconds.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// --interval 5m
+    /// ```
+    #[arg(
+        short,
+        long,
+        default_value = "30s",
+        env = "RVP_INTERVAL",
+        value_parser = parse_interval
+    )]
+    interval: Duration,
+
+    /// Highlight the values that changed since the previous run
+    #[arg(long)]
+    highlight: bool,
+
+    /// Run even when the market is closed according to the `market_hours` of the config
+    #[arg(long, env = "RVP_IGNORE_MARKET_HOURS", value_parser = FalseyValueParser::new())]
+    ignore_market_hours: bool,
+
+    #[command(flatten)]
+    webhook: WebhookArgs,
+
+    /// Used with `--webhook` (or the `webhook` of the settings) to call it only when a value
+    /// crosses the threshold of its `alert` rule, with the new alerts as the body instead of
+    /// the results
+    #[arg(long, env = "RVP_WEBHOOK_ON_ALERT", value_parser = FalseyValueParser::new())]
+    webhook_on_alert: bool,
+
+    /// (Optional) Used with `--webhook-on-alert` to send the new alerts of all runs in one
+    /// message once per interval instead of after every run, e.g. `1h`. The alerts left are
+    /// sent when the watch is stopped.
+    #[arg(
+        long,
+        value_name = "INTERVAL",
+        env = "RVP_DIGEST",
+        requires = "webhook_on_alert",
+        value_parser = parse_interval
+    )]
+    digest: Option<Duration>,
+
+    /// (Optional) Serve `/healthz` and `/readyz` on the address for the liveness and
+    /// readiness probes, e.g. `0.0.0.0:8080`.
+    ///
+    /// `/healthz` also returns the status of the last run of the config, and fails once the
+    /// next run is late by more than the interval and a minute.
+    #[cfg(feature = "health")]
+    #[arg(long, value_name = "ADDR", env = "RVP_HEALTH")]
+    health: Option<std::net::SocketAddr>,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let config = args.config.load(ctx).await?;
+    warn_unchecked_changes(ctx, &config);
+    let webhook = args.webhook.prepare(&config, ctx)?;
+    if args.webhook_on_alert && webhook.is_none() {
+        return Err(anyhow!(
+            "--webhook-on-alert needs --webhook or the webhook of the config or the settings!"
+        ));
+    }
+
+    let timezone = match ctx.utc {
+        true => Timezone::UTC,
+        false => config.timezone.unwrap_or(Timezone::UTC),
+    };
+
+    #[cfg(feature = "health")]
+    let health = match args.health {
+        Some(address) => Some(crate::health::Health::serve(address).await?),
+        None => None,
+    };
+
+    let mut previous: Option<Vec<ParsedValue>> = None;
+    let mut previous_alerts: Vec<Alert> = Vec::new();
+    let mut digest = args.digest.map(Digest::new);
+    let redact = |text: &str| ctx.redact(text).into_owned();
+    let mut market_closed = false;
+    let mut ticker = tokio::time::interval(args.interval);
+    let shutdown = shutdown_signal()?;
+    tokio::pin!(shutdown);
+    loop {
+        // A signal during a run stops the watch after it
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = &mut shutdown => break,
+        }
+        #[cfg(feature = "health")]
+        if let Some(health) = &health {
+            health.expect(Utc::now() + args.interval, args.interval);
+        }
+
+        // The values can't change while the market is closed, so the sources are not requested
+        if !args.ignore_market_hours && !config.is_market_open(Utc::now()) {
+            if !market_closed {
+                println!(
+                    "Market is closed at {}, waiting for it to open. Press Ctrl+C to stop.",
+                    format_time(Utc::now(), timezone)
+                );
+                market_closed = true;
+            }
+            continue;
+        }
+        market_closed = false;
+
+        let leased = match lease_resources(config.clone(), ctx).await {
+            Ok(leased) => expand_sitemaps(leased, ctx).await,
+            Err(err) => Err(err),
+        };
+        let leased = match leased {
+            Ok(leased) => leased,
+            Err(err) => {
+                // Keep watching, the backend or the sitemap might be back on the next run
+                eprintln!("{}", ctx.redact(&format!("{:?}", err)));
+                continue;
+            }
+        };
+        let resources = leased.resources.clone();
+        let mut results = scrape(leased, ctx).await;
+        flag_anomalies(ctx, &config.name, &resources, &mut results);
+        let changes = change_alerts(ctx, &config.name, &resources, &results);
+        record_run(ctx, "watch", &config.name, &results);
+        #[cfg(feature = "health")]
+        if let Some(health) = &health {
+            health.record(&config.name, &results);
+        }
+        let mut values = collect_values(&results);
+        // The previous values come from the previous run of the watch
+        compute::append_computed(
+            &config.computed,
+            &mut values,
+            previous.as_deref().unwrap_or_default(),
+        );
+        let mut alerts = alert::evaluate(&resources, &results);
+        alerts.extend(changes);
+
+        let changes = match args.highlight {
+            true => previous.as_deref(),
+            false => None,
+        };
+        let mut table = generate_table_with_changes(&values, changes, ctx.max_value_chars());
+        ctx.style_table(&mut table);
+
+        // Clear the terminal and move the cursor to the top left corner
+        print!("\x1B[2J\x1B[1;1H");
+        println!("{}", table);
+        // Keep watching on errors, the next run might succeed
+        if !ctx.quiet {
+            report_errors(&results, ctx);
+        }
+        report_alerts(&alerts, ctx);
+        // The alerts which were already triggered by the previous run are not sent again
+        let crossed = alert::crossed(&alerts, &previous_alerts);
+        let body = match (args.webhook_on_alert, &mut digest) {
+            (true, Some(digest)) => {
+                let crossed = crossed.into_iter().cloned().collect();
+                digest.collect(crossed, Instant::now()).map(|alerts| {
+                    alerts_json(&config.name, &alerts.iter().collect::<Vec<_>>(), redact)
+                })
+            }
+            (true, None) if crossed.is_empty() => None,
+            (true, None) => Some(alerts_json(&config.name, &crossed, redact)),
+            (false, _) => Some(generate_nested_json(&config.name, &results, redact)),
+        };
+        if let (Some(webhook), Some(body)) = (&webhook, body) {
+            if let Err(err) = webhook.deliver(body, ctx).await {
+                eprintln!("{}", ctx.redact(&err.to_string()));
+            }
+        }
+        println!(
+            "Updated at {}. Refreshing every {}s. Press Ctrl+C to stop.",
+            format_time(Utc::now(), timezone),
+            args.interval.as_secs()
+        );
+
+        previous = Some(values);
+        previous_alerts = alerts;
+    }
+
+    flush_digest(digest, webhook.as_ref(), &config.name, ctx).await;
+    Ok(())
+}
+
+/// Format the time of the run in the timezone, e.g. `2024-05-01 10:30:00 EDT`
+pub fn format_time(time: DateTime<Utc>, timezone: Timezone) -> String {
+    time.with_timezone(&timezone.0)
+        .format("%Y-%m-%d %H:%M:%S %Z")
+        .to_string()
+}
+
+/// Parse the interval string like `30s`, `5m` or `1h` into a [Duration]
+pub(super) fn parse_interval(interval: &str) -> Result<Duration> {
+    let interval = interval.trim();
+    let (number, multiplier) = match interval.chars().last() {
+        Some('s') => (&interval[..interval.len() - 1], 1),
+        Some('m') => (&interval[..interval.len() - 1], 60),
+        Some('h') => (&interval[..interval.len() - 1], 60 * 60),
+        _ => (interval, 1),
+    };
+
+    let seconds: u64 = match number.parse() {
+        Ok(seconds) => seconds,
+        Err(_) => return Err(anyhow!("Invalid interval \"{}\"!", interval)),
+    };
+    if seconds == 0 {
+        return Err(anyhow!("Interval should be greater than zero!"));
+    }
+
+    Ok(Duration::from_secs(seconds * multiplier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() -> Result<()> {
+        assert_eq!(parse_interval("30")?, Duration::from_secs(30));
+        assert_eq!(parse_interval("30s")?, Duration::from_secs(30));
+        assert_eq!(parse_interval("5m")?, Duration::from_secs(300));
+        assert_eq!(parse_interval("1h")?, Duration::from_secs(3600));
+
+        parse_interval("0s").expect_err("should fail with zero interval!");
+        parse_interval("abc").expect_err("should fail with invalid interval!");
+        parse_interval("").expect_err("should fail with empty interval!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_time() -> Result<()> {
+        let time = DateTime::parse_from_rfc3339("2024-05-01T14:30:00Z")?.with_timezone(&Utc);
+        assert_eq!(
+            format_time(time, "America/New_York".parse()?),
+            "2024-05-01 10:30:00 EDT"
+        );
+        assert_eq!(format_time(time, Timezone::UTC), "2024-05-01 14:30:00 UTC");
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use clap::{builder::FalseyValueParser, Parser};
+use rvp::fetcher::{Fetcher, RateLimitedFetcher};
+
+use super::batch::robots_fetcher;
+use super::run::run_job;
+use crate::context::Context;
+use crate::redis::{Client, Reply};
+
+/// Consume scrape jobs from a Redis queue and push the results back
+///
+/// The jobs have the same format as in `rvp run`. Start as many workers as needed,
+/// every job is taken by a single worker.
+#[derive(Parser)]
+pub struct Args {
+    /// URL of the Redis server: `redis://[:password@]host[:port][/db]`
+    #[arg(long, value_name = "URL", env = "RVP_REDIS")]
+    redis: String,
+
+    /// Name of the list to pop the jobs from
+    #[arg(long, value_name = "NAME", env = "RVP_QUEUE")]
+    queue: String,
+
+    /// (Optional) Name of the list to push the results to. Defaults to `<queue>:results`.
+    #[arg(long, value_name = "NAME", env = "RVP_RESULTS")]
+    results: Option<String>,
+
+    /// Render the pages of the jobs with `render = true` in a headless browser, only for the
+    /// trusted queues. Such jobs fail by default.
+    #[arg(long, env = "RVP_ALLOW_RENDER", value_parser = FalseyValueParser::new())]
+    allow_render: bool,
+}
+
+pub async fn command(args: Args, ctx: &Context) -> Result<()> {
+    let results_queue = args
+        .results
+        .unwrap_or_else(|| format!("{}:results", args.queue));
+
+    // Jobs are popped and results are pushed over separate connections, so a blocking pop
+    // does not hold the results
+    let mut jobs = Client::connect(&args.redis).await?;
+    let mut results = Client::connect(&args.redis).await?;
+
+    // The HTTP client and the rate limit are shared by all jobs
+    let rate_limited = ctx
+        .rate_limit
+        .map(|rate_limit| RateLimitedFetcher::new(&ctx.fetcher, rate_limit));
+    let limited: &dyn Fetcher = match &rate_limited {
+        Some(fetcher) => fetcher,
+        None => &ctx.fetcher,
+    };
+    let robots = robots_fetcher(false, limited, ctx);
+    let fetcher: &dyn Fetcher = match &robots {
+        Some(fetcher) => fetcher,
+        None => limited,
+    };
+
+    ctx.log(format!(
+        "Waiting for jobs in \"{}\", results go to \"{}\"",
+        args.queue, results_queue
+    ));
+    let mut job_number = 0;
+    loop {
+        // Block until a job is available
+        let payload = match jobs.command(&["BLPOP", &args.queue, "0"]).await? {
+            Reply::Array(Some(mut reply)) if reply.len() == 2 => match reply.pop() {
+                Some(Reply::Bulk(Some(payload))) => payload,
+                _ => return Err(anyhow!("Unexpected reply to BLPOP")),
+            },
+            _ => return Err(anyhow!("Unexpected reply to BLPOP")),
+        };
+        job_number += 1;
+
+        // The jobs come over the network, so they never read the local files
+        let result = run_job(fetcher, &payload, job_number, false, args.allow_render).await;
+        let result = serde_json::to_string(&result)?;
+        results.command(&["RPUSH", &results_queue, &result]).await?;
+        ctx.log(format!("Job #{} is done", job_number));
+    }
+}
@@ -0,0 +1,387 @@
+//! Values computed from the parsed values at output time, the [Computed] values of the config.
+//!
+//! An expression is an arithmetic of numbers and names of the values:
+//!
+//! ```text
+//! change = price - prev_price
+//! spread = (ask - bid) / ask * 100
+//! ```
+//!
+//! The names are the output names of the values (after the `key_template`), or of the computed
+//! values defined above. `prev_` before a name is the value of the previous run. The names with
+//! other characters than letters, digits and `_` are quoted with backticks: `` `Price (USD)` ``.
+use serde_json::Value;
+
+use crate::error::{Result, RvpError};
+use crate::scalper::ParsedValue;
+use crate::structure::Computed;
+
+/// Prefix of the names of the previous values in the expressions
+pub const PREVIOUS_PREFIX: &str = "prev_";
+
+/// A parsed expression
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    /// A value by its name
+    Name(String),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// It parses the expression
+    pub fn parse(text: &str) -> Result<Expr> {
+        let error = |message: String| {
+            RvpError::ConfigError(format!("Invalid expression \"{}\": {}", text, message))
+        };
+        let tokens = tokenize(text).map_err(error)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let expr = parser.sum().map_err(error)?;
+        match parser.tokens.get(parser.next) {
+            Some(token) => Err(error(format!("unexpected {}", token))),
+            None => Ok(expr),
+        }
+    }
+
+    /// It returns the names used in the expression, without duplicates
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Name(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Expr::Neg(expr) => expr.collect_names(names),
+            Expr::Binary(_, left, right) => {
+                left.collect_names(names);
+                right.collect_names(names);
+            }
+        }
+    }
+
+    /// It evaluates the expression with the numbers of the names returned by `value`, fails
+    /// with the reason if a name has no number or the result is not a number
+    pub fn eval(
+        &self,
+        value: &impl Fn(&str) -> std::result::Result<f64, String>,
+    ) -> std::result::Result<f64, String> {
+        let result = match self {
+            Expr::Number(number) => *number,
+            Expr::Name(name) => value(name)?,
+            Expr::Neg(expr) => -expr.eval(value)?,
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (left.eval(value)?, right.eval(value)?);
+                match op {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    _ if right == 0.0 => return Err("division by zero".to_string()),
+                    _ => left / right,
+                }
+            }
+        };
+        match result.is_finite() {
+            true => Ok(result),
+            false => Err("the result is not a finite number".to_string()),
+        }
+    }
+}
+
+/// It returns the names of the values whose previous values are used by the expressions,
+/// without the [PREVIOUS_PREFIX]
+pub fn previous_names(computed: &[Computed]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for expr in computed.iter().filter_map(|c| Expr::parse(&c.expr).ok()) {
+        for name in expr.names() {
+            if let Some(name) = name.strip_prefix(PREVIOUS_PREFIX) {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// It appends the computed values to the parsed values.
+///
+/// A value which could not be computed is `null` with the reason in the `error`, the same way
+/// as the values which could not be parsed.
+///
+/// Arguments:
+///
+/// - `computed`: The computed values of the config, in order.
+/// - `values`: The parsed values of the run.
+/// - `previous`: The values of the previous run, for the `prev_` names.
+pub fn append_computed(
+    computed: &[Computed],
+    values: &mut Vec<ParsedValue>,
+    previous: &[ParsedValue],
+) {
+    for c in computed {
+        let result = Expr::parse(&c.expr)
+            .map_err(|err| err.to_string())
+            .and_then(|expr| expr.eval(&|name| lookup(name, values, previous)));
+        values.push(match result {
+            Ok(number) => ParsedValue {
+                name: c.name.clone(),
+                value: Value::from(number),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            Err(error) => ParsedValue {
+                name: c.name.clone(),
+                value: Value::Null,
+                error: Some(error),
+                stale: None,
+                anomaly: None,
+            },
+        });
+    }
+}
+
+/// It returns the number of the value by its name, the later values overwrite the earlier ones
+fn lookup(
+    name: &str,
+    values: &[ParsedValue],
+    previous: &[ParsedValue],
+) -> std::result::Result<f64, String> {
+    let find = |values: &[ParsedValue], name: &str| {
+        values
+            .iter()
+            .rev()
+            .find(|v| v.name == name)
+            .map(|v| v.value.clone())
+    };
+    let value = match (find(values, name), name.strip_prefix(PREVIOUS_PREFIX)) {
+        (Some(value), _) => value,
+        (None, Some(current)) => match find(previous, current) {
+            Some(value) => value,
+            None => return Err(format!("no previous value of \"{}\"", current)),
+        },
+        (None, None) => return Err(format!("unknown value \"{}\"", name)),
+    };
+    match value {
+        Value::Number(number) => number
+            .as_f64()
+            .ok_or_else(|| format!("value \"{}\" is not a number", name)),
+        Value::Null => Err(format!("value \"{}\" is empty", name)),
+        _ => Err(format!("value \"{}\" is not a number", name)),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "number {}", number),
+            Token::Name(name) => write!(f, "name \"{}\"", name),
+            Token::Op(op) => write!(f, "\"{}\"", op),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '+' | '-' | '*' | '/' | '(' | ')' => tokens.push(Token::Op(c)),
+            '`' => {
+                let rest = &text[start + 1..];
+                let Some(end) = rest.find('`') else {
+                    return Err("unclosed \"`\"".to_string());
+                };
+                tokens.push(Token::Name(rest[..end].to_string()));
+                while chars.next_if(|(i, _)| *i <= start + end + 1).is_some() {}
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = start + 1;
+                while let Some((i, _)) = chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.') {
+                    end = i + 1;
+                }
+                let number = &text[start..end];
+                match number.parse() {
+                    Ok(number) => tokens.push(Token::Number(number)),
+                    Err(_) => return Err(format!("invalid number \"{}\"", number)),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    end = i + c.len_utf8();
+                }
+                tokens.push(Token::Name(text[start..end].to_string()));
+            }
+            c => return Err(format!("unexpected \"{}\"", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser of the tokens
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    /// `sum = product (("+" | "-") product)*`
+    fn sum(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.next_op(&['+', '-']) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    /// `product = unary (("*" | "/") unary)*`
+    fn product(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.next_op(&['*', '/']) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    /// `unary = "-" unary | number | name | "(" sum ")"`
+    fn unary(&mut self) -> std::result::Result<Expr, String> {
+        if self.next_op(&['-']).is_some() {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        let token = self.tokens.get(self.next);
+        self.next += 1;
+        match token {
+            Some(Token::Number(number)) => Ok(Expr::Number(*number)),
+            Some(Token::Name(name)) => Ok(Expr::Name(name.clone())),
+            Some(Token::Op('(')) => {
+                let expr = self.sum()?;
+                match self.next_op(&[')']) {
+                    Some(_) => Ok(expr),
+                    None => Err("unclosed \"(\"".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    /// It consumes the next token if it is one of the operators
+    fn next_op(&mut self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.next) {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                self.next += 1;
+                Some(*op)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(name: &str, value: Value) -> ParsedValue {
+        ParsedValue {
+            name: name.to_string(),
+            value,
+            error: None,
+            stale: None,
+            anomaly: None,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Expr::parse("-a + 2 * (b - 1)").unwrap(),
+            Expr::Binary(
+                '+',
+                Box::new(Expr::Neg(Box::new(Expr::Name("a".to_string())))),
+                Box::new(Expr::Binary(
+                    '*',
+                    Box::new(Expr::Number(2.0)),
+                    Box::new(Expr::Binary(
+                        '-',
+                        Box::new(Expr::Name("b".to_string())),
+                        Box::new(Expr::Number(1.0))
+                    ))
+                ))
+            )
+        );
+        assert_eq!(
+            Expr::parse("`Price (USD)` / prev_price").unwrap().names(),
+            vec!["Price (USD)", "prev_price"]
+        );
+        for invalid in ["", "a +", "(a", "a b", "1.2.3", "a % b", "`a"] {
+            assert!(Expr::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_append_computed() {
+        let computed = |name: &str, expr: &str| Computed {
+            name: name.to_string(),
+            expr: expr.to_string(),
+        };
+        let computed = vec![
+            computed("change", "price - prev_price"),
+            computed("percent", "change / prev_price * 100"),
+            computed("mid", "(bid + ask) / 2"),
+            computed("ratio", "price / zero"),
+            computed("invalid", "price +"),
+        ];
+        assert_eq!(previous_names(&computed), vec!["price"]);
+
+        let mut values = vec![
+            value("price", Value::from(110)),
+            value("bid", Value::from(1.5)),
+            value("ask", Value::String("n/a".to_string())),
+            value("zero", Value::from(0)),
+        ];
+        append_computed(&computed, &mut values, &[value("price", Value::from(100))]);
+
+        let results: Vec<(&str, &Value, Option<&str>)> = values[4..]
+            .iter()
+            .map(|v| (v.name.as_str(), &v.value, v.error.as_deref()))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ("change", &Value::from(10.0), None),
+                ("percent", &Value::from(10.0), None),
+                ("mid", &Value::Null, Some("value \"ask\" is not a number")),
+                ("ratio", &Value::Null, Some("division by zero")),
+                (
+                    "invalid",
+                    &Value::Null,
+                    Some("Invalid expression \"price +\": unexpected end")
+                ),
+            ]
+        );
+
+        let mut values = vec![value("price", Value::from(110))];
+        append_computed(&computed[..1], &mut values, &[]);
+        assert_eq!(
+            values[1].error.as_deref(),
+            Some("no previous value of \"price\"")
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Cache of the parsed and checked configs, so the frequent runs of big configs (e.g. from
+//! cron) skip parsing and checking the config file when it has not changed
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use rvp::structure::{Config, ConfigFormat};
+use rvp::validate::validate;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Directory of the cached configs inside the cache directory
+const CONFIGS_DIR: &str = "configs";
+
+/// A parsed config with the problems found by [validate].
+///
+/// The config is cached as it is in the file: the `${NAME}` references of the auth are
+/// resolved only when the requests are sent, so their values never reach the cache.
+#[derive(Serialize, Deserialize)]
+pub struct CachedConfig {
+    pub config: Config,
+    /// The problems of the config, as printed by `rvp check`
+    pub problems: Vec<String>,
+}
+
+/// It reads the config file, from the cache if a file with the same content was cached.
+///
+/// The cache is best effort: an unreadable or unwritable cache is ignored.
+///
+/// Arguments:
+///
+/// - `cache_dir`: The cache directory, the cache is not used if `None`.
+/// - `path`: Path to the config file.
+/// - `format`: Format of the config file.
+///
+/// Returns:
+///
+/// The config, and whether it was found in the cache.
+pub fn load(
+    cache_dir: Option<&Path>,
+    path: &Path,
+    format: &ConfigFormat,
+) -> Result<(CachedConfig, bool)> {
+    let data = fs::read_to_string(path)
+        .map_err(|err| anyhow!("Failed reading config \"{}\": {}", path.display(), err))?;
+    let entry = cache_dir.map(|dir| entry_path(dir, &data, format));
+
+    if let Some(cached) = entry.as_deref().and_then(read_entry) {
+        return Ok((cached, true));
+    }
+
+    let config = Config::from_data(&data, format)?;
+    let problems = validate(&config).iter().map(|p| p.to_string()).collect();
+    let cached = CachedConfig { config, problems };
+    if let Some(entry) = entry {
+        let _ = write_entry(&entry, &cached);
+    }
+    Ok((cached, false))
+}
+
+/// It returns the path of the cache entry of the config: `configs/<SHA-256>.json`, by the
+/// content and the format of the file and the RVP version, so a changed file or another
+/// version never gets a stale entry
+fn entry_path(cache_dir: &Path, data: &str, format: &ConfigFormat) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update([0]);
+    hasher.update(format.extension().as_bytes());
+    hasher.update([0]);
+    hasher.update(data.as_bytes());
+    let hash = hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        });
+    cache_dir.join(CONFIGS_DIR).join(format!("{}.json", hash))
+}
+
+fn read_entry(path: &Path) -> Option<CachedConfig> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn write_entry(path: &Path, cached: &CachedConfig) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(cached)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join("rvp_test_config_cache");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.toml");
+        let cache = dir.join("cache");
+        std::env::set_var("RVP_TEST_CACHE_TOKEN", "secret123");
+
+        let config = r#"
+            name = "test"
+            description = ""
+            rate_limit = "2/s"
+            timezone = "America/New_York"
+
+            [market_hours]
+            open = "09:30"
+            close = "16:00"
+
+            [[resources]]
+            url = "https://example.com"
+            auth = { bearer = { token = "${RVP_TEST_CACHE_TOKEN}" } }
+
+            [[resources.selectors]]
+            path = "h1"
+            name = "title"
+            parsed_type = "String"
+
+            [[resources.selectors]]
+            path = ".price"
+            name = "price"
+            parsed_type = "Number"
+            alert = "< 100"
+        "#;
+        fs::write(&path, config).unwrap();
+
+        let (cached, hit) = load(Some(&cache), &path, &ConfigFormat::Toml).unwrap();
+        assert!(!hit);
+        assert!(cached.problems.is_empty());
+        let (cached, hit) = load(Some(&cache), &path, &ConfigFormat::Toml).unwrap();
+        assert!(hit);
+        // The cached config is the same as the parsed one
+        assert_eq!(
+            cached.config.to_toml(),
+            Config::from_data(config, &ConfigFormat::Toml)
+                .unwrap()
+                .to_toml()
+        );
+
+        // Only the references of the secrets are cached, not their values
+        let entries: Vec<PathBuf> = fs::read_dir(cache.join(CONFIGS_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        let entry = fs::read_to_string(&entries[0]).unwrap();
+        assert!(entry.contains("${RVP_TEST_CACHE_TOKEN}"));
+        assert!(!entry.contains("secret123"));
+
+        // The changed file is parsed and checked again
+        fs::write(&path, config.replace("\"h1\"", "\"h1 >\"")).unwrap();
+        let (cached, hit) = load(Some(&cache), &path, &ConfigFormat::Toml).unwrap();
+        assert!(!hit);
+        assert_eq!(cached.problems.len(), 1);
+        let (_, hit) = load(Some(&cache), &path, &ConfigFormat::Toml).unwrap();
+        assert!(hit);
+
+        let (_, hit) = load(None, &path, &ConfigFormat::Toml).unwrap();
+        assert!(!hit);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,426 @@
+use std::{borrow::Cow, env, fmt::Display, path::PathBuf, sync::OnceLock, time::Duration};
+
+use anyhow::Result;
+use clap::{
+    builder::{FalseyValueParser, RangedU64ValueParser},
+    value_parser, ValueEnum,
+};
+use comfy_table::Table;
+use rvp::{fetcher::HttpFetcher, redact::Redactor, structure::RateLimit};
+use serde::{Deserialize, Serialize};
+
+use crate::output::MAX_TABLE_VALUE_CHARS;
+use crate::paths;
+use crate::report::Recorder;
+use crate::settings::Settings;
+
+/// Global options, shared by all the commands
+#[derive(clap::Args)]
+pub struct GlobalArgs {
+    /// Print more details about what is going on (to stderr)
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Don't print the error details, only the exit status reports the failures
+    #[arg(short, long, global = true, env = "RVP_QUIET", value_parser = FalseyValueParser::new())]
+    quiet: bool,
+
+    /// Print the errors to stderr as JSON objects with their `kind` and exit `code`, for the
+    /// scripts wrapping rvp
+    #[arg(long, global = true, env = "RVP_JSON_ERRORS", value_parser = FalseyValueParser::new())]
+    pub json_errors: bool,
+
+    /// When to use colors in the output, `auto` by default
+    #[arg(long, value_name = "WHEN", global = true, env = "RVP_COLOR")]
+    color: Option<ColorChoice>,
+
+    /// Show the long values in full in the tables, they are truncated to 80 characters by
+    /// default. The JSON and CSV outputs always have the full values.
+    #[arg(long, global = true)]
+    full: bool,
+
+    /// (Optional) Directory to use instead of the global config directory
+    /// (`$XDG_CONFIG_HOME/rvp` or `~/.config/rvp`)
+    #[arg(
+        long,
+        value_name = "DIR",
+        global = true,
+        env = "RVP_CONFIG_DIR",
+        value_parser = value_parser!(PathBuf)
+    )]
+    config_dir: Option<PathBuf>,
+
+    /// (Optional) Directory to use instead of the data directory with the audit log
+    /// (`$XDG_DATA_HOME/rvp` or `~/.local/share/rvp`)
+    #[arg(
+        long,
+        value_name = "DIR",
+        global = true,
+        env = "RVP_DATA_DIR",
+        value_parser = value_parser!(PathBuf)
+    )]
+    data_dir: Option<PathBuf>,
+
+    /// (Optional) Directory to use instead of the cache directory with the downloaded and the
+    /// parsed configs (`$XDG_CACHE_HOME/rvp` or `~/.cache/rvp`)
+    #[arg(
+        long,
+        value_name = "DIR",
+        global = true,
+        env = "RVP_CACHE_DIR",
+        value_parser = value_parser!(PathBuf)
+    )]
+    cache_dir: Option<PathBuf>,
+
+    /// Don't use the cache of the parsed configs, read and check the config file every time
+    #[arg(long, global = true, env = "RVP_NO_CACHE", value_parser = FalseyValueParser::new())]
+    no_cache: bool,
+
+    /// Output the dates and the times in UTC, ignoring the `timezone` of the config
+    #[arg(long, global = true, env = "RVP_UTC", value_parser = FalseyValueParser::new())]
+    utc: bool,
+
+    /// (Optional) Timeout for each HTTP request in seconds
+    #[arg(long, value_name = "SECONDS", global = true, env = "RVP_TIMEOUT")]
+    timeout: Option<u64>,
+
+    /// (Optional) Custom `User-Agent` header for the HTTP requests
+    #[arg(long, value_name = "AGENT", global = true, env = "RVP_USER_AGENT")]
+    user_agent: Option<String>,
+
+    /// (Optional) Maximum number of resources fetched at the same time.
+    /// All resources of the config are fetched at once by default.
+    #[arg(
+        long,
+        value_name = "N",
+        global = true,
+        env = "RVP_CONCURRENCY",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    concurrency: Option<usize>,
+
+    /// Pick the number of the parallel requests and the delay between them for each host
+    /// from the latency and the error rate recorded in the previous runs. `--concurrency`
+    /// still limits the total number of the requests.
+    #[arg(long, global = true)]
+    auto_concurrency: bool,
+
+    /// Skip the URLs disallowed by the `robots.txt` of their hosts for the `User-Agent` of the
+    /// requests, with a warning. The same as `respect_robots = true` in the config.
+    #[arg(long, global = true, conflicts_with = "ignore_robots")]
+    respect_robots: bool,
+
+    /// Fetch all URLs, even if the config sets `respect_robots`
+    #[arg(long, global = true)]
+    ignore_robots: bool,
+
+    /// (Optional) Soft limit of the memory retained by the downloaded pages, e.g. `512M` or
+    /// `2G`. New pages are not downloaded and parsed while the estimated memory of the
+    /// pages in progress exceeds it.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        global = true,
+        env = "RVP_MAX_MEMORY",
+        value_parser = parse_memory_size
+    )]
+    max_memory: Option<usize>,
+
+    /// (Optional) Maximum number of requests to the same host, e.g. `2/s`, `30/m` or `100/h`.
+    /// Overrides the `rate_limit` of the config.
+    #[arg(long, value_name = "RATE", global = true, env = "RVP_RATE_LIMIT")]
+    rate_limit: Option<RateLimit>,
+
+    /// (Optional) Write a JSON report of each run to the file: the status, the requests, the
+    /// retries, the downloaded bytes and the durations of each resource
+    #[arg(
+        long,
+        value_name = "FILE",
+        global = true,
+        env = "RVP_REPORT_FILE",
+        value_parser = value_parser!(PathBuf)
+    )]
+    report_file: Option<PathBuf>,
+
+    /// (Optional) Redis URL of the lease backend, `redis://[:password@]host[:port][/db]`.
+    ///
+    /// When the same config runs on many nodes, each resource is scraped by the node
+    /// which leased it first, the others skip it until the lease expires.
+    #[cfg(feature = "lease")]
+    #[arg(long, value_name = "URL", global = true, env = "RVP_LEASE_BACKEND")]
+    lease_backend: Option<String>,
+
+    /// Lease duration in seconds, should match the run interval
+    #[cfg(feature = "lease")]
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        global = true,
+        env = "RVP_LEASE_TTL",
+        default_value_t = 60
+    )]
+    lease_ttl: u64,
+
+    /// (Optional) Store the parsed values of every run in the history, `sqlite://history.db`.
+    ///
+    /// The stored values can be queried with `rvp history`.
+    #[cfg(feature = "history")]
+    #[arg(long, value_name = "URL", global = true, env = "RVP_STORE")]
+    store: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Use colors when the output is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// State built from the [GlobalArgs] and passed to every command
+#[derive(Default)]
+pub struct Context {
+    pub verbose: bool,
+    pub quiet: bool,
+    pub color: ColorChoice,
+    /// Don't truncate the long values in the tables
+    pub full: bool,
+    /// Output the dates in UTC instead of the timezone of the config
+    pub utc: bool,
+    config_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    /// Don't use the cache of the parsed configs
+    pub no_cache: bool,
+    /// Fetcher configured with the network options
+    pub fetcher: HttpFetcher,
+    /// Timeout of the HTTP requests, as configured in the fetcher
+    pub timeout: Option<Duration>,
+    /// `User-Agent` header of the HTTP requests, as configured in the fetcher
+    pub user_agent: Option<String>,
+    /// Maximum number of resources fetched at the same time
+    pub concurrency: Option<usize>,
+    /// Limit the requests to each host by its recorded latency and error rate
+    pub auto_concurrency: bool,
+    /// `robots.txt` override: `Some(true)` with `--respect-robots`, `Some(false)` with
+    /// `--ignore-robots`, the config decides otherwise
+    pub robots: Option<bool>,
+    /// Soft limit of the memory retained by the downloaded pages, in bytes
+    pub max_memory: Option<usize>,
+    /// Maximum number of requests to the same host
+    pub rate_limit: Option<RateLimit>,
+    /// File of the run report
+    pub report_file: Option<PathBuf>,
+    /// Requests of the current run, for the report
+    pub requests: Recorder,
+    /// Redis URL of the lease backend
+    #[cfg(feature = "lease")]
+    pub lease_backend: Option<String>,
+    /// Lease duration
+    #[cfg(feature = "lease")]
+    pub lease_ttl: Duration,
+    /// URL of the history store
+    #[cfg(feature = "history")]
+    pub store: Option<String>,
+    /// Global settings of the user, the options above already take them into account
+    pub settings: Settings,
+    /// Redaction of the secrets, set once the config is loaded
+    redactor: OnceLock<Redactor>,
+}
+
+impl Context {
+    pub fn new(args: &GlobalArgs) -> Result<Self> {
+        // A broken settings file should not block `rvp init`, which rewrites it
+        let settings = match args.config_dir.clone().or_else(paths::config_dir) {
+            Some(dir) => Settings::load(&dir).unwrap_or_else(|err| {
+                eprintln!("Warning: {:#}, using the defaults", err);
+                Settings::default()
+            }),
+            None => Settings::default(),
+        };
+        // The options are used first, then the settings
+        let user_agent = args.user_agent.clone().or(settings.user_agent.clone());
+        let timeout = args.timeout.or(settings.timeout).map(Duration::from_secs);
+        let fetcher = HttpFetcher::new(timeout, user_agent.as_deref())?;
+
+        Ok(Self {
+            verbose: args.verbose,
+            quiet: args.quiet,
+            color: args.color.or(settings.color).unwrap_or_default(),
+            full: args.full,
+            utc: args.utc,
+            config_dir: args.config_dir.clone(),
+            data_dir: args.data_dir.clone(),
+            cache_dir: args.cache_dir.clone(),
+            no_cache: args.no_cache,
+            fetcher,
+            timeout,
+            user_agent,
+            concurrency: args.concurrency.or(settings.concurrency),
+            auto_concurrency: args.auto_concurrency,
+            robots: match (args.respect_robots, args.ignore_robots) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => settings.respect_robots.then_some(true),
+            },
+            max_memory: args.max_memory,
+            rate_limit: args.rate_limit,
+            report_file: args.report_file.clone(),
+            requests: Recorder::default(),
+            #[cfg(feature = "lease")]
+            lease_backend: args.lease_backend.clone(),
+            #[cfg(feature = "lease")]
+            lease_ttl: Duration::from_secs(args.lease_ttl),
+            #[cfg(feature = "history")]
+            store: args.store.clone(),
+            settings,
+            redactor: OnceLock::new(),
+        })
+    }
+
+    /// It returns the directory for the global configs, `--config-dir` takes precedence
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        match &self.config_dir {
+            Some(dir) => Some(dir.clone()),
+            None => paths::config_dir(),
+        }
+    }
+
+    /// It returns the directories where the configs are looked up by name: the current
+    /// directory, then the global config directory
+    pub fn config_search_dirs(&self) -> Vec<PathBuf> {
+        env::current_dir()
+            .ok()
+            .into_iter()
+            .chain(self.config_dir())
+            .collect()
+    }
+
+    /// It returns the data directory, `--data-dir` takes precedence
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        match &self.data_dir {
+            Some(dir) => Some(dir.clone()),
+            None => paths::data_dir(),
+        }
+    }
+
+    /// It returns the cache directory, `--cache-dir` takes precedence
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        match &self.cache_dir {
+            Some(dir) => Some(dir.clone()),
+            None => paths::cache_dir(),
+        }
+    }
+
+    /// It prints the message to stderr in the verbose mode
+    pub fn log(&self, message: impl Display) {
+        if self.verbose {
+            eprintln!("{}", self.redact(&message.to_string()));
+        }
+    }
+
+    /// It sets the redaction of the secrets for the rest of the run, only the first call counts
+    pub fn set_redactor(&self, redactor: Redactor) {
+        let _ = self.redactor.set(redactor);
+    }
+
+    /// It returns the redaction of the secrets, empty until the config is loaded
+    pub fn redactor(&self) -> Redactor {
+        self.redactor.get().cloned().unwrap_or_default()
+    }
+
+    /// It hides the secrets of the loaded config in the text
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.redactor.get() {
+            Some(redactor) => redactor.redact(text),
+            None => Cow::Borrowed(text),
+        }
+    }
+
+    /// It returns the maximum number of characters of the values in the tables, `None` with
+    /// `--full`
+    pub fn max_value_chars(&self) -> Option<usize> {
+        match self.full {
+            true => None,
+            false => Some(MAX_TABLE_VALUE_CHARS),
+        }
+    }
+
+    /// It applies the `--color` choice to the table
+    pub fn style_table(&self, table: &mut Table) {
+        match self.color {
+            ColorChoice::Auto => {}
+            ColorChoice::Always => {
+                table.enforce_styling();
+            }
+            ColorChoice::Never => {
+                table.force_no_tty();
+            }
+        }
+    }
+}
+
+/// Parse the size of `--max-memory`: bytes, or with the `K`, `M` or `G` suffix (powers of 1024)
+fn parse_memory_size(text: &str) -> Result<usize> {
+    let text = text.trim();
+    let lower = text.to_ascii_lowercase();
+    let number = lower
+        .trim_end_matches("ib")
+        .trim_end_matches('b')
+        .trim_end();
+    let (number, unit) = match number.char_indices().last() {
+        Some((i, 'k')) => (&number[..i], 1 << 10),
+        Some((i, 'm')) => (&number[..i], 1 << 20),
+        Some((i, 'g')) => (&number[..i], 1 << 30),
+        _ => (number, 1),
+    };
+    let size = number
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .filter(|&n| n > 0);
+    size.ok_or_else(|| {
+        anyhow::anyhow!(
+            "invalid size \"{}\", expected bytes or a number with K, M or G, e.g. 512M",
+            text
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_dir_override() {
+        let ctx = Context {
+            config_dir: Some(PathBuf::from("/tmp/rvp_configs")),
+            ..Default::default()
+        };
+        assert_eq!(ctx.config_dir(), Some(PathBuf::from("/tmp/rvp_configs")));
+    }
+
+    #[test]
+    fn test_parse_memory_size() {
+        assert_eq!(parse_memory_size("1024").unwrap(), 1024);
+        assert_eq!(parse_memory_size("512M").unwrap(), 512 << 20);
+        assert_eq!(parse_memory_size("2gb").unwrap(), 2 << 30);
+        assert_eq!(parse_memory_size("64 KiB").unwrap(), 64 << 10);
+        assert!(parse_memory_size("0").is_err());
+        assert!(parse_memory_size("M").is_err());
+        assert!(parse_memory_size("1.5G").is_err());
+    }
+
+    #[test]
+    fn test_redact() {
+        let ctx = Context::default();
+        assert_eq!(ctx.redact("?token=abc"), "?token=abc");
+
+        ctx.set_redactor(Redactor::new(&["token=([^&]+)".to_string()]).unwrap());
+        ctx.set_redactor(Redactor::default());
+        assert_eq!(ctx.redact("?token=abc"), "?token=[REDACTED]");
+    }
+}
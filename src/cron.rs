@@ -0,0 +1,263 @@
+//! Cron expressions of the `schedule` command: `minute hour day-of-month month day-of-week`
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, RvpError};
+
+/// Names of the months, `jan` is 1
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Names of the weekdays, `sun` is 0
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Days searched for the next run. `0 0 29 2 *` can be 8 years away around 2100.
+const SEARCH_DAYS: u32 = 366 * 8 + 2;
+
+/// Allowed values of a single field, as bits
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Field {
+    bits: u64,
+    /// `false` if the field starts with `*`, see [Schedule::matches_day]
+    restricted: bool,
+}
+
+impl Field {
+    fn contains(&self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+/// A parsed cron expression with the 5 standard fields.
+///
+/// Supports `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`), lists (`1,15`), the
+/// month and weekday names (`jan`, `mon`) and the `@hourly`, `@daily`, `@weekly`, `@monthly`
+/// and `@yearly` shorthands. Sunday is both `0` and `7`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct Schedule {
+    source: String,
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+}
+
+impl Schedule {
+    /// It returns the first time after the given one matching the schedule, in the same
+    /// timezone. The local times skipped by a DST change are skipped.
+    ///
+    /// Returns `None` if the schedule never matches, e.g. `0 0 31 2 *`.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date();
+
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_day(date) {
+                for hour in (0..24).filter(|&h| self.hours.contains(h)) {
+                    for minute in (0..60).filter(|&m| self.minutes.contains(m)) {
+                        let time = date.and_hms_opt(hour, minute, 0)?;
+                        if time < start {
+                            continue;
+                        }
+                        if let Some(time) = after.timezone().from_local_datetime(&time).earliest() {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// Like cron, when both the day of month and the day of week are restricted, either
+    /// of them matches
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !self.months.contains(date.month()) {
+            return false;
+        }
+        let day = self.days.contains(date.day());
+        let weekday = self
+            .weekdays
+            .contains(date.weekday().num_days_from_sunday());
+        match (self.days.restricted, self.weekdays.restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+/// It parses a single field with the values from `min` to `max`, `names` start at `min`
+fn parse_field(text: &str, min: u32, max: u32, names: &[&str]) -> Option<Field> {
+    let value = |text: &str| -> Option<u32> {
+        let position = names.iter().position(|n| n.eq_ignore_ascii_case(text));
+        let value = match position {
+            Some(position) => position as u32 + min,
+            None => text.parse().ok()?,
+        };
+        (min..=max).contains(&value).then_some(value)
+    };
+
+    let mut bits = 0;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().ok().filter(|&s| s > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/15` is from 5 to the maximum
+            None if part.contains('/') => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if start > end {
+            return None;
+        }
+        for value in (start..=end).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+
+    Some(Field {
+        bits,
+        restricted: !text.starts_with('*'),
+    })
+}
+
+impl FromStr for Schedule {
+    type Err = RvpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            RvpError::ConfigError(format!(
+                "Invalid cron expression \"{}\", expected 5 fields like `*/15 * * * *`!",
+                s
+            ))
+        };
+
+        let expression = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid());
+        };
+
+        let mut weekdays = parse_field(weekdays, 0, 7, &WEEKDAYS).ok_or_else(invalid)?;
+        // Sunday is 7 too
+        if weekdays.contains(7) {
+            weekdays.bits = (weekdays.bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            source: s.trim().to_string(),
+            minutes: parse_field(minutes, 0, 59, &[]).ok_or_else(invalid)?,
+            hours: parse_field(hours, 0, 23, &[]).ok_or_else(invalid)?,
+            days: parse_field(days, 1, 31, &[]).ok_or_else(invalid)?,
+            months: parse_field(months, 1, 12, &MONTHS).ok_or_else(invalid)?,
+            weekdays,
+        })
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = RvpError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(value: Schedule) -> Self {
+        value.source
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn next(expression: &str, after: &str) -> String {
+        let schedule: Schedule = expression.parse().unwrap();
+        let after: DateTime<Utc> = after.parse().unwrap();
+        schedule.next_after(&after).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(
+            next("*/15 * * * *", "2024-05-01T10:07:30Z"),
+            "2024-05-01T10:15:00+00:00"
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2024-05-01T10:15:00Z"),
+            "2024-05-01T10:30:00+00:00"
+        );
+        assert_eq!(
+            next("30 9 * * mon-fri", "2024-05-03T10:00:00Z"),
+            "2024-05-06T09:30:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 29 feb *", "2024-03-01T00:00:00Z"),
+            "2028-02-29T00:00:00+00:00"
+        );
+        // Either the day of month or the day of week
+        assert_eq!(
+            next("0 12 1 * 7", "2024-05-01T13:00:00Z"),
+            "2024-05-05T12:00:00+00:00"
+        );
+        assert_eq!(
+            next("@daily", "2024-12-31T23:59:00Z"),
+            "2025-01-01T00:00:00+00:00"
+        );
+
+        // 02:30 doesn't exist on the day of the DST change
+        let schedule: Schedule = "30 2 * * *".parse().unwrap();
+        let after = chrono_tz::Europe::Berlin
+            .with_ymd_and_hms(2024, 3, 30, 12, 0, 0)
+            .unwrap();
+        assert_eq!(
+            schedule.next_after(&after).unwrap().to_rfc3339(),
+            "2024-04-01T02:30:00+02:00"
+        );
+
+        let never: Schedule = "0 0 31 2 *".parse().unwrap();
+        assert!(never.next_after(&Utc::now()).is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * foo *",
+            "* * * * * *",
+        ] {
+            assert!(expression.parse::<Schedule>().is_err(), "{}", expression);
+        }
+        assert_eq!(
+            "5/20 * * * *".parse::<Schedule>().unwrap().minutes.bits,
+            (1 << 5) | (1 << 25) | (1 << 45)
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use thiserror::Error;
+
+/// Errors returned by the core of RVP: fetching, parsing and config handling.
+///
+/// The CLI layer wraps them into [anyhow::Error], but embedders can match on the
+/// failure kind to decide what to do.
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug, Clone)]
+pub enum RvpError {
+    /// The document could not be downloaded or decoded
+    #[error("failed to fetch \"{url}\": {message}")]
+    FetchError { url: String, message: String },
+
+    /// The CSS selector of a config entry has invalid syntax
+    #[error("invalid selector \"{name}\" ({path}): {message}")]
+    SelectorParseError {
+        name: String,
+        path: String,
+        message: String,
+    },
+
+    /// The grabbed text could not be converted to the type of the selector
+    #[error("failed to convert \"{value}\" to {to} for \"{name}\"")]
+    TypeConversionError {
+        name: String,
+        value: String,
+        to: String,
+    },
+
+    /// The config file could not be read, parsed or saved
+    #[error("{0}")]
+    ConfigError(String),
+
+    /// A config, a file or a directory needed by the command does not exist
+    #[error("{0}")]
+    NotFoundError(String),
+}
+
+impl RvpError {
+    /// It returns the category of the error: `network`, `selector-parse`, `not-found`,
+    /// `type-conversion` or `config`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RvpError::FetchError { .. } => "network",
+            RvpError::SelectorParseError { .. } => "selector-parse",
+            RvpError::NotFoundError(_) => "not-found",
+            RvpError::TypeConversionError { .. } => "type-conversion",
+            RvpError::ConfigError(_) => "config",
+        }
+    }
+}
+
+/// A [Result] alias with [RvpError] as the error type
+pub type Result<T> = std::result::Result<T, RvpError>;
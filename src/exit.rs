@@ -0,0 +1,117 @@
+//! Exit codes of the CLI, besides `1` for the other errors and `2` for the invalid arguments
+use rvp::{error::RvpError, redact::Redactor};
+use serde_json::json;
+use thiserror::Error;
+
+/// Exit code when an `alert` rule of a selector is matched
+pub const ALERT: i32 = 3;
+
+/// Exit code when a page could not be downloaded or decoded
+pub const NETWORK: i32 = 4;
+
+/// Exit code when a CSS selector has invalid syntax
+pub const SELECTOR_PARSE: i32 = 5;
+
+/// Exit code when a config, a file or a directory does not exist
+pub const NOT_FOUND: i32 = 6;
+
+/// Exit code when a value could not be converted to the type of its selector
+pub const TYPE_CONVERSION: i32 = 7;
+
+/// Exit code when a config could not be read, parsed or is invalid
+pub const CONFIG: i32 = 8;
+
+/// The run succeeded, but some values crossed the thresholds of their selectors
+#[derive(Error, Debug)]
+#[error("{0} alert(s) triggered!")]
+pub struct AlertsTriggered(pub usize);
+
+/// It returns the first [RvpError] in the chain of the error
+fn rvp_error(err: &anyhow::Error) -> Option<&RvpError> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<RvpError>())
+}
+
+/// It returns the exit code for the error of the command
+pub fn code(err: &anyhow::Error) -> i32 {
+    if err.is::<AlertsTriggered>() {
+        return ALERT;
+    }
+    match rvp_error(err) {
+        Some(RvpError::FetchError { .. }) => NETWORK,
+        Some(RvpError::SelectorParseError { .. }) => SELECTOR_PARSE,
+        Some(RvpError::NotFoundError(_)) => NOT_FOUND,
+        Some(RvpError::TypeConversionError { .. }) => TYPE_CONVERSION,
+        Some(RvpError::ConfigError(_)) => CONFIG,
+        None => 1,
+    }
+}
+
+/// It returns the error as a JSON object for `--json-errors`: the `kind` of the error, its
+/// exit `code`, the `message` with the causes, and the details of the failure, e.g. its `url`.
+/// The secrets of the config are hidden in the message and the details.
+pub fn json(err: &anyhow::Error, redactor: &Redactor) -> String {
+    let redact = |text: &str| redactor.redact(text).into_owned();
+    let kind = match (err.is::<AlertsTriggered>(), rvp_error(err)) {
+        (true, _) => "alert",
+        (false, Some(rvp_error)) => rvp_error.kind(),
+        (false, None) => "other",
+    };
+    let mut object = json!({
+        "kind": kind,
+        "code": code(err),
+        "message": redact(&format!("{:#}", err)),
+    });
+    let details = match rvp_error(err) {
+        Some(RvpError::FetchError { url, .. }) => json!({ "url": redact(url) }),
+        Some(RvpError::SelectorParseError { name, path, .. }) => {
+            json!({ "name": name, "path": redact(path) })
+        }
+        Some(RvpError::TypeConversionError { name, value, to }) => {
+            json!({ "name": name, "value": redact(value), "to": to })
+        }
+        _ => json!({}),
+    };
+    if let (Some(object), Some(details)) = (object.as_object_mut(), details.as_object()) {
+        object.extend(details.clone());
+    }
+    object.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_code() {
+        let fetch = RvpError::FetchError {
+            url: "https://a.com".to_string(),
+            message: "timeout".to_string(),
+        };
+        let failed = anyhow::Error::new(fetch).context("1 of 2 resources failed to parse!");
+        assert_eq!(code(&failed), NETWORK);
+        let output: Value = serde_json::from_str(&json(&failed, &Redactor::default())).unwrap();
+        assert_eq!(
+            output,
+            serde_json::json!({
+                "kind": "network",
+                "code": 4,
+                "message": "1 of 2 resources failed to parse!: failed to fetch \"https://a.com\": timeout",
+                "url": "https://a.com",
+            })
+        );
+
+        let redactor = Redactor::new(&["a\\.com".to_string()]).unwrap();
+        let output: Value = serde_json::from_str(&json(&failed, &redactor)).unwrap();
+        assert_eq!(output["url"], "https://[REDACTED]");
+        assert!(!output["message"].as_str().unwrap().contains("a.com"));
+
+        let alerts = anyhow::Error::new(AlertsTriggered(2));
+        assert_eq!(code(&alerts), ALERT);
+        assert!(json(&alerts, &Redactor::default()).contains("\"kind\":\"alert\""));
+        let missing = RvpError::NotFoundError("File does not exist!".to_string());
+        assert_eq!(code(&missing.into()), NOT_FOUND);
+        assert_eq!(code(&anyhow::anyhow!("Invalid interval")), 1);
+    }
+}
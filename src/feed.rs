@@ -0,0 +1,271 @@
+//! RSS and Atom feeds of the [ResourceKind::Feed](crate::structure::ResourceKind::Feed)
+//! resources. The feed is converted to a JSON document, so the selectors use the same JSON
+//! paths as the JSON resources:
+//!
+//! ```json
+//! {
+//!   "title": "Feed title",
+//!   "link": "https://example.com",
+//!   "items": [
+//!     {"title": "...", "link": "...", "pubDate": "...", "description": "..."}
+//!   ]
+//! }
+//! ```
+use serde_json::{json, Map, Value};
+
+use crate::error::{Result, RvpError};
+
+/// An XML element found by [elements]
+struct Element<'a> {
+    /// The attributes of the start tag, as written
+    attributes: &'a str,
+    /// The content between the tags, `None` for the self-closing elements
+    content: Option<&'a str>,
+}
+
+impl Element<'_> {
+    /// It returns the unescaped value of the attribute
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes.match_indices(name).find_map(|(start, _)| {
+            // `href` is not `xhref`
+            let before = self.attributes[..start].chars().last();
+            if !before.is_some_and(char::is_whitespace) {
+                return None;
+            }
+            let value = self.attributes[start + name.len()..]
+                .trim_start()
+                .strip_prefix('=')?
+                .trim_start();
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let end = value[1..].find(quote)?;
+            Some(unescape(&value[1..end + 1]))
+        })
+    }
+
+    /// It returns the unescaped text of the element
+    fn text(&self) -> Option<String> {
+        self.content.map(unescape).filter(|text| !text.is_empty())
+    }
+}
+
+/// It parses the RSS (including RSS 1.0) or Atom feed into the JSON document
+pub fn parse(url: &str, text: &str) -> Result<Value> {
+    let is_feed = ["<rss", "<feed", "<rdf:RDF"]
+        .iter()
+        .any(|tag| text.contains(tag));
+    if !is_feed {
+        return Err(RvpError::FetchError {
+            url: url.to_string(),
+            message: "failed to parse the feed: no <rss> or Atom <feed> element".to_string(),
+        });
+    }
+
+    let items: Vec<Value> = elements(text, "item")
+        .chain(elements(text, "entry"))
+        .filter_map(|item| item.content)
+        .map(parse_item)
+        .collect();
+
+    // The title and the link of the feed are before its first item
+    let first_item = ["<item", "<entry"]
+        .iter()
+        .filter_map(|tag| text.find(tag))
+        .min()
+        .unwrap_or(text.len());
+    let header = &text[..first_item];
+
+    Ok(json!({
+        "title": first_text(header, &["title"]),
+        "link": link(header),
+        "items": items,
+    }))
+}
+
+/// It converts a single `<item>` or `<entry>` to a JSON object
+fn parse_item(xml: &str) -> Value {
+    let mut item = Map::new();
+    item.insert("title".to_string(), first_text(xml, &["title"]).into());
+    item.insert("link".to_string(), link(xml).into());
+    item.insert(
+        "pubDate".to_string(),
+        first_text(xml, &["pubDate", "published", "updated", "dc:date"]).into(),
+    );
+    item.insert(
+        "description".to_string(),
+        first_text(
+            xml,
+            &["description", "summary", "content", "content:encoded"],
+        )
+        .into(),
+    );
+    Value::Object(item)
+}
+
+/// It returns the text of the first element found with one of the names, in the order of
+/// the names
+fn first_text(xml: &str, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| elements(xml, name).find_map(|element| element.text()))
+}
+
+/// It returns the link: the text of the RSS `<link>`, or the `href` of the Atom `<link>`
+/// without `rel` or with `rel="alternate"`
+fn link(xml: &str) -> Option<String> {
+    elements(xml, "link").find_map(|element| match element.attribute("href") {
+        Some(href) => match element.attribute("rel").as_deref() {
+            None | Some("alternate") => Some(href),
+            Some(_) => None,
+        },
+        None => element.text(),
+    })
+}
+
+/// It iterates over the elements with the name, not nested in each other
+fn elements<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = Element<'a>> + 'a {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut rest = xml;
+
+    std::iter::from_fn(move || loop {
+        let start = rest.find(&open)?;
+        let tag = &rest[start + open.len()..];
+        // `<linked>` is not `<link>`
+        if !tag.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            rest = tag;
+            continue;
+        }
+        let end = tag.find('>')?;
+        let attributes = &tag[..end];
+        let after = &tag[end + 1..];
+        if let Some(attributes) = attributes.strip_suffix('/') {
+            rest = after;
+            return Some(Element {
+                attributes,
+                content: None,
+            });
+        }
+        let content_end = after.find(&close)?;
+        rest = &after[content_end + close.len()..];
+        return Some(Element {
+            attributes,
+            content: Some(&after[..content_end]),
+        });
+    })
+}
+
+/// It returns the text without the CDATA wrapper and with the XML entities replaced
+pub(crate) fn unescape(text: &str) -> String {
+    let text = text.trim();
+    if let Some(data) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+    {
+        return data.trim().to_string();
+    }
+
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+        match (character, entity) {
+            (Some(character), Some(entity)) => {
+                unescaped.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() {
+        let rss = r#"<?xml version="1.0"?>
+            <rss version="2.0"><channel>
+              <title>News</title>
+              <link>https://news.com</link>
+              <item>
+                <title>Rates &amp; bonds</title>
+                <link>https://news.com/1</link>
+                <pubDate>Wed, 01 May 2024 10:00:00 GMT</pubDate>
+                <description><![CDATA[<p>Rates are up</p>]]></description>
+              </item>
+              <item><title>It&#8217;s &#x201C;quiet&#x201D;</title></item>
+            </channel></rss>"#;
+        assert_eq!(
+            parse("https://news.com/rss", rss).unwrap(),
+            json!({
+                "title": "News",
+                "link": "https://news.com",
+                "items": [
+                    {
+                        "title": "Rates & bonds",
+                        "link": "https://news.com/1",
+                        "pubDate": "Wed, 01 May 2024 10:00:00 GMT",
+                        "description": "<p>Rates are up</p>",
+                    },
+                    {
+                        "title": "It\u{2019}s \u{201C}quiet\u{201D}",
+                        "link": null,
+                        "pubDate": null,
+                        "description": null,
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+              <title type="text">Blog</title>
+              <link rel="self" href="https://blog.com/atom.xml"/>
+              <link href="https://blog.com/"/>
+              <entry>
+                <title>Release</title>
+                <link rel="alternate" href="https://blog.com/release?a=1&amp;b=2" />
+                <updated>2024-05-01T10:00:00Z</updated>
+                <summary>Released</summary>
+              </entry>
+            </feed>"#;
+        let feed = parse("https://blog.com/atom.xml", atom).unwrap();
+        assert_eq!(feed["title"], "Blog");
+        assert_eq!(feed["link"], "https://blog.com/");
+        assert_eq!(
+            feed["items"][0],
+            json!({
+                "title": "Release",
+                "link": "https://blog.com/release?a=1&b=2",
+                "pubDate": "2024-05-01T10:00:00Z",
+                "description": "Released",
+            })
+        );
+
+        assert!(parse("https://blog.com", "<html></html>").is_err());
+    }
+}
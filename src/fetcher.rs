@@ -0,0 +1,753 @@
+use crate::error::Result;
+use crate::structure::Auth;
+#[cfg(not(target_family = "wasm"))]
+use crate::structure::{url_host, RateLimit};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A source of documents for the [scalper](crate::scalper).
+///
+/// The default [HttpFetcher] is only available on native targets. Runtimes without
+/// native networking (e.g. wasm32-wasi hosts) can plug in their own implementation.
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    /// It fetches the document at the given URL and returns its body as text
+    async fn fetch(&self, url: &str) -> Result<String>;
+
+    /// Same as [Fetcher::fetch], but with the headers of the [Request], and it also returns
+    /// the details of the response, e.g. the URL the request was redirected to.
+    ///
+    /// By default the headers are not sent, the details are not reported, and only the `GET`
+    /// requests without the auth are supported.
+    async fn fetch_document(&self, request: &Request) -> Result<Document> {
+        if request.method != Method::Get {
+            return Err(crate::error::RvpError::FetchError {
+                url: request.url.clone(),
+                message: format!(
+                    "{} requests are not supported by the fetcher",
+                    request.method
+                ),
+            });
+        }
+        if request.auth.is_some() {
+            return Err(crate::error::RvpError::FetchError {
+                url: request.url.clone(),
+                message: "requests with the auth are not supported by the fetcher".to_string(),
+            });
+        }
+        Ok(Document {
+            text: self.fetch(&request.url).await?,
+            ..Document::default()
+        })
+    }
+
+    /// It loads the page at the given URL in a browser and returns the DOM after the scripts
+    /// have run. Used for the resources with `render = true`.
+    ///
+    /// Not supported by default.
+    async fn render(&self, url: &str) -> Result<String> {
+        Err(crate::error::RvpError::FetchError {
+            url: url.to_string(),
+            message: "rendering JavaScript is not supported, rvp should be built with the `render` feature".to_string(),
+        })
+    }
+}
+
+/// Query parameter added by [Request::cache_busting]
+pub const CACHE_BUSTING_PARAM: &str = "_rvp";
+
+/// Headers with the credentials, [Request::link] sends them only to the same origin
+const CREDENTIAL_HEADERS: [&str; 3] = ["Authorization", "Cookie", "Proxy-Authorization"];
+
+/// HTTP method of a [Request]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Patch,
+}
+
+impl Method {
+    /// It returns a vector of all the possible [Method]s
+    pub fn list_as_vec() -> Vec<Method> {
+        vec![Method::Get, Method::Post, Method::Put, Method::Patch]
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Method::default()
+    }
+
+    /// It returns the name of the method, e.g. `POST`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Patch => "PATCH",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A request of a document with the extra HTTP headers
+#[derive(Clone, PartialEq, Debug)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    /// Headers added to the request, e.g. `Accept-Language`
+    pub headers: Vec<(String, String)>,
+    /// Body of the request, e.g. a form or a GraphQL query
+    pub body: Option<String>,
+    /// Authentication of the request, with the `${NAME}` references resolved when it is sent
+    pub auth: Option<Auth>,
+    /// Whether a `file://` URL may be read from the disk. Only the URLs of the config or the
+    /// command line are, never the links found in the fetched documents.
+    pub local_files: bool,
+}
+
+impl Request {
+    /// Create a new request of the URL without the extra headers
+    pub fn get(url: &str) -> Self {
+        Self {
+            method: Method::Get,
+            url: url.to_string(),
+            headers: vec![],
+            body: None,
+            auth: None,
+            local_files: false,
+        }
+    }
+
+    /// It returns the same request, which may read a `file://` URL from the disk
+    pub fn with_local_files(&self) -> Self {
+        Self {
+            local_files: true,
+            ..self.clone()
+        }
+    }
+
+    /// It returns an error if the request is of a `file://` URL which may not be read
+    pub fn check_local_files(&self) -> Result<()> {
+        match self.url.starts_with("file:") && !self.local_files {
+            true => Err(crate::error::RvpError::FetchError {
+                url: self.url.clone(),
+                message:
+                    "the local files are read only from the URLs of the config or the command line"
+                        .to_string(),
+            }),
+            false => Ok(()),
+        }
+    }
+
+    /// It returns the same request of another URL
+    pub fn with_url(&self, url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// It returns the `GET` request of a link found in the document, e.g. of the next page,
+    /// with the same headers except the `Content-Type` of the body. The auth and the
+    /// credential headers are kept only for the links of the same origin (scheme, host and
+    /// port), and the links to the local files are never read.
+    pub fn link(&self, url: &str) -> Self {
+        let origin = |url: &str| url::Url::parse(url).ok().map(|url| url.origin());
+        let same_origin = origin(url)
+            .is_some_and(|link| link.is_tuple() && Some(&link) == origin(&self.url).as_ref());
+        let dropped = |name: &str| {
+            name.eq_ignore_ascii_case("Content-Type")
+                || !same_origin
+                    && CREDENTIAL_HEADERS
+                        .iter()
+                        .any(|header| name.eq_ignore_ascii_case(header))
+        };
+        Self {
+            method: Method::Get,
+            url: url.to_string(),
+            headers: self
+                .headers
+                .iter()
+                .filter(|(name, _)| !dropped(name))
+                .cloned()
+                .collect(),
+            body: None,
+            auth: self.auth.clone().filter(|_| same_origin),
+            local_files: false,
+        }
+    }
+
+    /// It returns the same request which bypasses the caches: with a unique query parameter
+    /// and the `no-cache` headers
+    pub fn cache_busting(&self) -> Self {
+        let mut url = self.url.clone();
+        if let Ok(mut parsed) = url::Url::parse(&self.url) {
+            parsed.query_pairs_mut().append_pair(
+                CACHE_BUSTING_PARAM,
+                &chrono::Utc::now().timestamp_millis().to_string(),
+            );
+            url = parsed.to_string();
+        }
+        let mut request = self.with_url(&url);
+        request
+            .headers
+            .push(("Cache-Control".to_string(), "no-cache".to_string()));
+        request
+            .headers
+            .push(("Pragma".to_string(), "no-cache".to_string()));
+        request
+    }
+}
+
+/// The body of a fetched document
+#[derive(Default)]
+pub struct Document {
+    pub text: String,
+    /// The final URL after the redirects, if it differs from the requested one
+    pub redirected_to: Option<String>,
+    /// The language of the document from the `Content-Language` header
+    pub language: Option<String>,
+}
+
+/// Number of the first bytes of a page searched for the `<meta>` charset, as the browsers do
+const CHARSET_SCAN_BYTES: usize = 1024;
+
+/// It decodes the body of a response. The charset is taken from the byte order mark, the
+/// `Content-Type` header, or the `<meta>` tags (the XML declaration of the feeds) at the
+/// start of the page. Without any, the body is UTF-8.
+pub fn decode(bytes: &[u8], content_type: Option<&str>) -> String {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(CHARSET_SCAN_BYTES)]);
+    let declared = content_type
+        .and_then(charset)
+        .or_else(|| declared_charset(&head));
+    let encoding = declared
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    // The byte order mark takes precedence over the declared charset
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// It returns the value of `charset=` in the text, e.g. of `text/html; charset=utf-8`
+fn charset(text: &str) -> Option<&str> {
+    attribute_value(text, "charset=")
+}
+
+/// It finds the charset declared in the `<meta>` tags or the XML declaration of the page
+fn declared_charset(head: &str) -> Option<&str> {
+    let lower = head.to_ascii_lowercase();
+    let tag = |start: usize| {
+        let tag = &head[start..];
+        &tag[..tag.find('>').unwrap_or(tag.len())]
+    };
+    lower
+        .match_indices("<meta")
+        .find_map(|(start, _)| charset(tag(start)))
+        .or_else(|| {
+            let start = lower.find("<?xml")?;
+            attribute_value(tag(start), "encoding=")
+        })
+}
+
+/// It returns the value after the `name=` in the text, with or without the quotes
+fn attribute_value<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    let start = text.to_ascii_lowercase().find(name)? + name.len();
+    let value = text[start..].trim_start().trim_start_matches(['"', '\'']);
+    let end = value
+        .find(|c: char| matches!(c, '"' | '\'' | ';' | '>' | '/' | '?') || c.is_whitespace())
+        .unwrap_or(value.len());
+    Some(&value[..end]).filter(|value| !value.is_empty())
+}
+
+/// Fetches documents over HTTP(S) with `reqwest`
+#[cfg(not(target_family = "wasm"))]
+#[derive(Default)]
+pub struct HttpFetcher {
+    client: reqwest::Client,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl HttpFetcher {
+    /// It creates a fetcher with the given request timeout and `User-Agent` header
+    pub fn new(timeout: Option<std::time::Duration>, user_agent: Option<&str>) -> Result<Self> {
+        use crate::error::RvpError;
+
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        match builder.build() {
+            Ok(client) => Ok(Self { client }),
+            Err(err) => Err(RvpError::ConfigError(format!(
+                "failed to create HTTP client: {}",
+                err
+            ))),
+        }
+    }
+
+    /// It sends the JSON body with a POST request, the responses other than 2xx are errors
+    pub async fn post_json(
+        &self,
+        url: &str,
+        body: String,
+        headers: &[(&str, String)],
+    ) -> Result<()> {
+        use crate::error::RvpError;
+
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+
+        let error = |message: String| RvpError::FetchError {
+            url: url.to_string(),
+            message,
+        };
+        let resp = request.send().await.map_err(|err| error(err.to_string()))?;
+        match resp.status().is_success() {
+            true => Ok(()),
+            false => Err(error(format!("server responded with {}", resp.status()))),
+        }
+    }
+
+    /// It sends a HEAD request to check that the host is reachable and returns the status
+    /// code. Any response but the server errors (5xx) counts, e.g. 405 for the hosts which
+    /// don't support HEAD.
+    pub async fn head(&self, url: &str) -> Result<u16> {
+        use crate::error::RvpError;
+
+        let error = |message: String| RvpError::FetchError {
+            url: url.to_string(),
+            message,
+        };
+        let resp = self
+            .client
+            .head(url)
+            .send()
+            .await
+            .map_err(|err| error(err.to_string()))?;
+        match resp.status().is_server_error() {
+            true => Err(error(format!("server responded with {}", resp.status()))),
+            false => Ok(resp.status().as_u16()),
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait]
+impl Fetcher for HttpFetcher {
+    async fn fetch(&self, url: &str) -> Result<String> {
+        Ok(self.fetch_document(&Request::get(url)).await?.text)
+    }
+
+    async fn fetch_document(&self, request: &Request) -> Result<Document> {
+        use crate::error::RvpError;
+
+        let url = request.url.as_str();
+        request.check_local_files()?;
+        if url.starts_with("file:") {
+            return read_file(request).await;
+        }
+        let method = match request.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+            Method::Put => reqwest::Method::PUT,
+            Method::Patch => reqwest::Method::PATCH,
+        };
+        let mut builder = self.client.request(method, url);
+        for (name, value) in request.headers.iter() {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+        builder = match request.auth.as_ref().map(Auth::resolve).transpose()? {
+            Some(Auth::Basic { user, pass }) => builder.basic_auth(user, Some(pass)),
+            Some(Auth::Bearer { token }) => builder.bearer_auth(token),
+            None => builder,
+        };
+        let resp = match builder.send().await {
+            Ok(resp) => resp,
+            Err(err) => {
+                return Err(RvpError::FetchError {
+                    url: url.to_string(),
+                    message: err.to_string(),
+                })
+            }
+        };
+        // The requested URL is parsed the same way, so only the real redirects differ
+        let redirected_to = match reqwest::Url::parse(url) {
+            Ok(requested) if requested == *resp.url() => None,
+            _ => Some(resp.url().to_string()),
+        };
+        let language = resp
+            .headers()
+            .get(reqwest::header::CONTENT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        match resp.bytes().await {
+            Ok(bytes) => Ok(Document {
+                text: decode(&bytes, content_type.as_deref()),
+                redirected_to,
+                language,
+            }),
+            Err(err) => Err(RvpError::FetchError {
+                url: url.to_string(),
+                message: format!("failed to parse HTML document:\n{}", err),
+            }),
+        }
+    }
+
+    #[cfg(feature = "render")]
+    async fn render(&self, url: &str) -> Result<String> {
+        render::dump_dom(url).await
+    }
+}
+
+/// It reads the page of a `file://` URL from the disk, the charset is taken from the page
+#[cfg(not(target_family = "wasm"))]
+async fn read_file(request: &Request) -> Result<Document> {
+    let error = |message: String| crate::error::RvpError::FetchError {
+        url: request.url.clone(),
+        message,
+    };
+    if request.method != Method::Get {
+        return Err(error(format!(
+            "{} requests are not supported for the files",
+            request.method
+        )));
+    }
+    let path = url::Url::parse(&request.url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| error("invalid file URL".to_string()))?;
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => Ok(Document {
+            text: decode(&bytes, None),
+            ..Document::default()
+        }),
+        Err(err) => Err(error(format!("failed to read {}: {}", path.display(), err))),
+    }
+}
+
+/// Rendering with a headless Chromium, started as a separate process for each page
+#[cfg(all(feature = "render", not(target_family = "wasm")))]
+mod render {
+    use crate::error::{Result, RvpError};
+    use std::{env, io::ErrorKind};
+    use tokio::process::Command;
+
+    /// Browser binaries tried in order, when `RVP_CHROMIUM` is not set
+    const BROWSERS: [&str; 3] = ["chromium", "chromium-browser", "google-chrome"];
+
+    /// Time in milliseconds given to the page scripts before the DOM is dumped
+    const VIRTUAL_TIME_BUDGET: u32 = 5000;
+
+    /// It loads the page in a headless Chromium and returns the rendered DOM.
+    ///
+    /// Only the HTTP(S) URLs are loaded, the others may read the local files or the browser
+    /// pages, e.g. `view-source:file:///` or `chrome://`.
+    pub async fn dump_dom(url: &str) -> Result<String> {
+        let parsed = url::Url::parse(url)
+            .ok()
+            .filter(|parsed| matches!(parsed.scheme(), "http" | "https"))
+            .ok_or_else(|| RvpError::FetchError {
+                url: url.to_string(),
+                message: "only the HTTP(S) pages are rendered".to_string(),
+            })?;
+        let browsers = match env::var("RVP_CHROMIUM") {
+            Ok(browser) => vec![browser],
+            Err(_) => BROWSERS.iter().map(|b| b.to_string()).collect(),
+        };
+
+        for browser in browsers.iter() {
+            let output = Command::new(browser)
+                .args([
+                    "--headless",
+                    "--disable-gpu",
+                    &format!("--virtual-time-budget={}", VIRTUAL_TIME_BUDGET),
+                    "--dump-dom",
+                    // The URL is never taken for a switch of the browser
+                    "--",
+                    parsed.as_str(),
+                ])
+                .kill_on_drop(true)
+                .output()
+                .await;
+
+            match output {
+                Ok(output) if output.status.success() => {
+                    return Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+                }
+                Ok(output) => {
+                    return Err(RvpError::FetchError {
+                        url: url.to_string(),
+                        message: format!(
+                            "{} exited with {}:\n{}",
+                            browser,
+                            output.status,
+                            String::from_utf8_lossy(&output.stderr).trim()
+                        ),
+                    })
+                }
+                // Try the next browser
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(RvpError::FetchError {
+                        url: url.to_string(),
+                        message: format!("failed to start {}: {}", browser, err),
+                    })
+                }
+            }
+        }
+
+        Err(RvpError::FetchError {
+            url: url.to_string(),
+            message: format!(
+                "headless browser was not found, tried: {}. Set RVP_CHROMIUM to the browser path",
+                browsers.join(", ")
+            ),
+        })
+    }
+}
+
+/// Wraps a [Fetcher] to keep the requests to the same host at most at the given [RateLimit]
+#[cfg(not(target_family = "wasm"))]
+pub struct RateLimitedFetcher<'a> {
+    inner: &'a dyn Fetcher,
+    interval: std::time::Duration,
+    /// The intervals of the hosts with their own [RateLimit], instead of `interval`
+    host_intervals: std::collections::HashMap<String, std::time::Duration>,
+    /// The time of the next allowed request for each host
+    next_requests: std::sync::Mutex<std::collections::HashMap<String, tokio::time::Instant>>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl<'a> RateLimitedFetcher<'a> {
+    pub fn new(inner: &'a dyn Fetcher, rate_limit: RateLimit) -> Self {
+        Self {
+            interval: rate_limit.interval(),
+            ..Self::per_host(inner)
+        }
+    }
+
+    /// It limits only the hosts set with [RateLimitedFetcher::with_host_limit]
+    pub fn per_host(inner: &'a dyn Fetcher) -> Self {
+        Self {
+            inner,
+            interval: std::time::Duration::ZERO,
+            host_intervals: Default::default(),
+            next_requests: Default::default(),
+        }
+    }
+
+    /// It sets the rate limit of the host instead of the common one. If the host is limited
+    /// more than once, e.g. by the resources of two groups, the strictest limit is kept.
+    pub fn with_host_limit(mut self, host: &str, rate_limit: RateLimit) -> Self {
+        let interval = rate_limit.interval();
+        self.host_intervals
+            .entry(host.to_ascii_lowercase())
+            .and_modify(|current| *current = (*current).max(interval))
+            .or_insert(interval);
+        self
+    }
+
+    /// It waits for the next free slot of the URL's host
+    async fn wait(&self, url: &str) {
+        let slot = {
+            let mut next_requests = self.next_requests.lock().expect("rate limiter is poisoned");
+            let now = tokio::time::Instant::now();
+            let host = url_host(url).to_ascii_lowercase();
+            let interval = self.host_intervals.get(&host).unwrap_or(&self.interval);
+            let next = next_requests.entry(host.clone()).or_insert(now);
+            let slot = (*next).max(now);
+            *next = slot + *interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait]
+impl Fetcher for RateLimitedFetcher<'_> {
+    async fn fetch(&self, url: &str) -> Result<String> {
+        self.wait(url).await;
+        self.inner.fetch(url).await
+    }
+
+    async fn fetch_document(&self, request: &Request) -> Result<Document> {
+        self.wait(&request.url).await;
+        self.inner.fetch_document(request).await
+    }
+
+    async fn render(&self, url: &str) -> Result<String> {
+        self.wait(url).await;
+        self.inner.render(url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    struct EmptyFetcher;
+
+    #[async_trait]
+    impl Fetcher for EmptyFetcher {
+        async fn fetch(&self, _url: &str) -> Result<String> {
+            Ok(String::new())
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        // "Цена" in Windows-1251
+        let cp1251 = b"<html><head><meta charset=\"windows-1251\"></head>\xd6\xe5\xed\xe0</html>";
+        assert!(decode(cp1251, None).contains("Цена"));
+        assert!(
+            decode(b"\xd6\xe5\xed\xe0", Some("text/html; charset=Windows-1251")).contains("Цена")
+        );
+
+        // "価格" in Shift-JIS, declared with `http-equiv`
+        let sjis = b"<meta http-equiv='Content-Type' content='text/html; charset=Shift_JIS'>\x89\xbf\x8a\x69";
+        assert!(decode(sjis, None).ends_with("価格"));
+
+        let feed = b"<?xml version=\"1.0\" encoding=\"windows-1251\"?><rss>\xd6\xe5\xed\xe0</rss>";
+        assert!(decode(feed, None).contains("Цена"));
+
+        // The header wins over the meta tag, and the byte order mark over both
+        let utf8 = "<meta charset=\"windows-1251\">Цена".as_bytes();
+        assert!(decode(utf8, Some("text/html; charset=utf-8")).ends_with("Цена"));
+        let bom = [b"\xef\xbb\xbf".as_slice(), "Цена".as_bytes()].concat();
+        assert_eq!(
+            decode(&bom, Some("text/html; charset=windows-1251")),
+            "Цена"
+        );
+        assert_eq!(decode("Цена".as_bytes(), Some("text/html")), "Цена");
+    }
+
+    #[tokio::test]
+    async fn test_read_file() -> Result<()> {
+        let dir = std::env::temp_dir().join("rvp_test_read_file");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("page.html");
+        std::fs::write(
+            &path,
+            b"<meta charset=\"windows-1251\"><h1>\xd6\xe5\xed\xe0</h1>",
+        )
+        .unwrap();
+        let url = url::Url::from_file_path(&path).unwrap().to_string();
+
+        let fetcher = HttpFetcher::new(None, None)?;
+        let request = Request::get(&url).with_local_files();
+        assert!(fetcher
+            .fetch_document(&request)
+            .await?
+            .text
+            .ends_with("<h1>Цена</h1>"));
+        let missing = request.with_url(&url.replace("page.html", "missing.html"));
+        assert!(fetcher.fetch_document(&missing).await.is_err());
+        let post = Request {
+            method: Method::Post,
+            ..request.clone()
+        };
+        assert!(fetcher.fetch_document(&post).await.is_err());
+
+        // Only the URLs of the config or the command line are read from the disk
+        assert!(fetcher.fetch(&url).await.is_err());
+        let link = Request::get("https://example.com").link(&url);
+        assert!(fetcher.fetch_document(&link).await.is_err());
+        assert!(fetcher.fetch_document(&request.link(&url)).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "render")]
+    #[tokio::test]
+    async fn test_render_only_web_pages() {
+        // Rejected before the browser is started
+        for url in [
+            "--renderer-cmd-prefix=sh",
+            "file:///etc/passwd",
+            "view-source:file:///etc/passwd",
+            "chrome://settings",
+        ] {
+            let err = render::dump_dom(url).await.unwrap_err().to_string();
+            assert!(
+                err.ends_with("only the HTTP(S) pages are rendered"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_fetch_document() -> Result<()> {
+        let request = Request::get("https://example.com");
+        assert!(EmptyFetcher.fetch_document(&request).await.is_ok());
+
+        // The fetcher can't send them, so it must not fetch the page without them
+        let post = Request {
+            method: Method::Post,
+            ..request.clone()
+        };
+        assert!(EmptyFetcher.fetch_document(&post).await.is_err());
+        let auth = Request {
+            auth: Some(Auth::Bearer {
+                token: "secret".to_string(),
+            }),
+            ..request
+        };
+        assert!(EmptyFetcher.fetch_document(&auth).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limited_fetcher() -> Result<()> {
+        let fetcher = RateLimitedFetcher::new(&EmptyFetcher, "2/s".parse()?);
+        let start = tokio::time::Instant::now();
+
+        fetcher.fetch("https://one.com/a").await?;
+        fetcher.fetch("https://two.com/a").await?;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        fetcher.fetch("https://one.com/b").await?;
+        fetcher.fetch("https://one.com/c").await?;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        // The hosts with their own limit, e.g. of a group, the others are not limited
+        let fetcher = RateLimitedFetcher::per_host(&EmptyFetcher)
+            .with_host_limit("slow.com", "1/s".parse()?)
+            .with_host_limit("SLOW.com", "2/s".parse()?);
+        let start = tokio::time::Instant::now();
+        fetcher.fetch("https://fast.com/a").await?;
+        fetcher.fetch("https://fast.com/b").await?;
+        fetcher.fetch("https://slow.com/a").await?;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        fetcher.fetch("https://slow.com/b").await?;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        Ok(())
+    }
+}
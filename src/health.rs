@@ -0,0 +1,278 @@
+//! Health endpoints of the long running commands (`schedule`, `watch`) for the liveness and
+//! readiness probes of Kubernetes:
+//!
+//! - `/healthz`: 200 while the runs keep up with the schedule, with the status of the last run
+//!   of each config. 503 once the next run is late by more than its period and [GRACE].
+//! - `/readyz`: 200 once the config is loaded, 503 while every resource of the last run
+//!   failed.
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rvp::scalper::ResourceResult;
+use serde::Serialize;
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+/// Extra time for a run to finish after its period, before `/healthz` reports it as late
+pub const GRACE: Duration = Duration::from_secs(60);
+
+/// Time to read the request, so a slow client doesn't hold the connection open
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum length of the request line and each header, longer requests are cut
+const MAX_LINE: u64 = 8 * 1024;
+
+/// Status of the last run of a config
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct RunStatus {
+    /// Time the run finished
+    pub time: String,
+    /// Number of the scraped resources
+    pub resources: usize,
+    /// Number of the resources which could not be fetched or parsed
+    pub failed: usize,
+}
+
+impl RunStatus {
+    pub fn new(results: &[ResourceResult]) -> Self {
+        Self {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            resources: results.len(),
+            failed: results.iter().filter(|r| r.values.is_err()).count(),
+        }
+    }
+
+    /// A run is failing if there were resources and none of them succeeded
+    fn is_failing(&self) -> bool {
+        self.resources > 0 && self.failed == self.resources
+    }
+}
+
+/// The last runs of the configs, shared by the command and the server
+#[derive(Clone, Default)]
+pub struct Health {
+    runs: Arc<Mutex<BTreeMap<String, RunStatus>>>,
+    /// The time by which the next run should have happened, see [Health::expect]
+    deadline: Arc<Mutex<Option<DateTime<Utc>>>>,
+}
+
+impl Health {
+    /// It starts serving the endpoints on the address in the background
+    pub async fn serve(address: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let health = Self::default();
+        health.spawn(listener);
+        Ok(health)
+    }
+
+    /// It accepts the connections of the listener in the background
+    fn spawn(&self, listener: TcpListener) {
+        let server = self.clone();
+        tokio::spawn(async move {
+            loop {
+                // A failed connection should not stop the server
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let health = server.clone();
+                tokio::spawn(async move {
+                    let _ = health.handle(stream).await;
+                });
+            }
+        });
+    }
+
+    /// It saves the time of the next run and the period between the runs. `/healthz` fails if
+    /// the command doesn't get to the following run within the period and [GRACE] after it.
+    pub fn expect(&self, next: DateTime<Utc>, period: Duration) {
+        let deadline = chrono::Duration::from_std(period + GRACE)
+            .ok()
+            .and_then(|late| next.checked_add_signed(late));
+        *self.deadline.lock().unwrap() = deadline;
+    }
+
+    /// It saves the status of the run of the config
+    pub fn record(&self, config: &str, results: &[ResourceResult]) {
+        let mut runs = self.runs.lock().unwrap();
+        runs.insert(config.to_string(), RunStatus::new(results));
+    }
+
+    /// It returns the status code and the JSON body of the path at the time
+    fn respond(&self, path: &str, now: DateTime<Utc>) -> (u16, String) {
+        let runs = self.runs.lock().unwrap();
+        match path {
+            "/healthz" => {
+                let deadline = *self.deadline.lock().unwrap();
+                match deadline.filter(|deadline| now > *deadline) {
+                    Some(deadline) => (
+                        503,
+                        json!({
+                            "status": "late",
+                            "deadline": deadline.to_rfc3339_opts(SecondsFormat::Secs, true),
+                            "configs": &*runs,
+                        })
+                        .to_string(),
+                    ),
+                    None => (
+                        200,
+                        json!({ "status": "ok", "configs": &*runs }).to_string(),
+                    ),
+                }
+            }
+            "/readyz" => {
+                let failing: Vec<&String> = runs
+                    .iter()
+                    .filter(|(_, run)| run.is_failing())
+                    .map(|(name, _)| name)
+                    .collect();
+                match failing.is_empty() {
+                    true => (200, json!({ "status": "ready" }).to_string()),
+                    false => (
+                        503,
+                        json!({ "status": "failing", "configs": failing }).to_string(),
+                    ),
+                }
+            }
+            _ => (404, json!({ "error": "Not found" }).to_string()),
+        }
+    }
+
+    /// It answers a single request and closes the connection
+    async fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await??;
+
+        // `GET /healthz?verbose HTTP/1.1`
+        let target = request.split_whitespace().nth(1).unwrap_or("/");
+        let path = target.split('?').next().unwrap_or(target);
+        let (status, body) = self.respond(path, Utc::now());
+        let reason = match status {
+            200 => "OK",
+            503 => "Service Unavailable",
+            _ => "Not Found",
+        };
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        Ok(())
+    }
+}
+
+/// It reads the request line and skips the headers, each line is cut at [MAX_LINE]
+async fn read_request(stream: &mut TcpStream) -> Result<String> {
+    let mut stream = BufReader::new(stream);
+    let mut request = String::new();
+    (&mut stream).take(MAX_LINE).read_line(&mut request).await?;
+    // The headers are not needed, but the probes expect them to be read
+    let mut header = String::new();
+    while (&mut stream).take(MAX_LINE).read_line(&mut header).await? > 0
+        && !header.trim_end().is_empty()
+    {
+        header.clear();
+    }
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::error::RvpError;
+    use serde_json::Value;
+
+    fn result(ok: bool) -> ResourceResult {
+        ResourceResult {
+            url: "https://example.com".to_string(),
+            param: None,
+            final_url: None,
+            accept_language: None,
+            language: None,
+            values: match ok {
+                true => Ok(vec![]),
+                false => Err(RvpError::NotFoundError("Not found".to_string())),
+            },
+        }
+    }
+
+    #[test]
+    fn test_respond() {
+        let health = Health::default();
+        assert_eq!(health.respond("/readyz", Utc::now()).0, 200);
+        assert_eq!(health.respond("/metrics", Utc::now()).0, 404);
+
+        health.record("stock", &[result(true), result(false)]);
+        health.record("weather", &[result(false)]);
+        let (status, body) = health.respond("/healthz", Utc::now());
+        assert_eq!(status, 200);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["configs"]["stock"]["resources"], 2);
+        assert_eq!(body["configs"]["stock"]["failed"], 1);
+
+        let (status, body) = health.respond("/readyz", Utc::now());
+        assert_eq!(status, 503);
+        assert_eq!(body, r#"{"configs":["weather"],"status":"failing"}"#);
+
+        health.record("weather", &[result(true)]);
+        assert_eq!(health.respond("/readyz", Utc::now()).0, 200);
+    }
+
+    #[test]
+    fn test_respond_late() {
+        let health = Health::default();
+        let next = Utc::now();
+        health.expect(next, Duration::from_secs(300));
+        let in_time = next + chrono::Duration::seconds(300);
+        assert_eq!(health.respond("/healthz", in_time).0, 200);
+
+        let late = in_time + chrono::Duration::from_std(GRACE).unwrap();
+        let (status, body) = health.respond("/healthz", late + chrono::Duration::seconds(1));
+        assert_eq!(status, 503);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["status"], "late");
+
+        // The next run moves the deadline
+        health.expect(late, Duration::from_secs(300));
+        assert_eq!(health.respond("/healthz", late).0, 200);
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let health = Health::default();
+        health.spawn(listener);
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /readyz?verbose HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"status":"ready"}"#));
+
+        // A request line without the end is cut instead of being read forever
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let long = format!(
+            "GET /{} HTTP/1.1\r\n\r\n",
+            "a".repeat(2 * MAX_LINE as usize)
+        );
+        stream.write_all(long.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
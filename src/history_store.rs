@@ -0,0 +1,379 @@
+//! History of the parsed values in SQLite, one row per value of every run
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+use rvp::scalper::ResourceResult;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Scheme of the store URL
+const SQLITE_SCHEME: &str = "sqlite://";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY,
+    time TEXT NOT NULL,
+    config TEXT NOT NULL,
+    resource TEXT NOT NULL,
+    param TEXT,
+    selector TEXT NOT NULL,
+    value TEXT NOT NULL,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS history_lookup ON history (config, selector, time);
+";
+
+/// A stored value
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Record {
+    /// UTC time of the run in RFC 3339 format
+    pub time: String,
+    pub config: String,
+    /// URL of the resource
+    pub resource: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub param: Option<String>,
+    pub selector: String,
+    /// The parsed value as JSON
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Filters of the stored values, newest first
+#[derive(Default)]
+pub struct Query<'a> {
+    pub config: Option<&'a str>,
+    pub selector: Option<&'a str>,
+    pub param: Option<&'a str>,
+    pub limit: usize,
+}
+
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// It opens the store by the URL `sqlite://path/to/history.db`, the database is created
+    /// if needed
+    pub fn open(url: &str) -> Result<Self> {
+        let Some(path) = url.strip_prefix(SQLITE_SCHEME) else {
+            return Err(anyhow!(
+                "Store URL should start with {}, e.g. sqlite://history.db",
+                SQLITE_SCHEME
+            ));
+        };
+        let connection = match path {
+            ":memory:" => Connection::open_in_memory()?,
+            path => Connection::open(path)?,
+        };
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// It appends the values of the run, the failed resources have no values to store.
+    ///
+    /// `redact` hides the secrets in the URLs and the parameters.
+    pub fn append(
+        &mut self,
+        config: &str,
+        results: &[ResourceResult],
+        redact: impl Fn(&str) -> String,
+    ) -> Result<usize> {
+        let time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let transaction = self.connection.transaction()?;
+        let mut count = 0;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO history (time, config, resource, param, selector, value, error)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for result in results {
+                let Ok(values) = &result.values else {
+                    continue;
+                };
+                let url = redact(&result.url);
+                let param = result.param.as_deref().map(&redact);
+                for value in values {
+                    insert.execute(params![
+                        time,
+                        config,
+                        url,
+                        param,
+                        value.name,
+                        value.value.to_string(),
+                        value.error,
+                    ])?;
+                    count += 1;
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(count)
+    }
+
+    /// It returns the latest values matching the query, newest first
+    pub fn recent(&self, query: &Query) -> Result<Vec<Record>> {
+        let mut statement = self.connection.prepare(
+            "SELECT time, config, resource, param, selector, value, error FROM history
+             WHERE (?1 IS NULL OR config = ?1)
+               AND (?2 IS NULL OR selector = ?2)
+               AND (?3 IS NULL OR param = ?3)
+             ORDER BY time DESC, id DESC
+             LIMIT ?4",
+        )?;
+        let rows = statement.query_map(
+            params![
+                query.config,
+                query.selector,
+                query.param,
+                query.limit as i64
+            ],
+            |row| {
+                Ok(Record {
+                    time: row.get(0)?,
+                    config: row.get(1)?,
+                    resource: row.get(2)?,
+                    param: row.get(3)?,
+                    selector: row.get(4)?,
+                    value: row.get(5)?,
+                    error: row.get(6)?,
+                })
+            },
+        )?;
+
+        Ok(rows.collect::<rusqlite::Result<Vec<Record>>>()?)
+    }
+
+    /// It returns the newest parsed numbers of the value of the resource, newest first. The
+    /// values which failed to parse or are not numbers are skipped. The `resource` and the
+    /// `param` should be redacted the same way as stored.
+    pub fn numbers(
+        &self,
+        config: &str,
+        resource: &str,
+        selector: &str,
+        param: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<f64>> {
+        let mut statement = self.connection.prepare(
+            "SELECT value FROM history
+             WHERE config = ?1 AND resource = ?2 AND selector = ?3 AND param IS ?4
+               AND error IS NULL
+             ORDER BY time DESC, id DESC
+             LIMIT ?5",
+        )?;
+        let rows = statement.query_map(
+            params![config, resource, selector, param, limit as i64],
+            |row| row.get::<_, String>(0),
+        )?;
+
+        let mut numbers = Vec::new();
+        for row in rows {
+            let value: Value = serde_json::from_str(&row?).unwrap_or_default();
+            numbers.extend(value.as_f64());
+        }
+        Ok(numbers)
+    }
+
+    /// It returns the newest parsed number of the value stored at or before the time. The
+    /// `resource` and the `param` should be redacted the same way as stored.
+    pub fn number_at(
+        &self,
+        config: &str,
+        resource: &str,
+        selector: &str,
+        param: Option<&str>,
+        time: DateTime<Utc>,
+    ) -> Result<Option<f64>> {
+        let time = time.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut statement = self.connection.prepare(
+            "SELECT value FROM history
+             WHERE config = ?1 AND resource = ?2 AND selector = ?3 AND param IS ?4
+               AND error IS NULL AND time <= ?5
+             ORDER BY time DESC, id DESC",
+        )?;
+        let mut rows = statement.query(params![config, resource, selector, param, time])?;
+        while let Some(row) = rows.next()? {
+            let value: Value = serde_json::from_str(&row.get::<_, String>(0)?).unwrap_or_default();
+            if let Some(number) = value.as_f64() {
+                return Ok(Some(number));
+            }
+        }
+        Ok(None)
+    }
+
+    /// It returns the newest parsed value of each selector of the resource, the values which
+    /// failed to parse are skipped. The `resource` and the `param` should be redacted the same
+    /// way as stored.
+    pub fn last_values(
+        &self,
+        config: &str,
+        resource: &str,
+        param: Option<&str>,
+    ) -> Result<Vec<Record>> {
+        let mut statement = self.connection.prepare(
+            "SELECT time, config, resource, param, selector, value, error, id FROM history
+             WHERE config = ?1 AND resource = ?2 AND param IS ?3 AND error IS NULL
+             ORDER BY time DESC, id DESC",
+        )?;
+        let rows = statement.query_map(params![config, resource, param], |row| {
+            let record = Record {
+                time: row.get(0)?,
+                config: row.get(1)?,
+                resource: row.get(2)?,
+                param: row.get(3)?,
+                selector: row.get(4)?,
+                value: row.get(5)?,
+                error: row.get(6)?,
+            };
+            Ok((row.get::<_, i64>(7)?, record))
+        })?;
+
+        let mut records: Vec<(i64, Record)> = Vec::new();
+        for row in rows {
+            let (id, record) = row?;
+            if !records.iter().any(|(_, r)| r.selector == record.selector) {
+                records.push((id, record));
+            }
+        }
+        // In the order the values were parsed
+        records.sort_by_key(|(id, _)| *id);
+        Ok(records.into_iter().map(|(_, record)| record).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::scalper::ParsedValue;
+
+    #[test]
+    fn test_store() -> Result<()> {
+        assert!(Store::open("history.db").is_err());
+
+        let mut store = Store::open("sqlite://:memory:")?;
+        let results = vec![
+            ResourceResult {
+                url: "https://example.com/AAPL?token=abc".to_string(),
+                param: Some("AAPL".to_string()),
+                final_url: None,
+                accept_language: None,
+                language: None,
+                values: Ok(vec![
+                    ParsedValue {
+                        name: "price".to_string(),
+                        value: Value::from(1.5),
+                        error: None,
+                        stale: None,
+                        anomaly: None,
+                    },
+                    ParsedValue {
+                        name: "title".to_string(),
+                        value: Value::from("Apple"),
+                        error: None,
+                        stale: None,
+                        anomaly: None,
+                    },
+                ]),
+            },
+            ResourceResult {
+                url: "https://down.com".to_string(),
+                param: None,
+                final_url: None,
+                accept_language: None,
+                language: None,
+                values: Err(rvp::RvpError::ConfigError("down".to_string())),
+            },
+        ];
+        let redact = |text: &str| text.replace("abc", "[REDACTED]");
+        assert_eq!(store.append("stocks", &results, redact)?, 2);
+        assert_eq!(store.append("other", &results, redact)?, 2);
+
+        let query = Query {
+            config: Some("stocks"),
+            selector: Some("price"),
+            limit: 10,
+            ..Default::default()
+        };
+        let records = store.recent(&query)?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].value, "1.5");
+        assert_eq!(records[0].param.as_deref(), Some("AAPL"));
+        assert_eq!(
+            records[0].resource,
+            "https://example.com/AAPL?token=[REDACTED]"
+        );
+
+        let all = Query {
+            limit: 3,
+            ..Default::default()
+        };
+        assert_eq!(store.recent(&all)?.len(), 3);
+
+        let resource = "https://example.com/AAPL?token=[REDACTED]";
+        let last = store.last_values("stocks", resource, Some("AAPL"))?;
+        let selectors: Vec<&str> = last.iter().map(|r| r.selector.as_str()).collect();
+        assert_eq!(selectors, ["price", "title"]);
+        assert!(store.last_values("stocks", resource, None)?.is_empty());
+        assert!(store
+            .last_values("stocks", "https://down.com", None)?
+            .is_empty());
+
+        assert_eq!(
+            store.numbers("stocks", resource, "price", Some("AAPL"), 5)?,
+            [1.5]
+        );
+        assert!(store
+            .numbers("stocks", resource, "title", Some("AAPL"), 5)?
+            .is_empty());
+        assert!(store
+            .numbers("stocks", resource, "price", None, 5)?
+            .is_empty());
+
+        let later = Utc::now() + chrono::Duration::seconds(10);
+        assert_eq!(
+            store.number_at("stocks", resource, "price", Some("AAPL"), later)?,
+            Some(1.5)
+        );
+        let earlier = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(
+            store.number_at("stocks", resource, "price", Some("AAPL"), earlier)?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_separates_resources() -> Result<()> {
+        let result = |url: &str, price: f64| ResourceResult {
+            url: url.to_string(),
+            param: None,
+            final_url: None,
+            accept_language: None,
+            language: None,
+            values: Ok(vec![ParsedValue {
+                name: "price".to_string(),
+                value: Value::from(price),
+                error: None,
+                stale: None,
+                anomaly: None,
+            }]),
+        };
+        let mut store = Store::open("sqlite://:memory:")?;
+        let results = [
+            result("https://shop.com/pear", 2.0),
+            result("https://shop.com/tv", 900.0),
+        ];
+        store.append("shop", &results, str::to_string)?;
+
+        let numbers = |url| store.numbers("shop", url, "price", None, 5);
+        assert_eq!(numbers("https://shop.com/pear")?, [2.0]);
+        assert_eq!(numbers("https://shop.com/tv")?, [900.0]);
+        let later = Utc::now() + chrono::Duration::seconds(10);
+        let number_at = |url| store.number_at("shop", url, "price", None, later);
+        assert_eq!(number_at("https://shop.com/pear")?, Some(2.0));
+        assert_eq!(number_at("https://shop.com/tv")?, Some(900.0));
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+//! Latency and error rate of the hosts recorded by the runs with `--auto-concurrency`, one
+//! JSON file in the data directory
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::Result;
+use rvp::tuning::HostStats;
+
+/// Name of the host stats file inside the data directory
+pub const HOST_STATS_FILE: &str = "hosts.json";
+
+/// It reads the recorded stats, by host. A missing or unreadable file means no stats yet.
+pub fn load(dir: &Path) -> BTreeMap<String, HostStats> {
+    fs::read_to_string(dir.join(HOST_STATS_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// It replaces the recorded stats
+pub fn save(dir: &Path, stats: &BTreeMap<String, HostStats>) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(
+        dir.join(HOST_STATS_FILE),
+        serde_json::to_string_pretty(stats)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = std::env::temp_dir().join("rvp_test_host_stats");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(load(&dir).is_empty());
+
+        let stats = BTreeMap::from([(
+            "example.com".to_string(),
+            HostStats {
+                requests: 3,
+                latency_ms: 120.5,
+                error_rate: 0.0,
+            },
+        )]);
+        save(&dir, &stats).unwrap();
+        assert_eq!(load(&dir), stats);
+
+        fs::write(dir.join(HOST_STATS_FILE), "not json").unwrap();
+        assert!(load(&dir).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
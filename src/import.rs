@@ -0,0 +1,572 @@
+//! Conversion of the selector definitions made by other tools into a [Config]
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, RvpError};
+use crate::structure::{Config, Resource, ResourceKind, Selector, SelectorType};
+use crate::validate::validate;
+
+/// The result of an import
+pub struct Imported {
+    pub config: Config,
+    /// Definitions which could not be converted, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Version of the `rvp-picker` format supported by this build
+pub const PICKER_VERSION: u32 = 1;
+
+/// The `rvp-picker` interchange format: the elements hand-picked on the pages,
+/// e.g. by a browser extension or a devtools snippet.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "name": "stocks",
+///   "pages": [
+///     {
+///       "url": "https://example.com/quote/AAPL",
+///       "picks": [{"selector": "#price > span", "name": "price", "text": "182.5"}]
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Serialize, Deserialize)]
+pub struct PickerExport {
+    pub version: u32,
+    /// (Optional) Name of the config, `imported` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub pages: Vec<PickerPage>,
+}
+
+/// A page with the picked elements, becomes a [Resource]
+#[derive(Serialize, Deserialize)]
+pub struct PickerPage {
+    pub url: String,
+    #[serde(default)]
+    pub kind: ResourceKind,
+    pub picks: Vec<Pick>,
+}
+
+/// A picked element, becomes a [Selector]
+#[derive(Serialize, Deserialize)]
+pub struct Pick {
+    /// CSS selector of the element, or JSON pointer for the JSON pages
+    pub selector: String,
+    /// (Optional) Name of the value, `value<N>` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// (Optional) Type of the value, guessed from the `text` if not set
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub parsed_type: Option<SelectorType>,
+    /// (Optional) Text of the element at the time it was picked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// It converts the `rvp-picker` JSON into a [Config], the result is checked with [validate]
+pub fn from_picker(data: &str) -> Result<Imported> {
+    let export: PickerExport = serde_json::from_str(data)
+        .map_err(|e| RvpError::ConfigError(format!("Invalid rvp-picker file: {}", e)))?;
+    if export.version != PICKER_VERSION {
+        return Err(RvpError::ConfigError(format!(
+            "Unsupported rvp-picker version {}, expected {}",
+            export.version, PICKER_VERSION
+        )));
+    }
+
+    let mut counter = 0;
+    let resources = export
+        .pages
+        .into_iter()
+        .map(|page| {
+            let selectors = page
+                .picks
+                .into_iter()
+                .map(|pick| {
+                    counter += 1;
+                    let parsed_type = pick
+                        .parsed_type
+                        .unwrap_or_else(|| guess_type(pick.text.as_deref().unwrap_or_default()));
+                    let name = pick.name.unwrap_or_else(|| format!("value{}", counter));
+                    Selector::new(pick.selector, name, parsed_type)
+                })
+                .collect();
+            let mut resource = Resource::new(page.url, selectors);
+            // HTML is the default, it is not written to the config
+            resource.kind = (page.kind != ResourceKind::Html).then_some(page.kind);
+            resource
+        })
+        .collect();
+
+    let name = export.name.unwrap_or_else(|| "imported".to_string());
+    let config = Config::new(name, export.description, resources);
+    into_valid(config, vec![])
+}
+
+/// A page of the "url + css list" YAML used by other scrapers
+#[derive(Deserialize)]
+struct YamlPage {
+    url: String,
+    css: YamlSelectors,
+}
+
+/// CSS selectors of the page, either named or a plain list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum YamlSelectors {
+    Named(serde_yaml::Mapping),
+    List(Vec<String>),
+}
+
+/// The YAML file is either a list of pages or a named set of pages
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum YamlFile {
+    Named {
+        name: Option<String>,
+        #[serde(default)]
+        description: String,
+        pages: Vec<YamlPage>,
+    },
+    Pages(Vec<YamlPage>),
+}
+
+/// It converts the "url + css list" YAML into a [Config]:
+///
+/// ```yaml
+/// name: stocks
+/// pages:
+///   - url: https://example.com/quote/%%
+///     css:
+///       price: "#price > span::text"
+///       company: h1
+/// ```
+///
+/// The `css` can also be a plain list, the values are named `value<N>` then.
+pub fn from_yaml(data: &str) -> Result<Imported> {
+    let file: YamlFile = serde_yaml::from_str(data)
+        .map_err(|e| RvpError::ConfigError(format!("Invalid YAML file: {}", e)))?;
+    let (name, description, pages) = match file {
+        YamlFile::Named {
+            name,
+            description,
+            pages,
+        } => (name, description, pages),
+        YamlFile::Pages(pages) => (None, String::new(), pages),
+    };
+
+    let mut counter = 0;
+    let mut skipped = Vec::new();
+    let mut resources = Vec::with_capacity(pages.len());
+    for page in pages {
+        let named: Vec<(Option<String>, String)> = match page.css {
+            YamlSelectors::Named(mapping) => mapping
+                .into_iter()
+                .filter_map(|(name, path)| match (name.as_str(), path.as_str()) {
+                    (Some(name), Some(path)) => Some((Some(name.to_string()), path.to_string())),
+                    _ => {
+                        skipped.push(format!(
+                            "{:?}: only text names and selectors are supported",
+                            name
+                        ));
+                        None
+                    }
+                })
+                .collect(),
+            YamlSelectors::List(paths) => paths.into_iter().map(|path| (None, path)).collect(),
+        };
+
+        let mut selectors = Vec::with_capacity(named.len());
+        for (name, path) in named {
+            counter += 1;
+            let name = name.unwrap_or_else(|| format!("value{}", counter));
+            match css_path(&path) {
+                Ok(path) => selectors.push(Selector::new(path, name, SelectorType::String)),
+                Err(reason) => skipped.push(format!("{} ({}): {}", name, path, reason)),
+            }
+        }
+        resources.push(Resource::new(page.url, selectors));
+    }
+
+    let name = name.unwrap_or_else(|| "imported".to_string());
+    into_valid(Config::new(name, description, resources), skipped)
+}
+
+/// It converts a Scrapy spider into a [Config].
+///
+/// The spider is not executed, its source is scanned for the `name`, the `start_urls`
+/// (a resource per URL) and the `response.css(...)` calls, named by the item keys they are
+/// assigned to (`"price": response.css(...)` or `item["price"] = response.css(...)`).
+/// XPath, attributes, loops and the selectors relative to other elements are skipped.
+pub fn from_scrapy(data: &str) -> Result<Imported> {
+    static NAME: OnceLock<Regex> = OnceLock::new();
+    static URLS: OnceLock<Regex> = OnceLock::new();
+    static STRING: OnceLock<Regex> = OnceLock::new();
+    static CALL: OnceLock<Regex> = OnceLock::new();
+    let name = NAME.get_or_init(|| Regex::new(r#"(?m)^\s+name\s*=\s*["']([^"']+)["']"#).unwrap());
+    let urls = URLS.get_or_init(|| Regex::new(r"(?s)start_urls\s*=\s*[\[(](.*?)[\])]").unwrap());
+    let string = STRING.get_or_init(|| Regex::new(r#""([^"]*)"|'([^']*)'"#).unwrap());
+    let call = CALL.get_or_init(|| {
+        Regex::new(
+            r#"(?:["'](\w+)["']\s*(?::|\]\s*=)\s*)?(\w+)\.(css|xpath)\(\s*(?:"([^"]*)"|'([^']*)')"#,
+        )
+        .unwrap()
+    });
+
+    let urls: Vec<&str> = match urls.captures(data) {
+        Some(list) => string
+            .captures_iter(list.get(1).unwrap().as_str())
+            .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+            .map(|m| m.as_str())
+            .collect(),
+        None => vec![],
+    };
+    if urls.is_empty() {
+        return Err(RvpError::ConfigError(
+            "No start_urls found in the spider".to_string(),
+        ));
+    }
+
+    let mut skipped = Vec::new();
+    let mut selectors: Vec<Selector> = Vec::new();
+    for captures in call.captures_iter(data) {
+        let path = captures
+            .get(4)
+            .or_else(|| captures.get(5))
+            .unwrap()
+            .as_str();
+        let name = match captures.get(1) {
+            Some(key) => key.as_str().to_string(),
+            None => format!("value{}", selectors.len() + 1),
+        };
+        let start = captures.get(0).unwrap().start();
+        if data[..start].trim_end().ends_with(" in") {
+            skipped.push(format!(
+                "{} ({}): loops over elements are not supported",
+                name, path
+            ));
+            continue;
+        }
+        if &captures[2] != "response" {
+            skipped.push(format!(
+                "{} ({}): relative selectors are not supported",
+                name, path
+            ));
+            continue;
+        }
+        if &captures[3] == "xpath" {
+            skipped.push(format!("{} ({}): XPath is not supported", name, path));
+            continue;
+        }
+        if selectors.iter().any(|s| s.name == name) {
+            skipped.push(format!("{} ({}): duplicate name", name, path));
+            continue;
+        }
+        match css_path(path) {
+            Ok(path) => selectors.push(Selector::new(path, name, SelectorType::String)),
+            Err(reason) => skipped.push(format!("{} ({}): {}", name, path, reason)),
+        }
+    }
+
+    let resources = urls
+        .into_iter()
+        .map(|url| Resource::new(url.to_string(), selectors.clone()))
+        .collect();
+    let name = name
+        .captures(data)
+        .map_or_else(|| "imported".to_string(), |c| c[1].to_string());
+    into_valid(Config::new(name, String::new(), resources), skipped)
+}
+
+/// A HAR capture (HTTP Archive) of a browsing session, only the fields used by [from_har]
+#[derive(Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize)]
+struct HarLog {
+    #[serde(default)]
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+    response: HarResponse,
+    /// Type of the request in the Chromium captures, e.g. `document` or `xhr`
+    #[serde(default, rename = "_resourceType")]
+    resource_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HarRequest {
+    method: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct HarResponse {
+    status: u16,
+    #[serde(default)]
+    content: HarContent,
+}
+
+#[derive(Deserialize, Default)]
+struct HarContent {
+    #[serde(default, rename = "mimeType")]
+    mime_type: String,
+}
+
+/// It converts a HAR capture of a browsing session (saved from the network tab of the
+/// browser devtools) into a [Config] with a resource per visited page.
+///
+/// The pages are the HTML documents loaded with `GET` requests, the scripts, the images and
+/// the other requests of the pages are left out. Each resource gets a `title` selector to
+/// start with, the selectors of the values are added with `rvp edit`.
+pub fn from_har(data: &str) -> Result<Imported> {
+    let har: Har = serde_json::from_str(data)
+        .map_err(|e| RvpError::ConfigError(format!("Invalid HAR file: {}", e)))?;
+
+    let mut skipped = Vec::new();
+    let mut urls: Vec<String> = Vec::new();
+    for entry in har.log.entries {
+        let is_document = match entry.resource_type.as_deref() {
+            Some(resource_type) => resource_type == "document",
+            None => entry.response.content.mime_type.starts_with("text/html"),
+        };
+        // The redirects are followed to the pages captured next
+        if !is_document || (300..400).contains(&entry.response.status) {
+            continue;
+        }
+        let mut url = entry.request.url;
+        if let Some(position) = url.find('#') {
+            url.truncate(position);
+        }
+        if entry.request.method != "GET" {
+            skipped.push(format!(
+                "{}: {} requests are not imported",
+                url, entry.request.method
+            ));
+        } else if !(200..300).contains(&entry.response.status) {
+            skipped.push(format!(
+                "{}: the page responded with {}",
+                url, entry.response.status
+            ));
+        } else if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    if urls.is_empty() {
+        return Err(RvpError::ConfigError(
+            "No pages found in the HAR file".to_string(),
+        ));
+    }
+
+    let resources = urls
+        .into_iter()
+        .map(|url| {
+            let title = Selector::new(
+                "title".to_string(),
+                "title".to_string(),
+                SelectorType::String,
+            );
+            Resource::new(url, vec![title])
+        })
+        .collect();
+    into_valid(
+        Config::new("imported".to_string(), String::new(), resources),
+        skipped,
+    )
+}
+
+/// It strips the Scrapy/parsel `::text` pseudo-element from the CSS selector
+fn css_path(path: &str) -> std::result::Result<String, &'static str> {
+    let path = path.trim();
+    match path.split_once("::") {
+        None => Ok(path.to_string()),
+        Some((path, "text")) => Ok(path.trim().to_string()),
+        Some(_) => Err("only the text of the elements is supported"),
+    }
+}
+
+/// It returns the import if [validate] finds no problems in the config
+fn into_valid(config: Config, skipped: Vec<String>) -> Result<Imported> {
+    let problems = validate(&config);
+    if problems.is_empty() {
+        return Ok(Imported { config, skipped });
+    }
+
+    let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+    Err(RvpError::ConfigError(format!(
+        "Imported config is invalid: {}",
+        problems.join("; ")
+    )))
+}
+
+/// It guesses the [SelectorType] by the sample text of the element
+fn guess_type(text: &str) -> SelectorType {
+    let text = text.trim();
+    if text.parse::<f64>().is_ok() {
+        SelectorType::Number
+    } else if ["true", "false"].contains(&text.to_lowercase().as_str()) {
+        SelectorType::Boolean
+    } else {
+        SelectorType::String
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_picker() {
+        let data = r##"{
+            "version": 1,
+            "name": "stocks",
+            "pages": [
+                {
+                    "url": "https://example.com/quote/%%",
+                    "picks": [
+                        {"selector": "#price > span", "name": "price", "text": " 182.5 "},
+                        {"selector": "h1", "text": "Apple Inc."},
+                        {"selector": "#open", "type": "Boolean", "text": "Open"}
+                    ]
+                }
+            ]
+        }"##;
+        let config = from_picker(data).unwrap().config;
+        assert_eq!(config.name, "stocks");
+        assert!(config.needs_parameters());
+
+        let selectors = &config.resources[0].selectors;
+        assert_eq!(selectors[0].name, "price");
+        assert!(selectors[0].parsed_type == SelectorType::Number);
+        assert_eq!(selectors[1].name, "value2");
+        assert!(selectors[1].parsed_type == SelectorType::String);
+        assert!(selectors[2].parsed_type == SelectorType::Boolean);
+
+        let unsupported = r#"{"version": 2, "pages": []}"#;
+        assert!(from_picker(unsupported).is_err());
+
+        let invalid = r#"{"version": 1, "pages": [{"url": "https://example.com", "picks": [{"selector": "div >"}]}]}"#;
+        assert!(from_picker(invalid).is_err());
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let data = r##"
+name: stocks
+pages:
+  - url: https://example.com/quote/%%
+    css:
+      price: "#price > span::text"
+      company: h1
+      logo: img::attr(src)
+"##;
+        let imported = from_yaml(data).unwrap();
+        assert_eq!(imported.config.name, "stocks");
+        let selectors = &imported.config.resources[0].selectors;
+        assert_eq!(selectors.len(), 2);
+        assert_eq!(selectors[0].name, "price");
+        assert_eq!(selectors[0].path, "#price > span");
+        assert_eq!(selectors[1].name, "company");
+        assert_eq!(imported.skipped.len(), 1);
+
+        let data = "- url: https://example.com\n  css: [h1, p::text]\n";
+        let imported = from_yaml(data).unwrap();
+        assert_eq!(imported.config.name, "imported");
+        let selectors = &imported.config.resources[0].selectors;
+        assert_eq!(selectors[1].name, "value2");
+        assert_eq!(selectors[1].path, "p");
+    }
+
+    #[test]
+    fn test_from_scrapy() {
+        let data = r##"
+import scrapy
+
+class QuotesSpider(scrapy.Spider):
+    name = "quotes"
+    start_urls = [
+        "https://example.com/page/1/",
+        'https://example.com/page/2/',
+    ]
+
+    def parse(self, response):
+        item = {"title": response.css("h1::text").get()}
+        item['author'] = response.css('.author::text').get()
+        item["link"] = response.xpath("//a/@href").get()
+        for quote in response.css("div.quote"):
+            yield {"text": quote.css("span.text::text").get()}
+"##;
+        let imported = from_scrapy(data).unwrap();
+        let config = imported.config;
+        assert_eq!(config.name, "quotes");
+        assert_eq!(config.resources.len(), 2);
+        assert_eq!(config.resources[1].url, "https://example.com/page/2/");
+
+        let names: Vec<&str> = config.resources[0]
+            .selectors
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["title", "author"]);
+        assert_eq!(config.resources[0].selectors[0].path, "h1");
+        assert_eq!(imported.skipped.len(), 3);
+
+        assert!(from_scrapy("class Spider: pass").is_err());
+    }
+
+    #[test]
+    fn test_from_har() {
+        let data = r#"{"log": {"version": "1.2", "entries": [
+            {"request": {"method": "GET", "url": "https://shop.com/"},
+             "response": {"status": 301, "content": {"mimeType": "text/html"}}},
+            {"request": {"method": "GET", "url": "https://www.shop.com/#top"},
+             "response": {"status": 200, "content": {"mimeType": "text/html; charset=utf-8"}},
+             "_resourceType": "document"},
+            {"request": {"method": "GET", "url": "https://www.shop.com/app.js"},
+             "response": {"status": 200, "content": {"mimeType": "text/javascript"}},
+             "_resourceType": "script"},
+            {"request": {"method": "GET", "url": "https://www.shop.com/item/1"},
+             "response": {"status": 200, "content": {"mimeType": "text/html"}}},
+            {"request": {"method": "GET", "url": "https://www.shop.com/"},
+             "response": {"status": 200, "content": {"mimeType": "text/html"}}},
+            {"request": {"method": "POST", "url": "https://www.shop.com/search"},
+             "response": {"status": 200, "content": {"mimeType": "text/html"}}},
+            {"request": {"method": "GET", "url": "https://www.shop.com/missing"},
+             "response": {"status": 404, "content": {"mimeType": "text/html"}}}
+        ]}}"#;
+        let imported = from_har(data).unwrap();
+        let urls: Vec<&str> = imported
+            .config
+            .resources
+            .iter()
+            .map(|r| r.url.as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            ["https://www.shop.com/", "https://www.shop.com/item/1"]
+        );
+        assert_eq!(imported.config.resources[0].selectors[0].path, "title");
+        assert_eq!(
+            imported.skipped,
+            [
+                "https://www.shop.com/search: POST requests are not imported",
+                "https://www.shop.com/missing: the page responded with 404"
+            ]
+        );
+
+        let empty = r#"{"log": {"entries": []}}"#;
+        assert!(from_har(empty).is_err());
+        assert!(from_har("not a HAR").is_err());
+    }
+}
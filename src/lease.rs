@@ -0,0 +1,62 @@
+//! Leases of the resources in Redis, so the same config can run on many nodes
+use std::{env, time::Duration};
+
+use anyhow::{anyhow, Result};
+
+use crate::redis::{Client, Reply};
+
+/// Prefix of the lease keys
+const KEY_PREFIX: &str = "rvp:lease";
+
+pub struct Leases {
+    client: Client,
+    /// Identifier of this node, stored as the value of the leases
+    node: String,
+    ttl: Duration,
+}
+
+impl Leases {
+    pub async fn connect(url: &str, ttl: Duration) -> Result<Self> {
+        let host = env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string());
+        Ok(Self {
+            client: Client::connect(url).await?,
+            node: format!("{}:{}", host, std::process::id()),
+            ttl,
+        })
+    }
+
+    /// It leases the resource of the config for this node.
+    ///
+    /// Returns `false` if the resource is leased by another node.
+    pub async fn acquire(&mut self, config: &str, url: &str) -> Result<bool> {
+        let key = lease_key(config, url);
+        let ttl = self.ttl.as_millis().max(1).to_string();
+        match self
+            .client
+            .command(&["SET", &key, &self.node, "NX", "PX", &ttl])
+            .await?
+        {
+            Reply::Simple(_) => Ok(true),
+            Reply::Bulk(None) => Ok(false),
+            reply => Err(anyhow!("Unexpected reply to SET: {:?}", reply)),
+        }
+    }
+}
+
+/// It returns the Redis key of the resource lease
+fn lease_key(config: &str, url: &str) -> String {
+    format!("{}:{}:{}", KEY_PREFIX, config, url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lease_key() {
+        assert_eq!(
+            lease_key("stock", "https://example.com/AAPL"),
+            "rvp:lease:stock:https://example.com/AAPL"
+        );
+    }
+}
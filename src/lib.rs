@@ -0,0 +1,84 @@
+//! # RVP - Remote Value Parser
+//!
+//! The core of the `rvp` CLI: fetching web pages and parsing values from them
+//! with the CSS selectors defined in a [Config].
+//!
+//! ```no_run
+//! use rvp::{Config, ConfigFormat, Params};
+//! use std::path::Path;
+//!
+//! # async fn run() -> rvp::error::Result<()> {
+//! let config = Config::from_file(Path::new("stock.toml"), &ConfigFormat::Toml)?;
+//! let results = rvp::scrape(config, &Params::One("AAPL".to_string())).await?;
+//!
+//! for result in results {
+//!     for value in result.values? {
+//!         println!("{}: {}", value.name, value.value);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+pub mod alert;
+pub mod anomaly;
+pub mod compute;
+pub mod cron;
+pub mod error;
+pub mod feed;
+pub mod fetcher;
+pub mod import;
+pub mod redact;
+pub mod robots;
+pub mod scalper;
+pub mod sitemap;
+pub mod structure;
+pub mod suggest;
+pub mod tuning;
+pub mod validate;
+
+pub use error::RvpError;
+pub use fetcher::Fetcher;
+pub use scalper::{extract, extract_json, ParsedValue, ResourceResult};
+pub use structure::{Config, ConfigFormat, Params, Resource, ResourceKind, Selector, SelectorType};
+
+/// It grabs a single value from the web page using the default HTTP fetcher
+///
+/// Arguments:
+///
+/// - `selector`: The CSS selector (full path from root) to use to grab the value.
+/// - `from`: The URL to fetch the HTML from.
+///
+/// Returns:
+///
+/// A [`error::Result<String>`]
+#[cfg(not(target_family = "wasm"))]
+pub async fn grab_one(selector: &str, from: &str) -> error::Result<String> {
+    scalper::grab_one(&fetcher::HttpFetcher::default(), selector, from).await
+}
+
+/// It applies the parameters to the config and grabs the values of all its resources
+/// using the default HTTP fetcher
+///
+/// Arguments:
+///
+/// - `config`: The [Config] to run.
+/// - `params`: [Params] for the resources with the placeholder in the URL.
+///
+/// Returns:
+///
+/// A [ResourceResult] for each resource. A failed resource doesn't fail the others.
+#[cfg(not(target_family = "wasm"))]
+pub async fn scrape(config: Config, params: &Params) -> error::Result<Vec<ResourceResult>> {
+    scrape_with(&fetcher::HttpFetcher::default(), config, params).await
+}
+
+/// Same as [scrape], but with a custom [Fetcher], e.g. for runtimes without native networking
+pub async fn scrape_with(
+    fetcher: &dyn Fetcher,
+    mut config: Config,
+    params: &Params,
+) -> error::Result<Vec<ResourceResult>> {
+    config.apply_params(params)?;
+    let resources = sitemap::expand(fetcher, config.resources).await?;
+    Ok(scalper::grab_resources(fetcher, resources).await)
+}
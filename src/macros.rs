@@ -4,30 +4,44 @@
 /// The macro takes a list of modules as arguments, and generates an enum with
 /// arguments for each module. The enum is then used to match the subcommand
 /// passed to the CLI.
+///
+/// Modules can be prefixed with attributes, e.g. `#[cfg(feature = "worker")] worker`.
 macro_rules! commands_builder {
-    ($($module:ident),*) => (
+    ($($(#[$meta:meta])* $module:ident),*) => (
       // `paste!` is used to generate non-existent identifiers for enum, in this case the enum elements
       // which should represent the modules in the /commands directory.
       paste::paste! {
         #[derive(Subcommand)]
         enum Commands {
             $(
+              $(#[$meta])*
               [<$module:camel>]($module::Args),
             )*
+            /// Unknown subcommands are passed to the `shorthand` function defined next to the macro call
+            #[command(external_subcommand)]
+            External(Vec<String>),
         }
 
         impl Commands {
-            async fn exec(cli: Cli) -> Result<()> {
+            /// It runs the command, and also returns the redaction of the secrets of the
+            /// loaded config for the error output
+            async fn exec(cli: Cli) -> (Result<()>, rvp::redact::Redactor) {
+              let ctx = match Context::new(&cli.global) {
+                Ok(ctx) => ctx,
+                Err(err) => return (Err(err), rvp::redact::Redactor::default()),
+              };
               // Match the subcommand passed to the CLI.
               // This little magic saves us from having to write a clap-match statement for each module.
               // Rust if fucking awesome! 🦀
-              match cli.command {
+              let result = match cli.command {
                 $(
-                  // In the `::command(args)` section you can pass global options after the args (from `cli`)
-                  Commands::[<$module:camel>](args) => $module::command(args).await?,
+                  // Global options are passed to every command through the shared `Context`
+                  $(#[$meta])*
+                  Commands::[<$module:camel>](args) => $module::command(args, &ctx).await,
                 )*
-              }
-              Ok(())
+                Commands::External(args) => shorthand(args, &ctx).await,
+              };
+              (result, ctx.redactor())
             }
         }
       }
@@ -1,28 +1,109 @@
+mod audit_log;
 mod commands;
-mod scalper;
-mod structure;
+mod config_cache;
+mod context;
+mod exit;
+#[cfg(feature = "health")]
+mod health;
+#[cfg(feature = "history")]
+mod history_store;
+mod host_stats;
+#[cfg(feature = "lease")]
+mod lease;
+mod output;
+mod paths;
+#[cfg(any(feature = "worker", feature = "lease"))]
+mod redis;
+mod remote_config;
+mod report;
+mod settings;
+mod signature;
 use commands::*;
+use context::{Context, GlobalArgs};
 
 #[macro_use]
 mod macros;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::{env, process};
 
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
-    // Specify optional parameters here, before the subcommand
+    #[command(flatten)]
+    global: GlobalArgs,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 // Specify the command modules to be included in the CLI
-commands_builder!(grab, new, batch, edit);
+commands_builder!(
+    init,
+    grab,
+    suggest,
+    new,
+    batch,
+    edit,
+    config,
+    watch,
+    schedule,
+    diff,
+    run,
+    check,
+    list,
+    test,
+    audit,
+    import,
+    export,
+    output_schema,
+    #[cfg(feature = "history")]
+    history,
+    #[cfg(feature = "worker")]
+    worker,
+    man,
+    debug
+);
+
+/// `rvp <config-name> [ARGS]` is a shorthand for `rvp batch --name <config-name> [ARGS]`, if
+/// the config is found in the current or the global config directory
+async fn shorthand(args: Vec<String>, ctx: &Context) -> Result<()> {
+    let name = &args[0];
+    if paths::resolve_config(&ctx.config_search_dirs(), name).is_err() {
+        return Err(anyhow!(
+            "Unknown command \"{}\"!\nMore info: rvp --help",
+            name
+        ));
+    }
+
+    // Re-parse the whole command line, so the global options after the name are supported too
+    let mut argv: Vec<String> = env::args().collect();
+    let position = argv.len() - args.len();
+    argv.splice(
+        position..position,
+        ["batch".to_string(), "--name".to_string()],
+    );
+    let cli = Cli::parse_from(argv);
+    let ctx = Context::new(&cli.global)?;
+    match cli.command {
+        Commands::Batch(args) => batch::command(args, &ctx).await,
+        _ => unreachable!(),
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     let cli = Cli::parse();
-    Commands::exec(cli).await?;
-    Ok(())
+    // The options after the config name of the shorthand are parsed by `shorthand`
+    let json_errors = cli.global.json_errors
+        || matches!(&cli.command, Commands::External(args) if args.iter().any(|a| a == "--json-errors"));
+    let (result, redactor) = Commands::exec(cli).await;
+    if let Err(err) = result {
+        match json_errors {
+            true => eprintln!("{}", exit::json(&err, &redactor)),
+            false => eprintln!("Error: {}", redactor.redact(&format!("{:?}", err))),
+        }
+        process::exit(exit::code(&err));
+    }
 }
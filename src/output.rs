@@ -0,0 +1,760 @@
+use anyhow::{anyhow, Result};
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::{UTF8_FULL, UTF8_FULL_CONDENSED};
+use comfy_table::{Attribute, Cell, Color, Table};
+use rvp::redact::Redactor;
+use rvp::scalper::{ParsedValue, ResourceProbe, SelectorStability};
+use serde_json::{json, to_string_pretty, Value};
+use std::{fs, io::Write, path::Path};
+
+/// Maximum number of characters of a value in the tables, the longer values are truncated
+/// unless `--full` is set. JSON and CSV always have the full values.
+pub const MAX_TABLE_VALUE_CHARS: usize = 80;
+
+/// Generate table from parsed values, the values are truncated to `max_chars`
+pub fn generate_table(parsed_values: &[ParsedValue], max_chars: Option<usize>) -> Table {
+    generate_table_with_changes(parsed_values, None, max_chars)
+}
+
+/// Generate table from parsed values and highlight the values which differ from the `previous` ones
+pub fn generate_table_with_changes(
+    parsed_values: &[ParsedValue],
+    previous: Option<&[ParsedValue]>,
+    max_chars: Option<usize>,
+) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Name", "Value"]);
+    for (i, parsed_value) in parsed_values.iter().enumerate() {
+        let changed = match previous.and_then(|p| p.get(i)) {
+            Some(old) => old.name == parsed_value.name && old.value != parsed_value.value,
+            None => false,
+        };
+        let value = Cell::new(parsed_value_text(parsed_value, max_chars));
+        let value = match changed {
+            true => value.fg(Color::Yellow).add_attribute(Attribute::Bold),
+            false => value,
+        };
+        table.add_row(vec![Cell::new(&parsed_value.name), value]);
+    }
+
+    table
+}
+
+/// It renders the value for a table cell: the lists with a value per line, the records with a
+/// `key: value` per line, and the lists of records (e.g. of the `follow` selectors) as a
+/// nested table. Each single value is truncated to `max_chars`.
+fn table_value(value: &Value, max_chars: Option<usize>) -> String {
+    match value {
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+            records_table(items, max_chars).to_string()
+        }
+        Value::Array(items) if !items.is_empty() => items
+            .iter()
+            .map(|item| table_value(item, max_chars))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Object(record) if !record.is_empty() => record
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, table_value(value, max_chars)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        value => match max_chars {
+            Some(max_chars) => truncate(&value.to_string(), max_chars),
+            None => value.to_string(),
+        },
+    }
+}
+
+/// It renders the value for the tables, the stale values with the time of their run and
+/// the anomalies with their distance from the previous values
+fn parsed_value_text(parsed_value: &ParsedValue, max_chars: Option<usize>) -> String {
+    let text = table_value(&parsed_value.value, max_chars);
+    match (&parsed_value.stale, &parsed_value.anomaly) {
+        (Some(stale), _) => format!("{} (stale, {})", text, stale.time),
+        (None, Some(anomaly)) => format!("{} (anomaly, {})", text, anomaly),
+        (None, None) => text,
+    }
+}
+
+/// It renders the records as a table with a column for each key, in the order of appearance
+fn records_table(records: &[Value], max_chars: Option<usize>) -> Table {
+    let mut columns: Vec<&String> = Vec::new();
+    for key in records
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(|r| r.keys())
+    {
+        if !columns.contains(&key) {
+            columns.push(key);
+        }
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(columns.clone());
+    for record in records {
+        table.add_row(
+            columns
+                .iter()
+                .map(|column| match record.get(column.as_str()) {
+                    Some(value) => table_value(value, max_chars),
+                    None => String::new(),
+                }),
+        );
+    }
+    table
+}
+
+/// Parsed values of a single group, labeled by the parameter (`--params-file`)
+/// or the resource (`--nest`)
+pub type ValueGroup = (String, Vec<ParsedValue>);
+
+/// Generate table from the grouped parsed values, `column` is the header of the group labels.
+/// The values are truncated to `max_chars`.
+pub fn generate_grouped_table(
+    column: &str,
+    groups: &[ValueGroup],
+    max_chars: Option<usize>,
+) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![column, "Name", "Value"]);
+    for (label, values) in groups.iter() {
+        for parsed_value in values.iter() {
+            table.add_row(vec![
+                label.clone(),
+                parsed_value.name.clone(),
+                parsed_value_text(parsed_value, max_chars),
+            ]);
+        }
+    }
+
+    table
+}
+
+/// Generate json list of `{"param", "data"}` objects from the parsed values grouped by the
+/// parameter. With `flatten`, the `data` is a `name -> value` map instead of a list of records.
+pub fn generate_grouped_json(groups: &[ValueGroup], flatten: bool) -> String {
+    let groups: Vec<Value> = groups
+        .iter()
+        .map(|(param, values)| {
+            let data = match flatten {
+                true => Value::Object(
+                    values
+                        .iter()
+                        .map(|v| (v.name.clone(), v.value.clone()))
+                        .collect(),
+                ),
+                false => json!(values),
+            };
+            json!({ "param": param, "data": data })
+        })
+        .collect();
+    to_string_pretty(&groups).expect("Error while prettifying json!")
+}
+
+/// Generate csv from the grouped parsed values, `column` is the name of the group labels column
+pub fn generate_grouped_csv(column: &str, groups: &[ValueGroup]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record([column, "name", "value"])?;
+    for (label, values) in groups.iter() {
+        for parsed_value in values.iter() {
+            writer.write_record([label, &parsed_value.name, &csv_value(&parsed_value.value)])?;
+        }
+    }
+
+    let data = writer.into_inner()?;
+    Ok(String::from_utf8(data)?.trim_end().to_string())
+}
+
+/// Maximum number of characters of the found text shown in the probe table
+const PROBE_TEXT_LENGTH: usize = 40;
+
+/// Generate the pass/fail table of the selectors checked against the live pages.
+///
+/// A selector passes if it matched an element and its value was converted to the selector's type.
+/// The secrets are redacted from the URLs and the errors.
+pub fn generate_probe_table(resources: &[ResourceProbe], redactor: &Redactor) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["#", "Name", "Path", "Found", "Value", "Result"]);
+
+    let status = |passed: bool| match passed {
+        true => Cell::new("PASS").fg(Color::Green),
+        false => Cell::new("FAIL")
+            .fg(Color::Red)
+            .add_attribute(Attribute::Bold),
+    };
+
+    for (i, resource) in resources.iter().enumerate() {
+        let probes = match &resource.probes {
+            Ok(probes) => probes,
+            Err(err) => {
+                table.add_row(vec![
+                    Cell::new(i + 1),
+                    Cell::new(""),
+                    Cell::new(redactor.redact(&resource.url)),
+                    Cell::new(""),
+                    Cell::new(redactor.redact(&err.to_string())).fg(Color::Red),
+                    status(false),
+                ]);
+                continue;
+            }
+        };
+        for probe in probes.iter() {
+            let found = match probe.matched {
+                true => Cell::new(truncate(&probe.text, PROBE_TEXT_LENGTH)),
+                false => Cell::new("no match").fg(Color::Red),
+            };
+            let value = match &probe.value {
+                Ok(value) => Cell::new(value.to_string()),
+                Err(err) => Cell::new(redactor.redact(&err.to_string())).fg(Color::Red),
+            };
+            table.add_row(vec![
+                Cell::new(i + 1),
+                Cell::new(&probe.name),
+                Cell::new(&probe.path),
+                found,
+                value,
+                status(probe.matched && probe.value.is_ok()),
+            ]);
+        }
+    }
+
+    table
+}
+
+/// Generate the table of the selectors probed several times.
+///
+/// A selector is `FLAKY` if the number of the matched elements varies or it fails only
+/// sometimes, `BROKEN` if it always fails. The values may change between the probes
+/// on live pages, so it is only shown.
+pub fn generate_stability_table(summary: &[SelectorStability], redactor: &Redactor) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            "URL", "Name", "Matches", "Values", "Failures", "Result",
+        ]);
+
+    for stability in summary.iter() {
+        let counts: Vec<String> = stability
+            .match_counts
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let result = if stability.is_broken() {
+            Cell::new("BROKEN")
+                .fg(Color::Red)
+                .add_attribute(Attribute::Bold)
+        } else if stability.is_flaky() {
+            Cell::new("FLAKY")
+                .fg(Color::Yellow)
+                .add_attribute(Attribute::Bold)
+        } else {
+            Cell::new("STABLE").fg(Color::Green)
+        };
+        table.add_row(vec![
+            Cell::new(redactor.redact(&stability.url)),
+            Cell::new(&stability.name),
+            Cell::new(counts.join(", ")),
+            Cell::new(stability.distinct_values),
+            Cell::new(format!("{}/{}", stability.failures, stability.probes)),
+            result,
+        ]);
+    }
+
+    table
+}
+
+/// It shortens the text to `length` characters, the cut is marked with `…`
+pub fn truncate(text: &str, length: usize) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(length) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Generate json from parsed values
+pub fn generate_json(parsed_values: &[ParsedValue]) -> String {
+    let json_str = json!(parsed_values);
+    to_string_pretty(&json_str).expect("Error while prettifying json!")
+}
+
+/// Generate json object with the `name -> value` pairs from parsed values.
+///
+/// The later values overwrite the earlier ones with the same name, use `key_template`
+/// in the config to make the names unique.
+pub fn generate_flat_json(parsed_values: &[ParsedValue]) -> String {
+    let map: serde_json::Map<String, Value> = parsed_values
+        .iter()
+        .map(|v| (v.name.clone(), v.value.clone()))
+        .collect();
+    to_string_pretty(&map).expect("Error while prettifying json!")
+}
+
+/// Generate csv from parsed values
+pub fn generate_csv(parsed_values: &[ParsedValue]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(["name", "value"])?;
+    for parsed_value in parsed_values {
+        writer.write_record([&parsed_value.name, &csv_value(&parsed_value.value)])?;
+    }
+
+    let data = writer.into_inner()?;
+    Ok(String::from_utf8(data)?.trim_end().to_string())
+}
+
+/// Render the template, the `{{name}}` placeholders are replaced with the values of the same name.
+///
+/// Strings are inserted without the JSON quotes. The later values overwrite the earlier ones
+/// with the same name, the same way as in [generate_flat_json].
+pub fn render_template(template: &str, parsed_values: &[ParsedValue]) -> Result<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            return Err(anyhow!("Unclosed \"{{{{\" in the template!"));
+        };
+        let name = rest[start + 2..start + end].trim();
+        let Some(parsed_value) = parsed_values.iter().rev().find(|v| v.name == name) else {
+            return Err(anyhow!("Unknown value \"{}\" in the template!", name));
+        };
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&match &parsed_value.value {
+            Value::Null => String::new(),
+            value => csv_value(value),
+        });
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Strings are written to csv as is, without the JSON quotes
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// It writes the rendered command output to a file
+///
+/// Arguments:
+///
+/// - `path`: The path to the output file. Missing parent directories are created.
+/// - `data`: The rendered output (table, JSON, CSV or a single value).
+/// - `append`: If `true`, the data is appended to the end of the file instead of overwriting it.
+///
+/// Returns:
+///
+/// A [Result<()>]
+pub fn write_to_file(path: &Path, data: &str, append: bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)?;
+    writeln!(file, "{}", data)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::anomaly::Anomaly;
+    use rvp::scalper::Stale;
+    use serde_json::Number;
+    use std::env;
+
+    #[test]
+    fn test_generate_table() {
+        let parsed_values = vec![
+            ParsedValue {
+                name: "name1".to_string(),
+                value: Value::String("value1".to_string()),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "name2".to_string(),
+                value: Value::Number(Number::from_f64(2.2).unwrap()),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+        ];
+
+        let table = generate_table(&parsed_values, Some(MAX_TABLE_VALUE_CHARS));
+
+        assert_eq!(
+            table.to_string(),
+            "\
+            ╭───────┬──────────╮\n\
+            │ Name  ┆ Value    │\n\
+            ╞═══════╪══════════╡\n\
+            │ name1 ┆ \"value1\" │\n\
+            ├╌╌╌╌╌╌╌┼╌╌╌╌╌╌╌╌╌╌┤\n\
+            │ name2 ┆ 2.2      │\n\
+            ╰───────┴──────────╯"
+        );
+    }
+
+    #[test]
+    fn test_generate_table_with_lists() {
+        let parsed_values = vec![
+            ParsedValue {
+                name: "prices".to_string(),
+                value: json!([1.5, 2.5]),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "items".to_string(),
+                value: json!([
+                    {"url": "https://shop.com/1", "title": "One"},
+                    {"url": "https://shop.com/2", "price": 2},
+                ]),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+        ];
+
+        let table = generate_table(&parsed_values, None);
+
+        assert_eq!(
+            table.to_string(),
+            "\
+            ╭────────┬──────────────────────────────────────────╮\n\
+            │ Name   ┆ Value                                    │\n\
+            ╞════════╪══════════════════════════════════════════╡\n\
+            │ prices ┆ 1.5                                      │\n\
+            │        ┆ 2.5                                      │\n\
+            ├╌╌╌╌╌╌╌╌┼╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌┤\n\
+            │ items  ┆ ╭───────┬──────────────────────┬───────╮ │\n\
+            │        ┆ │ title ┆ url                  ┆ price │ │\n\
+            │        ┆ ╞═══════╪══════════════════════╪═══════╡ │\n\
+            │        ┆ │ \"One\" ┆ \"https://shop.com/1\" ┆       │ │\n\
+            │        ┆ │       ┆ \"https://shop.com/2\" ┆ 2     │ │\n\
+            │        ┆ ╰───────┴──────────────────────┴───────╯ │\n\
+            ╰────────┴──────────────────────────────────────────╯"
+        );
+    }
+
+    #[test]
+    fn test_table_value_truncation() {
+        let long = "word ".repeat(40);
+        let value = table_value(&json!([long, {"text": long}]), Some(10));
+        assert_eq!(value, "\"word word…\ntext: \"word word…");
+        assert!(table_value(&json!(long), None).ends_with("word \""));
+    }
+
+    #[test]
+    fn test_stale_value() {
+        let stale = ParsedValue {
+            name: "price".to_string(),
+            value: json!(1.5),
+            error: None,
+            stale: Some(Stale {
+                time: "2024-05-01T10:00:00Z".to_string(),
+            }),
+            anomaly: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&stale).unwrap(),
+            json!({"name": "price", "value": 1.5, "stale": true, "time": "2024-05-01T10:00:00Z"})
+        );
+        assert_eq!(
+            parsed_value_text(&stale, None),
+            "1.5 (stale, 2024-05-01T10:00:00Z)"
+        );
+    }
+
+    #[test]
+    fn test_anomaly_value() {
+        let spike = ParsedValue {
+            name: "price".to_string(),
+            value: json!(74000),
+            error: None,
+            stale: None,
+            anomaly: Some(Anomaly {
+                median: 7.4,
+                change: Some(999900.0),
+                z_score: None,
+            }),
+        };
+        assert_eq!(
+            serde_json::to_value(&spike).unwrap(),
+            json!({"name": "price", "value": 74000, "anomaly": {"median": 7.4, "change": 999900.0}})
+        );
+        assert_eq!(
+            parsed_value_text(&spike, None),
+            "74000 (anomaly, +999900% from the median 7.4)"
+        );
+    }
+
+    #[test]
+    fn test_generate_table_with_changes() {
+        let previous = vec![ParsedValue {
+            name: "name1".to_string(),
+            value: Value::String("value1".to_string()),
+            error: None,
+            stale: None,
+            anomaly: None,
+        }];
+        let parsed_values = vec![ParsedValue {
+            name: "name1".to_string(),
+            value: Value::String("value2".to_string()),
+            error: None,
+            stale: None,
+            anomaly: None,
+        }];
+
+        let mut table = generate_table_with_changes(&parsed_values, Some(&previous), None);
+        // Changed values are rendered with ANSI styles
+        assert!(table.enforce_styling().to_string().contains("\u{1b}["));
+
+        let mut unchanged = generate_table_with_changes(&previous, Some(&previous), None);
+        assert!(!unchanged.enforce_styling().to_string().contains("\u{1b}["));
+    }
+
+    #[test]
+    fn test_generate_probe_table() {
+        use rvp::error::RvpError;
+        use rvp::scalper::SelectorProbe;
+
+        let resources = vec![
+            ResourceProbe {
+                url: "https://example.com".to_string(),
+                probes: Ok(vec![
+                    SelectorProbe {
+                        name: "title".to_string(),
+                        path: "h1".to_string(),
+                        matched: true,
+                        matches: 1,
+                        text: "Example".to_string(),
+                        value: Ok(Value::String("Example".to_string())),
+                    },
+                    SelectorProbe {
+                        name: "price".to_string(),
+                        path: "h2".to_string(),
+                        matched: false,
+                        matches: 0,
+                        text: "".to_string(),
+                        value: Ok(Value::String("".to_string())),
+                    },
+                ]),
+            },
+            ResourceProbe {
+                url: "https://example.org".to_string(),
+                probes: Err(RvpError::ConfigError("unreachable".to_string())),
+            },
+        ];
+
+        let mut table = generate_probe_table(&resources, &Redactor::default());
+        table.force_no_tty();
+        let table = table.to_string();
+        assert_eq!(table.matches("PASS").count(), 1);
+        assert_eq!(table.matches("FAIL").count(), 2);
+        assert!(table.contains("no match"));
+        assert!(table.contains("https://example.org"));
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("  short ", 10), "short");
+        assert_eq!(truncate("ÄÖÜ long text", 3), "ÄÖÜ…");
+    }
+
+    #[test]
+    fn test_generate_grouped() {
+        let groups = vec![
+            (
+                "AAPL".to_string(),
+                vec![ParsedValue {
+                    name: "price".to_string(),
+                    value: Value::from(1.5),
+                    error: None,
+                    stale: None,
+                    anomaly: None,
+                }],
+            ),
+            (
+                "MSFT".to_string(),
+                vec![ParsedValue {
+                    name: "price".to_string(),
+                    value: Value::from(2.5),
+                    error: None,
+                    stale: None,
+                    anomaly: None,
+                }],
+            ),
+        ];
+
+        assert!(generate_grouped_table("Param", &groups, None)
+            .to_string()
+            .contains("MSFT"));
+        assert_eq!(
+            generate_grouped_csv("param", &groups).unwrap(),
+            "param,name,value\nAAPL,price,1.5\nMSFT,price,2.5"
+        );
+        let json: Value = serde_json::from_str(&generate_grouped_json(&groups, false)).unwrap();
+        assert_eq!(json[1]["param"], "MSFT");
+        assert_eq!(json[1]["data"][0]["value"], 2.5);
+        let json: Value = serde_json::from_str(&generate_grouped_json(&groups, true)).unwrap();
+        assert_eq!(json[0]["data"]["price"], 1.5);
+    }
+
+    #[test]
+    fn test_render_template() {
+        let parsed_values = vec![
+            ParsedValue {
+                name: "price".to_string(),
+                value: Value::from(1.5),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "date".to_string(),
+                value: Value::from("2024-05-01"),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "broken".to_string(),
+                value: Value::Null,
+                error: Some("invalid selector".to_string()),
+                stale: None,
+                anomaly: None,
+            },
+        ];
+
+        assert_eq!(
+            render_template("{{price}} USD as of {{ date }}{{broken}}", &parsed_values).unwrap(),
+            "1.5 USD as of 2024-05-01"
+        );
+        assert!(render_template("{{volume}}", &parsed_values).is_err());
+        assert!(render_template("{{price", &parsed_values).is_err());
+    }
+
+    #[test]
+    fn test_generate_json() {
+        let parsed_values = vec![
+            ParsedValue {
+                name: "name1".to_string(),
+                value: Value::String("value1".to_string()),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "name2".to_string(),
+                value: Value::Number(Number::from_f64(25.6).unwrap()),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+        ];
+
+        let json = generate_json(&parsed_values);
+
+        assert_eq!(
+            json,
+            "[\n  {\n    \"name\": \"name1\",\n    \"value\": \"value1\"\n  },\n  \
+            {\n    \"name\": \"name2\",\n    \"value\": 25.6\n  }\n]"
+        );
+    }
+
+    #[test]
+    fn test_generate_flat_json() {
+        let parsed_values = vec![
+            ParsedValue {
+                name: "name1".to_string(),
+                value: Value::String("value1".to_string()),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "name2".to_string(),
+                value: Value::Null,
+                error: Some("invalid selector".to_string()),
+                stale: None,
+                anomaly: None,
+            },
+        ];
+
+        let json = generate_flat_json(&parsed_values);
+
+        assert_eq!(json, "{\n  \"name1\": \"value1\",\n  \"name2\": null\n}");
+    }
+
+    #[test]
+    fn test_generate_csv() -> Result<()> {
+        let parsed_values = vec![
+            ParsedValue {
+                name: "name1".to_string(),
+                value: Value::String("value, with comma".to_string()),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            ParsedValue {
+                name: "name2".to_string(),
+                value: Value::Number(Number::from_f64(25.6).unwrap()),
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+        ];
+
+        let csv = generate_csv(&parsed_values)?;
+
+        assert_eq!(csv, "name,value\nname1,\"value, with comma\"\nname2,25.6");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_file() -> Result<()> {
+        let dir = env::temp_dir().join("rvp_test_write_to_file");
+        let path = dir.join("nested").join("output.csv");
+        let _ = fs::remove_dir_all(&dir);
+
+        write_to_file(&path, "first", false)?;
+        write_to_file(&path, "second", true)?;
+        assert_eq!(fs::read_to_string(&path)?, "first\nsecond\n");
+
+        write_to_file(&path, "third", false)?;
+        assert_eq!(fs::read_to_string(&path)?, "third\n");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use rvp::error::RvpError;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Name of the RVP directory inside the base directories
+const APP_DIR: &str = "rvp";
+
+/// It returns the global config directory: `$XDG_CONFIG_HOME/rvp` or `~/.config/rvp`
+pub fn config_dir() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// It returns the data directory for the run history: `$XDG_DATA_HOME/rvp` or `~/.local/share/rvp`
+pub fn data_dir() -> Option<PathBuf> {
+    base_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// It returns the cache directory for the downloaded and the parsed configs: `$XDG_CACHE_HOME/rvp` or `~/.cache/rvp`
+pub fn cache_dir() -> Option<PathBuf> {
+    base_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// It looks up the config file by its name in the directory.
+///
+/// The name can be given with or without the extension, `.toml` is preferred over `.json`.
+pub fn find_config(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exact = dir.join(name);
+    if exact.extension().is_some() && exact.is_file() {
+        return Some(exact);
+    }
+    ["toml", "json"]
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", name, extension)))
+        .find(|path| path.is_file())
+}
+
+/// It looks up the config file by its name in the directories, the first one found wins.
+///
+/// Returns the path of the config, or the error listing the directories searched.
+pub fn resolve_config(dirs: &[PathBuf], name: &str) -> Result<PathBuf, RvpError> {
+    if let Some(path) = dirs.iter().find_map(|dir| find_config(dir, name)) {
+        return Ok(path);
+    }
+    let searched: Vec<String> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+    Err(RvpError::NotFoundError(match searched.is_empty() {
+        true => "Could not find the global config directory!".to_string(),
+        false => format!(
+            "Config \"{}\" was not found in {}!",
+            name,
+            searched.join(" or ")
+        ),
+    }))
+}
+
+/// It returns the name usable as a file name inside a directory, e.g. of a config name.
+///
+/// The characters other than the letters, digits, `-`, `_` and `.` are replaced with `_`, and so
+/// is a leading `.`, so the name can't refer to another directory (`/`, `..`) or be hidden.
+pub fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            '.' if i == 0 => '_',
+            c if c.is_alphanumeric() || "-_.".contains(c) => c,
+            _ => '_',
+        })
+        .collect();
+    match name.is_empty() {
+        true => "_".to_string(),
+        false => name,
+    }
+}
+
+/// It resolves the XDG base directory from the environment variable or falls back to the
+/// given directory inside the user's home
+fn base_dir(env_var: &str, home_fallback: &str) -> Option<PathBuf> {
+    let base = match env::var_os(env_var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(home_fallback),
+    };
+    Some(base.join(APP_DIR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_dir() {
+        env::set_var("RVP_TEST_BASE_DIR", "/tmp/rvp_base");
+        assert_eq!(
+            base_dir("RVP_TEST_BASE_DIR", ".config"),
+            Some(PathBuf::from("/tmp/rvp_base/rvp"))
+        );
+
+        env::set_var("RVP_TEST_BASE_DIR", "");
+        let fallback = base_dir("RVP_TEST_BASE_DIR", ".config").unwrap();
+        assert!(fallback.ends_with(".config/rvp"));
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("Stock prices-2.v1"), "Stock_prices-2.v1");
+        assert_eq!(file_name("../../etc/passwd"), "_._.._etc_passwd");
+        assert_eq!(file_name("..\\config"), "_._config");
+        assert_eq!(file_name(".hidden"), "_hidden");
+        assert_eq!(file_name("Цены"), "Цены");
+        assert_eq!(file_name(""), "_");
+    }
+
+    #[test]
+    fn test_find_config() {
+        let dir = env::temp_dir().join("rvp_test_find_config");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("stock.json"), "{}").unwrap();
+
+        assert_eq!(find_config(&dir, "stock"), Some(dir.join("stock.json")));
+        assert_eq!(
+            find_config(&dir, "stock.json"),
+            Some(dir.join("stock.json"))
+        );
+        assert_eq!(find_config(&dir, "weather"), None);
+
+        let other = env::temp_dir().join("rvp_test_find_config_other");
+        std::fs::create_dir_all(&other).unwrap();
+        std::fs::write(other.join("stock.toml"), "").unwrap();
+        std::fs::write(other.join("weather.toml"), "").unwrap();
+        let dirs = [dir.clone(), other.clone()];
+        assert_eq!(
+            resolve_config(&dirs, "stock").unwrap(),
+            dir.join("stock.json")
+        );
+        assert_eq!(
+            resolve_config(&dirs, "weather").unwrap(),
+            other.join("weather.toml")
+        );
+        let err = resolve_config(&dirs, "rates").unwrap_err().to_string();
+        assert!(err.starts_with("Config \"rates\" was not found in "));
+        assert!(err.contains(" or "));
+        assert!(resolve_config(&[], "stock").is_err());
+        std::fs::remove_dir_all(&other).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
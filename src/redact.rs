@@ -0,0 +1,118 @@
+//! Redaction of secrets (tokens in URLs, API keys) from the diagnostic output
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::error::{Result, RvpError};
+
+/// The text which replaces the redacted secrets
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Compiled `redact` patterns of the config
+#[derive(Default, Clone, Debug)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    /// It compiles the patterns.
+    ///
+    /// If a pattern has a capture group, only the first group is redacted, e.g. `token=([^&]+)`
+    /// keeps the `token=` part. Otherwise the whole match is redacted.
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|err| {
+                    RvpError::ConfigError(format!(
+                        "Invalid redact pattern \"{}\": {}",
+                        pattern, err
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// It replaces the secrets in the text with [REDACTED]
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for pattern in self.patterns.iter() {
+            if !pattern.is_match(&text) {
+                continue;
+            }
+            let redacted = pattern.replace_all(&text, |captures: &regex::Captures| {
+                let whole = captures.get(0).expect("group 0 is the whole match");
+                match captures.get(1) {
+                    Some(secret) => {
+                        let start = secret.start() - whole.start();
+                        let end = secret.end() - whole.start();
+                        let whole = whole.as_str();
+                        format!("{}{}{}", &whole[..start], REDACTED, &whole[end..])
+                    }
+                    None => REDACTED.to_string(),
+                }
+            });
+            text = Cow::Owned(redacted.into_owned());
+        }
+        text
+    }
+
+    /// It returns the same error with the secrets hidden in its URL and messages, so its kind
+    /// (and the exit code) is kept
+    pub fn redact_error(&self, err: &RvpError) -> RvpError {
+        let redact = |text: &str| self.redact(text).into_owned();
+        match err {
+            RvpError::FetchError { url, message } => RvpError::FetchError {
+                url: redact(url),
+                message: redact(message),
+            },
+            RvpError::SelectorParseError {
+                name,
+                path,
+                message,
+            } => RvpError::SelectorParseError {
+                name: name.clone(),
+                path: redact(path),
+                message: redact(message),
+            },
+            RvpError::TypeConversionError { name, value, to } => RvpError::TypeConversionError {
+                name: name.clone(),
+                value: redact(value),
+                to: to.clone(),
+            },
+            RvpError::ConfigError(message) => RvpError::ConfigError(redact(message)),
+            RvpError::NotFoundError(message) => RvpError::NotFoundError(redact(message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let redactor =
+            Redactor::new(&["token=([^&]+)".to_string(), "sk-[A-Za-z0-9]+".to_string()]).unwrap();
+
+        assert_eq!(
+            redactor.redact("failed to fetch \"https://api.com/?token=abc&q=1\": timeout"),
+            "failed to fetch \"https://api.com/?token=[REDACTED]&q=1\": timeout"
+        );
+        assert_eq!(redactor.redact("key sk-123abc"), "key [REDACTED]");
+        assert!(matches!(redactor.redact("nothing"), Cow::Borrowed(_)));
+        assert_eq!(Redactor::default().redact("token=abc"), "token=abc");
+        assert!(Redactor::new(&["(".to_string()]).is_err());
+
+        let err = RvpError::FetchError {
+            url: "https://api.com/?token=abc".to_string(),
+            message: "timeout of https://api.com/?token=abc".to_string(),
+        };
+        assert_eq!(
+            redactor.redact_error(&err).to_string(),
+            "failed to fetch \"https://api.com/?token=[REDACTED]\": timeout of https://api.com/?token=[REDACTED]"
+        );
+        assert_eq!(redactor.redact_error(&err).kind(), "network");
+    }
+}
@@ -0,0 +1,139 @@
+//! Minimal Redis client, just enough for the job queues of `rvp worker` and the leases
+use anyhow::{anyhow, Result};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+/// Default port of the Redis server
+const DEFAULT_PORT: u16 = 6379;
+
+/// A reply of the Redis server in the RESP2 protocol
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Simple(String),
+    Integer(i64),
+    Bulk(Option<String>),
+    Array(Option<Vec<Reply>>),
+}
+
+pub struct Client {
+    stream: BufReader<TcpStream>,
+}
+
+impl Client {
+    /// It connects to the server by the URL `redis://[:password@]host[:port][/db]`
+    pub async fn connect(url: &str) -> Result<Self> {
+        let Some(address) = url.strip_prefix("redis://") else {
+            return Err(anyhow!("Redis URL should start with redis://"));
+        };
+        let (credentials, address) = match address.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, address),
+        };
+        let (host, db) = match address.split_once('/') {
+            Some((host, db)) => (host, Some(db).filter(|db| !db.is_empty())),
+            None => (address, None),
+        };
+        let host = match host.contains(':') {
+            true => host.to_string(),
+            false => format!("{}:{}", host, DEFAULT_PORT),
+        };
+
+        let stream = TcpStream::connect(&host).await?;
+        let mut client = Self {
+            stream: BufReader::new(stream),
+        };
+
+        if let Some(credentials) = credentials {
+            match credentials.split_once(':') {
+                Some(("", password)) => client.command(&["AUTH", password]).await?,
+                Some((user, password)) => client.command(&["AUTH", user, password]).await?,
+                None => client.command(&["AUTH", credentials]).await?,
+            };
+        }
+        if let Some(db) = db {
+            client.command(&["SELECT", db]).await?;
+        }
+
+        Ok(client)
+    }
+
+    /// It sends the command and waits for the reply
+    pub async fn command(&mut self, args: &[&str]) -> Result<Reply> {
+        let mut request = format!("*{}\r\n", args.len());
+        for arg in args {
+            request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        self.stream.get_mut().write_all(request.as_bytes()).await?;
+
+        read_reply(&mut self.stream).await
+    }
+}
+
+/// It reads a single reply, the error replies are returned as errors
+pub async fn read_reply<R: AsyncBufRead + Unpin + Send>(reader: &mut R) -> Result<Reply> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Err(anyhow!("Redis connection was closed"));
+    }
+    let line = line.trim_end_matches("\r\n");
+    let (kind, value) = line.split_at(line.len().min(1));
+
+    match kind {
+        "+" => Ok(Reply::Simple(value.to_string())),
+        "-" => Err(anyhow!("Redis error: {}", value)),
+        ":" => Ok(Reply::Integer(value.parse()?)),
+        "$" => {
+            let length: i64 = value.parse()?;
+            if length < 0 {
+                return Ok(Reply::Bulk(None));
+            }
+            // The data is followed by CRLF
+            let mut data = vec![0; length as usize + 2];
+            reader.read_exact(&mut data).await?;
+            data.truncate(length as usize);
+            Ok(Reply::Bulk(Some(String::from_utf8(data)?)))
+        }
+        "*" => {
+            let length: i64 = value.parse()?;
+            if length < 0 {
+                return Ok(Reply::Array(None));
+            }
+            let mut items = Vec::with_capacity(length as usize);
+            for _ in 0..length {
+                items.push(Box::pin(read_reply(reader)).await?);
+            }
+            Ok(Reply::Array(Some(items)))
+        }
+        _ => Err(anyhow!("Unexpected Redis reply: {}", line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_reply() -> Result<()> {
+        let mut input: &[u8] =
+            b"+OK\r\n:3\r\n$-1\r\n*2\r\n$4\r\njobs\r\n$7\r\n{\"a\":1}\r\n-ERR wrong\r\n";
+
+        assert_eq!(
+            read_reply(&mut input).await?,
+            Reply::Simple("OK".to_string())
+        );
+        assert_eq!(read_reply(&mut input).await?, Reply::Integer(3));
+        assert_eq!(read_reply(&mut input).await?, Reply::Bulk(None));
+        assert_eq!(
+            read_reply(&mut input).await?,
+            Reply::Array(Some(vec![
+                Reply::Bulk(Some("jobs".to_string())),
+                Reply::Bulk(Some("{\"a\":1}".to_string())),
+            ]))
+        );
+        assert!(read_reply(&mut input).await.is_err());
+        assert!(read_reply(&mut input).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+//! Machine-readable report of a run (`--report-file`): the status of each resource with its
+//! requests, separate from the data output
+use std::{path::Path, sync::Mutex, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use rvp::fetcher::{Document, Fetcher, Request, CACHE_BUSTING_PARAM};
+use rvp::scalper::ResourceResult;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::context::Context;
+
+/// A request sent during the run
+#[derive(Clone, Debug, PartialEq)]
+struct Measured {
+    /// The requested URL, without the cache busting parameter
+    url: String,
+    /// Whether it was a fresh request of a page fetched before, see `refetch_on_miss`
+    retry: bool,
+    bytes: usize,
+    duration: Duration,
+}
+
+/// Collects the requests of the runs, until the report of the run takes them
+#[derive(Default)]
+pub struct Recorder {
+    requests: Mutex<Vec<Measured>>,
+}
+
+impl Recorder {
+    fn record(&self, url: &str, bytes: usize, duration: Duration) {
+        let (url, retry) = match strip_cache_busting(url) {
+            Some(url) => (url, true),
+            None => (url.to_string(), false),
+        };
+        let mut requests = self
+            .requests
+            .lock()
+            .expect("recorded requests are poisoned");
+        requests.push(Measured {
+            url,
+            retry,
+            bytes,
+            duration,
+        });
+    }
+
+    /// It returns the requests recorded since the previous call
+    fn take(&self) -> Vec<Measured> {
+        std::mem::take(
+            &mut *self
+                .requests
+                .lock()
+                .expect("recorded requests are poisoned"),
+        )
+    }
+}
+
+/// Wraps a [Fetcher] to record the size and the duration of each request
+pub struct RecordingFetcher<'a> {
+    pub inner: &'a dyn Fetcher,
+    pub recorder: &'a Recorder,
+}
+
+impl RecordingFetcher<'_> {
+    async fn measure<T>(
+        &self,
+        url: &str,
+        request: impl std::future::Future<Output = rvp::error::Result<T>>,
+        size: impl Fn(&T) -> usize,
+    ) -> rvp::error::Result<T> {
+        let start = Instant::now();
+        let result = request.await;
+        let bytes = result.as_ref().map_or(0, size);
+        self.recorder.record(url, bytes, start.elapsed());
+        result
+    }
+}
+
+#[async_trait]
+impl Fetcher for RecordingFetcher<'_> {
+    async fn fetch(&self, url: &str) -> rvp::error::Result<String> {
+        self.measure(url, self.inner.fetch(url), String::len).await
+    }
+
+    async fn fetch_document(&self, request: &Request) -> rvp::error::Result<Document> {
+        let document = self.inner.fetch_document(request);
+        self.measure(&request.url, document, |d| d.text.len()).await
+    }
+
+    async fn render(&self, url: &str) -> rvp::error::Result<String> {
+        self.measure(url, self.inner.render(url), String::len).await
+    }
+}
+
+/// It returns the URL without the cache busting parameter, `None` if the URL has none
+fn strip_cache_busting(url: &str) -> Option<String> {
+    let mut parsed = url::Url::parse(url).ok()?;
+    if !parsed
+        .query_pairs()
+        .any(|(key, _)| key == CACHE_BUSTING_PARAM)
+    {
+        return None;
+    }
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| key != CACHE_BUSTING_PARAM)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    match pairs.is_empty() {
+        true => parsed.set_query(None),
+        false => {
+            parsed.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+    Some(parsed.to_string())
+}
+
+/// Status of a resource in the report
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    /// All values were parsed
+    Ok,
+    /// Some values could not be parsed
+    Partial,
+    /// The resource could not be fetched or parsed
+    Failed,
+}
+
+/// A resource in the report
+#[derive(Serialize, Debug, PartialEq)]
+struct ResourceReport {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    param: Option<String>,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    values: usize,
+    failed_values: usize,
+    /// Requests of the resource's URL
+    requests: usize,
+    retries: usize,
+    bytes: usize,
+    duration_ms: u128,
+    /// Whether the page was downloaded for an earlier resource with the same URL
+    cached: bool,
+}
+
+/// The report of a run
+#[derive(Serialize, Debug, PartialEq)]
+struct Report {
+    /// UTC time of the report in RFC 3339 format
+    time: String,
+    command: String,
+    config: String,
+    resources: Vec<ResourceReport>,
+    failed_resources: usize,
+    failed_values: usize,
+    /// Requests of the run, including the next pages and the followed links
+    requests: usize,
+    retries: usize,
+    bytes: usize,
+    duration_ms: u128,
+    cache_hits: usize,
+}
+
+/// It builds the report of the results from the recorded requests. The requests of a URL are
+/// counted for the first resource with the URL, the later ones are `cached`.
+fn build(
+    command: &str,
+    config: &str,
+    results: &[ResourceResult],
+    requests: &[Measured],
+    redact: impl Fn(&str) -> String,
+) -> Report {
+    let mut resources: Vec<ResourceReport> = Vec::with_capacity(results.len());
+    for (i, result) in results.iter().enumerate() {
+        let cached = results[..i].iter().any(|r| r.url == result.url);
+        let own: Vec<&Measured> = match cached {
+            true => Vec::new(),
+            false => requests.iter().filter(|r| r.url == result.url).collect(),
+        };
+        let (values, failed_values) = match &result.values {
+            Ok(values) => (values.len(), result.failed_values().len()),
+            Err(_) => (0, 0),
+        };
+        let status = match (&result.values, failed_values) {
+            (Err(_), _) => Status::Failed,
+            (Ok(_), 0) => Status::Ok,
+            (Ok(_), _) => Status::Partial,
+        };
+        resources.push(ResourceReport {
+            url: redact(&result.url),
+            param: result.param.as_deref().map(&redact),
+            status,
+            error: result.values.as_ref().err().map(|e| redact(&e.to_string())),
+            values,
+            failed_values,
+            requests: own.len(),
+            retries: own.iter().filter(|r| r.retry).count(),
+            bytes: own.iter().map(|r| r.bytes).sum(),
+            duration_ms: own.iter().map(|r| r.duration).sum::<Duration>().as_millis(),
+            cached,
+        });
+    }
+
+    Report {
+        time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        command: command.to_string(),
+        config: config.to_string(),
+        failed_resources: resources
+            .iter()
+            .filter(|r| r.status == Status::Failed)
+            .count(),
+        failed_values: resources.iter().map(|r| r.failed_values).sum(),
+        requests: requests.len(),
+        retries: requests.iter().filter(|r| r.retry).count(),
+        bytes: requests.iter().map(|r| r.bytes).sum(),
+        duration_ms: requests
+            .iter()
+            .map(|r| r.duration)
+            .sum::<Duration>()
+            .as_millis(),
+        cache_hits: resources.iter().filter(|r| r.cached).count(),
+        resources,
+    }
+}
+
+/// It writes the report of the run to the file, replacing the report of the previous run
+pub fn write(
+    path: &Path,
+    ctx: &Context,
+    command: &str,
+    config: &str,
+    results: &[ResourceResult],
+) -> Result<()> {
+    let requests = ctx.requests.take();
+    let report = build(command, config, results, &requests, |text| {
+        ctx.redact(text).into_owned()
+    });
+    std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvp::error::RvpError;
+    use rvp::scalper::ParsedValue;
+    use serde_json::Value;
+
+    fn result(url: &str, values: rvp::error::Result<Vec<ParsedValue>>) -> ResourceResult {
+        ResourceResult {
+            url: url.to_string(),
+            param: None,
+            final_url: None,
+            accept_language: None,
+            language: None,
+            values,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recording_fetcher() {
+        struct PageFetcher;
+
+        #[async_trait]
+        impl Fetcher for PageFetcher {
+            async fn fetch(&self, _url: &str) -> rvp::error::Result<String> {
+                Ok("page".to_string())
+            }
+        }
+
+        let recorder = Recorder::default();
+        let fetcher = RecordingFetcher {
+            inner: &PageFetcher,
+            recorder: &recorder,
+        };
+        fetcher.fetch("https://a.com/?q=1").await.unwrap();
+        let fresh = Request::get("https://a.com/?q=1").cache_busting();
+        fetcher.fetch_document(&fresh).await.unwrap();
+
+        let requests = recorder.take();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].url, "https://a.com/?q=1");
+        assert!(requests[1].retry);
+        assert_eq!(requests[1].bytes, 4);
+        assert!(recorder.take().is_empty());
+        assert_eq!(
+            strip_cache_busting("https://a.com/?_rvp=1").unwrap(),
+            "https://a.com/"
+        );
+        assert_eq!(strip_cache_busting("https://a.com/"), None);
+    }
+
+    #[test]
+    fn test_build() {
+        let measured = |url: &str, retry: bool| Measured {
+            url: url.to_string(),
+            retry,
+            bytes: 100,
+            duration: Duration::from_millis(20),
+        };
+        let requests = vec![
+            measured("https://ok.com", false),
+            measured("https://ok.com", true),
+            measured("https://ok.com/page/2", false),
+        ];
+        let parsed = |error: Option<&str>| ParsedValue {
+            name: "price".to_string(),
+            value: Value::Null,
+            error: error.map(str::to_string),
+            stale: None,
+            anomaly: None,
+        };
+        let results = vec![
+            result(
+                "https://ok.com",
+                Ok(vec![parsed(None), parsed(Some("missing"))]),
+            ),
+            result("https://ok.com", Ok(vec![parsed(None)])),
+            result(
+                "https://down.com",
+                Err(RvpError::FetchError {
+                    url: "https://down.com".to_string(),
+                    message: "timeout".to_string(),
+                }),
+            ),
+        ];
+
+        let report = build("batch", "stock", &results, &requests, str::to_string);
+        let statuses: Vec<&Status> = report.resources.iter().map(|r| &r.status).collect();
+        assert_eq!(statuses, [&Status::Partial, &Status::Ok, &Status::Failed]);
+        assert_eq!(report.resources[0].requests, 2);
+        assert_eq!(report.resources[0].retries, 1);
+        assert_eq!(report.resources[0].bytes, 200);
+        assert_eq!(report.resources[0].duration_ms, 40);
+        assert!(report.resources[1].cached);
+        assert_eq!(report.resources[1].requests, 0);
+        assert_eq!(
+            report.resources[2].error.as_deref(),
+            Some("failed to fetch \"https://down.com\": timeout")
+        );
+        assert_eq!(
+            (
+                report.requests,
+                report.retries,
+                report.bytes,
+                report.cache_hits
+            ),
+            (3, 1, 300, 1)
+        );
+        assert_eq!((report.failed_resources, report.failed_values), (1, 1));
+    }
+}
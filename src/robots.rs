@@ -0,0 +1,287 @@
+//! The `robots.txt` rules of the hosts (`--respect-robots`): the URLs disallowed for the
+//! `User-Agent` of the requests are not fetched.
+//!
+//! The rules follow RFC 9309: the group of the most specific matching user agent is used,
+//! otherwise the `*` group, and the longest matching `Allow` or `Disallow` path wins, `Allow`
+//! on a tie. The paths support the `*` wildcard and the `$` end anchor.
+
+/// Product token of the requests when no `User-Agent` is set
+pub const DEFAULT_AGENT: &str = "rvp";
+
+/// Message of the errors of the disallowed URLs
+pub const DISALLOWED: &str = "disallowed by robots.txt";
+
+/// Whether the path is allowed, and the path pattern
+type Rule = (bool, String);
+
+/// The rules of a host for a user agent
+#[derive(Default, Debug, PartialEq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// It parses the `robots.txt` and keeps the rules for the user agent
+    pub fn parse(text: &str, agent: &str) -> Rules {
+        let agent = product_token(agent);
+        // User agents of the group and its rules
+        let mut groups: Vec<(Vec<String>, Vec<Rule>)> = Vec::new();
+        let mut in_agents = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    // The consecutive user agents share the group
+                    match (in_agents, groups.last_mut()) {
+                        (true, Some((agents, _))) => agents.push(value.to_lowercase()),
+                        _ => groups.push((vec![value.to_lowercase()], Vec::new())),
+                    }
+                    in_agents = true;
+                }
+                key @ ("allow" | "disallow") => {
+                    in_agents = false;
+                    // An empty `Disallow` allows everything
+                    if let (Some((_, rules)), false) = (groups.last_mut(), value.is_empty()) {
+                        rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let has_group = |name: &str| {
+            groups
+                .iter()
+                .any(|(agents, _)| agents.iter().any(|a| a == name))
+        };
+        let name = match has_group(&agent) {
+            true => agent.as_str(),
+            false => "*",
+        };
+        Rules {
+            rules: groups
+                .iter()
+                .filter(|(agents, _)| agents.iter().any(|a| a == name))
+                .flat_map(|(_, rules)| rules.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// It checks the path with the query of the URL
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map_or(true, |(allow, _)| *allow)
+    }
+}
+
+/// It returns the lowercase product token of the `User-Agent`, `Mozilla` of `Mozilla/5.0 (...)`
+fn product_token(agent: &str) -> String {
+    agent
+        .split(['/', ' '])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// It matches the path with the pattern of a rule, a prefix with the `*` and `$` wildcards
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// Wraps a [Fetcher](crate::fetcher::Fetcher) to skip the URLs disallowed by the `robots.txt`
+/// of their hosts. The `robots.txt` is fetched once per host, a missing or failed one
+/// allows everything.
+#[cfg(not(target_family = "wasm"))]
+pub struct RobotsFetcher<'a> {
+    inner: &'a dyn crate::fetcher::Fetcher,
+    agent: String,
+    /// The rules by the origin of the URLs
+    hosts: std::sync::Mutex<
+        std::collections::HashMap<String, std::sync::Arc<tokio::sync::OnceCell<Rules>>>,
+    >,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl<'a> RobotsFetcher<'a> {
+    /// It creates the fetcher for the `User-Agent` of the requests, [DEFAULT_AGENT] if not set
+    pub fn new(inner: &'a dyn crate::fetcher::Fetcher, user_agent: Option<&str>) -> Self {
+        Self {
+            inner,
+            agent: user_agent.unwrap_or(DEFAULT_AGENT).to_string(),
+            hosts: Default::default(),
+        }
+    }
+
+    /// It checks the URL with the `robots.txt` of its host, fetched on the first check
+    pub async fn is_allowed(&self, url: &str) -> bool {
+        let Ok(parsed) = url::Url::parse(url) else {
+            return true;
+        };
+        // The local files have no robots.txt
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return true;
+        }
+        let origin = parsed.origin().ascii_serialization();
+        let rules = {
+            let mut hosts = self.hosts.lock().expect("robots rules are poisoned");
+            hosts.entry(origin.clone()).or_default().clone()
+        };
+        let rules = rules
+            .get_or_init(|| async {
+                match self.inner.fetch(&format!("{}/robots.txt", origin)).await {
+                    Ok(text) => Rules::parse(&text, &self.agent),
+                    Err(_) => Rules::default(),
+                }
+            })
+            .await;
+
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        rules.is_allowed(&path)
+    }
+
+    async fn check(&self, url: &str) -> crate::error::Result<()> {
+        match self.is_allowed(url).await {
+            true => Ok(()),
+            false => Err(crate::error::RvpError::FetchError {
+                url: url.to_string(),
+                message: DISALLOWED.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait::async_trait]
+impl crate::fetcher::Fetcher for RobotsFetcher<'_> {
+    async fn fetch(&self, url: &str) -> crate::error::Result<String> {
+        self.check(url).await?;
+        self.inner.fetch(url).await
+    }
+
+    async fn fetch_document(
+        &self,
+        request: &crate::fetcher::Request,
+    ) -> crate::error::Result<crate::fetcher::Document> {
+        self.check(&request.url).await?;
+        self.inner.fetch_document(request).await
+    }
+
+    async fn render(&self, url: &str) -> crate::error::Result<String> {
+        self.check(url).await?;
+        self.inner.render(url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Result, RvpError};
+    use crate::fetcher::Fetcher;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ROBOTS: &str = "
+        # comments are skipped
+        User-agent: *
+        Disallow: /private
+        Allow: /private/public
+        Disallow: /*.pdf$
+
+        User-agent: rvp
+        User-agent: other
+        Disallow: /admin
+        Disallow:
+    ";
+
+    #[test]
+    fn test_rules() {
+        let rules = Rules::parse(ROBOTS, "Mozilla/5.0 (X11; Linux x86_64)");
+        assert!(rules.is_allowed("/"));
+        assert!(!rules.is_allowed("/private/page"));
+        assert!(rules.is_allowed("/private/public/page"));
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf?download=1"));
+        assert!(rules.is_allowed("/admin"));
+
+        // The group of the agent replaces the `*` group
+        let rules = Rules::parse(ROBOTS, "rvp/1.0");
+        assert!(!rules.is_allowed("/admin?page=1"));
+        assert!(rules.is_allowed("/private"));
+        assert!(rules.is_allowed("/robots.txt"));
+
+        assert!(matches("/*/items$", "/shop/items"));
+        assert!(!matches("/*/items$", "/shop/items/1"));
+        assert!(matches("/a*b*c", "/a-b-b-c-d"));
+        assert!(!matches("/a*b*c", "/a-c-b"));
+    }
+
+    #[tokio::test]
+    async fn test_robots_fetcher() -> Result<()> {
+        /// Serves the robots.txt of `rules.com`, the other hosts have none
+        #[derive(Default)]
+        struct RobotsServer {
+            robots_requests: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Fetcher for RobotsServer {
+            async fn fetch(&self, url: &str) -> Result<String> {
+                match url {
+                    "https://rules.com/robots.txt" => {
+                        self.robots_requests.fetch_add(1, Ordering::SeqCst);
+                        Ok(ROBOTS.to_string())
+                    }
+                    url if url.ends_with("/robots.txt") => Err(RvpError::FetchError {
+                        url: url.to_string(),
+                        message: "server responded with 404 Not Found".to_string(),
+                    }),
+                    _ => Ok("page".to_string()),
+                }
+            }
+        }
+
+        let server = RobotsServer::default();
+        let fetcher = RobotsFetcher::new(&server, None);
+        assert_eq!(fetcher.fetch("https://rules.com/").await?, "page");
+        match fetcher.fetch("https://rules.com/admin").await {
+            Err(RvpError::FetchError { message, .. }) => assert_eq!(message, DISALLOWED),
+            _ => panic!("the disallowed URL is fetched"),
+        }
+        assert!(fetcher.is_allowed("https://other.com/admin").await);
+        assert!(fetcher.is_allowed("file:///tmp/admin").await);
+        assert_eq!(server.robots_requests.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
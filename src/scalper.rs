@@ -1,15 +1,110 @@
-use anyhow::{anyhow, Result};
+use crate::error::{Result, RvpError};
+use crate::fetcher::{Document, Fetcher, Request};
+use crate::structure::{
+    url_host, OutOfRange, ResourceKind, Transform, DEFAULT_MAX_LINKS, MAX_FOLLOW_DEPTH,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use futures_util::future::{BoxFuture, FutureExt};
+use futures_util::stream::FuturesUnordered;
+use futures_util::{stream, StreamExt};
 use regex::Regex;
-use reqwest::get;
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use serde::Serialize;
 use serde_json::{Number, Value};
+use std::collections::VecDeque;
+use std::sync::OnceLock;
 
 /// Parsed key-value structure
 #[derive(Serialize, Clone)]
 pub struct ParsedValue {
     pub name: String,
     pub value: Value,
+    /// The reason why the value could not be parsed. The `value` is `null` in this case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Set for the values of a failed resource taken from a previous run (`--fallback-to-last`)
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub stale: Option<Stale>,
+    /// Set for the numbers far from their previous values, see [crate::anomaly]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anomaly: Option<crate::anomaly::Anomaly>,
+}
+
+/// A value from a previous run, serialized as `"stale": true` with the `time` of the run
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stale {
+    /// UTC time of the run in RFC 3339 format
+    pub time: String,
+}
+
+impl Serialize for Stale {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("stale", &true)?;
+        map.serialize_entry("time", &self.time)?;
+        map.end()
+    }
+}
+
+/// The result of grabbing a single resource
+pub struct ResourceResult {
+    /// The URL of the resource (with the parameters applied)
+    pub url: String,
+    /// The parameter applied to the URL, if any
+    pub param: Option<String>,
+    /// The URL the request was redirected to, if it differs from `url`
+    pub final_url: Option<String>,
+    /// The `accept_language` of the resource
+    pub accept_language: Option<String>,
+    /// The language of the returned page, from the `lang` of the HTML or the
+    /// `Content-Language` header
+    pub language: Option<String>,
+    /// Parsed values or the error which prevented the whole resource from being parsed
+    pub values: Result<Vec<ParsedValue>>,
+}
+
+impl ResourceResult {
+    /// It checks if the request was redirected to another host than the one of `url`,
+    /// e.g. to a login or a consent page
+    pub fn redirected_off_host(&self) -> bool {
+        self.final_url
+            .as_deref()
+            .is_some_and(|final_url| !url_host(final_url).eq_ignore_ascii_case(url_host(&self.url)))
+    }
+
+    /// It checks if the language of the page is not one of the `accept_language` languages,
+    /// e.g. after a redirect to a localized site. Only the primary language (`en` of `en-US`)
+    /// is compared. Always `false` if either language is unknown.
+    pub fn language_mismatch(&self) -> bool {
+        let (Some(accepted), Some(language)) = (&self.accept_language, &self.language) else {
+            return false;
+        };
+        let primary = |tag: &str| {
+            let tag = tag.split(';').next().unwrap_or_default().trim();
+            tag.split(['-', '_'])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+        };
+        let language = primary(language);
+        !accepted
+            .split(',')
+            .map(primary)
+            .any(|accepted| accepted == "*" || accepted == language)
+    }
+
+    /// It returns the values which failed to parse
+    pub fn failed_values(&self) -> Vec<&ParsedValue> {
+        match &self.values {
+            Ok(values) => values.iter().filter(|v| v.error.is_some()).collect(),
+            Err(_) => vec![],
+        }
+    }
 }
 
 /// It fetches the HTML from the given URL, parses it into a DOM, and then uses the given CSS selector
@@ -17,16 +112,44 @@ pub struct ParsedValue {
 ///
 /// Arguments:
 ///
+/// - `fetcher`: The [Fetcher] used to download the HTML.
 /// - `selector`: The CSS selector (full path from root) to use to grab the value.
 /// - `from`: The URL to fetch the HTML from
 ///
 /// Returns:
 ///
 /// A [`Result<String>`]
-pub async fn grab_one(selector: &str, from: &str) -> Result<String> {
-    let document = fetch_html(from).await?;
-    let selector = Selector::parse(selector).unwrap();
-    parse_value(&document, &selector)
+pub async fn grab_one(fetcher: &dyn Fetcher, selector: &str, from: &str) -> Result<String> {
+    let document = fetch_html(fetcher, &Request::get(from).with_local_files()).await?;
+    select_one(&document, selector)
+}
+
+/// Same as [grab_one], but for the HTML already at hand, e.g. a saved page or the stdin
+///
+/// Arguments:
+///
+/// - `html`: The HTML document.
+/// - `selector`: The CSS selector (full path from root) to use to grab the value.
+///
+/// Returns:
+///
+/// A [`Result<String>`]
+pub fn grab_one_from_html(html: &str, selector: &str) -> Result<String> {
+    select_one(&Html::parse_document(html), selector)
+}
+
+fn select_one(document: &Html, selector: &str) -> Result<String> {
+    let selector = match Selector::parse(selector) {
+        Ok(selector) => selector,
+        Err(err) => {
+            return Err(RvpError::SelectorParseError {
+                name: selector.to_string(),
+                path: selector.to_string(),
+                message: err.to_string(),
+            })
+        }
+    };
+    parse_value(document, &selector)
 }
 
 /// It takes a list of selectors and a URL, fetches the HTML from the URL, and then parses the HTML
@@ -34,6 +157,7 @@ pub async fn grab_one(selector: &str, from: &str) -> Result<String> {
 ///
 /// Arguments:
 ///
+/// - `fetcher`: The [Fetcher] used to download the HTML.
 /// - `selectors`: A vector of CSS selectors.
 /// - `from`: The URL to fetch the HTML from.
 ///
@@ -41,58 +165,1209 @@ pub async fn grab_one(selector: &str, from: &str) -> Result<String> {
 ///
 /// A vector of parsed values [Result<Vec<ParsedValue>>].
 pub async fn grab(
+    fetcher: &dyn Fetcher,
     selectors: Vec<crate::structure::Selector>,
     from: String,
 ) -> Result<Vec<ParsedValue>> {
-    let document = fetch_html(&from).await?;
+    let document = fetch_html(fetcher, &Request::get(&from).with_local_files()).await?;
+    Ok(extract(&document, &selectors))
+}
+
+/// Same as [grab], but for the JSON API responses: the selector paths are JSON pointers
+/// or simple JSONPath expressions
+///
+/// Arguments:
+///
+/// - `fetcher`: The [Fetcher] used to download the JSON.
+/// - `selectors`: A vector of selectors with JSON paths.
+/// - `from`: The URL to fetch the JSON from.
+///
+/// Returns:
+///
+/// A vector of parsed values [Result<Vec<ParsedValue>>].
+pub async fn grab_json(
+    fetcher: &dyn Fetcher,
+    selectors: Vec<crate::structure::Selector>,
+    from: String,
+) -> Result<Vec<ParsedValue>> {
+    let request = Request::get(&from).with_local_files();
+    let text = fetcher.fetch_document(&request).await?.text;
+    let document = parse_json(&from, &text)?;
+    Ok(extract_json(&document, &selectors))
+}
+
+/// It grabs the values of all given resources concurrently
+///
+/// Arguments:
+///
+/// - `fetcher`: The [Fetcher] used to download the HTML.
+/// - `resources`: The resources to grab, with the parameters already applied.
+///
+/// Returns:
+///
+/// A [ResourceResult] for each resource, in the same order.
+pub async fn grab_resources(
+    fetcher: &dyn Fetcher,
+    resources: Vec<crate::structure::Resource>,
+) -> Vec<ResourceResult> {
+    let concurrency = resources.len();
+    grab_resources_limited(fetcher, resources, concurrency).await
+}
+
+/// Same as [grab_resources], but at most `concurrency` URLs are fetched at the same time
+///
+/// Arguments:
+///
+/// - `fetcher`: The [Fetcher] used to download the HTML.
+/// - `resources`: The resources to grab, with the parameters already applied.
+/// - `concurrency`: The maximum number of the requests in flight, at least 1 is used.
+///
+/// Returns:
+///
+/// A [ResourceResult] for each resource, in the same order.
+pub async fn grab_resources_limited(
+    fetcher: &dyn Fetcher,
+    resources: Vec<crate::structure::Resource>,
+    concurrency: usize,
+) -> Vec<ResourceResult> {
+    grab_resources_bounded(fetcher, resources, concurrency, None).await
+}
+
+/// Rough ratio of the memory retained by a parsed document to the size of its text, used
+/// to estimate the memory of the documents for `max_memory`
+const DOM_SIZE_FACTOR: usize = 8;
+
+/// A finished task of [grab_resources_bounded]
+enum Step {
+    /// The document of the request at the position was downloaded
+    Fetched(usize, Result<Document>),
+    /// The resources of a document were parsed, with the estimated memory of the document
+    Parsed(Vec<(usize, ResourceResult)>, usize),
+}
+
+/// Same as [grab_resources_limited], but the estimated memory of the retained documents is
+/// kept around `max_memory` bytes.
+///
+/// Each document is dropped as soon as its resources are parsed. While the estimated memory
+/// of the documents exceeds the limit, no new documents are downloaded and the downloaded
+/// ones wait for the parses in progress. It is a soft limit: a document larger than the
+/// limit is still parsed, alone.
+///
+/// Arguments:
+///
+/// - `fetcher`: The [Fetcher] used to download the HTML.
+/// - `resources`: The resources to grab, with the parameters already applied.
+/// - `concurrency`: The maximum number of the requests in flight, at least 1 is used.
+/// - `max_memory`: The memory limit in bytes, not limited if `None`.
+///
+/// Returns:
+///
+/// A [ResourceResult] for each resource, in the same order.
+pub async fn grab_resources_bounded(
+    fetcher: &dyn Fetcher,
+    resources: Vec<crate::structure::Resource>,
+    concurrency: usize,
+    max_memory: Option<usize>,
+) -> Vec<ResourceResult> {
+    let (requests, positions) = group_requests(&resources);
+    let concurrency = concurrency.max(1);
+
+    let mut tasks: FuturesUnordered<BoxFuture<Step>> = FuturesUnordered::new();
+    let mut next_request = 0;
+    let mut fetching = 0;
+    // The downloaded documents waiting for the parse, with their estimated memory
+    let mut fetched: VecDeque<(usize, Result<Document>, usize)> = VecDeque::new();
+    // Estimated memory of the documents being parsed, and of all retained documents
+    let mut parsing = 0;
+    let mut retained: usize = 0;
+    // Number and estimated memory of all downloaded documents, for the average size
+    let mut downloaded = 0;
+    let mut downloaded_size = 0;
+    let mut results: Vec<Option<ResourceResult>> = resources.iter().map(|_| None).collect();
+
+    loop {
+        while next_request < requests.len() && fetching < concurrency {
+            if let Some(max_memory) = max_memory {
+                // The documents in flight are expected to be of the average size, and of the
+                // whole limit until the first one arrives
+                let average = match downloaded {
+                    0 => max_memory,
+                    _ => downloaded_size / downloaded,
+                };
+                let expected = retained.saturating_add(fetching.saturating_mul(average));
+                if expected > 0 && expected.saturating_add(average) > max_memory {
+                    break;
+                }
+            }
+            let position = next_request;
+            let (request, render) = &requests[position];
+            tasks.push(
+                async move { Step::Fetched(position, fetch_document(fetcher, request, *render).await) }
+                    .boxed(),
+            );
+            next_request += 1;
+            fetching += 1;
+        }
+
+        while fetched.front().is_some_and(|(_, _, size)| {
+            max_memory.map_or(true, |max| parsing == 0 || parsing + size <= max)
+        }) {
+            let Some((position, document, size)) = fetched.pop_front() else {
+                break;
+            };
+            let owners: Vec<usize> = (0..resources.len())
+                .filter(|&i| positions[i] == position)
+                .collect();
+            let resources = &resources;
+            tasks.push(
+                async move {
+                    let mut parsed = Vec::with_capacity(owners.len());
+                    for i in owners {
+                        let result =
+                            grab_resource(fetcher, &resources[i], i, &document, concurrency).await;
+                        parsed.push((i, result));
+                    }
+                    Step::Parsed(parsed, size)
+                }
+                .boxed(),
+            );
+            parsing += size;
+        }
+
+        match tasks.next().await {
+            Some(Step::Fetched(position, document)) => {
+                fetching -= 1;
+                let size = document
+                    .as_ref()
+                    .map_or(0, |d| d.text.len().saturating_mul(DOM_SIZE_FACTOR));
+                retained += size;
+                downloaded += 1;
+                downloaded_size += size;
+                fetched.push_back((position, document, size));
+            }
+            Some(Step::Parsed(parsed, size)) => {
+                parsing -= size;
+                retained -= size;
+                for (i, result) in parsed {
+                    results[i] = Some(result);
+                }
+            }
+            None => break,
+        }
+    }
+
+    results.into_iter().flatten().collect()
+}
+
+/// It parses the resource at the position `i` from its downloaded document
+async fn grab_resource(
+    fetcher: &dyn Fetcher,
+    r: &crate::structure::Resource,
+    i: usize,
+    document: &Result<Document>,
+    concurrency: usize,
+) -> ResourceResult {
+    let mut values = match document {
+        Ok(document) => parse_resource(fetcher, r, document, concurrency).await,
+        Err(err) => Err(err.clone()),
+    };
+    let mut refetched = None;
+    if r.refetch_on_miss() && values.as_ref().is_ok_and(|v| has_missing_values(r, v)) {
+        let request = r.request().cache_busting();
+        // The first values are kept if the page can't be fetched again
+        if let Ok(document) = fetch_document(fetcher, &request, needs_render(r)).await {
+            values = parse_resource(fetcher, r, &document, concurrency).await;
+            refetched = Some(document);
+        }
+    }
+    let values = values.map(|values| {
+        report_missing_values(r, values)
+            .into_iter()
+            .map(|value| ParsedValue {
+                name: r.value_key(&value.name, i + 1),
+                ..value
+            })
+            .collect()
+    });
+    let document = refetched.as_ref().or(document.as_ref().ok());
+    ResourceResult {
+        url: r.url.clone(),
+        param: r.param.clone(),
+        final_url: document.and_then(|d| d.redirected_to.clone()),
+        accept_language: r.accept_language.clone(),
+        language: document.and_then(|d| detect_language(r, d)),
+        values,
+    }
+}
+
+/// It parses the values of the resource from its first page, following the next pages and
+/// the links of the `follow` selectors
+async fn parse_resource(
+    fetcher: &dyn Fetcher,
+    resource: &crate::structure::Resource,
+    document: &Document,
+    concurrency: usize,
+) -> Result<Vec<ParsedValue>> {
+    let mut values = parse_pages(fetcher, resource, document).await?;
+    follow_links(
+        fetcher,
+        resource,
+        &resource.selectors,
+        &mut values,
+        1,
+        concurrency,
+    )
+    .await;
+    Ok(localize_dates(resource, values))
+}
+
+/// It checks if the value is missing: null, an empty string or an empty array
+fn is_missing(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.trim().is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// It checks if any `required` selector of the resource has a missing value
+fn has_missing_values(resource: &crate::structure::Resource, values: &[ParsedValue]) -> bool {
+    resource
+        .selectors
+        .iter()
+        .zip(values)
+        .any(|(selector, value)| selector.required && is_missing(&value.value))
+}
+
+/// It sets the error of the missing values of the `required` selectors, unless they already
+/// have one, e.g. a failed conversion of the empty text
+fn report_missing_values(
+    resource: &crate::structure::Resource,
+    mut values: Vec<ParsedValue>,
+) -> Vec<ParsedValue> {
+    for (selector, value) in resource.selectors.iter().zip(values.iter_mut()) {
+        if selector.required && value.error.is_none() && is_missing(&value.value) {
+            value.value = Value::Null;
+            value.error = Some(format!("required value \"{}\" is missing", selector.name));
+        }
+    }
+    values
+}
+
+/// It parses the first page of the resource and follows its `next_page` links up to the
+/// `max_pages`, appending the values of the `list` selectors of each page.
+///
+/// The other selectors keep the values of the first page. The pages are fetched one by one,
+/// and a failed page fails the whole resource.
+async fn parse_pages(
+    fetcher: &dyn Fetcher,
+    resource: &crate::structure::Resource,
+    first: &Document,
+) -> Result<Vec<ParsedValue>> {
+    let mut url = first
+        .redirected_to
+        .clone()
+        .unwrap_or_else(|| resource.url.clone());
+    let mut values = parse_document(resource, &first.text)?;
+    resolve_links(&resource.selectors, &mut values, &url);
+    if resource.page_limit() <= 1 {
+        return Ok(values);
+    }
+    let mut visited = vec![url.clone()];
+    let mut text = first.text.clone();
+
+    for _ in 1..resource.page_limit() {
+        let Some(next) = find_next_page(resource, &url, &text)? else {
+            break;
+        };
+        // The last page often links to itself or back to the first one
+        if visited.contains(&next) {
+            break;
+        }
+        let request = resource.request().link(&next);
+        let page = fetch_document(fetcher, &request, needs_render(resource)).await?;
+
+        let page_url = page.redirected_to.clone().unwrap_or_else(|| next.clone());
+        let mut page_values = parse_document(resource, &page.text)?;
+        resolve_links(&resource.selectors, &mut page_values, &page_url);
+        for ((value, page_value), selector) in values
+            .iter_mut()
+            .zip(page_values)
+            .zip(resource.selectors.iter())
+        {
+            if !selector.is_multiple() || value.error.is_some() {
+                continue;
+            }
+            match (&mut value.value, page_value.value) {
+                (Value::Array(items), Value::Array(page_items)) => items.extend(page_items),
+                // The value failed to parse on the page
+                _ => {
+                    value.value = Value::Null;
+                    value.error = page_value.error;
+                }
+            }
+        }
+
+        visited.push(next.clone());
+        url = page_url;
+        text = page.text;
+    }
+
+    Ok(values)
+}
+
+/// It resolves the links of the `follow` selectors against the URL of the page they were
+/// found on. The links which can't be resolved are kept as they are and fail to fetch later.
+fn resolve_links(selectors: &[crate::structure::Selector], values: &mut [ParsedValue], url: &str) {
+    let Ok(base) = url::Url::parse(url) else {
+        return;
+    };
+    for (selector, value) in selectors.iter().zip(values.iter_mut()) {
+        let Value::Array(links) = &mut value.value else {
+            continue;
+        };
+        if !selector.follow {
+            continue;
+        }
+        for link in links.iter_mut() {
+            if let Value::String(text) = link {
+                if let Ok(absolute) = base.join(text.trim()) {
+                    *text = absolute.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// It fetches the pages linked by the `follow` selectors and replaces each link with an
+/// object with the `url` of the page and the values of the `children` selectors.
+///
+/// Only the first `max_links` of each selector are followed. The `follow` selectors of the
+/// children are followed too, up to [MAX_FOLLOW_DEPTH] levels.
+fn follow_links<'a>(
+    fetcher: &'a dyn Fetcher,
+    resource: &'a crate::structure::Resource,
+    selectors: &'a [crate::structure::Selector],
+    values: &'a mut [ParsedValue],
+    depth: usize,
+    concurrency: usize,
+) -> BoxFuture<'a, ()> {
+    async move {
+        for (selector, value) in selectors.iter().zip(values.iter_mut()) {
+            if !selector.follow || value.error.is_some() {
+                continue;
+            }
+            if depth > MAX_FOLLOW_DEPTH {
+                value.value = Value::Null;
+                value.error = Some(format!(
+                    "links nested deeper than {} levels are not followed",
+                    MAX_FOLLOW_DEPTH
+                ));
+                continue;
+            }
+            let Value::Array(links) = &value.value else {
+                continue;
+            };
+            let links: Vec<String> = links
+                .iter()
+                .filter_map(|link| link.as_str().map(str::to_string))
+                .take(selector.max_links.unwrap_or(DEFAULT_MAX_LINKS))
+                .collect();
+            let pages = stream::iter(links)
+                .map(|link| follow_link(fetcher, resource, selector, link, depth, concurrency))
+                .buffered(concurrency.max(1))
+                .collect::<Vec<_>>()
+                .await;
+            value.value = Value::Array(pages);
+        }
+    }
+    .boxed()
+}
+
+/// It grabs the `children` of the selector from the linked page. The errors are kept in the
+/// `error` key of the page, so that a single broken link doesn't fail the whole value.
+async fn follow_link(
+    fetcher: &dyn Fetcher,
+    resource: &crate::structure::Resource,
+    selector: &crate::structure::Selector,
+    link: String,
+    depth: usize,
+    concurrency: usize,
+) -> Value {
+    let mut page = serde_json::Map::new();
+    page.insert("url".to_string(), Value::String(link.clone()));
+
+    let request = resource.request().link(&link);
+    let parsed = match fetch_document(fetcher, &request, needs_render(resource)).await {
+        Ok(document) => {
+            let url = document.redirected_to.unwrap_or(link);
+            parse_selectors(resource, &url, &document.text, &selector.children).map(|mut values| {
+                resolve_links(&selector.children, &mut values, &url);
+                values
+            })
+        }
+        Err(err) => Err(err),
+    };
+    let mut values = match parsed {
+        Ok(values) => values,
+        Err(err) => {
+            page.insert("error".to_string(), Value::String(err.to_string()));
+            return Value::Object(page);
+        }
+    };
+    follow_links(
+        fetcher,
+        resource,
+        &selector.children,
+        &mut values,
+        depth + 1,
+        concurrency,
+    )
+    .await;
+
+    let errors: Vec<String> = values.iter().filter_map(|v| v.error.clone()).collect();
+    for value in values {
+        page.insert(value.name, value.value);
+    }
+    if !errors.is_empty() {
+        page.insert("error".to_string(), Value::String(errors.join("; ")));
+    }
+    Value::Object(page)
+}
+
+/// It returns the absolute URL of the next page found with the `next_page` of the resource,
+/// `None` if the page has no link to the next one
+fn find_next_page(
+    resource: &crate::structure::Resource,
+    url: &str,
+    text: &str,
+) -> Result<Option<String>> {
+    let Some(next_page) = &resource.next_page else {
+        return Ok(None);
+    };
+    let selector = crate::structure::Selector::new(
+        next_page.clone(),
+        "next_page".to_string(),
+        crate::structure::SelectorType::String,
+    );
+    let link = match resource.kind() {
+        ResourceKind::Html => {
+            let parsed = parse_css(&selector)?;
+            Html::parse_document(text)
+                .select(&parsed)
+                .find_map(|element| element.value().attr("href").map(str::to_string))
+        }
+        ResourceKind::Json | ResourceKind::Feed => {
+            find_json_text(&parse_data(resource.kind(), url, text)?, &selector)?
+        }
+    };
+    let Some(link) = link.filter(|link| !link.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    match url::Url::parse(url).and_then(|base| base.join(link.trim())) {
+        Ok(next) => Ok(Some(next.to_string())),
+        Err(err) => Err(RvpError::FetchError {
+            url: url.to_string(),
+            message: format!("invalid next page link \"{}\": {}", link, err),
+        }),
+    }
+}
+
+/// It fetches the documents of the resources, resources with the same URL share the document,
+/// so each URL is fetched only once per run
+///
+/// Returns:
+///
+/// The downloaded documents and the position of the document of each resource.
+async fn fetch_documents(
+    fetcher: &dyn Fetcher,
+    resources: &[crate::structure::Resource],
+    concurrency: usize,
+) -> (Vec<Result<Document>>, Vec<usize>) {
+    let (requests, positions) = group_requests(resources);
+
+    let downloads = requests
+        .iter()
+        .map(|(request, render)| fetch_document(fetcher, request, *render));
+    // `buffered` keeps the documents in the order of the requests
+    let documents = stream::iter(downloads)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    (documents, positions)
+}
+
+/// It returns the unique requests of the resources, and the position of the request of each
+/// resource. Resources with the same URL and headers share the request.
+fn group_requests(resources: &[crate::structure::Resource]) -> (Vec<(Request, bool)>, Vec<usize>) {
+    let mut requests: Vec<(Request, bool)> = Vec::new();
+    let mut positions = Vec::with_capacity(resources.len());
+    for r in resources.iter() {
+        let request = (r.request(), needs_render(r));
+        match requests.iter().position(|rq| *rq == request) {
+            Some(position) => positions.push(position),
+            None => {
+                positions.push(requests.len());
+                requests.push(request);
+            }
+        }
+    }
+    (requests, positions)
+}
+
+/// It fetches the document of the request, or renders it in the browser.
+///
+/// The headers are not sent by the browser. The links to the local files are rejected for
+/// any fetcher, as the browser would open them as well.
+async fn fetch_document(
+    fetcher: &dyn Fetcher,
+    request: &Request,
+    render: bool,
+) -> Result<Document> {
+    request.check_local_files()?;
+    match render {
+        true => Ok(Document {
+            text: fetcher.render(&request.url).await?,
+            ..Document::default()
+        }),
+        false => fetcher.fetch_document(request).await,
+    }
+}
+
+/// The outcome of a single selector checked against a live document, see [probe_resources]
+pub struct SelectorProbe {
+    pub name: String,
+    pub path: String,
+    /// Whether the selector matched an element (or a non-null JSON value)
+    pub matched: bool,
+    /// Number of the elements matched by the selector, the value is taken from the first one
+    pub matches: usize,
+    /// The text found by the selector, before the regex and the type conversion
+    pub text: String,
+    /// The converted value, or the reason why the selector failed
+    pub value: Result<Value>,
+}
+
+/// The probes of a single resource
+pub struct ResourceProbe {
+    /// The URL of the resource (with the parameters applied)
+    pub url: String,
+    /// Probe of each selector or the error which prevented the whole resource from being parsed
+    pub probes: Result<Vec<SelectorProbe>>,
+}
+
+/// Stability of a selector over several probes of the same resource
+#[derive(Debug, PartialEq)]
+pub struct SelectorStability {
+    /// The URL of the resource (with the parameters applied)
+    pub url: String,
+    pub name: String,
+    /// Distinct numbers of the matched elements, sorted
+    pub match_counts: Vec<usize>,
+    /// Number of the distinct converted values
+    pub distinct_values: usize,
+    /// Number of the probes where the selector (or its resource) failed
+    pub failures: usize,
+    pub probes: usize,
+}
+
+impl SelectorStability {
+    /// A selector is flaky if the number of the matched elements varies, or it fails
+    /// only in some of the probes
+    pub fn is_flaky(&self) -> bool {
+        self.match_counts.len() > 1 || (self.failures > 0 && self.failures < self.probes)
+    }
+
+    /// A selector is broken if it failed in every probe
+    pub fn is_broken(&self) -> bool {
+        self.failures == self.probes
+    }
+}
+
+/// It summarizes the repeated probes of the same resources by selector.
+///
+/// Arguments:
+///
+/// - `resources`: The probed resources.
+/// - `rounds`: The results of [probe_resources] for the `resources`, one per probe.
+///
+/// Returns:
+///
+/// A [SelectorStability] for each selector of the resources, in the config order.
+pub fn probe_stability(
+    resources: &[crate::structure::Resource],
+    rounds: &[Vec<ResourceProbe>],
+) -> Vec<SelectorStability> {
+    let mut summary = Vec::new();
+    for (i, resource) in resources.iter().enumerate() {
+        for (j, selector) in resource.selectors.iter().enumerate() {
+            let mut stability = SelectorStability {
+                url: resource.url.clone(),
+                name: selector.name.clone(),
+                match_counts: vec![],
+                distinct_values: 0,
+                failures: 0,
+                probes: rounds.len(),
+            };
+            let mut values: Vec<String> = Vec::new();
+            for round in rounds {
+                let probe = match round.get(i).map(|r| &r.probes) {
+                    Some(Ok(probes)) => &probes[j],
+                    _ => {
+                        stability.failures += 1;
+                        continue;
+                    }
+                };
+                if !stability.match_counts.contains(&probe.matches) {
+                    stability.match_counts.push(probe.matches);
+                }
+                match &probe.value {
+                    Ok(value) if probe.matched => {
+                        let value = value.to_string();
+                        if !values.contains(&value) {
+                            values.push(value);
+                        }
+                    }
+                    _ => stability.failures += 1,
+                }
+            }
+            stability.match_counts.sort_unstable();
+            stability.distinct_values = values.len();
+            summary.push(stability);
+        }
+    }
+    summary
+}
+
+/// It fetches the resources and reports for each selector whether it matched, what text
+/// was found and whether the type conversion succeeded.
+///
+/// Arguments:
+///
+/// - `fetcher`: The [Fetcher] used to download the documents.
+/// - `resources`: The resources to check, with the parameters already applied.
+/// - `concurrency`: The maximum number of the requests in flight, at least 1 is used.
+///
+/// Returns:
+///
+/// A [ResourceProbe] for each resource, in the same order.
+pub async fn probe_resources(
+    fetcher: &dyn Fetcher,
+    resources: Vec<crate::structure::Resource>,
+    concurrency: usize,
+) -> Vec<ResourceProbe> {
+    let (documents, positions) = fetch_documents(fetcher, &resources, concurrency).await;
+
+    resources
+        .iter()
+        .enumerate()
+        .map(|(i, r)| ResourceProbe {
+            url: r.url.clone(),
+            probes: match &documents[positions[i]] {
+                Ok(document) => probe_document(r, &document.text),
+                Err(err) => Err(err.clone()),
+            },
+        })
+        .collect()
+}
+
+/// It checks the selectors of the resource against the downloaded document
+fn probe_document(resource: &crate::structure::Resource, text: &str) -> Result<Vec<SelectorProbe>> {
+    match resource.kind() {
+        ResourceKind::Html => {
+            let document = Html::parse_document(text);
+            Ok(probe_selectors(&resource.selectors, |selector| {
+                find_all_text(&document, selector)
+            }))
+        }
+        ResourceKind::Json | ResourceKind::Feed => {
+            let document = parse_data(resource.kind(), &resource.url, text)?;
+            Ok(probe_selectors(&resource.selectors, |selector| {
+                let text = find_json_text(&document, selector)?;
+                Ok((usize::from(text.is_some()), text))
+            }))
+        }
+    }
+}
+
+/// It finds the text of each selector and converts it, keeping the intermediate results
+fn probe_selectors(
+    selectors: &[crate::structure::Selector],
+    find_text: impl Fn(&crate::structure::Selector) -> Result<(usize, Option<String>)>,
+) -> Vec<SelectorProbe> {
+    selectors
+        .iter()
+        .map(|selector| {
+            let (matches, text, value) = match find_text(selector) {
+                Ok((matches, Some(text))) => (matches, text.clone(), convert_text(selector, text)),
+                Ok((_, None)) => (0, String::new(), convert_text(selector, String::new())),
+                Err(err) => (0, String::new(), Err(err)),
+            };
+            SelectorProbe {
+                name: selector.name.clone(),
+                path: selector.path.clone(),
+                matched: matches > 0,
+                matches,
+                text,
+                value,
+            }
+        })
+        .collect()
+}
+
+/// It converts the dates with a UTC offset to the timezone of the resource.
+///
+/// The dates without an offset are kept as they are, their timezone is unknown.
+fn localize_dates(
+    resource: &crate::structure::Resource,
+    values: Vec<ParsedValue>,
+) -> Vec<ParsedValue> {
+    let Some(timezone) = resource.timezone else {
+        return values;
+    };
+
+    values
+        .into_iter()
+        .zip(resource.selectors.iter())
+        .map(|(value, selector)| {
+            if selector.parsed_type != crate::structure::SelectorType::Date {
+                return value;
+            }
+            let localize = |date: &Value| match date {
+                Value::String(date) => DateTime::parse_from_rfc3339(date)
+                    .ok()
+                    .map(|date| Value::String(date.with_timezone(&timezone.0).to_rfc3339())),
+                _ => None,
+            };
+            let localized = match &value.value {
+                Value::Array(dates) => Some(Value::Array(
+                    dates
+                        .iter()
+                        .map(|date| localize(date).unwrap_or_else(|| date.clone()))
+                        .collect(),
+                )),
+                date => localize(date),
+            };
+            match localized {
+                Some(localized) => ParsedValue {
+                    value: localized,
+                    ..value
+                },
+                None => value,
+            }
+        })
+        .collect()
+}
+
+/// It returns the language of the page: the `lang` attribute of the `<html>` element,
+/// or the `Content-Language` header
+fn detect_language(resource: &crate::structure::Resource, document: &Document) -> Option<String> {
+    static HTML_LANG: OnceLock<Regex> = OnceLock::new();
+
+    let lang = match resource.kind() {
+        ResourceKind::Html => HTML_LANG
+            .get_or_init(|| {
+                Regex::new(
+                    r#"(?i)<html\b[^>]*?\slang\s*=\s*["']?([A-Za-z]{2,3}(?:[-_][A-Za-z0-9]+)*)"#,
+                )
+                .expect("HTML lang regex is valid")
+            })
+            .captures(&document.text)
+            .and_then(|captures| captures.get(1))
+            .map(|lang| lang.as_str().to_string()),
+        ResourceKind::Json | ResourceKind::Feed => None,
+    };
+    lang.or_else(|| document.language.clone())
+}
+
+/// Only the HTML pages are rendered in the browser
+fn needs_render(resource: &crate::structure::Resource) -> bool {
+    resource.render() && resource.kind() == ResourceKind::Html
+}
+
+/// It parses the downloaded document of the resource and extracts the values of its selectors
+fn parse_document(resource: &crate::structure::Resource, text: &str) -> Result<Vec<ParsedValue>> {
+    parse_selectors(resource, &resource.url, text, &resource.selectors)
+}
+
+/// It parses the given selectors from the page of the resource downloaded from the URL
+fn parse_selectors(
+    resource: &crate::structure::Resource,
+    url: &str,
+    text: &str,
+    selectors: &[crate::structure::Selector],
+) -> Result<Vec<ParsedValue>> {
+    match resource.kind() {
+        ResourceKind::Html => Ok(extract(&Html::parse_document(text), selectors)),
+        kind => Ok(extract_json(&parse_data(kind, url, text)?, selectors)),
+    }
+}
+
+/// It parses the JSON document downloaded from the URL, the feeds are converted to JSON
+fn parse_data(kind: ResourceKind, url: &str, text: &str) -> Result<Value> {
+    match kind {
+        ResourceKind::Feed => crate::feed::parse(url, text),
+        _ => parse_json(url, text),
+    }
+}
+
+/// It parses the JSON document downloaded from the URL
+fn parse_json(url: &str, text: &str) -> Result<Value> {
+    match serde_json::from_str(text) {
+        Ok(document) => Ok(document),
+        Err(err) => Err(RvpError::FetchError {
+            url: url.to_string(),
+            message: format!("failed to parse JSON document:\n{}", err),
+        }),
+    }
+}
+
+/// It extracts the values of the given selectors from an already parsed HTML document.
+///
+/// A selector which fails to parse does not fail the others: its value is set to `null`
+/// and the error is stored in the [ParsedValue].
+///
+/// Arguments:
+///
+/// - `document`: The HTML document to extract the values from.
+/// - `selectors`: A slice of CSS selectors.
+///
+/// Returns:
+///
+/// A vector of parsed values [Vec<ParsedValue>].
+pub fn extract(document: &Html, selectors: &[crate::structure::Selector]) -> Vec<ParsedValue> {
+    collect_values(selectors, |selector| extract_value(document, selector))
+}
+
+/// Same as [extract], but for the JSON documents
+///
+/// Arguments:
+///
+/// - `document`: The JSON document to extract the values from.
+/// - `selectors`: A slice of selectors with JSON pointers or JSONPath expressions.
+///
+/// Returns:
+///
+/// A vector of parsed values [Vec<ParsedValue>].
+pub fn extract_json(
+    document: &Value,
+    selectors: &[crate::structure::Selector],
+) -> Vec<ParsedValue> {
+    collect_values(selectors, |selector| extract_json_value(document, selector))
+}
+
+/// It extracts the value of each selector, storing the errors in the [ParsedValue]s
+fn collect_values(
+    selectors: &[crate::structure::Selector],
+    extract_value: impl Fn(&crate::structure::Selector) -> Result<Value>,
+) -> Vec<ParsedValue> {
     let mut values = Vec::new();
 
-    for selector in selectors.iter() {
-        let parsed = Selector::parse(&selector.path).unwrap();
-        let value = parse_value(&document, &parsed)?;
-        let value = match selector.parsed_type {
-            crate::structure::SelectorType::String => Value::String(value),
-            crate::structure::SelectorType::Number => {
-                let number = any_string_to_number(&value);
-
-                let value: Value = if number.is_nan() {
-                    Value::String("NaN".to_string())
-                } else {
-                    Value::Number(Number::from_f64(number).unwrap_or_else(|| {
-                        panic!("failed to parse number for \"{}\"", &selector.name)
-                    }))
-                };
-                value
+    for selector in selectors.iter() {
+        let parsed = match extract_value(selector) {
+            Ok(value) => ParsedValue {
+                name: selector.name.clone(),
+                value,
+                error: None,
+                stale: None,
+                anomaly: None,
+            },
+            Err(err) => ParsedValue {
+                name: selector.name.clone(),
+                value: Value::Null,
+                error: Some(err.to_string()),
+                stale: None,
+                anomaly: None,
+            },
+        };
+        values.push(parsed);
+    }
+
+    values
+}
+
+/// It extracts the value of a single selector from the HTML document and converts it to the
+/// selector's type
+fn extract_value(document: &Html, selector: &crate::structure::Selector) -> Result<Value> {
+    // The links are followed later, see [follow_links]
+    if selector.follow {
+        let parsed = parse_css(selector)?;
+        return Ok(document
+            .select(&parsed)
+            .map(|element| match element.value().attr("href") {
+                Some(href) => Value::String(href.to_string()),
+                None => Value::String(element_text(element)),
+            })
+            .collect());
+    }
+    if selector.list {
+        let parsed = parse_css(selector)?;
+        let regex = selector_regex(selector)?;
+        return document
+            .select(&parsed)
+            .map(|element| convert_value(selector, regex.as_ref(), element_text(element)))
+            .collect::<Result<_>>()
+            .map(list_value);
+    }
+
+    // No need to fail if the selector doesn't match anything, the value is an empty string
+    let value = find_text(document, selector)?.unwrap_or_default();
+    convert_text(selector, value)
+}
+
+/// It returns the text of the first element matching the selector, `None` if nothing matched
+fn find_text(document: &Html, selector: &crate::structure::Selector) -> Result<Option<String>> {
+    let parsed = parse_css(selector)?;
+    Ok(document.select(&parsed).next().map(element_text))
+}
+
+/// It returns the text of the element and its children, joined with spaces
+fn element_text(element: ElementRef) -> String {
+    element.text().collect::<Vec<_>>().join(" ")
+}
+
+/// It returns the number of the elements matching the selector and the text of the first one
+fn find_all_text(
+    document: &Html,
+    selector: &crate::structure::Selector,
+) -> Result<(usize, Option<String>)> {
+    let parsed = parse_css(selector)?;
+    let mut elements = document.select(&parsed);
+    let text = elements.next().map(element_text);
+    Ok((usize::from(text.is_some()) + elements.count(), text))
+}
+
+/// It parses the CSS path of the selector
+fn parse_css(selector: &crate::structure::Selector) -> Result<Selector> {
+    Selector::parse(&selector.path).map_err(|err| RvpError::SelectorParseError {
+        name: selector.name.clone(),
+        path: selector.path.clone(),
+        message: err.to_string(),
+    })
+}
+
+/// It extracts the value of a single selector from the JSON document and converts it to the
+/// selector's type.
+///
+/// Missing values are treated as an empty string, the same way as unmatched CSS selectors.
+fn extract_json_value(document: &Value, selector: &crate::structure::Selector) -> Result<Value> {
+    if selector.is_multiple() {
+        let items = json_items(document, &json_pointer(selector)?);
+        // The links are followed later, see [follow_links]
+        if selector.follow {
+            return Ok(items
+                .into_iter()
+                .map(|item| Value::String(json_text(item)))
+                .collect());
+        }
+        let regex = selector_regex(selector)?;
+        return items
+            .into_iter()
+            .map(|item| convert_value(selector, regex.as_ref(), json_text(item)))
+            .collect::<Result<_>>()
+            .map(list_value);
+    }
+
+    let value = find_json_text(document, selector)?.unwrap_or_default();
+    convert_text(selector, value)
+}
+
+/// It returns the items of the array at the JSON pointer, or the single value at it.
+///
+/// The `*` segments (`$.items[*].title`) take the values from each item of the array.
+fn json_items<'a>(document: &'a Value, pointer: &str) -> Vec<&'a Value> {
+    if let Some((array, rest)) = pointer.split_once("/*") {
+        if rest.is_empty() || rest.starts_with('/') {
+            return match document.pointer(array) {
+                Some(Value::Array(items)) => items
+                    .iter()
+                    .flat_map(|item| json_items(item, rest))
+                    .collect(),
+                _ => vec![],
+            };
+        }
+    }
+    match document.pointer(pointer) {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(Value::Null) | None => vec![],
+        Some(item) => vec![item],
+    }
+}
+
+/// It checks if the JSON pointer has a `*` segment, see [json_items]
+pub(crate) fn is_wildcard(pointer: &str) -> bool {
+    pointer.split('/').any(|segment| segment == "*")
+}
+
+/// It returns the value at the selector path as text, `None` for the missing and null values
+fn find_json_text(
+    document: &Value,
+    selector: &crate::structure::Selector,
+) -> Result<Option<String>> {
+    let pointer = json_pointer(selector)?;
+    Ok(match document.pointer(&pointer) {
+        Some(Value::Null) | None => None,
+        Some(value) => Some(json_text(value)),
+    })
+}
+
+/// It returns the JSON strings without the quotes, and the other values as JSON
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// It converts the selector path to a JSON pointer.
+///
+/// The paths starting with `/` are JSON pointers already. The paths starting with `$` are
+/// simple JSONPath expressions with `.key`, `[0]` and `['key']` segments.
+pub(crate) fn json_pointer(selector: &crate::structure::Selector) -> Result<String> {
+    let path = selector.path.trim();
+    let Some(mut rest) = path.strip_prefix('$') else {
+        if path.is_empty() || path.starts_with('/') {
+            return Ok(path.to_string());
+        }
+        return Err(RvpError::SelectorParseError {
+            name: selector.name.clone(),
+            path: selector.path.clone(),
+            message: "JSON path should start with `/` or `$`".to_string(),
+        });
+    };
+
+    let invalid = |rest: &str| RvpError::SelectorParseError {
+        name: selector.name.clone(),
+        path: selector.path.clone(),
+        message: format!("unexpected JSONPath segment \"{}\"", rest),
+    };
+
+    let mut pointer = String::new();
+    while !rest.is_empty() {
+        let (segment, tail) = if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            (&tail[..end], &tail[end..])
+        } else if let Some(tail) = rest.strip_prefix("['") {
+            let end = tail.find("']").ok_or_else(|| invalid(rest))?;
+            (&tail[..end], &tail[end + 2..])
+        } else if let Some(tail) = rest.strip_prefix("[\"") {
+            let end = tail.find("\"]").ok_or_else(|| invalid(rest))?;
+            (&tail[..end], &tail[end + 2..])
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']').ok_or_else(|| invalid(rest))?;
+            (&tail[..end], &tail[end + 1..])
+        } else {
+            return Err(invalid(rest));
+        };
+        if segment.is_empty() {
+            return Err(invalid(rest));
+        }
+
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        rest = tail;
+    }
+
+    Ok(pointer)
+}
+
+/// The converted values of a list selector, without the values dropped as out of range
+fn list_value(values: Vec<Value>) -> Value {
+    Value::Array(values.into_iter().filter(|v| !v.is_null()).collect())
+}
+
+/// It converts a single grabbed text of the selector, see [convert_value]
+fn convert_text(selector: &crate::structure::Selector, value: String) -> Result<Value> {
+    convert_value(selector, selector_regex(selector)?.as_ref(), value)
+}
+
+/// It applies the selector's regex (compiled by [selector_regex]) and transforms to the
+/// grabbed text and converts it to the selector's type
+fn convert_value(
+    selector: &crate::structure::Selector,
+    regex: Option<&Regex>,
+    value: String,
+) -> Result<Value> {
+    let value = match regex {
+        Some(regex) => apply_regex(selector, regex, &value),
+        None => value,
+    };
+    let value = selector
+        .transforms
+        .iter()
+        .fold(value, |value, transform| apply_transform(transform, value));
+    let value = match selector.parsed_type {
+        crate::structure::SelectorType::String => Value::String(value),
+        crate::structure::SelectorType::Number => {
+            // NaN and infinite numbers can't be represented in JSON
+            match Number::from_f64(any_string_to_number(&value)) {
+                Some(number) if !selector.in_range(number.as_f64().unwrap_or_default()) => {
+                    match selector.out_of_range {
+                        OutOfRange::Flag => {
+                            return Err(RvpError::TypeConversionError {
+                                name: selector.name.clone(),
+                                value,
+                                to: format!(
+                                    "{} {}",
+                                    selector.parsed_type,
+                                    selector.range().unwrap_or_default()
+                                ),
+                            })
+                        }
+                        OutOfRange::Drop => Value::Null,
+                    }
+                }
+                Some(number) => Value::Number(number),
+                None => {
+                    return Err(RvpError::TypeConversionError {
+                        name: selector.name.clone(),
+                        value,
+                        to: selector.parsed_type.to_string(),
+                    })
+                }
             }
-        };
-        values.push(ParsedValue {
-            name: selector.name.clone(),
-            value,
-        });
-    }
+        }
+        crate::structure::SelectorType::Boolean => match any_string_to_bool(selector, &value) {
+            Some(boolean) => Value::Bool(boolean),
+            None => {
+                return Err(RvpError::TypeConversionError {
+                    name: selector.name.clone(),
+                    value,
+                    to: selector.parsed_type.to_string(),
+                })
+            }
+        },
+        crate::structure::SelectorType::Date => match any_string_to_date(selector, &value) {
+            Some(date) => Value::String(date),
+            None => {
+                return Err(RvpError::TypeConversionError {
+                    name: selector.name.clone(),
+                    value,
+                    to: selector.parsed_type.to_string(),
+                })
+            }
+        },
+    };
 
-    Ok(values)
+    Ok(value)
 }
 
-/// It fetches the HTML document at the given URL, parses it, and returns the result
+/// It sends the request of the HTML document, parses the response, and returns the result
 ///
 /// Arguments:
 ///
-/// - `url`: &str - The URL to fetch the HTML from.
+/// - `fetcher`: The [Fetcher] used to download the HTML.
+/// - `request`: &Request - The request of the HTML, with its method, headers and body.
 ///
 /// Returns:
 ///
 /// A [`Result<Html>`]
-async fn fetch_html(url: &str) -> Result<Html> {
-    let resp = match get(url).await {
-        Ok(resp) => resp,
-        Err(err) => return Err(anyhow!(err)),
-    };
-    let text = match resp.text().await {
-        Ok(text) => text,
-        Err(err) => return Err(anyhow!("failed to parse HTML document:\n{}", err)),
-    };
-    Ok(Html::parse_document(&text))
+async fn fetch_html(fetcher: &dyn Fetcher, request: &Request) -> Result<Html> {
+    let document = fetcher.fetch_document(request).await?;
+    Ok(Html::parse_document(&document.text))
 }
 
 /// Parses the HTML document and returns the text of the first element that matches the selector.
@@ -116,6 +1391,138 @@ fn parse_value(document: &Html, selector: &Selector) -> Result<String> {
     Ok(element.text().collect::<Vec<_>>().join(" "))
 }
 
+/// It compiles the selector's regex, once for all the values of the selector
+fn selector_regex(selector: &crate::structure::Selector) -> Result<Option<Regex>> {
+    let Some(regex) = &selector.regex else {
+        return Ok(None);
+    };
+    match Regex::new(regex) {
+        Ok(re) => Ok(Some(re)),
+        Err(err) => Err(RvpError::SelectorParseError {
+            name: selector.name.clone(),
+            path: regex.to_string(),
+            message: err.to_string(),
+        }),
+    }
+}
+
+/// It applies the selector's regex to the grabbed text and returns the matched group.
+///
+/// The whole match is returned if the `regex_group` is not set.
+/// If the regex does not match, an empty string is returned.
+fn apply_regex(selector: &crate::structure::Selector, re: &Regex, value: &str) -> String {
+    let group = selector.regex_group.unwrap_or(0);
+    let matched = re
+        .captures(value)
+        .and_then(|captures| captures.get(group))
+        .map(|m| m.as_str().to_string());
+
+    matched.unwrap_or_default()
+}
+
+/// It applies a single [Transform] of the selector to the text
+fn apply_transform(transform: &Transform, value: String) -> String {
+    match transform {
+        Transform::Trim => value.trim().to_string(),
+        Transform::Lowercase => value.to_lowercase(),
+        Transform::Uppercase => value.to_uppercase(),
+        Transform::StripPrefix(prefix) => match value.strip_prefix(prefix.as_str()) {
+            Some(rest) => rest.to_string(),
+            None => value,
+        },
+        Transform::StripSuffix(suffix) => match value.strip_suffix(suffix.as_str()) {
+            Some(rest) => rest.to_string(),
+            None => value,
+        },
+        Transform::Replace { from, to } => value.replace(from.as_str(), to),
+        Transform::Split { separator, index } => {
+            let parts: Vec<&str> = value.split(separator.as_str()).collect();
+            // The missing part is empty, the same as a regex without a match
+            let index = match *index {
+                index if index < 0 => parts.len().checked_sub(index.unsigned_abs()),
+                index => Some(index as usize),
+            };
+            index
+                .and_then(|index| parts.get(index))
+                .map(|part| part.to_string())
+                .unwrap_or_default()
+        }
+    }
+}
+
+/// Common strings treated as `true` by the Boolean selectors
+const TRUTHY_VALUES: [&str; 7] = ["true", "yes", "y", "1", "on", "in stock", "available"];
+
+/// Common strings treated as `false` by the Boolean selectors
+const FALSY_VALUES: [&str; 8] = [
+    "false",
+    "no",
+    "n",
+    "0",
+    "off",
+    "out of stock",
+    "unavailable",
+    "",
+];
+
+/// Converts a string to a boolean.
+///
+/// If the selector has custom `truthy_values`, any other value is `false`.
+/// Otherwise the common truthy/falsy strings are used and [None] is returned for unknown ones.
+fn any_string_to_bool(selector: &crate::structure::Selector, str: &str) -> Option<bool> {
+    let value = str.trim().to_lowercase();
+
+    if let Some(truthy_values) = &selector.truthy_values {
+        return Some(
+            truthy_values
+                .iter()
+                .any(|v| v.trim().to_lowercase() == value),
+        );
+    }
+
+    if TRUTHY_VALUES.contains(&value.as_str()) {
+        Some(true)
+    } else if FALSY_VALUES.contains(&value.as_str()) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Converts a date string to the ISO-8601 format.
+///
+/// The selector's `date_format` is used if set, otherwise RFC 3339, RFC 2822 and `%Y-%m-%d`
+/// are tried. Dates without time are returned as `YYYY-MM-DD`, dates without the timezone
+/// are returned without the offset.
+fn any_string_to_date(selector: &crate::structure::Selector, str: &str) -> Option<String> {
+    let value = str.trim();
+
+    let format = match &selector.date_format {
+        Some(format) => format,
+        None => {
+            if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+                return Some(date.to_rfc3339());
+            }
+            if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+                return Some(date.to_rfc3339());
+            }
+            "%Y-%m-%d"
+        }
+    };
+
+    if let Ok(date) = DateTime::parse_from_str(value, format) {
+        return Some(date.to_rfc3339());
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+        return Some(date.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+        return Some(date.format("%Y-%m-%d").to_string());
+    }
+
+    None
+}
+
 /// Converts a complex string to a number
 fn any_string_to_number(str: &str) -> f64 {
     let value = str.to_lowercase();
@@ -150,10 +1557,23 @@ fn any_string_to_number(str: &str) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fetcher::{HttpFetcher, Method};
+    use async_trait::async_trait;
+
+    /// Serves the same HTML for every URL
+    struct StaticFetcher(&'static str);
+
+    #[async_trait]
+    impl Fetcher for StaticFetcher {
+        async fn fetch(&self, _url: &str) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+    }
 
     #[tokio::test]
     async fn test_fetch_html() -> Result<()> {
-        let document = fetch_html("http://example.com").await?;
+        let document =
+            fetch_html(&HttpFetcher::default(), &Request::get("http://example.com")).await?;
         assert!(document
             .select(&Selector::parse("body").unwrap())
             .next()
@@ -163,7 +1583,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_html_with_invalid_url() -> Result<()> {
-        fetch_html("invalid-url")
+        fetch_html(&HttpFetcher::default(), &Request::get("invalid-url"))
             .await
             .expect_err("should fail with invalid URL!");
         Ok(())
@@ -171,19 +1591,40 @@ mod tests {
 
     #[tokio::test]
     async fn test_grab_one() -> Result<()> {
-        let value = grab_one("body > div > h1", "http://example.com").await?;
+        let value = grab_one(
+            &HttpFetcher::default(),
+            "body > div > h1",
+            "http://example.com",
+        )
+        .await?;
         assert_eq!(value, "Example Domain");
         Ok(())
     }
 
+    #[test]
+    fn test_grab_one_from_html() -> Result<()> {
+        let html = "<html><body><div><h1>Example Domain</h1></div></body></html>";
+        assert_eq!(
+            grab_one_from_html(html, "body > div > h1")?,
+            "Example Domain"
+        );
+        assert!(grab_one_from_html(html, "body >").is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_grab() -> Result<()> {
-        let selectors = vec![crate::structure::Selector {
-            name: "title".to_string(),
-            path: "body > div > h1".to_string(),
-            parsed_type: crate::structure::SelectorType::String,
-        }];
-        let values = grab(selectors, "http://example.com".to_string()).await?;
+        let selectors = vec![crate::structure::Selector::new(
+            "body > div > h1".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        )];
+        let values = grab(
+            &HttpFetcher::default(),
+            selectors,
+            "http://example.com".to_string(),
+        )
+        .await?;
         assert_eq!(values.len(), 1);
         assert_eq!(&values[0].name, "title");
         match &values[0].value {
@@ -195,12 +1636,19 @@ mod tests {
 
     #[tokio::test]
     async fn test_grab_with_invalid_url() -> Result<()> {
-        let selectors = vec![crate::structure::Selector {
-            name: "title".to_string(),
-            path: "body > div > h1".to_string(),
-            parsed_type: crate::structure::SelectorType::String,
-        }];
-        if grab(selectors, "invalid-url".to_string()).await.is_ok() {
+        let selectors = vec![crate::structure::Selector::new(
+            "body > div > h1".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        )];
+        if grab(
+            &HttpFetcher::default(),
+            selectors,
+            "invalid-url".to_string(),
+        )
+        .await
+        .is_ok()
+        {
             panic!("should fail with invalid URL!");
         }
         Ok(())
@@ -208,14 +1656,18 @@ mod tests {
 
     #[tokio::test]
     async fn test_grab_with_invalid_selector() -> Result<()> {
-        let selectors = vec![crate::structure::Selector {
-            name: "title".to_string(),
-            path: "body > div > h2".to_string(),
-            parsed_type: crate::structure::SelectorType::String,
-        }];
-        if grab(selectors, "http://example.com".to_string())
-            .await
-            .is_err()
+        let selectors = vec![crate::structure::Selector::new(
+            "body > div > h2".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        )];
+        if grab(
+            &HttpFetcher::default(),
+            selectors,
+            "http://example.com".to_string(),
+        )
+        .await
+        .is_err()
         {
             panic!("should not fail with invalid selector and return an empty string!");
         }
@@ -224,12 +1676,17 @@ mod tests {
 
     #[tokio::test]
     async fn test_grab_with_invalid_selector_type() -> Result<()> {
-        let selectors = vec![crate::structure::Selector {
-            name: "title".to_string(),
-            path: "body > div > h2".to_string(),
-            parsed_type: crate::structure::SelectorType::Number,
-        }];
-        let grabbed = grab(selectors, "http://example.com".to_string()).await;
+        let selectors = vec![crate::structure::Selector::new(
+            "body > div > h2".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::Number,
+        )];
+        let grabbed = grab(
+            &HttpFetcher::default(),
+            selectors,
+            "http://example.com".to_string(),
+        )
+        .await;
         if grabbed.is_err() {
             panic!("should not fail with invalid selector and return an empty string!");
         }
@@ -237,13 +1694,977 @@ mod tests {
         let values = grabbed.unwrap();
         assert_eq!(values.len(), 1);
         assert_eq!(&values[0].name, "title");
-        match &values[0].value {
-            Value::String(value) => assert_eq!(value, "NaN"),
-            _ => panic!("value should be a string!"),
+        assert_eq!(values[0].value, Value::Null);
+        assert!(values[0].error.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_grab_with_malformed_selector() -> Result<()> {
+        let fetcher = StaticFetcher("<html><body><h1>Example</h1></body></html>");
+        let selectors = vec![
+            crate::structure::Selector::new(
+                "h1 >".to_string(),
+                "title".to_string(),
+                crate::structure::SelectorType::String,
+            ),
+            crate::structure::Selector::new(
+                "h1".to_string(),
+                "heading".to_string(),
+                crate::structure::SelectorType::String,
+            ),
+        ];
+        let values = grab(&fetcher, selectors, "http://example.com".to_string()).await?;
+
+        // Only the broken selector fails, the rest are still parsed
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].value, Value::Null);
+        let error = values[0].error.as_ref().expect("should contain an error!");
+        assert!(error.contains("title") && error.contains("h1 >"));
+        assert_eq!(values[1].value, Value::from("Example"));
+        assert!(values[1].error.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_number_failure() {
+        let document = Html::parse_document("<html><body><p>N/A</p><b>42</b></body></html>");
+        let selectors = [
+            crate::structure::Selector::new(
+                "p".to_string(),
+                "missing".to_string(),
+                crate::structure::SelectorType::Number,
+            ),
+            crate::structure::Selector::new(
+                "b".to_string(),
+                "answer".to_string(),
+                crate::structure::SelectorType::Number,
+            ),
+        ];
+
+        let values = extract(&document, &selectors);
+        assert_eq!(values[0].value, Value::Null);
+        assert!(values[0].error.as_ref().unwrap().contains("missing"));
+        assert_eq!(values[1].value, Value::from(42.0));
+    }
+
+    #[test]
+    fn test_extract_number_out_of_range() {
+        let document = Html::parse_document(
+            "<html><body><p>74000</p><i>7.4</i><i>74000</i><i>8.1</i></body></html>",
+        );
+        let mut price = crate::structure::Selector::new(
+            "p".to_string(),
+            "price".to_string(),
+            crate::structure::SelectorType::Number,
+        );
+        price.min = Some(1.0);
+        price.max = Some(100.0);
+        let mut prices = crate::structure::Selector::new(
+            "i".to_string(),
+            "prices".to_string(),
+            crate::structure::SelectorType::Number,
+        );
+        prices.list = true;
+        prices.max = Some(100.0);
+        prices.out_of_range = crate::structure::OutOfRange::Drop;
+
+        let values = extract(&document, &[price, prices]);
+        assert_eq!(values[0].value, Value::Null);
+        assert_eq!(
+            values[0].error.as_deref(),
+            Some("failed to convert \"74000\" to Number between 1 and 100 for \"price\"")
+        );
+        assert_eq!(values[1].value, serde_json::json!([7.4, 8.1]));
+        assert!(values[1].error.is_none());
+    }
+
+    #[test]
+    fn test_apply_transform() {
+        let apply = |transform: &str, value: &str| {
+            apply_transform(&transform.parse().unwrap(), value.to_string())
+        };
+        assert_eq!(apply("trim", "  $1,024.50 \n"), "$1,024.50");
+        assert_eq!(apply("lowercase", "In Stock"), "in stock");
+        assert_eq!(apply("uppercase", "aapl"), "AAPL");
+        assert_eq!(apply(r#"strip_prefix:"$""#, "$100"), "100");
+        assert_eq!(apply(r#"strip_prefix:"$""#, "100$"), "100$");
+        assert_eq!(apply(r#"strip_suffix:"%""#, "5.2%"), "5.2");
+        assert_eq!(apply(r#"strip_suffix:"%""#, "%5.2"), "%5.2");
+        assert_eq!(apply(r#"replace:","->"""#, "1,024,000"), "1024000");
+        assert_eq!(apply(r#"replace:"\"" -> "'""#, r#"say "hi""#), "say 'hi'");
+        assert_eq!(apply(r#"split:" / "[0]"#, "12 / 100"), "12");
+        assert_eq!(apply(r#"split:" / "[-1]"#, "12 / 100"), "100");
+        assert_eq!(apply(r#"split:","[5]"#, "a,b"), "");
+        assert_eq!(apply(r#"split:","[-3]"#, "a,b"), "");
+    }
+
+    #[test]
+    fn test_extract_with_transforms() {
+        let document = Html::parse_document("<html><body><p> Price: $1,024.50 </p></body></html>");
+        let mut price = crate::structure::Selector::new(
+            "p".to_string(),
+            "price".to_string(),
+            crate::structure::SelectorType::Number,
+        );
+        price.regex = Some(r"\$[\d,.]+".to_string());
+        price.transforms = vec![
+            r#"strip_prefix:"$""#.parse().unwrap(),
+            r#"replace:","->"""#.parse().unwrap(),
+        ];
+
+        let values = extract(&document, &[price]);
+        assert_eq!(values[0].value, serde_json::json!(1024.5));
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources() {
+        let fetcher = StaticFetcher("<html><body><h1>Example</h1></body></html>");
+        let selector = crate::structure::Selector::new(
+            "h1".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        let resources = vec![
+            crate::structure::Resource::new("https://one.com".to_string(), vec![selector.clone()]),
+            crate::structure::Resource::new("https://two.com".to_string(), vec![selector]),
+        ];
+
+        let results = grab_resources(&fetcher, resources).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].url, "https://two.com");
+        assert_eq!(
+            results[1].values.as_ref().unwrap()[0].value,
+            Value::from("Example")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grab_with_custom_fetcher() -> Result<()> {
+        let fetcher = StaticFetcher("<html><body><h1>Price: 1.5k</h1></body></html>");
+        let selectors = vec![crate::structure::Selector::new(
+            "h1".to_string(),
+            "price".to_string(),
+            crate::structure::SelectorType::Number,
+        )];
+        let values = grab(&fetcher, selectors, "wasi://anything".to_string()).await?;
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].value, Value::from(1500.0));
+        Ok(())
+    }
+
+    /// Serves the page of each URL
+    struct PagesFetcher(Vec<(&'static str, &'static str)>);
+
+    #[async_trait]
+    impl Fetcher for PagesFetcher {
+        async fn fetch(&self, url: &str) -> Result<String> {
+            match self.0.iter().find(|(u, _)| *u == url) {
+                Some((_, page)) => Ok(page.to_string()),
+                None => Err(RvpError::FetchError {
+                    url: url.to_string(),
+                    message: "not found".to_string(),
+                }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_follows_pages() {
+        let fetcher = PagesFetcher(vec![
+            (
+                "https://shop.com/items?page=1",
+                r#"<h1>Items</h1><li>1</li><li>2</li><a class="next" href="?page=2">Next</a>"#,
+            ),
+            (
+                "https://shop.com/items?page=2",
+                r#"<h1>Page 2</h1><li>3</li><a class="next" href="/items?page=1">Next</a>"#,
+            ),
+            (
+                "https://api.shop.com/items",
+                r#"{"items": ["a", "b"], "next": "/items/2"}"#,
+            ),
+            ("https://api.shop.com/items/2", r#"{"items": ["c"]}"#),
+        ]);
+
+        let mut prices = crate::structure::Selector::new(
+            "li".to_string(),
+            "prices".to_string(),
+            crate::structure::SelectorType::Number,
+        );
+        prices.list = true;
+        let title = crate::structure::Selector::new(
+            "h1".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        let mut resource = crate::structure::Resource::new(
+            "https://shop.com/items?page=1".to_string(),
+            vec![prices, title],
+        );
+        resource.next_page = Some("a.next".to_string());
+
+        // The link back to the first page ends the pagination
+        let results = grab_resources(&fetcher, vec![resource.clone()]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, serde_json::json!([1.0, 2.0, 3.0]));
+        assert_eq!(values[1].value, Value::from("Items"));
+
+        resource.max_pages = Some(1);
+        let results = grab_resources(&fetcher, vec![resource]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, serde_json::json!([1.0, 2.0]));
+
+        let mut items = crate::structure::Selector::new(
+            "/items".to_string(),
+            "items".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        items.list = true;
+        let resource = crate::structure::Resource {
+            kind: Some(ResourceKind::Json),
+            next_page: Some("$.next".to_string()),
+            ..crate::structure::Resource::new("https://api.shop.com/items".to_string(), vec![items])
+        };
+        let results = grab_resources(&fetcher, vec![resource]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, serde_json::json!(["a", "b", "c"]));
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_feed() {
+        let fetcher = PagesFetcher(vec![(
+            "https://news.com/rss",
+            r#"<rss><channel><title>News</title>
+            <item><title>First</title><pubDate>Wed, 01 May 2024 10:00:00 GMT</pubDate></item>
+            <item><title>Second</title></item>
+            </channel></rss>"#,
+        )]);
+
+        let latest = crate::structure::Selector::new(
+            "$.items[0].pubDate".to_string(),
+            "latest".to_string(),
+            crate::structure::SelectorType::Date,
+        );
+        let mut titles = crate::structure::Selector::new(
+            "$.items[*].title".to_string(),
+            "titles".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        titles.list = true;
+        let resource = crate::structure::Resource {
+            kind: Some(ResourceKind::Feed),
+            ..crate::structure::Resource::new(
+                "https://news.com/rss".to_string(),
+                vec![latest, titles],
+            )
+        };
+
+        let results = grab_resources(&fetcher, vec![resource]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, Value::from("2024-05-01T10:00:00+00:00"));
+        assert_eq!(values[1].value, serde_json::json!(["First", "Second"]));
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_follows_links() {
+        let fetcher = PagesFetcher(vec![
+            (
+                "https://shop.com/items/",
+                r#"<a class="item" href="/items/1">1</a><a class="item" href="2">2</a>
+                <a class="item" href="https://shop.com/items/3">3</a>"#,
+            ),
+            (
+                "https://shop.com/items/1",
+                r#"<h1>Apple</h1><span>1.5</span><a class="seller" href="/sellers/1">s</a>"#,
+            ),
+            (
+                "https://shop.com/items/2",
+                r#"<h1>Pear</h1><span>n/a</span>"#,
+            ),
+            ("https://shop.com/sellers/1", r#"<h2>Farm</h2>"#),
+        ]);
+
+        let seller = crate::structure::Selector::new(
+            "h2".to_string(),
+            "seller".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        let mut sellers = crate::structure::Selector::new(
+            "a.seller".to_string(),
+            "sellers".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        sellers.follow = true;
+        sellers.children = vec![seller];
+        let mut items = crate::structure::Selector::new(
+            "a.item".to_string(),
+            "items".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        items.follow = true;
+        items.max_links = Some(2);
+        items.children = vec![
+            crate::structure::Selector::new(
+                "h1".to_string(),
+                "name".to_string(),
+                crate::structure::SelectorType::String,
+            ),
+            crate::structure::Selector::new(
+                "span".to_string(),
+                "price".to_string(),
+                crate::structure::SelectorType::Number,
+            ),
+            sellers,
+        ];
+        let resource =
+            crate::structure::Resource::new("https://shop.com/items/".to_string(), vec![items]);
+
+        let results = grab_resources(&fetcher, vec![resource.clone()]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(
+            values[0].value,
+            serde_json::json!([
+                {
+                    "url": "https://shop.com/items/1",
+                    "name": "Apple",
+                    "price": 1.5,
+                    "sellers": [{"url": "https://shop.com/sellers/1", "seller": "Farm"}],
+                },
+                {
+                    "url": "https://shop.com/items/2",
+                    "name": "Pear",
+                    "price": null,
+                    "sellers": [],
+                    "error": "failed to convert \"n/a\" to Number for \"price\"",
+                },
+            ])
+        );
+        assert!(values[0].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_ignores_local_links() {
+        let dir = std::env::temp_dir().join("rvp_test_ignores_local_links");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secret.html");
+        std::fs::write(&path, "<h1>secret</h1>").unwrap();
+        let secret = url::Url::from_file_path(&path).unwrap().to_string();
+
+        /// Serves a remote page which links to a local file, the rest is read by the HTTP fetcher
+        struct RemoteFetcher(String);
+
+        #[async_trait]
+        impl Fetcher for RemoteFetcher {
+            async fn fetch(&self, url: &str) -> Result<String> {
+                Ok(self.fetch_document(&Request::get(url)).await?.text)
+            }
+
+            async fn fetch_document(&self, request: &Request) -> Result<Document> {
+                match request.url.starts_with("https://") {
+                    true => Ok(Document {
+                        text: format!(
+                            r#"<a class="item" href="{0}">1</a><a class="next" href="{0}">Next</a>"#,
+                            self.0
+                        ),
+                        ..Document::default()
+                    }),
+                    false => HttpFetcher::default().fetch_document(request).await,
+                }
+            }
+        }
+
+        let mut items = crate::structure::Selector::new(
+            "a.item".to_string(),
+            "items".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        items.follow = true;
+        items.children = vec![crate::structure::Selector::new(
+            "h1".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        )];
+        let mut resource =
+            crate::structure::Resource::new("https://shop.com/items".to_string(), vec![items]);
+        let fetcher = RemoteFetcher(secret.clone());
+
+        let results = grab_resources(&fetcher, vec![resource.clone()]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value[0]["url"], Value::from(secret.as_str()));
+        assert!(values[0].value[0]["title"].is_null());
+        assert!(values[0].value[0]["error"].is_string());
+
+        resource.next_page = Some("a.next".to_string());
+        let results = grab_resources(&fetcher, vec![resource]).await;
+        assert!(results[0].values.is_err());
+
+        // The local file of the config is read
+        let resource = crate::structure::Resource::new(
+            secret,
+            vec![crate::structure::Selector::new(
+                "h1".to_string(),
+                "title".to_string(),
+                crate::structure::SelectorType::String,
+            )],
+        );
+        let results = grab_resources(&fetcher, vec![resource]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, Value::from("secret"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_refetches_on_miss() {
+        /// Serves a stripped page from the cache, unless the cache is bypassed
+        struct CdnFetcher;
+
+        #[async_trait]
+        impl Fetcher for CdnFetcher {
+            async fn fetch(&self, _url: &str) -> Result<String> {
+                Ok("<h1>Item</h1>".to_string())
+            }
+
+            async fn fetch_document(&self, request: &Request) -> Result<Document> {
+                let fresh = request.url.contains(crate::fetcher::CACHE_BUSTING_PARAM)
+                    && request
+                        .headers
+                        .iter()
+                        .any(|(name, _)| name == "Cache-Control");
+                Ok(Document {
+                    text: match fresh {
+                        true => "<h1>Item</h1><span>9.99</span>".to_string(),
+                        false => self.fetch(&request.url).await?,
+                    },
+                    ..Document::default()
+                })
+            }
+        }
+
+        let mut price = crate::structure::Selector::new(
+            "span".to_string(),
+            "price".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        price.required = true;
+        let mut resource =
+            crate::structure::Resource::new("https://shop.com/item".to_string(), vec![price]);
+
+        let results = grab_resources(&CdnFetcher, vec![resource.clone()]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, Value::Null);
+        assert_eq!(
+            values[0].error.as_deref(),
+            Some("required value \"price\" is missing")
+        );
+
+        resource.refetch_on_miss = Some(true);
+        let results = grab_resources(&CdnFetcher, vec![resource]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, Value::from("9.99"));
+        assert!(values[0].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_detects_language() {
+        /// Serves the German page, unless English is accepted
+        struct LocalizedFetcher;
+
+        #[async_trait]
+        impl Fetcher for LocalizedFetcher {
+            async fn fetch(&self, _url: &str) -> Result<String> {
+                Ok(r#"<html lang="de-DE"><body><h1>1.234,50</h1></body></html>"#.to_string())
+            }
+
+            async fn fetch_document(&self, request: &Request) -> Result<Document> {
+                let english = request
+                    .headers
+                    .iter()
+                    .any(|(name, value)| name == "Accept-Language" && value.starts_with("en"));
+                Ok(Document {
+                    text: match english {
+                        true => "<html><body><h1>1,234.50</h1></body></html>".to_string(),
+                        false => self.fetch(&request.url).await?,
+                    },
+                    language: Some("en".to_string()),
+                    ..Document::default()
+                })
+            }
+        }
+
+        let selector = crate::structure::Selector::new(
+            "h1".to_string(),
+            "price".to_string(),
+            crate::structure::SelectorType::Number,
+        );
+        let mut english =
+            crate::structure::Resource::new("https://shop.com".to_string(), vec![selector]);
+        english.accept_language = Some("en-US,en;q=0.9".to_string());
+        let mut french = english.clone();
+        french.accept_language = Some("fr-FR, fr;q=0.9".to_string());
+
+        let results = grab_resources(&LocalizedFetcher, vec![english, french]).await;
+        // The `Content-Language` header is used without the `lang` attribute
+        assert_eq!(results[0].language.as_deref(), Some("en"));
+        assert!(!results[0].language_mismatch());
+        assert_eq!(results[1].language.as_deref(), Some("de-DE"));
+        assert!(results[1].language_mismatch());
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_records_redirects() {
+        /// Redirects every URL to the same page
+        struct RedirectingFetcher(&'static str);
+
+        #[async_trait]
+        impl Fetcher for RedirectingFetcher {
+            async fn fetch(&self, _url: &str) -> Result<String> {
+                Ok("<html><body><h1>Sign in</h1></body></html>".to_string())
+            }
+
+            async fn fetch_document(&self, request: &Request) -> Result<Document> {
+                Ok(Document {
+                    text: self.fetch(&request.url).await?,
+                    redirected_to: Some(self.0.to_string()),
+                    ..Document::default()
+                })
+            }
+        }
+
+        let selector = crate::structure::Selector::new(
+            "h1".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        let resources = vec![crate::structure::Resource::new(
+            "https://Example.com/item".to_string(),
+            vec![selector],
+        )];
+
+        let results = grab_resources(
+            &RedirectingFetcher("https://example.com/item/"),
+            resources.clone(),
+        )
+        .await;
+        assert_eq!(
+            results[0].final_url.as_deref(),
+            Some("https://example.com/item/")
+        );
+        assert!(!results[0].redirected_off_host());
+
+        let results =
+            grab_resources(&RedirectingFetcher("https://login.example.org/"), resources).await;
+        assert!(results[0].redirected_off_host());
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_posts_body() {
+        /// Answers the GraphQL queries with the price of the requested symbol
+        struct GraphqlFetcher;
+
+        #[async_trait]
+        impl Fetcher for GraphqlFetcher {
+            async fn fetch(&self, url: &str) -> Result<String> {
+                Ok(self.fetch_document(&Request::get(url)).await?.text)
+            }
+
+            async fn fetch_document(&self, request: &Request) -> Result<Document> {
+                let has_json_type = request
+                    .headers
+                    .contains(&("Content-Type".to_string(), "application/json".to_string()));
+                match (request.method, &request.body) {
+                    (Method::Post, Some(body)) if has_json_type && body.contains("AAPL") => {
+                        Ok(Document {
+                            text: r#"{"data": {"price": 190.5}}"#.to_string(),
+                            ..Document::default()
+                        })
+                    }
+                    _ => Err(RvpError::FetchError {
+                        url: request.url.clone(),
+                        message: "only POST requests are served".to_string(),
+                    }),
+                }
+            }
+        }
+
+        let mut resource = crate::structure::Resource::new(
+            "https://api.com/graphql".to_string(),
+            vec![crate::structure::Selector::new(
+                "$.data.price".to_string(),
+                "price".to_string(),
+                crate::structure::SelectorType::Number,
+            )],
+        );
+        resource.kind = Some(ResourceKind::Json);
+        resource.method = Some(Method::Post);
+        resource.body = Some(r#"{"query": "{ price(symbol: \"%%\") }"}"#.to_string());
+        resource.content_type = Some("application/json".to_string());
+        resource.mut_url_with_param("AAPL");
+
+        let results = grab_resources(&GraphqlFetcher, vec![resource.clone()]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, Value::from(190.5));
+
+        // The fetchers without the support of the requests fail instead of sending a GET
+        let results = grab_resources(&StaticFetcher("{}"), vec![resource]).await;
+        assert!(results[0].values.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_grab_json_resource() {
+        let fetcher =
+            StaticFetcher(r#"{"data": [{"price": "1.5k", "symbol": "AAPL", "open": true}]}"#);
+        let mut resource = crate::structure::Resource::new(
+            "https://api.com".to_string(),
+            vec![
+                crate::structure::Selector::new(
+                    "$.data[0].price".to_string(),
+                    "price".to_string(),
+                    crate::structure::SelectorType::Number,
+                ),
+                crate::structure::Selector::new(
+                    "/data/0/symbol".to_string(),
+                    "symbol".to_string(),
+                    crate::structure::SelectorType::String,
+                ),
+                crate::structure::Selector::new(
+                    "$['data'][0]['open']".to_string(),
+                    "open".to_string(),
+                    crate::structure::SelectorType::Boolean,
+                ),
+                crate::structure::Selector::new(
+                    "data.0".to_string(),
+                    "invalid".to_string(),
+                    crate::structure::SelectorType::String,
+                ),
+            ],
+        );
+        resource.kind = Some(ResourceKind::Json);
+
+        let results = grab_resources(&fetcher, vec![resource]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, Value::from(1500.0));
+        assert_eq!(values[1].value, Value::String("AAPL".to_string()));
+        assert_eq!(values[2].value, Value::Bool(true));
+        assert!(values[3].error.is_some());
+
+        let results = grab_resources(
+            &StaticFetcher("<html></html>"),
+            vec![crate::structure::Resource {
+                kind: Some(ResourceKind::Json),
+                ..crate::structure::Resource::new("https://api.com".to_string(), vec![])
+            }],
+        )
+        .await;
+        assert!(results[0].values.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_limited() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Counts the maximum number of fetches in flight
+        #[derive(Default)]
+        struct CountingFetcher {
+            current: AtomicUsize,
+            max: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Fetcher for CountingFetcher {
+            async fn fetch(&self, _url: &str) -> Result<String> {
+                let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                self.current.fetch_sub(1, Ordering::SeqCst);
+                Ok("<html></html>".to_string())
+            }
+        }
+
+        let fetcher = CountingFetcher::default();
+        let resources: Vec<_> = (0..6)
+            .map(|i| crate::structure::Resource::new(format!("https://{}.com", i), vec![]))
+            .collect();
+
+        let results = grab_resources_limited(&fetcher, resources, 2).await;
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[5].url, "https://5.com");
+        assert_eq!(fetcher.max.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_bounded() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Counts the maximum number of fetches in flight
+        #[derive(Default)]
+        struct CountingFetcher {
+            current: AtomicUsize,
+            max: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Fetcher for CountingFetcher {
+            async fn fetch(&self, _url: &str) -> Result<String> {
+                let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                self.current.fetch_sub(1, Ordering::SeqCst);
+                Ok("<h1>1</h1>".repeat(10))
+            }
+        }
+
+        let resources: Vec<_> = (0..6)
+            .map(|i| {
+                crate::structure::Resource::new(
+                    format!("https://{}.com", i),
+                    vec![crate::structure::Selector::new(
+                        "h1".to_string(),
+                        "value".to_string(),
+                        crate::structure::SelectorType::Number,
+                    )],
+                )
+            })
+            .collect();
+        let size = "<h1>1</h1>".len() * 10 * DOM_SIZE_FACTOR;
+
+        // Each document is over the limit, so they are downloaded one by one
+        let fetcher = CountingFetcher::default();
+        let results = grab_resources_bounded(&fetcher, resources.clone(), 4, Some(1)).await;
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[5].url, "https://5.com");
+        assert_eq!(
+            results[5].values.as_ref().unwrap()[0].value,
+            Value::from(1.0)
+        );
+        assert_eq!(fetcher.max.load(Ordering::SeqCst), 1);
+
+        // Two documents fit, after the size of the first one is known
+        let fetcher = CountingFetcher::default();
+        grab_resources_bounded(&fetcher, resources.clone(), 4, Some(size * 2)).await;
+        assert_eq!(fetcher.max.load(Ordering::SeqCst), 2);
+
+        let fetcher = CountingFetcher::default();
+        grab_resources_bounded(&fetcher, resources, 4, None).await;
+        assert_eq!(fetcher.max.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_fetches_each_url_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct CountingFetcher(AtomicUsize);
+
+        #[async_trait]
+        impl Fetcher for CountingFetcher {
+            async fn fetch(&self, _url: &str) -> Result<String> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok("<html><body><h1>Example</h1></body></html>".to_string())
+            }
+        }
+
+        let selector = crate::structure::Selector::new(
+            "h1".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        let resources = vec![
+            crate::structure::Resource::new("https://one.com".to_string(), vec![selector.clone()]),
+            crate::structure::Resource::new("https://two.com".to_string(), vec![selector.clone()]),
+            crate::structure::Resource::new("https://one.com".to_string(), vec![selector]),
+        ];
+
+        let fetcher = CountingFetcher::default();
+        let results = grab_resources(&fetcher, resources).await;
+        assert_eq!(fetcher.0.load(Ordering::SeqCst), 2);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].url, "https://one.com");
+        assert_eq!(
+            results[2].values.as_ref().unwrap()[0].value,
+            Value::String("Example".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grab_resources_localizes_dates() {
+        let fetcher = StaticFetcher(
+            "<html><body><p>2024-05-01T14:30:00Z</p><b>2024-05-01 14:30:00</b></body></html>",
+        );
+        let mut naive = crate::structure::Selector::new(
+            "b".to_string(),
+            "naive".to_string(),
+            crate::structure::SelectorType::Date,
+        );
+        naive.date_format = Some("%Y-%m-%d %H:%M:%S".to_string());
+        let mut resource = crate::structure::Resource::new(
+            "https://one.com".to_string(),
+            vec![
+                crate::structure::Selector::new(
+                    "p".to_string(),
+                    "time".to_string(),
+                    crate::structure::SelectorType::Date,
+                ),
+                naive,
+            ],
+        );
+        resource.timezone = Some("America/New_York".parse().unwrap());
+
+        let results = grab_resources(&fetcher, vec![resource]).await;
+        let values = results[0].values.as_ref().unwrap();
+        assert_eq!(values[0].value, Value::from("2024-05-01T10:30:00-04:00"));
+        assert_eq!(values[1].value, Value::from("2024-05-01T14:30:00"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_resources() {
+        let fetcher = StaticFetcher("<html><body><h1>Price: n/a</h1></body></html>");
+        let resource = crate::structure::Resource::new(
+            "https://one.com".to_string(),
+            vec![
+                crate::structure::Selector::new(
+                    "h1".to_string(),
+                    "title".to_string(),
+                    crate::structure::SelectorType::String,
+                ),
+                crate::structure::Selector::new(
+                    "h1".to_string(),
+                    "price".to_string(),
+                    crate::structure::SelectorType::Number,
+                ),
+                crate::structure::Selector::new(
+                    "h2".to_string(),
+                    "subtitle".to_string(),
+                    crate::structure::SelectorType::String,
+                ),
+            ],
+        );
+
+        let results = probe_resources(&fetcher, vec![resource], 1).await;
+        let probes = results[0].probes.as_ref().unwrap();
+        assert!(probes[0].matched);
+        assert_eq!(probes[0].matches, 1);
+        assert_eq!(probes[0].text, "Price: n/a");
+        assert!(probes[0].value.is_ok());
+        assert!(probes[1].matched);
+        assert!(probes[1].value.is_err());
+        assert!(!probes[2].matched);
+        assert_eq!(probes[2].value.as_ref().unwrap(), &Value::from(""));
+    }
+
+    #[tokio::test]
+    async fn test_probe_stability() {
+        let resource = crate::structure::Resource::new(
+            "https://one.com".to_string(),
+            vec![
+                crate::structure::Selector::new(
+                    "h1".to_string(),
+                    "title".to_string(),
+                    crate::structure::SelectorType::String,
+                ),
+                crate::structure::Selector::new(
+                    "li".to_string(),
+                    "item".to_string(),
+                    crate::structure::SelectorType::String,
+                ),
+                crate::structure::Selector::new(
+                    "h2".to_string(),
+                    "missing".to_string(),
+                    crate::structure::SelectorType::String,
+                ),
+            ],
+        );
+        let pages = [
+            "<html><body><h1>A</h1><li>1</li></body></html>",
+            "<html><body><h1>B</h1><li>1</li><li>2</li></body></html>",
+        ];
+        let mut rounds = Vec::new();
+        for page in pages {
+            rounds.push(probe_resources(&StaticFetcher(page), vec![resource.clone()], 1).await);
         }
+
+        let summary = probe_stability(&[resource], &rounds);
+        assert_eq!(summary[0].match_counts, vec![1]);
+        assert_eq!(summary[0].distinct_values, 2);
+        assert!(!summary[0].is_flaky());
+        assert_eq!(summary[1].match_counts, vec![1, 2]);
+        assert!(summary[1].is_flaky());
+        assert!(summary[2].is_broken());
+        assert!(!summary[2].is_flaky());
+    }
+
+    #[tokio::test]
+    async fn test_grab_rendered_resource_is_not_supported_by_default() {
+        let resource = crate::structure::Resource {
+            render: Some(true),
+            ..crate::structure::Resource::new("https://spa.com".to_string(), vec![])
+        };
+
+        let results = grab_resources(&StaticFetcher("<html></html>"), vec![resource]).await;
+        assert!(results[0].values.is_err());
+    }
+
+    #[test]
+    fn test_extract_with_regex() -> Result<()> {
+        let document = Html::parse_document(
+            "<html><body><p>Price: $123.45 USD</p><span>Rating: 4 of 5</span></body></html>",
+        );
+
+        let mut price = crate::structure::Selector::new(
+            "p".to_string(),
+            "price".to_string(),
+            crate::structure::SelectorType::Number,
+        );
+        price.regex = Some(r"\$([\d.]+)".to_string());
+        price.regex_group = Some(1);
+
+        let mut rating = crate::structure::Selector::new(
+            "span".to_string(),
+            "rating".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        rating.regex = Some(r"\d of \d".to_string());
+
+        let values = extract(&document, &[price, rating]);
+        assert_eq!(values[0].value, Value::from(123.45));
+        assert_eq!(values[1].value, Value::from("4 of 5"));
+
+        // The regex of a list selector applies to each value, and fails it if invalid
+        let document = Html::parse_document("<ul><li>$1.5 USD</li><li>$2 USD</li></ul>");
+        let mut prices = crate::structure::Selector::new(
+            "li".to_string(),
+            "prices".to_string(),
+            crate::structure::SelectorType::Number,
+        );
+        prices.list = true;
+        prices.regex = Some(r"\$([\d.]+)".to_string());
+        prices.regex_group = Some(1);
+        let mut invalid = prices.clone();
+        invalid.regex = Some("(unclosed".to_string());
+        let values = extract(&document, &[prices, invalid]);
+        assert_eq!(values[0].value, serde_json::json!([1.5, 2.0]));
+        assert!(values[1].error.is_some());
         Ok(())
     }
 
+    #[test]
+    fn test_extract_with_invalid_regex() {
+        let document = Html::parse_document("<html><body><p>Example</p></body></html>");
+        let mut selector = crate::structure::Selector::new(
+            "p".to_string(),
+            "title".to_string(),
+            crate::structure::SelectorType::String,
+        );
+        selector.regex = Some("(unclosed".to_string());
+
+        let values = extract(&document, &[selector]);
+        let error = values[0]
+            .error
+            .as_ref()
+            .expect("should fail with invalid regex!");
+        assert!(error.contains("title"));
+    }
+
     #[test]
     fn test_parse_value() -> Result<()> {
         let document = Html::parse_document("<html><body><h1>Example</h1></body></html>");
@@ -261,6 +2682,83 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_any_string_to_bool() {
+        let mut selector = crate::structure::Selector::new(
+            "p".to_string(),
+            "stock".to_string(),
+            crate::structure::SelectorType::Boolean,
+        );
+
+        assert_eq!(any_string_to_bool(&selector, " In Stock "), Some(true));
+        assert_eq!(any_string_to_bool(&selector, "YES"), Some(true));
+        assert_eq!(any_string_to_bool(&selector, "Out of stock"), Some(false));
+        assert_eq!(any_string_to_bool(&selector, "false"), Some(false));
+        assert_eq!(any_string_to_bool(&selector, "maybe"), None);
+
+        selector.truthy_values = Some(vec!["Ships today".to_string()]);
+        assert_eq!(any_string_to_bool(&selector, "ships today"), Some(true));
+        assert_eq!(any_string_to_bool(&selector, "yes"), Some(false));
+    }
+
+    #[test]
+    fn test_extract_boolean() {
+        let document = Html::parse_document("<html><body><p>maybe</p><b>Yes</b></body></html>");
+        let selectors = [
+            crate::structure::Selector::new(
+                "b".to_string(),
+                "available".to_string(),
+                crate::structure::SelectorType::Boolean,
+            ),
+            crate::structure::Selector::new(
+                "p".to_string(),
+                "unknown".to_string(),
+                crate::structure::SelectorType::Boolean,
+            ),
+        ];
+
+        let values = extract(&document, &selectors);
+        assert_eq!(values[0].value, Value::Bool(true));
+        assert_eq!(values[1].value, Value::Null);
+        assert!(values[1].error.is_some());
+    }
+
+    #[test]
+    fn test_any_string_to_date() {
+        let mut selector = crate::structure::Selector::new(
+            "p".to_string(),
+            "date".to_string(),
+            crate::structure::SelectorType::Date,
+        );
+
+        assert_eq!(
+            any_string_to_date(&selector, "2024-01-02T10:20:30+02:00"),
+            Some("2024-01-02T10:20:30+02:00".to_string())
+        );
+        assert_eq!(
+            any_string_to_date(&selector, "Tue, 2 Jan 2024 10:20:30 GMT"),
+            Some("2024-01-02T10:20:30+00:00".to_string())
+        );
+        assert_eq!(
+            any_string_to_date(&selector, " 2024-01-02 "),
+            Some("2024-01-02".to_string())
+        );
+        assert_eq!(any_string_to_date(&selector, "yesterday"), None);
+
+        selector.date_format = Some("%d.%m.%Y %H:%M".to_string());
+        assert_eq!(
+            any_string_to_date(&selector, "02.01.2024 10:20"),
+            Some("2024-01-02T10:20:00".to_string())
+        );
+
+        selector.date_format = Some("%B %d, %Y".to_string());
+        assert_eq!(
+            any_string_to_date(&selector, "January 02, 2024"),
+            Some("2024-01-02".to_string())
+        );
+        assert_eq!(any_string_to_date(&selector, "02.01.2024"), None);
+    }
+
     #[test]
     fn test_any_string_to_number() {
         let value = any_string_to_number("1.234,56");
@@ -0,0 +1,108 @@
+//! Global settings of the user, one TOML file in the global config directory, written by
+//! `rvp init`. The command line options and the options of the configs take precedence.
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use rvp::{
+    error::RvpError,
+    structure::{ConfigFormat, OutputFormat, RateLimit},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::context::ColorChoice;
+
+/// Name of the settings file inside the global config directory
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Format of the new configs, preselected by `rvp new`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ConfigFormat>,
+    /// `User-Agent` header of the HTTP requests, used without `--user-agent`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Timeout for each HTTP request in seconds, used without `--timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Maximum number of resources fetched at the same time, used without `--concurrency`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+    /// Output of `batch`, used without `--json`, `--csv` and `--table`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+    /// When to use colors, used without `--color`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorChoice>,
+    /// Maximum number of requests to the same host, used when neither `--rate-limit`
+    /// nor the config sets it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// Skip the URLs disallowed by the `robots.txt` for all configs, unless `--ignore-robots`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub respect_robots: bool,
+    /// Webhook to send the results of `batch` and `watch` to, used without `--webhook`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Settings {
+    /// It reads the settings from the directory, the defaults if there is no settings file
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(SETTINGS_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        toml::from_str(&fs::read_to_string(&path)?).map_err(|err| {
+            RvpError::ConfigError(format!("Invalid settings in {}: {}", path.display(), err)).into()
+        })
+    }
+
+    /// It writes the settings file to the directory, creating the directory if needed
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(SETTINGS_FILE), toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_save() -> Result<()> {
+        let dir = std::env::temp_dir().join("rvp_test_settings");
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(Settings::load(&dir)?, Settings::default());
+
+        let settings = Settings {
+            format: Some(ConfigFormat::Json),
+            user_agent: Some("rvp-bot/1.0".to_string()),
+            timeout: Some(10),
+            concurrency: None,
+            output: Some(OutputFormat::Json),
+            color: Some(ColorChoice::Never),
+            rate_limit: Some("2/s".parse()?),
+            respect_robots: true,
+            webhook: None,
+        };
+        settings.save(&dir)?;
+        assert_eq!(Settings::load(&dir)?, settings);
+        let saved = fs::read_to_string(dir.join(SETTINGS_FILE))?;
+        assert!(saved.contains("output = \"json\""));
+        assert!(saved.contains("color = \"never\""));
+        assert!(!saved.contains("webhook"));
+
+        fs::write(dir.join(SETTINGS_FILE), "user_agnet = \"typo\"")?;
+        assert!(Settings::load(&dir).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//! HMAC signatures of the JSON output, so the consumers can verify the results were not changed
+use std::{env, fmt::Write};
+
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use serde_json::{json, to_string_pretty, Value};
+use sha2::Sha256;
+
+/// Environment variable with the secret key for `--sign-output`
+pub const SIGNING_KEY_ENV: &str = "RVP_SIGNING_KEY";
+
+/// Name of the algorithm in the signed output
+pub const ALGORITHM: &str = "hmac-sha256";
+
+/// Header with the signature of the webhook body
+pub const SIGNATURE_HEADER: &str = "X-Rvp-Signature";
+
+/// It reads the signing key from the environment
+pub fn signing_key() -> Result<Vec<u8>> {
+    match env::var(SIGNING_KEY_ENV) {
+        Ok(key) if !key.is_empty() => Ok(key.into_bytes()),
+        _ => Err(anyhow!(
+            "Signing needs the secret key in the {} environment variable!",
+            SIGNING_KEY_ENV
+        )),
+    }
+}
+
+/// It wraps the JSON output into an envelope with its signature:
+///
+/// ```json
+/// {"data": <output>, "signature": {"algorithm": "hmac-sha256", "value": "<hex>"}}
+/// ```
+///
+/// The signature is computed over the compact JSON of `data` with the object keys sorted,
+/// so the consumers can verify it after parsing the envelope.
+pub fn sign_json(output: &str, key: &[u8]) -> Result<String> {
+    let data: Value = serde_json::from_str(output)?;
+    // serde_json keeps the object keys sorted, the compact form is canonical
+    let signature = hmac_hex(key, serde_json::to_string(&data)?.as_bytes());
+
+    let envelope = json!({
+        "data": data,
+        "signature": {
+            "algorithm": ALGORITHM,
+            "value": signature,
+        },
+    });
+    Ok(to_string_pretty(&envelope)?)
+}
+
+/// It returns the value of the [SIGNATURE_HEADER] for the body, `sha256=<hex>`
+pub fn signature_header(body: &str, key: &[u8]) -> String {
+    format!("sha256={}", hmac_hex(key, body.as_bytes()))
+}
+
+/// It computes the HMAC-SHA256 of the payload as a lowercase hex string
+fn hmac_hex(key: &[u8], payload: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(payload);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_hex() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            hmac_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            signature_header("what do ya want for nothing?", b"Jefe"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_sign_json() {
+        let signed = sign_json("[\n  {\"value\": 1.5, \"name\": \"price\"}\n]", b"secret").unwrap();
+        let envelope: Value = serde_json::from_str(&signed).unwrap();
+
+        assert_eq!(envelope["data"][0]["name"], "price");
+        assert_eq!(envelope["signature"]["algorithm"], "hmac-sha256");
+        assert_eq!(
+            envelope["signature"]["value"],
+            hmac_hex(b"secret", br#"[{"name":"price","value":1.5}]"#)
+        );
+        assert!(sign_json("not json", b"secret").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Resources with a [Sitemap](crate::structure::Sitemap): the `url` of the resource is a
+//! `sitemap.xml`, and its selectors are grabbed from each page listed in it.
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::error::{Result, RvpError};
+use crate::feed::unescape;
+use crate::fetcher::{Fetcher, Request};
+use crate::structure::{Resource, DEFAULT_SITEMAP_LIMIT};
+
+/// Maximum number of the sitemaps fetched for a resource, including the nested sitemaps
+/// of a sitemap index
+const MAX_SITEMAPS: usize = 50;
+
+/// The URLs listed in a sitemap
+#[derive(Default, PartialEq, Debug)]
+pub struct Entries {
+    /// The pages, `<url><loc>`
+    pub pages: Vec<String>,
+    /// The nested sitemaps of a sitemap index, `<sitemap><loc>`
+    pub sitemaps: Vec<String>,
+}
+
+/// It parses the `<loc>` of the `<url>` and `<sitemap>` entries of the sitemap XML
+pub fn parse(xml: &str) -> Entries {
+    static ENTRY: OnceLock<Regex> = OnceLock::new();
+
+    let entry = ENTRY.get_or_init(|| {
+        Regex::new(
+            r"(?is)<(?:\w+:)?(url|sitemap)\b[^>]*>.*?<(?:\w+:)?loc\b[^>]*>(.*?)</(?:\w+:)?loc>",
+        )
+        .expect("sitemap entry regex is valid")
+    });
+
+    let mut entries = Entries::default();
+    for captures in entry.captures_iter(xml) {
+        let loc = unescape(&captures[2]);
+        if loc.is_empty() {
+            continue;
+        }
+        match captures[1].eq_ignore_ascii_case("url") {
+            true => entries.pages.push(loc),
+            false => entries.sitemaps.push(loc),
+        }
+    }
+    entries
+}
+
+/// It replaces each resource with a `sitemap` by a copy of the resource for each page of
+/// the sitemap, the other resources are kept as they are.
+///
+/// Arguments:
+///
+/// - `fetcher`: The [Fetcher] used to download the sitemaps.
+/// - `resources`: The resources, with the parameters already applied.
+///
+/// Returns:
+///
+/// The resources to grab. Fails if a sitemap could not be downloaded or has no matching pages.
+pub async fn expand(fetcher: &dyn Fetcher, resources: Vec<Resource>) -> Result<Vec<Resource>> {
+    let mut expanded = Vec::with_capacity(resources.len());
+    for resource in resources {
+        let Some(sitemap) = &resource.sitemap else {
+            expanded.push(resource);
+            continue;
+        };
+        let filter = match &sitemap.filter {
+            Some(filter) => Some(Regex::new(filter).map_err(|err| {
+                RvpError::ConfigError(format!("Invalid sitemap filter \"{}\": {}", filter, err))
+            })?),
+            None => None,
+        };
+        let limit = sitemap.limit.unwrap_or(DEFAULT_SITEMAP_LIMIT);
+
+        let pages = find_pages(fetcher, &resource.url, filter.as_ref(), limit).await?;
+        if pages.is_empty() {
+            return Err(RvpError::ConfigError(format!(
+                "Sitemap \"{}\" has no matching pages!",
+                resource.url
+            )));
+        }
+        for page in pages {
+            expanded.push(Resource {
+                url: page,
+                sitemap: None,
+                ..resource.clone()
+            });
+        }
+    }
+    Ok(expanded)
+}
+
+/// It collects up to `limit` pages of the sitemap matching the filter, the nested sitemaps
+/// of a sitemap index are fetched in order until the limit is reached.
+///
+/// Only the sitemap of the config may be a local file, the listed URLs other than HTTP(S)
+/// are skipped.
+async fn find_pages(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    filter: Option<&Regex>,
+    limit: usize,
+) -> Result<Vec<String>> {
+    let mut pages: Vec<String> = Vec::new();
+    let mut queue = vec![Request::get(url).with_local_files()];
+    let mut fetched = 0;
+
+    while !queue.is_empty() && pages.len() < limit && fetched < MAX_SITEMAPS {
+        let request = queue.remove(0);
+        let entries = parse(&fetcher.fetch_document(&request).await?.text);
+        fetched += 1;
+
+        for page in entries.pages.into_iter().filter(|page| is_web_url(page)) {
+            if pages.len() >= limit {
+                break;
+            }
+            if filter.map_or(true, |f| f.is_match(&page)) && !pages.contains(&page) {
+                pages.push(page);
+            }
+        }
+        queue.extend(
+            entries
+                .sitemaps
+                .iter()
+                .filter(|sitemap| is_web_url(sitemap))
+                .map(|sitemap| Request::get(sitemap)),
+        );
+    }
+    Ok(pages)
+}
+
+/// It checks if the URL listed in a sitemap is an HTTP(S) one
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    #[test]
+    fn test_parse() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://shop.com/item?id=1&amp;lang=en</loc><lastmod>2024-05-01</lastmod></url>
+              <url>
+                <loc><![CDATA[ https://shop.com/item?id=2 ]]></loc>
+              </url>
+            </urlset>"#;
+        assert_eq!(
+            parse(xml),
+            Entries {
+                pages: vec![
+                    "https://shop.com/item?id=1&lang=en".to_string(),
+                    "https://shop.com/item?id=2".to_string(),
+                ],
+                sitemaps: vec![],
+            }
+        );
+
+        let index =
+            r#"<sitemapindex><sitemap><loc>https://shop.com/a.xml</loc></sitemap></sitemapindex>"#;
+        assert_eq!(parse(index).sitemaps, vec!["https://shop.com/a.xml"]);
+    }
+
+    #[tokio::test]
+    async fn test_expand() {
+        /// Serves a sitemap index with two sitemaps
+        struct SitemapFetcher;
+
+        #[async_trait]
+        impl Fetcher for SitemapFetcher {
+            async fn fetch(&self, url: &str) -> Result<String> {
+                let xml = match url {
+                    "https://shop.com/sitemap.xml" => concat!(
+                        "<sitemapindex>",
+                        "<sitemap><loc>https://shop.com/blog.xml</loc></sitemap>",
+                        "<sitemap><loc>file:///etc/sitemap.xml</loc></sitemap>",
+                        "<sitemap><loc>https://shop.com/items.xml</loc></sitemap>",
+                        "</sitemapindex>"
+                    ),
+                    "https://shop.com/blog.xml" => concat!(
+                        "<urlset>",
+                        "<url><loc>https://shop.com/blog/1</loc></url>",
+                        "<url><loc>https://shop.com/items/1</loc></url>",
+                        "</urlset>"
+                    ),
+                    "https://shop.com/items.xml" => concat!(
+                        "<urlset>",
+                        "<url><loc>file:///etc/items/passwd</loc></url>",
+                        "<url><loc>https://shop.com/items/1</loc></url>",
+                        "<url><loc>https://shop.com/items/2</loc></url>",
+                        "<url><loc>https://shop.com/items/3</loc></url>",
+                        "</urlset>"
+                    ),
+                    url => panic!("{} is fetched", url),
+                };
+                Ok(xml.to_string())
+            }
+        }
+
+        let mut resource = Resource::new("https://shop.com/sitemap.xml".to_string(), vec![]);
+        resource.sitemap = Some(crate::structure::Sitemap {
+            filter: Some("/items/".to_string()),
+            limit: Some(2),
+        });
+        let other = Resource::new("https://other.com".to_string(), vec![]);
+
+        let resources = expand(&SitemapFetcher, vec![resource.clone(), other])
+            .await
+            .unwrap();
+        let urls: Vec<&str> = resources.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://shop.com/items/1",
+                "https://shop.com/items/2",
+                "https://other.com"
+            ]
+        );
+        assert!(resources.iter().all(|r| r.sitemap.is_none()));
+
+        resource.sitemap = Some(crate::structure::Sitemap {
+            filter: Some("/nothing/".to_string()),
+            limit: None,
+        });
+        assert!(expand(&SitemapFetcher, vec![resource]).await.is_err());
+    }
+}
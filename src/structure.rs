@@ -1,38 +1,272 @@
-use anyhow::{anyhow, Result};
+use crate::cron::Schedule;
+use crate::error::{Result, RvpError};
+use crate::fetcher::{Method, Request};
 /// This file contains the structure of the config file.
 /// It is used to create and serialize the config file.
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
 use serde::{Deserialize, Serialize};
 use std::{
-    env, fmt, fs, ops,
+    collections::BTreeMap,
+    ffi::OsStr,
+    fmt, fs, ops,
     path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
 /// This is the placeholder for the parameters in the URL
 pub const URL_PARAM_PLACEHOLDER: &str = "%%";
 
+/// Number of the pages grabbed with `next_page` if the resource has no `max_pages`
+pub const DEFAULT_MAX_PAGES: usize = 10;
+
+/// Number of the pages grabbed from the `sitemap` of a resource if it has no `limit`
+pub const DEFAULT_SITEMAP_LIMIT: usize = 100;
+
+/// Number of the links followed by a `follow` selector if it has no `max_links`
+pub const DEFAULT_MAX_LINKS: usize = 20;
+
+/// Maximum nesting of the `follow` selectors, the links deeper are not followed
+pub const MAX_FOLLOW_DEPTH: usize = 3;
+
+/// Number of the previous values an `anomaly` is checked against if it has no `window`
+pub const DEFAULT_ANOMALY_WINDOW: usize = 20;
+
+/// A part of the URL or the selector path: plain text, the [URL_PARAM_PLACEHOLDER]
+/// or a named `%name%` placeholder
+#[derive(Debug, PartialEq)]
+enum TextPart<'a> {
+    Text(&'a str),
+    Param,
+    Named(&'a str),
+}
+
+/// It splits the text into the plain text and the parameter placeholders.
+///
+/// A named placeholder is `%name%` with letters, digits and `_` in the name, starting with a
+/// letter or `_`. Two hex digits (`%ab%`) are treated as a percent-encoded byte instead, as
+/// is the text between two encoded bytes, e.g. `%A9t%` of `%C3%A9t%C3%A9`.
+fn placeholder_parts(text: &str) -> Vec<TextPart<'_>> {
+    let mut parts = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while let Some(offset) = text[i..].find('%') {
+        let start = i + offset;
+        let rest = &text[start + 1..];
+        let placeholder = match rest.find('%') {
+            Some(end) if is_placeholder_name(&rest[..end]) && !is_encoded_text(rest, end) => {
+                Some((TextPart::Named(&rest[..end]), end + 2))
+            }
+            _ if rest.starts_with('%') => Some((TextPart::Param, URL_PARAM_PLACEHOLDER.len())),
+            _ => None,
+        };
+
+        match placeholder {
+            Some((part, length)) => {
+                if text_start < start {
+                    parts.push(TextPart::Text(&text[text_start..start]));
+                }
+                parts.push(part);
+                i = start + length;
+                text_start = i;
+            }
+            None => i = start + 1,
+        }
+    }
+    if text_start < text.len() {
+        parts.push(TextPart::Text(&text[text_start..]));
+    }
+    parts
+}
+
+/// It replaces the placeholders in the text: [URL_PARAM_PLACEHOLDER] with the `param` if given,
+/// and the named ones with the values of the `named` pairs. Other placeholders are kept.
+fn fill_placeholders(text: &str, param: Option<&str>, named: &[(String, String)]) -> String {
+    placeholder_parts(text)
+        .iter()
+        .map(|part| match part {
+            TextPart::Text(text) => text.to_string(),
+            TextPart::Param => param.unwrap_or(URL_PARAM_PLACEHOLDER).to_string(),
+            TextPart::Named(name) => match named.iter().find(|(n, _)| n == name) {
+                Some((_, value)) => value.clone(),
+                None => format!("%{}%", name),
+            },
+        })
+        .collect()
+}
+
+/// It escapes the text to be put inside a JSON string, e.g. `a"b` to `a\"b`
+fn escape_json(text: &str) -> String {
+    let quoted = serde_json::Value::from(text).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// It escapes the text to be put inside a form-urlencoded body, e.g. `a&b` to `a%26b`
+fn escape_form(text: &str) -> String {
+    url::form_urlencoded::byte_serialize(text.as_bytes()).collect()
+}
+
+fn is_placeholder_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_well = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    let is_hex_byte = name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit());
+    starts_well && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && !is_hex_byte
+}
+
+/// Whether both `%` around the name (at `end` of the `rest` after the first one) start
+/// percent-encoded bytes
+fn is_encoded_text(rest: &str, end: usize) -> bool {
+    let starts_with_byte =
+        |text: &str| text.len() >= 2 && text.as_bytes()[..2].iter().all(u8::is_ascii_hexdigit);
+    starts_with_byte(rest) && starts_with_byte(&rest[end + 1..])
+}
+
+/// It returns the host part of the URL, without the credentials and the port
+pub fn url_host(url: &str) -> &str {
+    let url = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        None => url,
+    };
+    let authority = &url[..url.find(['/', '?', '#']).unwrap_or(url.len())];
+    let host = match authority.rsplit_once('@') {
+        Some((_, host)) => host,
+        None => authority,
+    };
+    let end = match host.starts_with('[') {
+        true => host.find(']').map_or(host.len(), |end| end + 1),
+        false => host.find(':').unwrap_or(host.len()),
+    };
+    &host[..end]
+}
+
+/// It replaces the `${NAME}` references to the environment variables in the text with the
+/// values returned by `value`. Fails if a referenced variable has no value.
+pub fn fill_env_vars(text: &str, value: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let is_name = |name: &str| {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name = after
+            .find('}')
+            .map(|end| &after[..end])
+            .filter(|n| is_name(n));
+        match name {
+            Some(name) => {
+                let Some(value) = value(name) else {
+                    return Err(RvpError::ConfigError(format!(
+                        "Environment variable {} is not set!",
+                        name
+                    )));
+                };
+                filled.push_str(&value);
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                filled.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// Parameters to be passed to the resources with the [URL_PARAM_PLACEHOLDER] in the URL
+#[derive(Clone, Default)]
+pub enum Params {
+    /// No parameters
+    #[default]
+    None,
+    /// A single parameter passed to all resources
+    One(String),
+    /// A parameter for each resource, in the order of the resources
+    PerResource(Vec<String>),
+    /// Each parameter is passed to all resources, so the resources are parsed once per parameter
+    Repeat(Vec<String>),
+}
+
 pub trait Position<T> {
-    /// It returns the position of the element in the [Vec]
-    fn position(&self, element: T) -> usize;
+    /// It returns the position of the element in the [Vec] or [None] if it is not found
+    fn try_position(&self, element: T) -> Option<usize>;
+}
+
+pub trait TryIndex<T> {
+    type Output;
+
+    /// It returns a reference to the matching element or [None] if it is not found
+    fn try_get(&self, index: T) -> Option<&Self::Output>;
+
+    /// It returns a mutable reference to the matching element or [None] if it is not found
+    fn try_get_mut(&mut self, index: T) -> Option<&mut Self::Output>;
 }
 
 /// This is the format of the config file to be saved or read
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum ConfigFormat {
     Toml,
     Json,
 }
 
+impl ConfigFormat {
+    /// It detects the config format by the file extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(OsStr::to_str) {
+            Some("json") => Ok(ConfigFormat::Json),
+            Some("toml") => Ok(ConfigFormat::Toml),
+            _ => Err(RvpError::ConfigError(format!(
+                "Invalid file format of \"{}\", expected .toml or .json!",
+                path.display()
+            ))),
+        }
+    }
+
+    /// It returns the file extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+        }
+    }
+}
+
+/// Format of the output of the values, a table by default
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
 /// The type for parsed [Selector] values
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub enum SelectorType {
     String,
     Number,
+    Boolean,
+    Date,
 }
 
 impl SelectorType {
     /// It returns a vector of all the possible [ParsedType]s
     pub fn list_as_vec() -> Vec<SelectorType> {
-        vec![SelectorType::String, SelectorType::Number]
+        vec![
+            SelectorType::String,
+            SelectorType::Number,
+            SelectorType::Boolean,
+            SelectorType::Date,
+        ]
     }
 
     /// It returns the string representation of the [ParsedType]
@@ -40,6 +274,8 @@ impl SelectorType {
         match self {
             SelectorType::String => "String",
             SelectorType::Number => "Number",
+            SelectorType::Boolean => "Boolean",
+            SelectorType::Date => "Date",
         }
     }
 }
@@ -56,6 +292,224 @@ pub struct Selector {
     pub path: String,
     pub name: String,
     pub parsed_type: SelectorType,
+    /// (Optional) Regex applied to the grabbed text before the type conversion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// (Optional) Capture group of the `regex` to use. The whole match is used by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex_group: Option<usize>,
+    /// (Optional) Cleanups of the grabbed text applied in order after the `regex` and before
+    /// the type conversion, e.g. `["trim", "strip_prefix:\"$\""]`. See [Transform].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<Transform>,
+    /// Grab all the matched elements (or the elements of the JSON array) as an array of
+    /// the `parsed_type` values, joined across the pages of the resource's `next_page`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub list: bool,
+    /// The value must not be empty: a missing value is reported as an error, and the page
+    /// is fetched again first if the resource has `refetch_on_miss`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub required: bool,
+    /// Follow the links (`href`) of the matched elements and grab the `children` selectors
+    /// from each linked page. For the JSON resources, the path of the URLs.
+    ///
+    /// The value is an array with an object per page: its `url` and the children values.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub follow: bool,
+    /// (Optional) Maximum number of the links followed, [DEFAULT_MAX_LINKS] by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_links: Option<usize>,
+    /// Selectors applied to the pages linked by the `follow` selector
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Selector>,
+    /// (Optional) Values treated as `true` by the [SelectorType::Boolean] selector.
+    /// Any other value is `false`. If not set, the common truthy/falsy strings are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truthy_values: Option<Vec<String>>,
+    /// (Optional) [chrono format](https://docs.rs/chrono/latest/chrono/format/strftime/index.html)
+    /// of the [SelectorType::Date] values, e.g. `%d.%m.%Y %H:%M`.
+    /// If not set, RFC 3339, RFC 2822 and `%Y-%m-%d` are tried.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    /// (Optional) Threshold of the [SelectorType::Number] value, e.g. `< 100`.
+    /// `batch` and `watch` report an alert when the value matches it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert: Option<AlertRule>,
+    /// (Optional) Shorthand for the `> N` alert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert_above: Option<f64>,
+    /// (Optional) Shorthand for the `< N` alert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert_below: Option<f64>,
+    /// (Optional) Smallest sane [SelectorType::Number] value, see `out_of_range`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// (Optional) Largest sane [SelectorType::Number] value, see `out_of_range`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// What to do with the values outside of `min` and `max`, usually the parsing glitches
+    #[serde(default, skip_serializing_if = "OutOfRange::is_default")]
+    pub out_of_range: OutOfRange,
+    /// (Optional) Flags the [SelectorType::Number] values far from their previous values
+    /// in the history of `--store`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anomaly: Option<AnomalyRule>,
+}
+
+/// What to do with the [SelectorType::Number] values outside of the `min` and `max` of the
+/// selector, so they are not recorded and don't raise the alerts
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutOfRange {
+    /// The value is `null` with the error, like a value which could not be converted
+    #[default]
+    Flag,
+    /// The value is `null` without an error, and dropped from the lists
+    Drop,
+}
+
+impl OutOfRange {
+    fn is_default(&self) -> bool {
+        *self == OutOfRange::default()
+    }
+}
+
+/// A cleanup of the grabbed text of a [Selector], written as `name` or `name:arguments`
+/// with the texts in double quotes
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub enum Transform {
+    /// `trim`: remove the whitespace around the text
+    Trim,
+    /// `lowercase`
+    Lowercase,
+    /// `uppercase`
+    Uppercase,
+    /// `strip_prefix:"$"`: remove the text from the start, if it's there
+    StripPrefix(String),
+    /// `strip_suffix:"%"`: remove the text from the end, if it's there
+    StripSuffix(String),
+    /// `replace:"a"->"b"`: replace all occurrences of the text
+    Replace { from: String, to: String },
+    /// `split:","[0]`: split the text and keep the part at the index, negative indexes count
+    /// from the end
+    Split { separator: String, index: isize },
+}
+
+impl FromStr for Transform {
+    type Err = RvpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |message: &str| {
+            RvpError::ConfigError(format!("Invalid transform \"{}\": {}!", s, message))
+        };
+
+        let (name, arguments) = match s.trim().split_once(':') {
+            Some((name, arguments)) => (name.trim(), Some(arguments.trim())),
+            None => (s.trim(), None),
+        };
+        let transform = match (name, arguments) {
+            ("trim", None) => Transform::Trim,
+            ("lowercase", None) => Transform::Lowercase,
+            ("uppercase", None) => Transform::Uppercase,
+            ("strip_prefix" | "strip_suffix", Some(arguments)) => {
+                let (text, rest) = quoted(arguments).ok_or_else(|| invalid("expected a text"))?;
+                if !rest.trim().is_empty() {
+                    return Err(invalid("expected a single text"));
+                }
+                match name {
+                    "strip_prefix" => Transform::StripPrefix(text),
+                    _ => Transform::StripSuffix(text),
+                }
+            }
+            ("replace", Some(arguments)) => {
+                let expected = || invalid("expected `replace:\"a\"->\"b\"`");
+                let (from, rest) = quoted(arguments).ok_or_else(expected)?;
+                let rest = rest.trim_start().strip_prefix("->").ok_or_else(expected)?;
+                let (to, rest) = quoted(rest.trim_start()).ok_or_else(expected)?;
+                if !rest.trim().is_empty() {
+                    return Err(expected());
+                }
+                if from.is_empty() {
+                    return Err(invalid("the replaced text is empty"));
+                }
+                Transform::Replace { from, to }
+            }
+            ("split", Some(arguments)) => {
+                let expected = || invalid("expected `split:\",\"[0]`");
+                let (separator, rest) = quoted(arguments).ok_or_else(expected)?;
+                let index = rest
+                    .trim()
+                    .strip_prefix('[')
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .and_then(|index| index.trim().parse().ok())
+                    .ok_or_else(expected)?;
+                if separator.is_empty() {
+                    return Err(invalid("the separator is empty"));
+                }
+                Transform::Split { separator, index }
+            }
+            ("trim" | "lowercase" | "uppercase", Some(_)) => {
+                return Err(invalid("no arguments expected"))
+            }
+            ("strip_prefix" | "strip_suffix" | "replace" | "split", None) => {
+                return Err(invalid("arguments expected"))
+            }
+            _ => return Err(invalid(
+                "supported: trim, lowercase, uppercase, strip_prefix, strip_suffix, replace, split",
+            )),
+        };
+        Ok(transform)
+    }
+}
+
+/// It parses the text in double quotes at the start, `\"` and `\\` are escaped quote and
+/// backslash. Returns the text and the rest after the closing quote.
+fn quoted(s: &str) -> Option<(String, &str)> {
+    let rest = s.strip_prefix('"')?;
+    let mut text = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((text, &rest[i + 1..])),
+            '\\' => text.push(chars.next()?.1),
+            c => text.push(c),
+        }
+    }
+    None
+}
+
+impl TryFrom<String> for Transform {
+    type Error = RvpError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Transform> for String {
+    fn from(value: Transform) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        match self {
+            Transform::Trim => write!(f, "trim"),
+            Transform::Lowercase => write!(f, "lowercase"),
+            Transform::Uppercase => write!(f, "uppercase"),
+            Transform::StripPrefix(text) => write!(f, "strip_prefix:{}", quote(text)),
+            Transform::StripSuffix(text) => write!(f, "strip_suffix:{}", quote(text)),
+            Transform::Replace { from, to } => {
+                write!(f, "replace:{}->{}", quote(from), quote(to))
+            }
+            Transform::Split { separator, index } => {
+                write!(f, "split:{}[{}]", quote(separator), index)
+            }
+        }
+    }
 }
 
 impl Selector {
@@ -65,6 +519,63 @@ impl Selector {
             path,
             name,
             parsed_type,
+            regex: None,
+            regex_group: None,
+            transforms: vec![],
+            list: false,
+            required: false,
+            follow: false,
+            max_links: None,
+            children: vec![],
+            truthy_values: None,
+            date_format: None,
+            alert: None,
+            alert_above: None,
+            alert_below: None,
+            min: None,
+            max: None,
+            out_of_range: OutOfRange::Flag,
+            anomaly: None,
+        }
+    }
+
+    /// It checks if the selector grabs all the matched elements, so its values are joined
+    /// across the pages of `next_page`
+    pub fn is_multiple(&self) -> bool {
+        self.list || self.follow
+    }
+
+    /// It returns the `alert`, `alert_above` and `alert_below` rules of the selector
+    pub fn alert_rules(&self) -> Vec<AlertRule> {
+        let above = self.alert_above.map(|threshold| AlertRule {
+            comparison: Comparison::Greater,
+            threshold,
+            change: None,
+        });
+        let below = self.alert_below.map(|threshold| AlertRule {
+            comparison: Comparison::Less,
+            threshold,
+            change: None,
+        });
+        [self.alert.clone(), above, below]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// It checks if the number is within the `min` and `max` of the selector
+    pub fn in_range(&self, number: f64) -> bool {
+        self.min.map_or(true, |min| number >= min) && self.max.map_or(true, |max| number <= max)
+    }
+
+    /// It describes the `min` and `max` of the selector, e.g. `between 1 and 100`.
+    /// `None` if neither is set.
+    pub fn range(&self) -> Option<String> {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => Some(format!("between {} and {}", min, max)),
+            (Some(min), None) => Some(format!("at least {}", min)),
+            (None, Some(max)) => Some(format!("at most {}", max)),
+            (None, None) => None,
         }
     }
 }
@@ -76,8 +587,20 @@ impl fmt::Display for Selector {
 }
 
 impl Position<&Selector> for Vec<Selector> {
-    fn position(&self, element: &Selector) -> usize {
-        self.iter().position(|s| s == element).unwrap()
+    fn try_position(&self, element: &Selector) -> Option<usize> {
+        self.iter().position(|s| s == element)
+    }
+}
+
+impl TryIndex<&Selector> for Vec<Selector> {
+    type Output = Selector;
+
+    fn try_get(&self, index: &Selector) -> Option<&Self::Output> {
+        self.iter().find(|s| *s == index)
+    }
+
+    fn try_get_mut(&mut self, index: &Selector) -> Option<&mut Self::Output> {
+        self.iter_mut().find(|s| *s == index)
     }
 }
 
@@ -85,23 +608,93 @@ impl ops::Index<&Selector> for Vec<Selector> {
     type Output = Selector;
 
     fn index(&self, index: &Selector) -> &Self::Output {
-        for selector in self.iter() {
-            if selector == index {
-                return selector;
-            }
-        }
-        panic!("selector not found");
+        self.try_get(index).expect("selector not found")
     }
 }
 
 impl ops::IndexMut<&Selector> for Vec<Selector> {
     fn index_mut(&mut self, index: &Selector) -> &mut Self::Output {
-        for selector in self {
-            if selector == index {
-                return selector;
-            }
+        self.try_get_mut(index).expect("selector not found")
+    }
+}
+
+/// The type of the document returned by the [Resource]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ResourceKind {
+    /// Server-rendered web page, the selector paths are CSS selectors
+    #[default]
+    Html,
+    /// JSON API response, the selector paths are JSON pointers (`/data/0/price`)
+    /// or simple JSONPath expressions (`$.data[0].price`)
+    Json,
+    /// RSS or Atom feed, converted to a JSON document with the `title`, `link` and `items`
+    /// of the feed (see [feed](crate::feed)). The selector paths are the same as for `Json`,
+    /// e.g. `$.items[0].title`.
+    Feed,
+}
+
+impl ResourceKind {
+    /// It returns a vector of all the possible [ResourceKind]s
+    pub fn list_as_vec() -> Vec<ResourceKind> {
+        vec![ResourceKind::Html, ResourceKind::Json, ResourceKind::Feed]
+    }
+
+    /// It returns the string representation of the [ResourceKind]
+    fn as_str(&self) -> &'static str {
+        match self {
+            ResourceKind::Html => "HTML",
+            ResourceKind::Json => "JSON",
+            ResourceKind::Feed => "Feed",
         }
-        panic!("selector not found");
+    }
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Pages of a `sitemap.xml` grabbed by the [Resource], see [Resource::sitemap]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Sitemap {
+    /// (Optional) Regex the URLs of the pages must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// (Optional) Maximum number of the pages, [DEFAULT_SITEMAP_LIMIT] by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// Authentication of the requests of a [Resource]. The values can reference the environment
+/// variables as `${NAME}`, so the secrets don't end up in the config file.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Auth {
+    /// HTTP Basic authentication
+    Basic { user: String, pass: String },
+    /// `Authorization: Bearer` token
+    Bearer { token: String },
+}
+
+impl Auth {
+    /// It returns the auth with the `${NAME}` references replaced with the values of the
+    /// environment variables
+    pub fn resolve(&self) -> Result<Auth> {
+        self.fill(|name| std::env::var(name).ok())
+    }
+
+    /// It returns the auth with the `${NAME}` references replaced, see [fill_env_vars]
+    pub fn fill(&self, value: impl Fn(&str) -> Option<String>) -> Result<Auth> {
+        Ok(match self {
+            Auth::Basic { user, pass } => Auth::Basic {
+                user: fill_env_vars(user, &value)?,
+                pass: fill_env_vars(pass, &value)?,
+            },
+            Auth::Bearer { token } => Auth::Bearer {
+                token: fill_env_vars(token, &value)?,
+            },
+        })
     }
 }
 
@@ -109,23 +702,400 @@ impl ops::IndexMut<&Selector> for Vec<Selector> {
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Resource {
     pub url: String,
+    /// (Optional) Name of the [Group] of the resource, it inherits the settings of the group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// (Optional) Type of the document at the URL, of the group or HTML by default, see
+    /// [Resource::kind]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ResourceKind>,
+    /// (Optional) Render the HTML page in a headless browser before parsing, for the pages
+    /// built with JavaScript. Requires the `render` feature. See [Resource::render]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render: Option<bool>,
+    /// (Optional) Template of the output names of the values, e.g. `{param}_{selector}`.
+    ///
+    /// Supported placeholders: `{selector}` - selector name, `{param}` - parameter applied to
+    /// the URL, `{resource}` - position of the resource (from 1), `{host}` - host of the URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_template: Option<String>,
+    /// The parameter applied to the URL, used by the `key_template`
+    #[serde(skip)]
+    pub param: Option<String>,
+    /// The timezone of the parsed dates, set from the `timezone` of the config
+    #[serde(skip)]
+    pub timezone: Option<Timezone>,
+    /// (Optional) CSS selector of the link to the next page, its `href` is followed.
+    /// For the JSON resources, the path of the next page URL.
+    ///
+    /// The values of the `list` selectors are joined across the pages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page: Option<String>,
+    /// (Optional) Maximum number of the pages grabbed with `next_page`, including the
+    /// first one. [DEFAULT_MAX_PAGES] by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<usize>,
+    /// (Optional) `Accept-Language` header of the requests, e.g. `en-US,en;q=0.9`.
+    ///
+    /// The language of the returned page is checked against it, as the localized pages
+    /// format the numbers differently.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_language: Option<String>,
+    /// (Optional) Fetch the page once more, bypassing the caches, when a `required` selector
+    /// is empty. See [Resource::refetch_on_miss]
+    ///
+    /// CDNs sometimes serve stale or stripped variants of the pages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refetch_on_miss: Option<bool>,
+    /// (Optional) The `url` is a `sitemap.xml` (or a sitemap index), and the selectors are
+    /// grabbed from each page listed in it instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sitemap: Option<Sitemap>,
+    /// (Optional) HTTP method of the request, of the group or `GET` by default, see
+    /// [Resource::method]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<Method>,
+    /// (Optional) Body of the request, e.g. a form or a GraphQL query. The parameter
+    /// placeholders are replaced in it the same way as in the `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// (Optional) `Content-Type` header of the `body`, e.g. `application/json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// (Optional) Authentication of the requests, not sent to the links on other hosts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+    /// (Optional) Extra headers of the requests, e.g. `Referer`. The ones of the group are
+    /// added unless the resource sets a header with the same name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// (Optional) Maximum number of requests to the host of the resource, instead of the
+    /// `rate_limit` of the config, e.g. `1/s`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// (Optional) Cron expression of the runs of the resource in `rvp schedule`, instead of
+    /// its `--cron`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    /// (Optional) Tags of the resource, with the tags of its group. `--tag` runs only the
+    /// resources with the tag.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether the resource is fetched. The disabled resources are parked in the config, e.g.
+    /// while the site is broken, and keep their positions for the `--params`.
+    #[serde(default = "enabled_by_default", skip_serializing_if = "is_true")]
+    pub enabled: bool,
+    /// The resource is going to be retired, e.g. the site published an official API.
+    /// `batch` warns about it on every run.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deprecated: bool,
+    /// (Optional) Note shown with the deprecation warning, e.g. what to use instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation_note: Option<String>,
+    /// (Optional) Date after which the deprecated resource should be removed,
+    /// `rvp check` fails after it, e.g. `2025-06-30`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<NaiveDate>,
     pub selectors: Vec<Selector>,
 }
 
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
 impl Resource {
-    /// Create a new resource
+    /// Create a new HTML resource
     pub fn new(url: String, selectors: Vec<Selector>) -> Self {
-        Self { url, selectors }
+        Self {
+            url,
+            group: None,
+            kind: None,
+            render: None,
+            key_template: None,
+            param: None,
+            timezone: None,
+            next_page: None,
+            max_pages: None,
+            accept_language: None,
+            refetch_on_miss: None,
+            sitemap: None,
+            method: None,
+            body: None,
+            content_type: None,
+            auth: None,
+            headers: BTreeMap::new(),
+            rate_limit: None,
+            schedule: None,
+            tags: vec![],
+            enabled: true,
+            deprecated: false,
+            deprecation_note: None,
+            sunset: None,
+            selectors,
+        }
+    }
+
+    /// Type of the document, HTML unless set by the resource or its group
+    pub fn kind(&self) -> ResourceKind {
+        self.kind.unwrap_or_default()
+    }
+
+    /// HTTP method of the request, `GET` unless set by the resource or its group
+    pub fn method(&self) -> Method {
+        self.method.unwrap_or_default()
+    }
+
+    /// Whether the page is rendered in a headless browser
+    pub fn render(&self) -> bool {
+        self.render.unwrap_or_default()
+    }
+
+    /// Whether the page is fetched once more when a `required` selector is empty
+    pub fn refetch_on_miss(&self) -> bool {
+        self.refetch_on_miss.unwrap_or_default()
+    }
+
+    /// It sets the settings of the group which the resource doesn't set itself, so a resource
+    /// can set them back to the defaults, e.g. `render = false`.
+    ///
+    /// A disabled group disables all its resources.
+    pub fn inherit(&mut self, group: &Group) {
+        if self.kind.is_none() {
+            self.kind = group.kind;
+        }
+        if self.method.is_none() {
+            self.method = group.method;
+        }
+        if self.render.is_none() && group.render {
+            self.render = Some(true);
+        }
+        if self.refetch_on_miss.is_none() && group.refetch_on_miss {
+            self.refetch_on_miss = Some(true);
+        }
+        self.enabled &= group.enabled;
+        let inherit = |field: &mut Option<String>, value: &Option<String>| {
+            if field.is_none() {
+                field.clone_from(value);
+            }
+        };
+        inherit(&mut self.key_template, &group.key_template);
+        inherit(&mut self.accept_language, &group.accept_language);
+        inherit(&mut self.content_type, &group.content_type);
+        if self.auth.is_none() {
+            self.auth.clone_from(&group.auth);
+        }
+        for (name, value) in group.headers.iter() {
+            if !self.headers.keys().any(|n| n.eq_ignore_ascii_case(name)) {
+                self.headers.insert(name.clone(), value.clone());
+            }
+        }
+        if self.rate_limit.is_none() {
+            self.rate_limit = group.rate_limit;
+        }
+        if self.schedule.is_none() {
+            self.schedule.clone_from(&group.schedule);
+        }
+        for tag in group.tags.iter() {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+    }
+
+    /// Whether the resource has any of the tags, `true` for no tags
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty() || tags.iter().any(|tag| self.tags.contains(tag))
+    }
+
+    /// It returns the deprecation warning of the resource with its note and sunset date,
+    /// `None` if the resource is not deprecated
+    pub fn deprecation(&self) -> Option<String> {
+        if !self.deprecated {
+            return None;
+        }
+        let mut message = format!("resource {} is deprecated", self.url);
+        if let Some(sunset) = self.sunset {
+            message.push_str(&format!(", sunset on {}", sunset));
+        }
+        if let Some(note) = &self.deprecation_note {
+            message.push_str(&format!(": {}", note));
+        }
+        Some(message)
+    }
+
+    /// Whether the `sunset` date of the resource is before the given day
+    pub fn is_past_sunset(&self, today: NaiveDate) -> bool {
+        self.sunset.is_some_and(|sunset| sunset < today)
+    }
+
+    /// It returns the request of the resource's URL with its method, headers, auth and body.
+    /// The URL is of the config, so it may be a local file.
+    pub fn request(&self) -> Request {
+        let mut request = Request::get(&self.url).with_local_files();
+        request.method = self.method();
+        request.auth = self.auth.clone();
+        if let Some(language) = &self.accept_language {
+            request
+                .headers
+                .push(("Accept-Language".to_string(), language.clone()));
+        }
+        for (name, value) in self.headers.iter() {
+            request.headers.push((name.clone(), value.clone()));
+        }
+        if let Some(body) = &self.body {
+            if let Some(content_type) = &self.content_type {
+                request
+                    .headers
+                    .push(("Content-Type".to_string(), content_type.clone()));
+            }
+            request.body = Some(body.clone());
+        }
+        request
+    }
+
+    /// It escapes the parameter put in the body by its `content_type`: as a JSON string for
+    /// JSON, percent-encoded for a form, and as is otherwise
+    fn escape_body_param(&self, param: &str) -> String {
+        let mime = self
+            .content_type
+            .as_deref()
+            .and_then(|content_type| content_type.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if mime == "application/json" || mime.ends_with("+json") {
+            escape_json(param)
+        } else if mime == "application/x-www-form-urlencoded" {
+            escape_form(param)
+        } else {
+            param.to_string()
+        }
+    }
+
+    /// It returns the maximum number of the pages to grab, 1 without `next_page`
+    pub fn page_limit(&self) -> usize {
+        match self.next_page {
+            Some(_) => self.max_pages.unwrap_or(DEFAULT_MAX_PAGES),
+            None => 1,
+        }
     }
 
-    /// It replaces the parameter placeholder with the given parameter
+    /// It replaces the parameter placeholder in the URL, the body and the selector paths with
+    /// the given parameter.
+    ///
+    /// The named `%name%` placeholders are kept, see [Resource::mut_url_with_named_params].
     pub fn mut_url_with_param(&mut self, param: &str) {
-        self.url = self.url.replace(URL_PARAM_PLACEHOLDER, param);
+        if !self.needs_parameter() {
+            return;
+        }
+        self.add_param(param);
+        self.url = fill_placeholders(&self.url, Some(param), &[]);
+        if let Some(body) = &self.body {
+            let param = self.escape_body_param(param);
+            self.body = Some(fill_placeholders(body, Some(&param), &[]));
+        }
+        for selector in self.selectors.iter_mut() {
+            selector.path = fill_placeholders(&selector.path, Some(param), &[]);
+        }
     }
 
-    /// It checks if the URL contains the parameter placeholder
-    fn needs_parameter(&self) -> bool {
-        self.url.contains(URL_PARAM_PLACEHOLDER)
+    /// It replaces the named `%name%` placeholders in the URL, the body and the selector paths
+    /// with the values of the given `name=value` pairs.
+    ///
+    /// The values are joined with `,` in the order of the placeholders for the `{param}` of the
+    /// `key_template`.
+    pub fn mut_url_with_named_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        let mut values: Vec<&str> = Vec::new();
+        for name in self.named_params() {
+            let Some((_, value)) = params.iter().find(|(n, _)| n == name) else {
+                return Err(RvpError::ConfigError(format!(
+                    "Missing value of the %{}% parameter in {}!",
+                    name, self.url
+                )));
+            };
+            if !values.contains(&value.as_str()) {
+                values.push(value);
+            }
+        }
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        self.add_param(&values.join(","));
+        self.url = fill_placeholders(&self.url, None, params);
+        if let Some(body) = &self.body {
+            let params: Vec<(String, String)> = params
+                .iter()
+                .map(|(name, value)| (name.clone(), self.escape_body_param(value)))
+                .collect();
+            self.body = Some(fill_placeholders(body, None, &params));
+        }
+        for selector in self.selectors.iter_mut() {
+            selector.path = fill_placeholders(&selector.path, None, params);
+        }
+        Ok(())
+    }
+
+    /// It returns the names of the `%name%` placeholders in the URL, the body and the selector
+    /// paths, without duplicates
+    pub fn named_params(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for part in self.texts().flat_map(|text| placeholder_parts(text)) {
+            if let TextPart::Named(name) = part {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// It stores the applied parameter, appending it to the already applied ones
+    fn add_param(&mut self, param: &str) {
+        self.param = match self.param.take() {
+            Some(applied) => Some(format!("{},{}", applied, param)),
+            None => Some(param.to_string()),
+        };
+    }
+
+    /// It returns the output name of the selector's value using the `key_template`
+    ///
+    /// Arguments:
+    ///
+    /// - `selector`: The name of the selector.
+    /// - `position`: The position of the resource in the config, starting from 1.
+    pub fn value_key(&self, selector: &str, position: usize) -> String {
+        let Some(template) = &self.key_template else {
+            return selector.to_string();
+        };
+
+        template
+            .replace("{selector}", selector)
+            .replace("{param}", self.param.as_deref().unwrap_or_default())
+            .replace("{resource}", &position.to_string())
+            .replace("{host}", url_host(&self.url))
+    }
+
+    /// It checks if the URL, the body or a selector path contains the [URL_PARAM_PLACEHOLDER]
+    pub fn needs_parameter(&self) -> bool {
+        self.texts()
+            .any(|text| placeholder_parts(text).contains(&TextPart::Param))
+    }
+
+    /// It iterates over the texts with the parameter placeholders: the URL, the body and the
+    /// selector paths
+    fn texts(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.url)
+            .chain(self.body.iter())
+            .chain(self.selectors.iter().map(|s| &s.path))
     }
 }
 
@@ -136,7 +1106,26 @@ impl fmt::Display for Resource {
             "\"{}\" with {} selectors",
             self.url,
             self.selectors.len()
-        )
+        )?;
+        if self.kind() != ResourceKind::Html {
+            write!(f, " ({})", self.kind())?;
+        }
+        match self.enabled {
+            true => Ok(()),
+            false => write!(f, " (disabled)"),
+        }
+    }
+}
+
+impl TryIndex<&Resource> for Vec<Resource> {
+    type Output = Resource;
+
+    fn try_get(&self, index: &Resource) -> Option<&Self::Output> {
+        self.iter().find(|r| *r == index)
+    }
+
+    fn try_get_mut(&mut self, index: &Resource) -> Option<&mut Self::Output> {
+        self.iter_mut().find(|r| *r == index)
     }
 }
 
@@ -145,12 +1134,7 @@ impl ops::Index<&Resource> for Vec<Resource> {
     type Output = Resource;
 
     fn index(&self, index: &Resource) -> &Self::Output {
-        for resource in self.iter() {
-            if resource == index {
-                return resource;
-            }
-        }
-        panic!("resource not found");
+        self.try_get(index).expect("resource not found")
     }
 }
 
@@ -158,26 +1142,545 @@ impl ops::Index<&Resource> for Vec<Resource> {
 // Requires the Index trait to be implemented.
 impl ops::IndexMut<&Resource> for Vec<Resource> {
     fn index_mut(&mut self, index: &Resource) -> &mut Self::Output {
-        for resource in self {
-            if resource == index {
-                return resource;
+        self.try_get_mut(index).expect("resource not found")
+    }
+}
+
+impl Position<&Resource> for Vec<Resource> {
+    fn try_position(&self, element: &Resource) -> Option<usize> {
+        self.iter().position(|r| r == element)
+    }
+}
+
+/// Settings shared by the resources with the same `group`, e.g. the pages of one site.
+///
+/// The resources inherit the settings they don't set themselves, see [Resource::inherit].
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Group {
+    pub name: String,
+    /// (Optional) Type of the documents of the resources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ResourceKind>,
+    /// Render the pages of the resources in a headless browser
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub render: bool,
+    /// (Optional) Template of the output names of the values, see [Resource::key_template]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_template: Option<String>,
+    /// (Optional) `Accept-Language` header of the requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_language: Option<String>,
+    /// Fetch the pages once more when a `required` selector is empty
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub refetch_on_miss: bool,
+    /// (Optional) HTTP method of the requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<Method>,
+    /// (Optional) `Content-Type` header of the request bodies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// (Optional) Authentication of the requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+    /// (Optional) Extra headers of the requests
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// (Optional) Maximum number of requests to the hosts of the resources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// (Optional) Cron expression of the runs of the resources in `rvp schedule`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    /// (Optional) Tags added to the tags of the resources
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether the resources of the group are fetched
+    #[serde(default = "enabled_by_default", skip_serializing_if = "is_true")]
+    pub enabled: bool,
+}
+
+impl Group {
+    /// Create a new group without any settings
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            kind: None,
+            render: false,
+            key_template: None,
+            accept_language: None,
+            refetch_on_miss: false,
+            method: None,
+            content_type: None,
+            auth: None,
+            headers: BTreeMap::new(),
+            rate_limit: None,
+            schedule: None,
+            tags: vec![],
+            enabled: true,
+        }
+    }
+}
+
+/// Maximum number of requests to the same host per time unit, e.g. `2/s`, `30/m` or `100/h`
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct RateLimit {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// It returns the minimal interval between two requests to the same host
+    pub fn interval(&self) -> Duration {
+        self.per / self.requests
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = RvpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            RvpError::ConfigError(format!(
+                "Invalid rate limit \"{}\", expected e.g. `2/s`, `30/m` or `100/h`!",
+                s
+            ))
+        };
+
+        let (requests, unit) = s.trim().split_once('/').ok_or_else(invalid)?;
+        let requests: u32 = requests.trim().parse().map_err(|_| invalid())?;
+        let per = match unit.trim() {
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            _ => return Err(invalid()),
+        };
+        if requests == 0 {
+            return Err(invalid());
+        }
+
+        Ok(Self { requests, per })
+    }
+}
+
+impl TryFrom<String> for RateLimit {
+    type Error = RvpError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<RateLimit> for String {
+    fn from(value: RateLimit) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.per.as_secs() {
+            60 => "m",
+            3600 => "h",
+            _ => "s",
+        };
+        write!(f, "{}/{}", self.requests, unit)
+    }
+}
+
+/// Comparison of an [AlertRule]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    /// It returns the operator of the comparison, e.g. `<=`
+    fn as_str(&self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        }
+    }
+}
+
+/// Alert threshold of a number, e.g. `< 100` or `>= 2.5`.
+///
+/// The threshold can be of the change of the value over a period instead, e.g.
+/// `change(price, 24h) > 5%`. The change is from the stored value of the history.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct AlertRule {
+    pub comparison: Comparison,
+    pub threshold: f64,
+    /// (Optional) The rule is about the change of the value, not the value itself
+    pub change: Option<Change>,
+}
+
+/// Change of a value over a period, `change(<name>, <period>)` of an [AlertRule]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Change {
+    /// Name of the value
+    pub name: String,
+    /// The value is compared with the newest stored value at least this old
+    pub period: Duration,
+    /// The threshold is in percent of the old value, e.g. `5%`
+    pub percent: bool,
+}
+
+impl Change {
+    /// It returns the change of the value from the old one, in percent if the rule is.
+    /// `None` if the old value is 0 and the change is in percent.
+    pub fn between(&self, old: f64, new: f64) -> Option<f64> {
+        match self.percent {
+            true if old == 0.0 => None,
+            true => Some((new - old) * 100.0 / old.abs()),
+            false => Some(new - old),
+        }
+    }
+}
+
+impl AlertRule {
+    /// It checks if the value crosses the threshold. For the `change(...)` rules, the value
+    /// is the change from [Change::between].
+    pub fn matches(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Less => value < self.threshold,
+            Comparison::LessOrEqual => value <= self.threshold,
+            Comparison::Greater => value > self.threshold,
+            Comparison::GreaterOrEqual => value >= self.threshold,
+            Comparison::Equal => value == self.threshold,
+            Comparison::NotEqual => value != self.threshold,
+        }
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = RvpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            RvpError::ConfigError(format!(
+                "Invalid alert \"{}\", expected e.g. `< 100`, `>= 2.5`, `!= 0` or `change(price, 24h) > 5%`!",
+                s
+            ))
+        };
+
+        let mut s = s.trim();
+        let mut change = None;
+        if let Some(rest) = s.strip_prefix("change(") {
+            let (arguments, rest) = rest.split_once(')').ok_or_else(invalid)?;
+            let (name, period) = arguments.split_once(',').ok_or_else(invalid)?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(invalid());
+            }
+            change = Some(Change {
+                name: name.to_string(),
+                period: parse_period(period.trim()).ok_or_else(invalid)?,
+                percent: false,
+            });
+            s = rest.trim();
+        }
+
+        // The two-character operators go first, so `<=` is not taken for `<`
+        let comparison = [
+            Comparison::LessOrEqual,
+            Comparison::GreaterOrEqual,
+            Comparison::Equal,
+            Comparison::NotEqual,
+            Comparison::Less,
+            Comparison::Greater,
+        ]
+        .into_iter()
+        .find(|c| s.starts_with(c.as_str()))
+        .ok_or_else(invalid)?;
+        let threshold = s[comparison.as_str().len()..].trim();
+        let threshold = match (threshold.strip_suffix('%'), change.as_mut()) {
+            (Some(percent), Some(change)) => {
+                change.percent = true;
+                percent.trim()
             }
+            // Only a change can be in percent
+            (Some(_), None) => return Err(invalid()),
+            (None, _) => threshold,
+        };
+        let threshold: f64 = threshold.parse().map_err(|_| invalid())?;
+        if !threshold.is_finite() {
+            return Err(invalid());
         }
-        panic!("resource not found");
+
+        Ok(Self {
+            comparison,
+            threshold,
+            change,
+        })
     }
 }
 
-impl Position<&Resource> for Vec<Resource> {
-    fn position(&self, element: &Resource) -> usize {
-        self.iter().position(|r| r == element).unwrap()
+/// It parses the period of a [Change], e.g. `90s`, `30m`, `24h` or `7d`
+fn parse_period(period: &str) -> Option<Duration> {
+    let unit = match period.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return None,
+    };
+    let number: u64 = period[..period.len() - 1].trim().parse().ok()?;
+    let seconds = number.checked_mul(unit)?;
+    (number > 0).then(|| Duration::from_secs(seconds))
+}
+
+impl TryFrom<String> for AlertRule {
+    type Error = RvpError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<AlertRule> for String {
+    fn from(value: AlertRule) -> Self {
+        value.to_string()
     }
 }
 
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(change) = &self.change else {
+            return write!(f, "{} {}", self.comparison.as_str(), self.threshold);
+        };
+        let seconds = change.period.as_secs();
+        let period = match seconds {
+            s if s % (24 * 60 * 60) == 0 => format!("{}d", s / (24 * 60 * 60)),
+            s if s % (60 * 60) == 0 => format!("{}h", s / (60 * 60)),
+            s if s % 60 == 0 => format!("{}m", s / 60),
+            s => format!("{}s", s),
+        };
+        let unit = if change.percent { "%" } else { "" };
+        write!(
+            f,
+            "change({}, {}) {} {}{}",
+            change.name,
+            period,
+            self.comparison.as_str(),
+            self.threshold,
+            unit
+        )
+    }
+}
+
+/// When a number is an anomaly compared to its previous values: its z-score against them,
+/// or its change from their median. A spike has to cross at least one of the limits.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct AnomalyRule {
+    /// Number of the previous values to compare with, [DEFAULT_ANOMALY_WINDOW] by default
+    #[serde(default = "AnomalyRule::default_window")]
+    pub window: usize,
+    /// (Optional) Largest z-score of a usual value, e.g. `3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z_score: Option<f64>,
+    /// (Optional) Largest change of a usual value from the median, in percent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jump: Option<f64>,
+    /// Report an alert for the anomalies, like the `alert` threshold
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub alert: bool,
+}
+
+impl AnomalyRule {
+    fn default_window() -> usize {
+        DEFAULT_ANOMALY_WINDOW
+    }
+}
+
+/// IANA timezone, e.g. `America/New_York` or `UTC`
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct Timezone(pub chrono_tz::Tz);
+
+impl Timezone {
+    pub const UTC: Timezone = Timezone(chrono_tz::Tz::UTC);
+}
+
+impl FromStr for Timezone {
+    type Err = RvpError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().parse() {
+            Ok(tz) => Ok(Self(tz)),
+            Err(_) => Err(RvpError::ConfigError(format!(
+                "Invalid timezone \"{}\", expected an IANA name like `America/New_York` or `UTC`!",
+                s
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for Timezone {
+    type Error = RvpError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Timezone> for String {
+    fn from(value: Timezone) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.name())
+    }
+}
+
+/// Trading hours of the market, the runs of `watch` are skipped outside of them
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct MarketHours {
+    /// Trading days, Monday to Friday by default
+    #[serde(default = "MarketHours::default_days")]
+    pub days: Vec<Weekday>,
+    /// (Optional) Opening time, e.g. `09:30`. The market is open all day if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<NaiveTime>,
+    /// (Optional) Closing time, e.g. `16:00`. Can be before the opening time for the overnight
+    /// sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close: Option<NaiveTime>,
+    /// (Optional) Dates when the market is closed, e.g. `2024-12-25`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl MarketHours {
+    fn default_days() -> Vec<Weekday> {
+        vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ]
+    }
+
+    /// It checks if the market is open at the given local time of the market
+    pub fn is_open(&self, now: NaiveDateTime) -> bool {
+        if !self.days.contains(&now.weekday()) || self.holidays.contains(&now.date()) {
+            return false;
+        }
+
+        let time = now.time();
+        match (self.open, self.close) {
+            (Some(open), Some(close)) if close < open => time >= open || time < close,
+            (open, close) => {
+                open.map_or(true, |open| time >= open) && close.map_or(true, |close| time < close)
+            }
+        }
+    }
+}
+
+/// A value computed from the parsed values, e.g. `change = price - prev_price`.
+///
+/// The expression is an arithmetic (`+`, `-`, `*`, `/` and parentheses) of numbers and names
+/// of the values, see [compute](crate::compute).
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Computed {
+    /// Name of the value in the output
+    pub name: String,
+    /// The expression, e.g. `(high + low) / 2`
+    pub expr: String,
+}
+
+/// Defaults of the config, `[defaults.selector]`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    /// (Optional) Fields of all selectors, see [SelectorDefaults]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<SelectorDefaults>,
+}
+
+/// The fields set on all [Selector]s of the config (and their `children`), unless a selector
+/// sets them itself
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SelectorDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parsed_type: Option<SelectorType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex_group: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transforms: Option<Vec<Transform>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truthy_values: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_of_range: Option<OutOfRange>,
+}
+
 // A config is a list of resources
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     pub name: String,
-    description: String,
+    pub description: String,
+    /// (Optional) Maximum number of requests to the same host, e.g. `2/s`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// (Optional) Regexes of the secrets to hide in the logs, the audit log and the error
+    /// messages, e.g. `token=([^&]+)`. Only the first capture group is hidden if there is one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<String>,
+    /// (Optional) Timezone of the dates in the output, e.g. `America/New_York`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Timezone>,
+    /// (Optional) Trading hours in the `timezone` of the config, `watch` skips the runs
+    /// outside of them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_hours: Option<MarketHours>,
+    /// Skip the URLs disallowed by the `robots.txt` of their hosts, the same as
+    /// `--respect-robots`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub respect_robots: bool,
+    /// (Optional) Values computed from the parsed values at output time, see [Computed]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub computed: Vec<Computed>,
+    /// (Optional) Output of `batch` for this config, used without `--json`, `--csv` and
+    /// `--table`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+    /// (Optional) File to write the output of `batch` to, used without `--output`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<PathBuf>,
+    /// (Optional) Webhook to send the results to, used without `--webhook`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    /// (Optional) Settings shared by the resources of each group, see [Group]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group>,
+    /// (Optional) Defaults of the selectors, see [Defaults].
+    ///
+    /// They are set on the selectors when the config is parsed, and the selector fields equal
+    /// to them are not written when the config is saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<Defaults>,
     pub resources: Vec<Resource>,
 }
 
@@ -187,6 +1690,17 @@ impl Config {
         Self {
             name,
             description,
+            rate_limit: None,
+            redact: vec![],
+            timezone: None,
+            market_hours: None,
+            respect_robots: false,
+            computed: vec![],
+            output: None,
+            output_file: None,
+            webhook: None,
+            groups: vec![],
+            defaults: None,
             resources,
         }
     }
@@ -202,80 +1716,467 @@ impl Config {
     ///
     /// A [Result<Self>]
     pub fn from_file(path: &Path, cf: &ConfigFormat) -> Result<Self> {
-        let data = fs::read_to_string(path)?;
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(RvpError::ConfigError(format!(
+                    "Failed reading config \"{}\": {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        Self::from_data(&data, cf)
+    }
+
+    /// Parses the [Config] from the content of a config file in the specified format
+    pub fn from_data(data: &str, cf: &ConfigFormat) -> Result<Self> {
         match cf {
-            ConfigFormat::Toml => Self::from_toml(&data),
-            ConfigFormat::Json => Self::from_json(&data),
+            ConfigFormat::Toml => Self::from_toml(data),
+            ConfigFormat::Json => Self::from_json(data),
+        }
+    }
+
+    /// Saves the [Config] structure to the given file in the specified format.
+    ///
+    /// Missing parent directories are created.
+    ///
+    /// Arguments:
+    ///
+    /// - `path`: The path to the file to save the config to.
+    /// - `cf`: [ConfigFormat] - This is the format that you want to save the config in.
+    ///
+    /// Returns:
+    ///
+    /// A path to the saved config [Result<PathBuf>]
+    pub fn save(&self, path: &Path, cf: &ConfigFormat) -> Result<PathBuf> {
+        let data = match cf {
+            ConfigFormat::Toml => self.to_toml(),
+            ConfigFormat::Json => self.to_json(),
+        };
+
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                if !parent.as_os_str().is_empty() {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            fs::write(path, data)
+        };
+        if let Err(e) = write() {
+            return Err(RvpError::ConfigError(format!(
+                "Failed saving config \"{}\": {}",
+                path.display(),
+                e
+            )));
         }
+
+        Ok(path.to_path_buf())
     }
 
-    /// Saves the [Config] structure to a file with the given name and specified format.
+    /// It returns the full path of the config file in the given directory
     ///
     /// Arguments:
     ///
+    /// - `dir`: The directory to save the config in.
     /// - `cf`: [ConfigFormat] - This is the format that you want to save the config in.
     ///
     /// Returns:
     ///
-    /// A path to the saved config [Result<PathBuf, std::io::Error>]
-    pub fn save(&self, cf: &ConfigFormat) -> Result<PathBuf> {
-        let data = match cf {
-            ConfigFormat::Toml => self.to_toml(),
-            ConfigFormat::Json => self.to_json(),
+    /// A [PathBuf]
+    pub fn get_full_path(&self, dir: &Path, cf: &ConfigFormat) -> PathBuf {
+        dir.join(format!("{}.{}", self.name, cf.extension()))
+    }
+
+    /// It checks if the config resources need parameters
+    pub fn needs_parameters(&self) -> bool {
+        self.resources.iter().any(|r| r.needs_parameter())
+    }
+
+    /// It checks if the market of the config is open at the given time.
+    ///
+    /// Always `true` if the config has no `market_hours`. The hours are in the `timezone`
+    /// of the config, UTC by default.
+    pub fn is_market_open(&self, now: DateTime<Utc>) -> bool {
+        let Some(market_hours) = &self.market_hours else {
+            return true;
         };
+        let timezone = self.timezone.unwrap_or(Timezone::UTC);
+        market_hours.is_open(now.with_timezone(&timezone.0).naive_local())
+    }
+
+    /// It applies the settings of the [Group]s to their resources.
+    ///
+    /// Fails if a resource refers to a group which is not defined.
+    pub fn apply_groups(&mut self) -> Result<()> {
+        for resource in self.resources.iter_mut() {
+            let Some(name) = &resource.group else {
+                continue;
+            };
+            let Some(group) = self.groups.iter().find(|g| &g.name == name) else {
+                return Err(RvpError::ConfigError(format!(
+                    "Group \"{}\" of the resource {} is not defined!",
+                    name, resource.url
+                )));
+            };
+            resource.inherit(group);
+        }
+        Ok(())
+    }
+
+    /// It sets the timezone of the parsed dates for all resources, the `timezone` of the config
+    /// is used if `None` is given
+    pub fn apply_timezone(&mut self, timezone: Option<Timezone>) {
+        let timezone = timezone.or(self.timezone);
+        for resource in self.resources.iter_mut() {
+            resource.timezone = timezone;
+        }
+    }
+
+    /// It returns the names of the `%name%` placeholders of all resources, without duplicates
+    pub fn named_params(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for name in self.resources.iter().flat_map(|r| r.named_params()) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
 
-        let full_path = self.get_full_path(cf);
-        fs::write(full_path.clone(), data)?;
+    /// It replaces the named `%name%` placeholders of all resources with the given values.
+    ///
+    /// Fails if a placeholder has no value or a value is given for an unknown name.
+    pub fn apply_named_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        let names = self.named_params();
+        if let Some((unknown, _)) = params
+            .iter()
+            .find(|(name, _)| !names.contains(&name.as_str()))
+        {
+            return Err(RvpError::ConfigError(format!(
+                "Unknown parameter \"{}\", the config has: {}!",
+                unknown,
+                match names.is_empty() {
+                    true => "no named parameters".to_string(),
+                    false => names.join(", "),
+                }
+            )));
+        }
+
+        for resource in self.resources.iter_mut() {
+            resource.mut_url_with_named_params(params)?;
+        }
+        Ok(())
+    }
+
+    /// It removes the disabled resources and returns their number. Called after the
+    /// positional parameters are applied, so they match the resources in the file.
+    pub fn remove_disabled(&mut self) -> usize {
+        let count = self.resources.len();
+        self.resources.retain(|r| r.enabled);
+        count - self.resources.len()
+    }
 
-        Ok(full_path)
+    /// It removes the resources without any of the tags and returns their number, nothing is
+    /// removed for no tags
+    pub fn remove_untagged(&mut self, tags: &[String]) -> usize {
+        let count = self.resources.len();
+        self.resources.retain(|r| r.has_any_tag(tags));
+        count - self.resources.len()
     }
 
-    /// It returns the full path of the config file
+    /// It replaces the parameter placeholders in the resource URLs with the given [Params]
     ///
     /// Arguments:
     ///
-    /// - `cf`: [ConfigFormat] - This is the format that you want to save the config in.
+    /// - `params`: [Params] - The parameters to pass to the resources.
     ///
     /// Returns:
     ///
-    /// A [PathBuf]
-    pub fn get_full_path(&self, cf: &ConfigFormat) -> PathBuf {
-        let file_name = match cf {
-            ConfigFormat::Toml => format!("{}.toml", self.name),
-            ConfigFormat::Json => format!("{}.json", self.name),
-        };
+    /// A [Result<()>], the error is returned if the parameters don't fit the resources.
+    pub fn apply_params(&mut self, params: &Params) -> Result<()> {
+        if !self.needs_parameters() {
+            return Ok(());
+        }
 
-        Path::new(&env::current_dir().unwrap()).join(file_name)
-    }
+        match params {
+            Params::None => Err(RvpError::ConfigError(
+                "This config needs parameters!".to_string(),
+            )),
+            Params::One(param) => {
+                for resource in self.resources.iter_mut() {
+                    resource.mut_url_with_param(param);
+                }
+                Ok(())
+            }
+            Params::PerResource(params) | Params::Repeat(params) if params.is_empty() => Err(
+                RvpError::ConfigError("You need to specify at least one parameter!".to_string()),
+            ),
+            Params::PerResource(params) => {
+                if self.resources.len() != params.len() {
+                    return Err(RvpError::ConfigError(format!(
+                        "The number of parameters does not match the number of resources ({})!",
+                        self.resources.len()
+                    )));
+                }
 
-    /// It checks if the config resources need parameters
-    pub fn needs_parameters(&self) -> bool {
-        self.resources.iter().any(|r| r.needs_parameter())
+                // Mutate resource urls with params
+                for (i, param) in params.iter().enumerate() {
+                    self.resources[i].mut_url_with_param(param);
+                }
+                Ok(())
+            }
+            Params::Repeat(params) => {
+                // Duplicate resources for each param and mutate their urls with the param
+                let mut duplicated_resources: Vec<Resource> = Vec::default();
+                for param in params.iter() {
+                    for r in self.resources.iter() {
+                        let mut r = r.clone();
+                        r.mut_url_with_param(param);
+                        // The resources without the placeholder are repeated for the param too
+                        if r.param.is_none() {
+                            r.param = Some(param.clone());
+                        }
+                        duplicated_resources.push(r);
+                    }
+                }
+                self.resources = duplicated_resources;
+                Ok(())
+            }
+        }
     }
 
-    /// Convert config to TOML string
-    fn to_toml(&self) -> String {
-        toml::to_string(&self).unwrap_or("".to_string())
+    /// Convert config to TOML string, without the selector fields equal to
+    /// `[defaults.selector]`
+    pub fn to_toml(&self) -> String {
+        let defaults = self.selector_defaults();
+        let Some(defaults) = defaults.and_then(|d| toml::Table::try_from(d).ok()) else {
+            return toml::to_string(&self).unwrap_or("".to_string());
+        };
+        let Ok(mut table) = toml::Table::try_from(self) else {
+            return "".to_string();
+        };
+        let unwritten = toml::Table::try_from(unwritten_selector_fields()).unwrap_or_default();
+        let resources = table
+            .get_mut("resources")
+            .and_then(toml::Value::as_array_mut);
+        for resource in resources.into_iter().flatten() {
+            if let Some(selectors) = resource
+                .get_mut("selectors")
+                .and_then(toml::Value::as_array_mut)
+            {
+                strip_toml_selectors(selectors, &defaults, &unwritten);
+            }
+        }
+        toml::to_string(&table).unwrap_or("".to_string())
     }
 
-    /// Convert config to JSON string
+    /// Convert config to JSON string, the same as [Config::to_toml]
     fn to_json(&self) -> String {
-        serde_json::to_string(&self).unwrap_or("".to_string())
+        let defaults = self.selector_defaults();
+        let Some(serde_json::Value::Object(defaults)) =
+            defaults.and_then(|d| serde_json::to_value(d).ok())
+        else {
+            return serde_json::to_string(&self).unwrap_or("".to_string());
+        };
+        let Ok(mut value) = serde_json::to_value(self) else {
+            return "".to_string();
+        };
+        let unwritten = match serde_json::to_value(unwritten_selector_fields()) {
+            Ok(serde_json::Value::Object(unwritten)) => unwritten,
+            _ => serde_json::Map::new(),
+        };
+        let resources = value
+            .get_mut("resources")
+            .and_then(serde_json::Value::as_array_mut);
+        for resource in resources.into_iter().flatten() {
+            if let Some(selectors) = resource
+                .get_mut("selectors")
+                .and_then(serde_json::Value::as_array_mut)
+            {
+                strip_json_selectors(selectors, &defaults, &unwritten);
+            }
+        }
+        serde_json::to_string(&value).unwrap_or("".to_string())
+    }
+
+    /// It returns the `[defaults.selector]` of the config, if it sets any field
+    fn selector_defaults(&self) -> Option<&SelectorDefaults> {
+        self.defaults
+            .as_ref()
+            .and_then(|defaults| defaults.selector.as_ref())
+            .filter(|selector| **selector != SelectorDefaults::default())
     }
 
     /// Parse a TOML string into a [Config]
     fn from_toml(data: &str) -> Result<Self> {
-        match toml::from_str(data) {
-            Ok(config) => Ok(config),
-            Err(e) => Err(anyhow!("Failed parsing TOML config: {}", e)),
+        let error = |e: toml::de::Error| {
+            RvpError::ConfigError(format!("Failed parsing TOML config: {}", e))
+        };
+        let mut table: toml::Table = toml::from_str(data).map_err(error)?;
+        let defaults = table
+            .get("defaults")
+            .and_then(|defaults| defaults.get("selector"))
+            .and_then(toml::Value::as_table)
+            .cloned();
+        // Parsed from the text when possible, as its errors point to the line
+        let Some(defaults) = defaults else {
+            return toml::from_str(data).map_err(error);
+        };
+        let resources = table
+            .get_mut("resources")
+            .and_then(toml::Value::as_array_mut);
+        for resource in resources.into_iter().flatten() {
+            if let Some(selectors) = resource
+                .get_mut("selectors")
+                .and_then(toml::Value::as_array_mut)
+            {
+                fill_toml_selectors(selectors, &defaults);
+            }
         }
+        toml::Value::Table(table).try_into().map_err(error)
     }
 
     /// Parse a JSON string into a [Config]
     fn from_json(data: &str) -> Result<Self> {
-        match serde_json::from_str(data) {
-            Ok(config) => Ok(config),
-            Err(e) => Err(anyhow!("Failed parsing JSON config: {}", e)),
+        let error = |e: serde_json::Error| {
+            RvpError::ConfigError(format!("Failed parsing JSON config: {}", e))
+        };
+        let mut value: serde_json::Value = serde_json::from_str(data).map_err(error)?;
+        let defaults = value
+            .pointer("/defaults/selector")
+            .and_then(serde_json::Value::as_object)
+            .cloned();
+        let Some(defaults) = defaults else {
+            return serde_json::from_str(data).map_err(error);
+        };
+        let resources = value
+            .get_mut("resources")
+            .and_then(serde_json::Value::as_array_mut);
+        for resource in resources.into_iter().flatten() {
+            if let Some(selectors) = resource
+                .get_mut("selectors")
+                .and_then(serde_json::Value::as_array_mut)
+            {
+                fill_json_selectors(selectors, &defaults);
+            }
+        }
+        serde_json::from_value(value).map_err(error)
+    }
+}
+
+/// The selector fields which are not written when they have these values, see
+/// [strip_toml_selectors]
+fn unwritten_selector_fields() -> SelectorDefaults {
+    SelectorDefaults {
+        transforms: Some(vec![]),
+        required: Some(false),
+        out_of_range: Some(OutOfRange::default()),
+        ..Default::default()
+    }
+}
+
+/// It sets the fields of the `[defaults.selector]` which the selectors of the TOML config
+/// don't set, in the nested `children` too
+fn fill_toml_selectors(selectors: &mut [toml::Value], defaults: &toml::Table) {
+    for selector in selectors.iter_mut().filter_map(toml::Value::as_table_mut) {
+        for (key, value) in defaults {
+            if !selector.contains_key(key) {
+                selector.insert(key.clone(), value.clone());
+            }
+        }
+        if let Some(children) = selector
+            .get_mut("children")
+            .and_then(toml::Value::as_array_mut)
+        {
+            fill_toml_selectors(children, defaults);
+        }
+    }
+}
+
+/// The same as [fill_toml_selectors] for the JSON config
+fn fill_json_selectors(
+    selectors: &mut [serde_json::Value],
+    defaults: &serde_json::Map<String, serde_json::Value>,
+) {
+    for selector in selectors
+        .iter_mut()
+        .filter_map(serde_json::Value::as_object_mut)
+    {
+        for (key, value) in defaults {
+            if !selector.contains_key(key) {
+                selector.insert(key.clone(), value.clone());
+            }
+        }
+        if let Some(children) = selector
+            .get_mut("children")
+            .and_then(serde_json::Value::as_array_mut)
+        {
+            fill_json_selectors(children, defaults);
+        }
+    }
+}
+
+/// It removes the fields of the TOML selectors equal to the `[defaults.selector]`, in the
+/// nested `children` too, so the defaults are not repeated on each selector. The `unwritten`
+/// fields the selector doesn't have are written when the defaults differ from them, so the
+/// config is parsed back the same.
+fn strip_toml_selectors(
+    selectors: &mut [toml::Value],
+    defaults: &toml::Table,
+    unwritten: &toml::Table,
+) {
+    for selector in selectors.iter_mut().filter_map(toml::Value::as_table_mut) {
+        for (key, default) in defaults {
+            match selector.get(key) {
+                Some(value) if value == default => {
+                    selector.remove(key);
+                }
+                None => {
+                    if let Some(value) = unwritten.get(key) {
+                        selector.insert(key.clone(), value.clone());
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(children) = selector
+            .get_mut("children")
+            .and_then(toml::Value::as_array_mut)
+        {
+            strip_toml_selectors(children, defaults, unwritten);
+        }
+    }
+}
+
+/// The same as [strip_toml_selectors] for the JSON config
+fn strip_json_selectors(
+    selectors: &mut [serde_json::Value],
+    defaults: &serde_json::Map<String, serde_json::Value>,
+    unwritten: &serde_json::Map<String, serde_json::Value>,
+) {
+    for selector in selectors
+        .iter_mut()
+        .filter_map(serde_json::Value::as_object_mut)
+    {
+        for (key, default) in defaults {
+            match selector.get(key) {
+                Some(value) if value == default => {
+                    selector.remove(key);
+                }
+                None => {
+                    if let Some(value) = unwritten.get(key) {
+                        selector.insert(key.clone(), value.clone());
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(children) = selector
+            .get_mut("children")
+            .and_then(serde_json::Value::as_array_mut)
+        {
+            strip_json_selectors(children, defaults, unwritten);
         }
     }
 }
@@ -299,7 +2200,13 @@ mod tests {
         assert_eq!(selectors[1].path, "test2");
 
         // Test position
-        assert_eq!(selectors.position(&s1), 1);
+        assert_eq!(selectors.try_position(&s1), Some(1));
+
+        // Test fallible lookups
+        let missing = Selector::new("none".to_string(), "none".to_string(), SelectorType::String);
+        assert_eq!(selectors.try_position(&missing), None);
+        assert!(selectors.try_get(&missing).is_none());
+        assert_eq!(selectors.try_get(&s0).unwrap().name, "test");
 
         // Test the Index trait
         assert_eq!(selectors[0].name, selectors[0].name);
@@ -312,7 +2219,7 @@ mod tests {
     }
 
     #[test]
-    fn test_resource() {
+    fn test_resource() -> Result<()> {
         let s0 = Selector::new("test".to_string(), "test".to_string(), SelectorType::String);
         let s1 = Selector::new(
             "test2".to_string(),
@@ -331,7 +2238,12 @@ mod tests {
         assert_eq!(resources[1].selectors[0].name, "test");
 
         // Test position
-        assert_eq!(resources.position(&r1), 1);
+        assert_eq!(resources.try_position(&r1), Some(1));
+
+        // Test fallible lookups
+        let missing = Resource::new("https://none.com".to_string(), vec![]);
+        assert_eq!(resources.try_position(&missing), None);
+        assert!(resources.try_get(&missing).is_none());
 
         // Test the Index trait
         assert_eq!(resources[&r0].url, resources[0].url);
@@ -350,9 +2262,562 @@ mod tests {
         r2.mut_url_with_param("test");
         assert_eq!(r2.url, "https://test.com/?id=test");
 
+        // The placeholders in the body are replaced as well
+        let mut r3 = r1.clone();
+        r3.method = Some(Method::Post);
+        r3.body = Some(r#"{"symbol": "%%", "date": "%date%"}"#.to_string());
+        r3.content_type = Some("application/json".to_string());
+        assert!(r3.needs_parameter());
+        assert_eq!(r3.named_params(), vec!["date"]);
+        r3.mut_url_with_param("AAPL");
+        r3.mut_url_with_named_params(&[("date".to_string(), "2024-05-01".to_string())])?;
+        let request = r3.request();
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(
+            request.body.as_deref(),
+            Some(r#"{"symbol": "AAPL", "date": "2024-05-01"}"#)
+        );
+        assert_eq!(
+            request.headers,
+            vec![("Content-Type".to_string(), "application/json".to_string())]
+        );
+
+        // The parameters are escaped in a JSON or form body, but not in the URL
+        let mut r4 = r1.clone();
+        r4.url = "https://test2.com/%%".to_string();
+        r4.body = Some(r#"{"symbol": "%%", "date": "%date%"}"#.to_string());
+        r4.content_type = Some("application/json; charset=utf-8".to_string());
+        r4.mut_url_with_param(r#"A"B\C"#);
+        r4.mut_url_with_named_params(&[("date".to_string(), "\"\n".to_string())])?;
+        assert_eq!(r4.url, r#"https://test2.com/A"B\C"#);
+        assert_eq!(
+            r4.body.as_deref(),
+            Some(r#"{"symbol": "A\"B\\C", "date": "\"\n"}"#)
+        );
+        let mut r5 = r1.clone();
+        r5.body = Some("symbol=%%&date=%date%".to_string());
+        r5.content_type = Some("application/x-www-form-urlencoded; charset=utf-8".to_string());
+        r5.mut_url_with_param("A&B=1");
+        r5.mut_url_with_named_params(&[("date".to_string(), "1 Jan+é".to_string())])?;
+        assert_eq!(
+            r5.body.as_deref(),
+            Some("symbol=A%26B%3D1&date=1+Jan%2B%C3%A9")
+        );
+        let mut r6 = r1.clone();
+        r6.body = Some("symbol=%%".to_string());
+        r6.content_type = Some("text/plain".to_string());
+        r6.mut_url_with_param("A&B");
+        assert_eq!(r6.body.as_deref(), Some("symbol=A&B"));
+        assert_eq!(
+            request.link("https://test2.com/2"),
+            Request::get("https://test2.com/2")
+        );
+
         // Test needs_parameter
         assert!(r0.needs_parameter());
         assert!(!r1.needs_parameter());
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(url_host("https://api.com:8443/quote?q=1"), "api.com");
+        assert_eq!(url_host("https://api.com:x@evil.com/p"), "evil.com");
+        assert_eq!(url_host("https://user@api.com"), "api.com");
+        assert_eq!(url_host("http://[::1]:8080/"), "[::1]");
+        assert_eq!(url_host("api.com/quote"), "api.com");
+    }
+
+    #[test]
+    fn test_auth() -> Result<()> {
+        let value = |name: &str| match name {
+            "TOKEN" => Some("secret".to_string()),
+            _ => None,
+        };
+        assert_eq!(fill_env_vars("Bearer ${TOKEN}", value)?, "Bearer secret");
+        assert_eq!(
+            fill_env_vars("$5 ${ not a var}", value)?,
+            "$5 ${ not a var}"
+        );
+        assert!(fill_env_vars("${MISSING}", value).is_err());
+
+        let config = r#"
+            name = "test"
+            description = ""
+
+            [[resources]]
+            url = "https://api.com/quote"
+            selectors = []
+
+            [resources.auth.basic]
+            user = "rvp"
+            pass = "${TOKEN}"
+        "#;
+        let config = Config::from_data(config, &ConfigFormat::Toml)?;
+        let auth = config.resources[0].auth.clone().unwrap();
+        assert_eq!(
+            auth.fill(value)?,
+            Auth::Basic {
+                user: "rvp".to_string(),
+                pass: "secret".to_string(),
+            }
+        );
+
+        // The auth is not sent to the other origins
+        let request = config.resources[0].request();
+        assert_eq!(
+            request.link("https://api.com/next").auth,
+            Some(auth.clone())
+        );
+        assert_eq!(request.link("https://API.com:443/next").auth, Some(auth));
+        assert_eq!(request.link("https://cdn.com/next").auth, None);
+        assert_eq!(request.link("https://api.com:x@evil.com/p").auth, None);
+        assert_eq!(request.link("http://api.com/next").auth, None);
+        assert_eq!(request.link("https://api.com:8443/next").auth, None);
+        assert_eq!(request.link("/next").auth, None);
+
+        // So are the credential headers
+        let mut resource = config.resources[0].clone();
+        resource.headers = [
+            ("Cookie".to_string(), "session=1".to_string()),
+            ("X-Client".to_string(), "rvp".to_string()),
+        ]
+        .into();
+        let request = resource.request();
+        assert_eq!(
+            request.link("https://api.com/next").headers,
+            request.headers
+        );
+        assert_eq!(
+            request.link("https://cdn.com/next").headers,
+            vec![("X-Client".to_string(), "rvp".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_alert_rule() -> Result<()> {
+        let rule: AlertRule = "<= 100".parse()?;
+        assert_eq!(rule.comparison, Comparison::LessOrEqual);
+        assert!(rule.matches(100.0));
+        assert!(!rule.matches(100.5));
+        assert_eq!(rule.to_string(), "<= 100");
+        assert!("!=0".parse::<AlertRule>()?.matches(-1.5));
+        assert!(">2.5".parse::<AlertRule>()?.matches(3.0));
+
+        assert!("100".parse::<AlertRule>().is_err());
+        assert!("< cheap".parse::<AlertRule>().is_err());
+        assert!("=> 1".parse::<AlertRule>().is_err());
+        assert!("< 5%".parse::<AlertRule>().is_err());
+
+        let change: AlertRule = "change(price, 24h) > 5%".parse()?;
+        assert_eq!(
+            change.change,
+            Some(Change {
+                name: "price".to_string(),
+                period: Duration::from_secs(24 * 60 * 60),
+                percent: true,
+            })
+        );
+        assert_eq!(change.to_string(), "change(price, 1d) > 5%");
+        let drop: AlertRule = "change( price ,90m)<=-2".parse()?;
+        assert_eq!(drop.to_string(), "change(price, 90m) <= -2");
+        let percent = change.change.as_ref().unwrap();
+        assert_eq!(percent.between(100.0, 106.0), Some(6.0));
+        assert_eq!(percent.between(0.0, 1.0), None);
+        assert_eq!(
+            drop.change.as_ref().unwrap().between(100.0, 97.5),
+            Some(-2.5)
+        );
+        assert!("change(price) > 5%".parse::<AlertRule>().is_err());
+        assert!("change(price, 0h) > 5%".parse::<AlertRule>().is_err());
+        assert!("change(price, 1w) > 5%".parse::<AlertRule>().is_err());
+        assert!("change(price, 18446744073709551615d) > 5%"
+            .parse::<AlertRule>()
+            .is_err());
+
+        let mut selector =
+            Selector::new("h1".to_string(), "price".to_string(), SelectorType::Number);
+        selector.alert = Some("< 100".parse()?);
+        selector.alert_above = Some(200.0);
+        assert_eq!(selector.alert_rules().len(), 2);
+
+        let config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![Resource::new(
+                "https://example.com".to_string(),
+                vec![selector],
+            )],
+        );
+        let toml = config.to_toml();
+        assert!(toml.contains("alert = \"< 100\""));
+        assert!(toml.contains("alert_above = 200.0"));
+        assert!(
+            Config::from_toml(&toml)?.resources[0].selectors[0].alert == Some("< 100".parse()?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_transform() -> Result<()> {
+        assert_eq!("trim".parse::<Transform>()?, Transform::Trim);
+        assert_eq!(
+            r#"replace: "a" -> "b""#.parse::<Transform>()?,
+            Transform::Replace {
+                from: "a".to_string(),
+                to: "b".to_string()
+            }
+        );
+        assert_eq!(
+            r#"split:","[-1]"#.parse::<Transform>()?,
+            Transform::Split {
+                separator: ",".to_string(),
+                index: -1
+            }
+        );
+        for transform in [
+            "lowercase",
+            r#"strip_prefix:"$""#,
+            r#"strip_suffix:"\"""#,
+            r#"replace:"\\"->"/""#,
+            r#"split:" "[2]"#,
+        ] {
+            assert_eq!(transform.parse::<Transform>()?.to_string(), transform);
+        }
+        for invalid in [
+            "reverse",
+            "trim:\"a\"",
+            "strip_prefix",
+            "strip_prefix:$",
+            r#"strip_prefix:"$"x"#,
+            r#"replace:"a""#,
+            r#"replace:""->"b""#,
+            r#"split:","#,
+            r#"split:","[first]"#,
+            r#"split:""[0]"#,
+        ] {
+            assert!(invalid.parse::<Transform>().is_err(), "{}", invalid);
+        }
+
+        let mut selector =
+            Selector::new("p".to_string(), "price".to_string(), SelectorType::Number);
+        selector.transforms = vec!["trim".parse()?, r#"strip_prefix:"$""#.parse()?];
+        let mut config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![Resource::new(
+                "https://test.com".to_string(),
+                vec![selector],
+            )],
+        );
+        let transforms = config.resources[0].selectors[0].transforms.clone();
+        config = Config::from_toml(&config.to_toml())?;
+        assert_eq!(config.resources[0].selectors[0].transforms, transforms);
+        Ok(())
+    }
+
+    #[test]
+    fn test_selector_defaults() -> Result<()> {
+        let config = Config::from_toml(
+            r##"
+            name = "test"
+            description = ""
+
+            [defaults.selector]
+            parsed_type = "Number"
+            transforms = ["trim", 'replace:","->""']
+            required = true
+
+            [[resources]]
+            url = "https://example.com"
+
+            [[resources.selectors]]
+            path = "#price"
+            name = "price"
+
+            [[resources.selectors]]
+            path = "h1"
+            name = "title"
+            parsed_type = "String"
+            transforms = []
+
+            [[resources.selectors]]
+            path = "a"
+            name = "links"
+            parsed_type = "String"
+            follow = true
+
+            [[resources.selectors.children]]
+            path = "#volume"
+            name = "volume"
+            "##,
+        )?;
+        let selectors = &config.resources[0].selectors;
+        assert!(selectors[0].parsed_type == SelectorType::Number);
+        assert_eq!(selectors[0].transforms.len(), 2);
+        assert!(selectors[0].required);
+        assert!(selectors[1].parsed_type == SelectorType::String);
+        assert!(selectors[1].transforms.is_empty());
+        assert!(selectors[2].children[0].parsed_type == SelectorType::Number);
+
+        let config = Config::from_json(
+            r#"{
+                "name": "test",
+                "description": "",
+                "defaults": {"selector": {"parsed_type": "Number"}},
+                "resources": [{"url": "https://example.com", "selectors": [
+                    {"path": "/price", "name": "price"}
+                ]}]
+            }"#,
+        )?;
+        assert!(config.resources[0].selectors[0].parsed_type == SelectorType::Number);
+        assert!(Config::from_toml(&config.to_toml())?.defaults == config.defaults);
+
+        let unknown = r#"
+            name = "test"
+            description = ""
+            resources = []
+
+            [defaults.selector]
+            name = "price"
+            "#;
+        assert!(Config::from_toml(unknown).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_selector_defaults_are_not_saved() -> Result<()> {
+        let config = Config::from_toml(
+            r##"
+            name = "test"
+            description = ""
+
+            [defaults.selector]
+            parsed_type = "Number"
+            required = true
+            transforms = ["trim"]
+
+            [[resources]]
+            url = "https://example.com"
+
+            [[resources.selectors]]
+            path = "#price"
+            name = "price"
+
+            [[resources.selectors]]
+            path = "h1"
+            name = "title"
+            parsed_type = "String"
+            required = false
+            transforms = []
+            "##,
+        )?;
+        let toml = config.to_toml();
+        assert_eq!(toml.matches("parsed_type").count(), 2);
+        assert_eq!(toml.matches("required").count(), 2);
+        assert_eq!(toml.matches("transforms").count(), 2);
+
+        // The changed defaults apply to the selectors which don't set the fields
+        let toml = toml.replace("parsed_type = \"Number\"", "parsed_type = \"Date\"");
+        let config = Config::from_toml(&toml)?;
+        let selectors = &config.resources[0].selectors;
+        assert!(selectors[0].parsed_type == SelectorType::Date);
+        assert!(selectors[0].required);
+        assert!(selectors[1].parsed_type == SelectorType::String);
+        assert!(!selectors[1].required);
+        assert!(selectors[1].transforms.is_empty());
+        assert_eq!(
+            Config::from_json(&config.to_json())?.to_toml(),
+            config.to_toml()
+        );
+
+        // Without the defaults the fields are written as before
+        let toml = Config::new("test".to_string(), "".to_string(), config.resources).to_toml();
+        assert_eq!(toml.matches("parsed_type").count(), 2);
+        assert_eq!(toml.matches("required").count(), 1);
+        assert_eq!(toml.matches("transforms").count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limit() -> Result<()> {
+        let rate_limit: RateLimit = "2/s".parse()?;
+        assert_eq!(rate_limit.interval(), Duration::from_millis(500));
+        assert_eq!(rate_limit.to_string(), "2/s");
+        assert_eq!(
+            "30/m".parse::<RateLimit>()?.interval(),
+            Duration::from_secs(2)
+        );
+
+        assert!("0/s".parse::<RateLimit>().is_err());
+        assert!("2/d".parse::<RateLimit>().is_err());
+        assert!("fast".parse::<RateLimit>().is_err());
+
+        let mut config = Config::new("test".to_string(), "".to_string(), vec![]);
+        config.rate_limit = Some("100/h".parse()?);
+        let toml = config.to_toml();
+        assert!(toml.contains("rate_limit = \"100/h\""));
+        assert!(Config::from_toml(&toml)?.rate_limit == config.rate_limit);
+        Ok(())
+    }
+
+    #[test]
+    fn test_timezone() -> Result<()> {
+        let timezone: Timezone = "America/New_York".parse()?;
+        assert_eq!(timezone.to_string(), "America/New_York");
+        assert!("Mars/Olympus".parse::<Timezone>().is_err());
+
+        let mut config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![Resource::new("https://test.com".to_string(), vec![])],
+        );
+        config.timezone = Some(timezone);
+        let toml = config.to_toml();
+        assert!(toml.contains("timezone = \"America/New_York\""));
+        assert!(Config::from_toml(&toml)?.timezone == Some(timezone));
+
+        config.apply_timezone(None);
+        assert_eq!(config.resources[0].timezone, Some(timezone));
+        config.apply_timezone(Some(Timezone::UTC));
+        assert_eq!(config.resources[0].timezone, Some(Timezone::UTC));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_groups() -> Result<()> {
+        let mut config = Config::from_toml(
+            r#"
+            name = "test"
+            description = ""
+
+            [[groups]]
+            name = "nasdaq"
+            kind = "Json"
+            render = true
+            accept_language = "en-US"
+            key_template = "{param}_{selector}"
+            headers = { Referer = "https://nasdaq.com", X-Client = "rvp" }
+            rate_limit = "1/s"
+            schedule = "*/5 9-16 * * mon-fri"
+            tags = ["stocks", "nasdaq"]
+
+            [[groups]]
+            name = "broken"
+            enabled = false
+
+            [[resources]]
+            url = "https://example.com/quote/%%"
+            group = "nasdaq"
+            selectors = []
+
+            [[resources]]
+            url = "https://example.com/html/%%"
+            group = "nasdaq"
+            kind = "Html"
+            render = false
+            accept_language = "de-DE"
+            headers = { x-client = "html" }
+            rate_limit = "10/m"
+            schedule = "@hourly"
+            tags = ["html", "stocks"]
+            selectors = []
+
+            [[resources]]
+            url = "https://example.org/%%"
+            group = "broken"
+            selectors = []
+            "#,
+        )?;
+        assert!(Config::from_toml(&config.to_toml())?.groups == config.groups);
+
+        config.apply_groups()?;
+        let quote = &config.resources[0];
+        assert!(quote.kind() == ResourceKind::Json);
+        assert!(quote.render());
+        assert_eq!(quote.accept_language.as_deref(), Some("en-US"));
+        assert_eq!(quote.key_template.as_deref(), Some("{param}_{selector}"));
+        assert_eq!(quote.rate_limit, Some("1/s".parse()?));
+        assert_eq!(quote.schedule, Some("*/5 9-16 * * mon-fri".parse()?));
+        assert_eq!(quote.tags, vec!["stocks", "nasdaq"]);
+        assert!(quote.has_any_tag(&["nasdaq".to_string()]));
+        assert_eq!(
+            quote.request().headers,
+            vec![
+                ("Accept-Language".to_string(), "en-US".to_string()),
+                ("Referer".to_string(), "https://nasdaq.com".to_string()),
+                ("X-Client".to_string(), "rvp".to_string()),
+            ]
+        );
+        // The resource sets the defaults back
+        let html = &config.resources[1];
+        assert_eq!(html.accept_language.as_deref(), Some("de-DE"));
+        assert!(html.kind() == ResourceKind::Html);
+        assert!(!html.render());
+        assert_eq!(html.rate_limit, Some("10/m".parse()?));
+        assert_eq!(html.schedule, Some("@hourly".parse()?));
+        // The tags are joined, the headers of the resource win regardless of the case
+        assert_eq!(html.tags, vec!["html", "stocks", "nasdaq"]);
+        assert_eq!(
+            html.request().headers,
+            vec![
+                ("Accept-Language".to_string(), "de-DE".to_string()),
+                ("Referer".to_string(), "https://nasdaq.com".to_string()),
+                ("x-client".to_string(), "html".to_string()),
+            ]
+        );
+        assert!(!config.resources[2].enabled);
+        assert!(!config.resources[2].has_any_tag(&["stocks".to_string()]));
+        assert!(config.resources[2].has_any_tag(&[]));
+        assert_eq!(config.clone().remove_untagged(&["nasdaq".to_string()]), 1);
+
+        config.resources[0].group = Some("nyse".to_string());
+        assert!(config.apply_groups().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_market_hours() -> Result<()> {
+        let mut config = Config::from_toml(
+            r#"
+            name = "test"
+            description = ""
+            timezone = "America/New_York"
+            resources = []
+
+            [market_hours]
+            open = "09:30"
+            close = "16:00:00"
+            holidays = ["2024-12-25"]
+            "#,
+        )?;
+        let at = |time: &str| {
+            DateTime::parse_from_rfc3339(time)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+
+        // 10:30 in New York on Wednesday
+        assert!(config.is_market_open(at("2024-05-01T14:30:00Z")));
+        // 08:30 in New York
+        assert!(!config.is_market_open(at("2024-05-01T12:30:00Z")));
+        // Saturday
+        assert!(!config.is_market_open(at("2024-05-04T14:30:00Z")));
+        // Holiday
+        assert!(!config.is_market_open(at("2024-12-25T15:30:00Z")));
+
+        // Overnight session
+        let market_hours = config.market_hours.as_mut().unwrap();
+        market_hours.open = Some(NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+        market_hours.close = Some(NaiveTime::from_hms_opt(2, 0, 0).unwrap());
+        market_hours.days = vec![Weekday::Sun];
+        assert!(config.is_market_open(at("2024-05-05T23:30:00Z")));
+        assert!(!config.is_market_open(at("2024-05-05T14:30:00Z")));
+
+        config.market_hours = None;
+        assert!(config.is_market_open(at("2024-05-04T14:30:00Z")));
+        Ok(())
     }
 
     #[test]
@@ -379,4 +2844,223 @@ mod tests {
         // Test needs_parameters
         assert!(config.needs_parameters());
     }
+
+    #[test]
+    fn test_config_apply_params() -> Result<()> {
+        let r0 = Resource::new("https://test.com/?id=%%".to_string(), vec![]);
+        let r1 = Resource::new("https://test2.com/%%".to_string(), vec![]);
+        let config = Config::new("test".to_string(), "".to_string(), vec![r0, r1]);
+
+        assert!(config.clone().apply_params(&Params::None).is_err());
+        assert!(config
+            .clone()
+            .apply_params(&Params::PerResource(vec![]))
+            .is_err());
+        assert!(config
+            .clone()
+            .apply_params(&Params::PerResource(vec!["a".to_string()]))
+            .is_err());
+
+        let mut one = config.clone();
+        one.apply_params(&Params::One("a".to_string()))?;
+        assert_eq!(one.resources[0].url, "https://test.com/?id=a");
+        assert_eq!(one.resources[1].url, "https://test2.com/a");
+
+        let mut per_resource = config.clone();
+        per_resource.apply_params(&Params::PerResource(vec!["a".to_string(), "b".to_string()]))?;
+        assert_eq!(per_resource.resources[0].url, "https://test.com/?id=a");
+        assert_eq!(per_resource.resources[1].url, "https://test2.com/b");
+
+        let mut repeat = config.clone();
+        repeat.apply_params(&Params::Repeat(vec!["a".to_string(), "b".to_string()]))?;
+        let urls: Vec<&str> = repeat.resources.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://test.com/?id=a",
+                "https://test2.com/a",
+                "https://test.com/?id=b",
+                "https://test2.com/b"
+            ]
+        );
+
+        // Key templates use the applied parameter
+        let mut templated = config.clone();
+        templated.resources[0].key_template = Some("{param}_{selector}@{host}".to_string());
+        templated.resources[1].key_template = Some("{resource}.{selector}".to_string());
+        templated.apply_params(&Params::Repeat(vec!["a".to_string(), "b".to_string()]))?;
+        assert_eq!(
+            templated.resources[2].value_key("price", 3),
+            "b_price@test.com"
+        );
+        assert_eq!(templated.resources[1].value_key("price", 2), "2.price");
+        assert_eq!(config.resources[0].value_key("price", 1), "price");
+
+        // Named placeholders are kept by the positional parameters
+        let mut named = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![
+                Resource::new("https://test.com/%ticker%%%?d=%date%".to_string(), vec![]),
+                Resource::new("https://test.com/%ticker%?q=%E2%80%99".to_string(), vec![]),
+                Resource::new(
+                    "https://test.com/%C3%A9t%C3%A9?d=%date%".to_string(),
+                    vec![],
+                ),
+            ],
+        );
+        assert_eq!(named.named_params(), vec!["ticker", "date"]);
+        assert!(named.needs_parameters());
+        assert!(!named.resources[1].needs_parameter());
+        named.apply_params(&Params::One("a".to_string()))?;
+        assert_eq!(
+            named.resources[0].url,
+            "https://test.com/%ticker%a?d=%date%"
+        );
+        assert!(named
+            .clone()
+            .apply_named_params(&[("ticker".to_string(), "AAPL".to_string())])
+            .is_err());
+        assert!(named
+            .clone()
+            .apply_named_params(&[("symbol".to_string(), "AAPL".to_string())])
+            .is_err());
+        named.apply_named_params(&[
+            ("date".to_string(), "2024-01-01".to_string()),
+            ("ticker".to_string(), "AAPL".to_string()),
+        ])?;
+        assert_eq!(
+            named.resources[0].url,
+            "https://test.com/AAPLa?d=2024-01-01"
+        );
+        assert_eq!(
+            named.resources[0].param.as_deref(),
+            Some("a,AAPL,2024-01-01")
+        );
+        assert_eq!(named.resources[1].url, "https://test.com/AAPL?q=%E2%80%99");
+        assert_eq!(named.resources[1].param.as_deref(), Some("AAPL"));
+        // The percent-encoded text is not a placeholder
+        assert_eq!(
+            named.resources[2].url,
+            "https://test.com/%C3%A9t%C3%A9?d=2024-01-01"
+        );
+
+        // Selector paths get the parameters too
+        let mut paths = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![Resource::new(
+                "https://test.com/quotes".to_string(),
+                vec![
+                    Selector::new(
+                        "#row-%%-price".to_string(),
+                        "price".to_string(),
+                        SelectorType::Number,
+                    ),
+                    Selector::new(
+                        "$.%ticker%.volume".to_string(),
+                        "volume".to_string(),
+                        SelectorType::Number,
+                    ),
+                ],
+            )],
+        );
+        assert!(paths.needs_parameters());
+        assert_eq!(paths.named_params(), vec!["ticker"]);
+        paths.apply_params(&Params::One("AAPL".to_string()))?;
+        paths.apply_named_params(&[("ticker".to_string(), "MSFT".to_string())])?;
+        assert_eq!(paths.resources[0].selectors[0].path, "#row-AAPL-price");
+        assert_eq!(paths.resources[0].selectors[1].path, "$.MSFT.volume");
+        assert_eq!(paths.resources[0].url, "https://test.com/quotes");
+
+        // Configs without placeholders ignore the parameters
+        let mut static_config = Config::new("test".to_string(), "".to_string(), vec![]);
+        static_config.apply_params(&Params::None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_remove_disabled() -> Result<()> {
+        let mut config = Config::from_toml(
+            r#"
+            name = "test"
+            description = ""
+
+            [[resources]]
+            url = "https://test.com/%%"
+            selectors = []
+
+            [[resources]]
+            url = "https://test2.com/%%"
+            enabled = false
+            selectors = []
+            "#,
+        )?;
+        assert!(config.resources[0].enabled);
+        assert!(!config.resources[1].enabled);
+        assert_eq!(config.to_toml().matches("enabled").count(), 1);
+
+        // The positional parameters still count the disabled resources
+        config.apply_params(&Params::PerResource(vec!["a".to_string(), "b".to_string()]))?;
+        assert_eq!(config.remove_disabled(), 1);
+        assert_eq!(config.resources.len(), 1);
+        assert_eq!(config.resources[0].url, "https://test.com/a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_save() -> Result<()> {
+        let dir = std::env::temp_dir().join("rvp_test_config_save");
+        let _ = fs::remove_dir_all(&dir);
+
+        let config = Config::new("saved".to_string(), "".to_string(), vec![]);
+        let path = config.get_full_path(&dir.join("nested"), &ConfigFormat::Json);
+        assert!(path.ends_with("nested/saved.json"));
+
+        let saved = config.save(&path, &ConfigFormat::Json)?;
+        assert_eq!(ConfigFormat::from_path(&saved)?.extension(), "json");
+        assert_eq!(
+            Config::from_file(&saved, &ConfigFormat::Json)?.name,
+            "saved"
+        );
+
+        assert!(ConfigFormat::from_path(Path::new("config.yaml")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_selector_regex_serialization() -> Result<()> {
+        let config = Config::from_toml(
+            r#"
+            name = "test"
+            description = ""
+
+            [[resources]]
+            url = "https://test.com"
+
+            [[resources.selectors]]
+            path = "p"
+            name = "price"
+            parsed_type = "Number"
+            regex = '\$([\d.]+)'
+            regex_group = 1
+
+            [[resources.selectors]]
+            path = "h1"
+            name = "title"
+            parsed_type = "String"
+            "#,
+        )?;
+
+        let selectors = &config.resources[0].selectors;
+        assert_eq!(selectors[0].regex.as_deref(), Some(r"\$([\d.]+)"));
+        assert_eq!(selectors[0].regex_group, Some(1));
+        assert_eq!(selectors[1].regex, None);
+
+        // Empty optional fields are not written back
+        assert_eq!(config.to_toml().matches("regex").count(), 2);
+        Ok(())
+    }
 }
@@ -0,0 +1,240 @@
+//! Suggestions of the CSS selectors for the elements with the given text on a page
+use std::sync::OnceLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+
+/// Attributes which usually name the value and survive the redesigns of the page
+const NAMING_ATTRIBUTES: [&str; 5] = ["itemprop", "data-testid", "data-test", "data-field", "name"];
+
+/// A CSS selector for an element with the searched text
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub selector: String,
+    /// Number of the elements matched by the selector on the page, only the first one is
+    /// grabbed
+    pub matches: usize,
+    /// Text of the element, as it is grabbed by the selector
+    pub text: String,
+    /// How the selector is built, the lower the more stable: naming attributes, ids,
+    /// classes, and the positions of the elements last
+    stability: u8,
+}
+
+/// It finds the innermost elements containing the text (case-insensitive, with the spaces
+/// collapsed) and suggests the selectors which grab them, ranked by stability and specificity
+///
+/// Arguments:
+///
+/// - `html`: The HTML of the page.
+/// - `text`: The text of the value to find, e.g. `123.45`.
+///
+/// Returns:
+///
+/// The suggestions, the best first. Empty if no element contains the text.
+pub fn suggest(html: &str, text: &str) -> Vec<Suggestion> {
+    let document = Html::parse_document(html);
+    let needle = normalize(text);
+    if needle.is_empty() {
+        return vec![];
+    }
+
+    let contains = |element: &ElementRef| normalize(&element_text(element)).contains(&needle);
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for element in document
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        if !contains(&element) || element.child_elements().any(|child| contains(&child)) {
+            continue;
+        }
+        for (stability, selector) in candidates(&element) {
+            let Ok(parsed) = Selector::parse(&selector) else {
+                continue;
+            };
+            let matched: Vec<ElementRef> = document.select(&parsed).collect();
+            // The first match is grabbed, so it must be the element itself
+            if matched.first().map(|m| m.id()) != Some(element.id()) {
+                continue;
+            }
+            if suggestions.iter().any(|s| s.selector == selector) {
+                continue;
+            }
+            suggestions.push(Suggestion {
+                selector,
+                matches: matched.len(),
+                text: element_text(&element),
+                stability,
+            });
+        }
+    }
+
+    suggestions.sort_by_key(|s| (s.stability, s.matches, s.selector.len()));
+    suggestions
+}
+
+/// The text of the element the same way as it is grabbed, with the spaces collapsed
+fn element_text(element: &ElementRef) -> String {
+    element
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The selectors for the element with their stability, from the most stable ones
+fn candidates(element: &ElementRef) -> Vec<(u8, String)> {
+    let tag = element.value().name();
+    let mut candidates = Vec::new();
+
+    for attribute in NAMING_ATTRIBUTES {
+        if let Some(value) = element.value().attr(attribute) {
+            if !value.is_empty() && !value.contains(['"', '\\']) {
+                candidates.push((0, format!("{}[{}=\"{}\"]", tag, attribute, value)));
+            }
+        }
+    }
+    if let Some(id) = element.value().id().filter(|id| is_stable(id)) {
+        candidates.push((1, format!("#{}", id)));
+    }
+
+    let classes = stable_classes(element);
+    let anchor = id_ancestor(element);
+    if !classes.is_empty() {
+        candidates.push((2, classes.clone()));
+        if let Some(anchor) = &anchor {
+            candidates.push((2, format!("#{} {}", anchor, classes)));
+        }
+    }
+
+    // The full path from the closest ancestor with an id, or from the body
+    let mut steps = vec![step(element)];
+    let mut parent = element.parent().and_then(ElementRef::wrap);
+    while let Some(current) = parent {
+        let name = current.value().name();
+        if let Some(id) = current.value().id().filter(|id| is_stable(id)) {
+            steps.push(format!("#{}", id));
+            break;
+        }
+        if name == "body" || name == "html" {
+            steps.push(name.to_string());
+            break;
+        }
+        steps.push(step(&current));
+        parent = current.parent().and_then(ElementRef::wrap);
+    }
+    steps.reverse();
+    candidates.push((3, steps.join(" > ")));
+    candidates
+}
+
+/// The tag with the stable classes of the element, e.g. `span.price.value`
+fn stable_classes(element: &ElementRef) -> String {
+    let classes: Vec<&str> = element
+        .value()
+        .classes()
+        .filter(|class| is_stable(class))
+        .collect();
+    match classes.is_empty() {
+        true => String::new(),
+        false => format!("{}.{}", element.value().name(), classes.join(".")),
+    }
+}
+
+/// The closest stable id among the ancestors of the element
+fn id_ancestor(element: &ElementRef) -> Option<String> {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .find_map(|e| e.value().id().filter(|id| is_stable(id)).map(String::from))
+}
+
+/// The tag of the element, with its position when the siblings have the same tag,
+/// e.g. `li:nth-child(3)`
+fn step(element: &ElementRef) -> String {
+    let tag = element.value().name();
+    let siblings: Vec<ElementRef> = element
+        .parent()
+        .map(|parent| parent.children().filter_map(ElementRef::wrap).collect())
+        .unwrap_or_default();
+    if siblings.iter().filter(|s| s.value().name() == tag).count() < 2 {
+        return tag.to_string();
+    }
+    let position = siblings
+        .iter()
+        .position(|s| s.id() == element.id())
+        .unwrap_or_default();
+    format!("{}:nth-child({})", tag, position + 1)
+}
+
+/// Whether the id or the class looks hand-written: a plain identifier without the long
+/// numbers and the hashes of the generated names, e.g. `css-1x2y3z` or `item-12345`
+fn is_stable(name: &str) -> bool {
+    static IDENTIFIER: OnceLock<Regex> = OnceLock::new();
+    static GENERATED: OnceLock<Regex> = OnceLock::new();
+    let identifier = IDENTIFIER.get_or_init(|| Regex::new(r"^[A-Za-z_][\w-]*$").unwrap());
+    let generated = GENERATED.get_or_init(|| {
+        Regex::new(r"\d{3,}|^(css|sc|jsx|emotion)-|^_|[a-z]\d[a-z]\d|[A-Z]{2}[a-z][A-Z]").unwrap()
+    });
+    identifier.is_match(name) && !generated.is_match(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest() {
+        let html = r#"
+            <html><body>
+                <div id="quote">
+                    <h1>Apple Inc.</h1>
+                    <span class="price css-1x2y3z">$ 123.45</span>
+                    <span class="change">+1.2%</span>
+                </div>
+                <ul>
+                    <li>Open</li>
+                    <li><b itemprop="price">123.45</b></li>
+                </ul>
+                <p id="row-98765">123.45</p>
+            </body></html>
+        "#;
+
+        let suggestions = suggest(html, " 123.45 ");
+        let selectors: Vec<&str> = suggestions.iter().map(|s| s.selector.as_str()).collect();
+        assert_eq!(
+            selectors,
+            vec![
+                "b[itemprop=\"price\"]",
+                "span.price",
+                "#quote span.price",
+                "body > p",
+                "#quote > span:nth-child(2)",
+                "body > ul > li:nth-child(2) > b",
+            ]
+        );
+        assert_eq!(suggestions[1].text, "$ 123.45");
+        assert_eq!(suggestions[1].matches, 1);
+
+        assert!(suggest(html, "APPLE")
+            .iter()
+            .any(|s| s.selector == "#quote > h1"));
+        assert!(suggest(html, "missing").is_empty());
+        assert!(suggest(html, " ").is_empty());
+
+        assert!(is_stable("price-value"));
+        assert!(!is_stable("css-1x2y3z"));
+        assert!(!is_stable("row-98765"));
+        assert!(!is_stable("a:b"));
+    }
+}
@@ -0,0 +1,326 @@
+//! Per-host tuning of the requests (`--auto-concurrency`): the latency and the error rate of
+//! each host recorded in the previous runs pick the number of the parallel requests to the
+//! host and the delay between them.
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Weight of the latest run in the recorded averages, the older runs fade out
+const SMOOTHING: f64 = 0.3;
+
+/// Parallel requests to a host without the recorded stats
+pub const DEFAULT_HOST_CONCURRENCY: usize = 4;
+
+/// Latency and error rate of a host, averaged over the runs
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub struct HostStats {
+    /// Number of the recorded requests
+    pub requests: u64,
+    /// Average latency of the requests in milliseconds
+    pub latency_ms: f64,
+    /// Share of the failed requests, from 0 to 1
+    pub error_rate: f64,
+}
+
+/// Parallelism and pace of the requests to a host, see [HostStats::limits]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HostLimits {
+    /// Maximum number of the requests in flight
+    pub concurrency: usize,
+    /// Minimum time between the starts of the requests
+    pub delay: Duration,
+}
+
+impl HostStats {
+    /// It picks the limits of the host: the failing hosts are usually throttling or overloaded,
+    /// so they get a single request at a time with a delay, and the faster the host responds,
+    /// the more parallel requests it gets.
+    pub fn limits(&self) -> HostLimits {
+        let (concurrency, delay_ms) = match (self.requests, self.error_rate, self.latency_ms) {
+            (0, _, _) => (DEFAULT_HOST_CONCURRENCY, 0),
+            (_, rate, _) if rate >= 0.25 => (1, 2000),
+            (_, rate, _) if rate >= 0.05 => (2, 500),
+            (_, _, latency) if latency < 200.0 => (8, 0),
+            (_, _, latency) if latency < 1000.0 => (4, 0),
+            _ => (2, 0),
+        };
+        HostLimits {
+            concurrency,
+            delay: Duration::from_millis(delay_ms),
+        }
+    }
+
+    /// It adds the requests of the latest run to the averages
+    pub fn merge(&self, run: &HostStats) -> HostStats {
+        if run.requests == 0 {
+            return *self;
+        }
+        if self.requests == 0 {
+            return *run;
+        }
+        HostStats {
+            requests: self.requests + run.requests,
+            latency_ms: self.latency_ms * (1.0 - SMOOTHING) + run.latency_ms * SMOOTHING,
+            error_rate: self.error_rate * (1.0 - SMOOTHING) + run.error_rate * SMOOTHING,
+        }
+    }
+}
+
+/// Wraps a [Fetcher](crate::fetcher::Fetcher) to keep the requests to each host within the
+/// [HostLimits] picked from the recorded stats, and records the stats of the current run
+#[cfg(not(target_family = "wasm"))]
+pub struct TunedFetcher<'a> {
+    inner: &'a dyn crate::fetcher::Fetcher,
+    recorded: BTreeMap<String, HostStats>,
+    hosts: std::sync::Mutex<std::collections::HashMap<String, std::sync::Arc<host::Host>>>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl<'a> TunedFetcher<'a> {
+    /// It creates the fetcher with the stats of the previous runs, by host
+    pub fn new(
+        inner: &'a dyn crate::fetcher::Fetcher,
+        recorded: BTreeMap<String, HostStats>,
+    ) -> Self {
+        Self {
+            inner,
+            recorded,
+            hosts: Default::default(),
+        }
+    }
+
+    /// It returns the limits of the URL's host
+    pub fn limits(&self, url: &str) -> HostLimits {
+        self.recorded_stats(crate::structure::url_host(url))
+            .limits()
+    }
+
+    /// It returns the recorded stats merged with the requests sent by this fetcher
+    pub fn stats(&self) -> BTreeMap<String, HostStats> {
+        let mut stats = self.recorded.clone();
+        let hosts = self.hosts.lock().expect("host limits are poisoned");
+        for (name, host) in hosts.iter() {
+            let merged = self.recorded_stats(name).merge(&host.stats());
+            stats.insert(name.clone(), merged);
+        }
+        stats
+    }
+
+    fn recorded_stats(&self, host: &str) -> HostStats {
+        self.recorded.get(host).copied().unwrap_or_default()
+    }
+
+    /// It returns the state of the URL's host, created on its first request
+    fn host(&self, url: &str) -> std::sync::Arc<host::Host> {
+        let name = crate::structure::url_host(url);
+        let mut hosts = self.hosts.lock().expect("host limits are poisoned");
+        hosts
+            .entry(name.to_string())
+            .or_insert_with(|| std::sync::Arc::new(host::Host::new(self.limits(url))))
+            .clone()
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[async_trait::async_trait]
+impl crate::fetcher::Fetcher for TunedFetcher<'_> {
+    async fn fetch(&self, url: &str) -> crate::error::Result<String> {
+        self.host(url).send(self.inner.fetch(url)).await
+    }
+
+    async fn fetch_document(
+        &self,
+        request: &crate::fetcher::Request,
+    ) -> crate::error::Result<crate::fetcher::Document> {
+        self.host(&request.url)
+            .send(self.inner.fetch_document(request))
+            .await
+    }
+
+    async fn render(&self, url: &str) -> crate::error::Result<String> {
+        self.host(url).send(self.inner.render(url)).await
+    }
+}
+
+/// The state of a host while the requests are sent
+#[cfg(not(target_family = "wasm"))]
+mod host {
+    use std::future::Future;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use tokio::sync::Semaphore;
+    use tokio::time::Instant;
+
+    use super::{HostLimits, HostStats};
+    use crate::error::Result;
+
+    pub struct Host {
+        permits: Semaphore,
+        delay: Duration,
+        /// The time of the next allowed request
+        next_request: Mutex<Option<Instant>>,
+        /// Requests, failed requests and the total latency of the current run
+        measured: Mutex<(u64, u64, Duration)>,
+    }
+
+    impl Host {
+        pub fn new(limits: HostLimits) -> Self {
+            Self {
+                permits: Semaphore::new(limits.concurrency.max(1)),
+                delay: limits.delay,
+                next_request: Mutex::new(None),
+                measured: Mutex::new((0, 0, Duration::ZERO)),
+            }
+        }
+
+        /// It sends the request once the host has a free slot, and measures it
+        pub async fn send<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+            let _permit = self
+                .permits
+                .acquire()
+                .await
+                .expect("host permits are never closed");
+            self.wait().await;
+
+            let start = Instant::now();
+            let result = request.await;
+            let mut measured = self.measured.lock().expect("host stats are poisoned");
+            measured.0 += 1;
+            measured.1 += u64::from(result.is_err());
+            measured.2 += start.elapsed();
+            result
+        }
+
+        /// It waits for the delay after the start of the previous request
+        async fn wait(&self) {
+            let slot = {
+                let mut next_request = self.next_request.lock().expect("host delay is poisoned");
+                let now = Instant::now();
+                let slot = next_request.map_or(now, |next| next.max(now));
+                *next_request = Some(slot + self.delay);
+                slot
+            };
+            tokio::time::sleep_until(slot).await;
+        }
+
+        /// It returns the stats of the requests sent in the current run
+        pub fn stats(&self) -> HostStats {
+            let (requests, errors, latency) =
+                *self.measured.lock().expect("host stats are poisoned");
+            if requests == 0 {
+                return HostStats::default();
+            }
+            HostStats {
+                requests,
+                latency_ms: latency.as_secs_f64() * 1000.0 / requests as f64,
+                error_rate: errors as f64 / requests as f64,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Result, RvpError};
+    use crate::fetcher::Fetcher;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_limits() {
+        let stats = |latency_ms: f64, error_rate: f64| HostStats {
+            requests: 10,
+            latency_ms,
+            error_rate,
+        };
+        assert_eq!(
+            HostStats::default().limits().concurrency,
+            DEFAULT_HOST_CONCURRENCY
+        );
+        assert_eq!(stats(50.0, 0.0).limits().concurrency, 8);
+        assert_eq!(stats(500.0, 0.0).limits().concurrency, 4);
+        assert_eq!(stats(5000.0, 0.0).limits().concurrency, 2);
+        assert_eq!(
+            stats(50.0, 0.1).limits(),
+            HostLimits {
+                concurrency: 2,
+                delay: Duration::from_millis(500)
+            }
+        );
+        assert_eq!(stats(50.0, 0.5).limits().concurrency, 1);
+
+        let merged = stats(100.0, 0.0).merge(&HostStats {
+            requests: 2,
+            latency_ms: 200.0,
+            error_rate: 1.0,
+        });
+        assert_eq!(merged.requests, 12);
+        assert!((merged.latency_ms - 130.0).abs() < 1e-9);
+        assert!((merged.error_rate - 0.3).abs() < 1e-9);
+        assert_eq!(HostStats::default().merge(&merged), merged);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tuned_fetcher() -> Result<()> {
+        /// Takes 100 ms per request, the `fail` URLs fail, and counts the requests in flight
+        #[derive(Default)]
+        struct SlowFetcher {
+            in_flight: AtomicUsize,
+            max_in_flight: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Fetcher for SlowFetcher {
+            async fn fetch(&self, url: &str) -> Result<String> {
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                match url.contains("fail") {
+                    true => Err(RvpError::FetchError {
+                        url: url.to_string(),
+                        message: "failed".to_string(),
+                    }),
+                    false => Ok(String::new()),
+                }
+            }
+        }
+
+        // The host failed before, so its requests are sent one at a time with a delay
+        let slow = SlowFetcher::default();
+        let recorded = BTreeMap::from([(
+            "flaky.com".to_string(),
+            HostStats {
+                requests: 10,
+                latency_ms: 100.0,
+                error_rate: 0.5,
+            },
+        )]);
+        let fetcher = TunedFetcher::new(&slow, recorded);
+        let start = tokio::time::Instant::now();
+        let requests =
+            ["https://flaky.com/1", "https://flaky.com/fail"].map(|url| fetcher.fetch(url));
+        futures_util::future::join_all(requests).await;
+        assert_eq!(slow.max_in_flight.load(Ordering::SeqCst), 1);
+        assert_eq!(start.elapsed(), Duration::from_millis(2100));
+
+        // The unknown hosts get the default parallelism
+        let urls: Vec<String> = (0..8).map(|i| format!("https://fast.com/{}", i)).collect();
+        futures_util::future::join_all(urls.iter().map(|url| fetcher.fetch(url))).await;
+        assert_eq!(
+            slow.max_in_flight.load(Ordering::SeqCst),
+            DEFAULT_HOST_CONCURRENCY
+        );
+
+        let stats = fetcher.stats();
+        assert_eq!(stats["flaky.com"].requests, 12);
+        assert!((stats["flaky.com"].error_rate - 0.5).abs() < 1e-9);
+        assert_eq!(stats["fast.com"].requests, 8);
+        assert_eq!(stats["fast.com"].error_rate, 0.0);
+        assert!((stats["fast.com"].latency_ms - 100.0).abs() < 1.0);
+        Ok(())
+    }
+}
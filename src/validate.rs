@@ -0,0 +1,885 @@
+//! Offline checks of the config: everything which can be verified without fetching the pages
+use std::fmt;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    NaiveDate,
+};
+use regex::Regex;
+use validator::ValidateUrl;
+
+use crate::anomaly::MIN_HISTORY;
+use crate::redact::Redactor;
+use crate::scalper::{is_wildcard, json_pointer};
+use crate::structure::{
+    Auth, Config, Resource, ResourceKind, Selector, SelectorType, MAX_FOLLOW_DEPTH,
+    URL_PARAM_PLACEHOLDER,
+};
+
+/// Placeholders supported by the `key_template` of the [Resource]
+const KEY_TEMPLATE_PLACEHOLDERS: [&str; 4] = ["{selector}", "{param}", "{resource}", "{host}"];
+
+/// A problem found in the config
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    /// Position of the resource in the config, starting from 1
+    pub resource: Option<usize>,
+    /// Name of the selector
+    pub selector: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(resource) = self.resource {
+            write!(f, "resource #{}", resource)?;
+        }
+        if let Some(selector) = &self.selector {
+            write!(f, ", selector \"{}\"", selector)?;
+        }
+        if self.resource.is_some() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// It checks the config without any network requests: URLs, selector syntax,
+/// unique selector names, the parameter placeholders and the computed expressions
+///
+/// Returns:
+///
+/// The list of problems, empty if the config is valid.
+pub fn validate(config: &Config) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    if config.name.trim().is_empty() {
+        problems.push(Problem {
+            resource: None,
+            selector: None,
+            message: "config name is empty".to_string(),
+        });
+    }
+    if config.resources.is_empty() {
+        problems.push(Problem {
+            resource: None,
+            selector: None,
+            message: "config has no resources".to_string(),
+        });
+    }
+
+    if let Some(webhook) = &config.webhook {
+        if !ValidateUrl::validate_url(webhook) {
+            problems.push(Problem {
+                resource: None,
+                selector: None,
+                message: format!("webhook \"{}\" is not a valid URL", webhook),
+            });
+        }
+    }
+
+    if let Err(err) = Redactor::new(&config.redact) {
+        problems.push(Problem {
+            resource: None,
+            selector: None,
+            message: err.to_string(),
+        });
+    }
+
+    let mut computed_names: Vec<&str> = Vec::new();
+    for computed in config.computed.iter() {
+        let mut report = |message: String| {
+            problems.push(Problem {
+                resource: None,
+                selector: None,
+                message: format!("computed \"{}\": {}", computed.name, message),
+            })
+        };
+        if computed.name.trim().is_empty() {
+            report("name is empty".to_string());
+        }
+        if computed_names.contains(&computed.name.as_str()) {
+            report("name is not unique".to_string());
+        }
+        computed_names.push(&computed.name);
+        if let Err(err) = crate::compute::Expr::parse(&computed.expr) {
+            report(err.to_string());
+        }
+    }
+
+    let mut group_names: Vec<&str> = Vec::new();
+    for group in config.groups.iter() {
+        if group_names.contains(&group.name.as_str()) {
+            problems.push(Problem {
+                resource: None,
+                selector: None,
+                message: format!("group \"{}\": name is not unique", group.name),
+            });
+        }
+        group_names.push(&group.name);
+    }
+
+    for (i, resource) in config.resources.iter().enumerate() {
+        let mut report = |selector: Option<&Selector>, message: String| {
+            problems.push(Problem {
+                resource: Some(i + 1),
+                selector: selector.map(|s| s.name.clone()),
+                message,
+            })
+        };
+
+        // The resource is checked with the settings of its group
+        let mut resource = resource.clone();
+        if let Some(name) = resource.group.clone() {
+            match config.groups.iter().find(|g| g.name == name) {
+                Some(group) => resource.inherit(group),
+                None => report(None, format!("group \"{}\" is not defined", name)),
+            }
+        }
+        let resource = &resource;
+
+        // The placeholders are replaced with a sample value, so the URL and the selector
+        // paths can be validated
+        let sample = sample_resource(resource);
+        for message in resource_problems(resource, &sample) {
+            report(None, message);
+        }
+
+        let mut names: Vec<&str> = Vec::new();
+        for selector in sample.selectors.iter() {
+            if names.contains(&selector.name.as_str()) {
+                report(Some(selector), "selector name is not unique".to_string());
+            }
+            names.push(&selector.name);
+
+            for message in selector_problems(resource.kind(), selector) {
+                report(Some(selector), message);
+            }
+            if follow_depth(selector) > MAX_FOLLOW_DEPTH {
+                report(
+                    Some(selector),
+                    format!(
+                        "links are nested deeper than {} levels, the deeper ones are not followed",
+                        MAX_FOLLOW_DEPTH
+                    ),
+                );
+            }
+        }
+
+        // A change rule refers to a value of the same resource by its name
+        for selector in sample.selectors.iter() {
+            for rule in selector.alert_rules() {
+                let Some(change) = &rule.change else {
+                    continue;
+                };
+                if !names.contains(&change.name.as_str()) {
+                    report(
+                        Some(selector),
+                        format!(
+                            "alert \"{}\" refers to \"{}\", which is not a selector of the resource",
+                            rule, change.name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+/// It returns the number of the nested `follow` selectors, 0 if the selector isn't followed
+fn follow_depth(selector: &Selector) -> usize {
+    match selector.follow {
+        true => {
+            1 + selector
+                .children
+                .iter()
+                .map(follow_depth)
+                .max()
+                .unwrap_or(0)
+        }
+        false => 0,
+    }
+}
+
+/// It finds the deprecated resources with the `sunset` date before the given day,
+/// they should be removed from the config
+pub fn past_sunsets(config: &Config, today: NaiveDate) -> Vec<Problem> {
+    config
+        .resources
+        .iter()
+        .enumerate()
+        .filter(|(_, resource)| resource.deprecated && resource.is_past_sunset(today))
+        .map(|(i, resource)| Problem {
+            resource: Some(i + 1),
+            selector: None,
+            message: format!(
+                "sunset on {} has passed, the resource should be removed",
+                resource.sunset.unwrap_or_default()
+            ),
+        })
+        .collect()
+}
+
+/// A selector which repeats a selector of an earlier resource with the same URL
+#[derive(Debug, PartialEq)]
+pub struct Duplicate {
+    /// Index of the resource with the duplicate
+    pub resource: usize,
+    /// Index of the duplicate selector in the resource
+    pub selector: usize,
+    pub name: String,
+    /// Index of the earlier resource with the same URL
+    pub original: usize,
+    /// Whether the path is the same, so the selector grabs the same value again.
+    /// Otherwise only the name is the same, and the values would mix in the output.
+    pub same_path: bool,
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resource #{}, selector \"{}\": ",
+            self.resource + 1,
+            self.name
+        )?;
+        match self.same_path {
+            true => write!(
+                f,
+                "same path as in resource #{} with the same URL",
+                self.original + 1
+            ),
+            false => write!(
+                f,
+                "same name as in resource #{} with the same URL, the values would mix",
+                self.original + 1
+            ),
+        }
+    }
+}
+
+/// It finds the resources with the same URL (with the same named placeholders) and the
+/// overlapping selectors: the same path or the same name.
+///
+/// The resources with the `%%` placeholder are skipped, as each of them usually gets its own
+/// value of `--params`.
+///
+/// Such configs are valid, but fetch the same page twice or mix the values.
+pub fn duplicates(config: &Config) -> Vec<Duplicate> {
+    let samples: Vec<Option<Resource>> = config
+        .resources
+        .iter()
+        .map(|resource| (!resource.needs_parameter()).then(|| sample_resource(resource)))
+        .collect();
+    let same_page = |r: &Option<Resource>, resource: &Resource| {
+        r.as_ref()
+            .is_some_and(|r| r.url == resource.url && r.kind() == resource.kind())
+    };
+
+    let mut duplicates = Vec::new();
+    for (i, resource) in samples.iter().enumerate() {
+        let Some(resource) = resource else {
+            continue;
+        };
+        let Some(original) = samples[..i].iter().position(|r| same_page(r, resource)) else {
+            continue;
+        };
+
+        // The selectors are compared with all earlier resources with the same URL
+        let earlier: Vec<&Selector> = samples[..i]
+            .iter()
+            .filter(|r| same_page(r, resource))
+            .flatten()
+            .flat_map(|r| r.selectors.iter())
+            .collect();
+        for (j, selector) in resource.selectors.iter().enumerate() {
+            let same_path = earlier.iter().any(|s| s.path == selector.path);
+            if same_path || earlier.iter().any(|s| s.name == selector.name) {
+                duplicates.push(Duplicate {
+                    resource: i,
+                    selector: j,
+                    name: config.resources[i].selectors[j].name.clone(),
+                    original,
+                    same_path,
+                });
+            }
+        }
+    }
+    duplicates
+}
+
+/// It removes the duplicate selectors from the config, the resources left without selectors
+/// are removed too.
+///
+/// Returns:
+///
+/// The number of the removed selectors.
+pub fn remove_duplicates(config: &mut Config, duplicates: &[Duplicate]) -> usize {
+    let mut removed = 0;
+    // From the end, so the indices of the remaining duplicates stay valid
+    for duplicate in duplicates.iter().rev() {
+        let Some(resource) = config.resources.get_mut(duplicate.resource) else {
+            continue;
+        };
+        if duplicate.selector < resource.selectors.len() {
+            resource.selectors.remove(duplicate.selector);
+            removed += 1;
+        }
+    }
+    let mut i = 0;
+    config.resources.retain(|r| {
+        let keep = !r.selectors.is_empty() || !duplicates.iter().any(|d| d.resource == i);
+        i += 1;
+        keep
+    });
+    removed
+}
+
+/// It returns a copy of the resource with the parameter placeholders filled with a sample value
+fn sample_resource(resource: &Resource) -> Resource {
+    let mut sample = resource.clone();
+    sample.mut_url_with_param("param");
+    let named: Vec<(String, String)> = resource
+        .named_params()
+        .iter()
+        .map(|name| (name.to_string(), "param".to_string()))
+        .collect();
+    sample
+        .mut_url_with_named_params(&named)
+        .expect("all named parameters have a value");
+    sample
+}
+
+/// It checks the URL and the key template of the resource, `sample` is the resource with the
+/// placeholders filled
+fn resource_problems(resource: &Resource, sample: &Resource) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if !sample.url.validate_url() {
+        problems.push(format!("invalid URL \"{}\"", resource.url));
+    }
+    if has_stray_percent(&sample.url) {
+        problems.push(format!(
+            "URL contains an incomplete parameter placeholder, use `{}` or `%name%`",
+            URL_PARAM_PLACEHOLDER
+        ));
+    }
+    if resource.selectors.is_empty() {
+        problems.push("resource has no selectors".to_string());
+    }
+
+    if let Some(template) = &resource.key_template {
+        let mut rest = template.clone();
+        for placeholder in KEY_TEMPLATE_PLACEHOLDERS {
+            rest = rest.replace(placeholder, "");
+        }
+        if rest.contains('{') || rest.contains('}') {
+            problems.push(format!(
+                "key template \"{}\" has unknown placeholders, supported: {}",
+                template,
+                KEY_TEMPLATE_PLACEHOLDERS.join(", ")
+            ));
+        }
+        let has_params = resource.needs_parameter() || !resource.named_params().is_empty();
+        if template.contains("{param}") && !has_params {
+            problems.push(format!(
+                "key template uses {{param}}, but the resource has no `{}` or `%name%` placeholders",
+                URL_PARAM_PLACEHOLDER
+            ));
+        }
+        if !template.contains("{selector}") && resource.selectors.len() > 1 {
+            problems.push(
+                "key template has no {selector}, all values would get the same name".to_string(),
+            );
+        }
+    }
+
+    if let Some(language) = &resource.accept_language {
+        let valid = !language.trim().is_empty()
+            && language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || " -_,;=.*".contains(c));
+        if !valid {
+            problems.push(format!("invalid accept_language \"{}\"", language));
+        }
+    }
+
+    if let Some(content_type) = &resource.content_type {
+        if content_type.trim().is_empty() || content_type.chars().any(|c| c.is_control()) {
+            problems.push(format!("invalid content_type \"{}\"", content_type));
+        }
+        if resource.body.is_none() {
+            problems.push("content_type is set without body".to_string());
+        }
+    }
+    for (name, value) in &resource.headers {
+        let token = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+        if !token {
+            problems.push(format!("invalid header name \"{}\"", name));
+        }
+        if value.chars().any(|c| c.is_control()) {
+            problems.push(format!("invalid value of the header \"{}\"", name));
+        }
+    }
+    if resource.render() && (!resource.method().is_default() || resource.body.is_some()) {
+        problems.push(
+            "render is set, but the browser only sends GET requests without a body".to_string(),
+        );
+    }
+    match &resource.auth {
+        Some(Auth::Basic { user, .. }) if user.trim().is_empty() => {
+            problems.push("auth user is empty".to_string())
+        }
+        Some(Auth::Bearer { token }) if token.trim().is_empty() => {
+            problems.push("auth token is empty".to_string())
+        }
+        _ => {}
+    }
+    if resource.render() && resource.auth.is_some() {
+        problems.push("render is set, but the browser doesn't send the auth".to_string());
+    }
+    if resource.render() && !resource.headers.is_empty() {
+        problems.push("render is set, but the browser doesn't send the headers".to_string());
+    }
+
+    match &resource.next_page {
+        Some(next_page) => {
+            let link = Selector::new(
+                next_page.clone(),
+                "next_page".to_string(),
+                SelectorType::String,
+            );
+            let invalid = match resource.kind() {
+                ResourceKind::Html => scraper::Selector::parse(next_page).is_err(),
+                ResourceKind::Json | ResourceKind::Feed => json_pointer(&link).is_err(),
+            };
+            if invalid {
+                problems.push(format!("invalid next page selector \"{}\"", next_page));
+            }
+            if resource.max_pages == Some(0) {
+                problems.push("max_pages is 0, at least the first page is grabbed".to_string());
+            }
+            if !resource.selectors.iter().any(|s| s.is_multiple()) {
+                problems.push(
+                    "next_page is set, but no selector is a list, only the first page is used"
+                        .to_string(),
+                );
+            }
+        }
+        None => {
+            if resource.max_pages.is_some() {
+                problems.push("max_pages is set without next_page".to_string());
+            }
+        }
+    }
+    if let Some(sitemap) = &resource.sitemap {
+        if let Some(filter) = &sitemap.filter {
+            if let Err(err) = Regex::new(filter) {
+                problems.push(format!("invalid sitemap filter \"{}\": {}", filter, err));
+            }
+        }
+        if sitemap.limit == Some(0) {
+            problems.push("sitemap limit is 0, no pages would be grabbed".to_string());
+        }
+    }
+    if resource.refetch_on_miss() && !resource.selectors.iter().any(|s| s.required) {
+        problems.push(
+            "refetch_on_miss is set, but no selector is required, the page is never fetched again"
+                .to_string(),
+        );
+    }
+    if !resource.deprecated && (resource.sunset.is_some() || resource.deprecation_note.is_some()) {
+        problems.push(
+            "sunset or deprecation_note is set, but the resource is not deprecated".to_string(),
+        );
+    }
+
+    problems
+}
+
+/// It checks if the URL has a `%` which does not start a percent-encoded byte
+fn has_stray_percent(url: &str) -> bool {
+    url.match_indices('%').any(|(i, _)| {
+        let byte = url.get(i + 1..i + 3).unwrap_or_default();
+        byte.len() != 2 || !byte.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// It checks the path, the regex and the type options of the selector
+fn selector_problems(kind: ResourceKind, selector: &Selector) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if selector.name.trim().is_empty() {
+        problems.push("selector name is empty".to_string());
+    }
+
+    match kind {
+        ResourceKind::Html => {
+            if let Err(err) = scraper::Selector::parse(&selector.path) {
+                problems.push(format!(
+                    "invalid CSS selector \"{}\": {}",
+                    selector.path, err
+                ));
+            }
+        }
+        ResourceKind::Json | ResourceKind::Feed => match json_pointer(selector) {
+            Ok(pointer) => {
+                if !selector.is_multiple() && is_wildcard(&pointer) {
+                    problems.push(
+                        "path has the [*] wildcard, but the selector is not a list".to_string(),
+                    );
+                }
+            }
+            Err(err) => problems.push(err.to_string()),
+        },
+    }
+
+    match &selector.regex {
+        Some(regex) => match Regex::new(regex) {
+            Ok(re) => {
+                if let Some(group) = selector.regex_group {
+                    if group >= re.captures_len() {
+                        problems.push(format!("regex has no capture group {}", group));
+                    }
+                }
+            }
+            Err(err) => problems.push(format!("invalid regex \"{}\": {}", regex, err)),
+        },
+        None => {
+            if selector.regex_group.is_some() {
+                problems.push("regex_group is set without regex".to_string());
+            }
+        }
+    }
+
+    if let Some(format) = &selector.date_format {
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            problems.push(format!("invalid date format \"{}\"", format));
+        }
+    }
+    if let Some(truthy_values) = &selector.truthy_values {
+        if truthy_values.is_empty() {
+            problems.push("truthy_values is empty, all values would be false".to_string());
+        }
+    }
+    if !selector.alert_rules().is_empty() && selector.parsed_type != SelectorType::Number {
+        problems.push(format!(
+            "alert is set on a {} selector, only Number values are checked",
+            selector.parsed_type
+        ));
+    }
+    if selector.range().is_some() && selector.parsed_type != SelectorType::Number {
+        problems.push(format!(
+            "min or max is set on a {} selector, only Number values are checked",
+            selector.parsed_type
+        ));
+    }
+    if let (Some(min), Some(max)) = (selector.min, selector.max) {
+        if min > max {
+            problems.push(format!(
+                "min {} is greater than max {}, all values would be out of range",
+                min, max
+            ));
+        }
+    }
+    if let Some(anomaly) = &selector.anomaly {
+        if selector.parsed_type != SelectorType::Number {
+            problems.push(format!(
+                "anomaly is set on a {} selector, only Number values are checked",
+                selector.parsed_type
+            ));
+        }
+        if anomaly.z_score.is_none() && anomaly.jump.is_none() {
+            problems.push("anomaly has neither z_score nor jump, nothing is flagged".to_string());
+        }
+        if anomaly.window < MIN_HISTORY {
+            problems.push(format!(
+                "anomaly window is {}, at least {} previous values are needed",
+                anomaly.window, MIN_HISTORY
+            ));
+        }
+    }
+    match (selector.follow, selector.children.is_empty()) {
+        (true, true) => {
+            problems.push("follow is set, but the selector has no children".to_string())
+        }
+        (false, false) => {
+            problems.push("children are set without follow, the links are not followed".to_string())
+        }
+        _ => {}
+    }
+    if selector.follow && selector.max_links == Some(0) {
+        problems.push("max_links is 0, no links would be followed".to_string());
+    }
+
+    let mut names: Vec<&str> = Vec::new();
+    for child in selector.children.iter() {
+        if names.contains(&child.name.as_str()) || child.name == "url" {
+            problems.push(format!("child \"{}\": name is not unique", child.name));
+        }
+        names.push(&child.name);
+        for message in selector_problems(kind, child) {
+            problems.push(format!("child \"{}\": {}", child.name, message));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::{AnomalyRule, Group, SelectorType};
+
+    #[test]
+    fn test_validate() {
+        let selector = Selector::new("h1".to_string(), "title".to_string(), SelectorType::String);
+        let row = Selector::new(
+            "#row-%%-price".to_string(),
+            "price".to_string(),
+            SelectorType::Number,
+        );
+        let valid = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![Resource::new(
+                "https://example.com/%%?date=%date%".to_string(),
+                vec![selector.clone(), row],
+            )],
+        );
+        assert!(validate(&valid).is_empty());
+        assert!(!has_stray_percent("https://example.com/?q=%E2%80%99"));
+        assert!(has_stray_percent("https://example.com/?q=%ticker"));
+
+        let mut broken_selector = Selector::new(
+            "h1 >".to_string(),
+            "price".to_string(),
+            SelectorType::Number,
+        );
+        broken_selector.regex = Some("([0-9".to_string());
+        broken_selector.date_format = Some("%Q".to_string());
+        broken_selector.min = Some(100.0);
+        broken_selector.max = Some(1.0);
+        broken_selector.alert = Some("change(cost, 1h) > 5%".parse().unwrap());
+        broken_selector.anomaly = Some(AnomalyRule {
+            window: 2,
+            z_score: None,
+            jump: None,
+            alert: false,
+        });
+        let mut alerted =
+            Selector::new("h2".to_string(), "status".to_string(), SelectorType::String);
+        alerted.alert_below = Some(1.0);
+        alerted.min = Some(0.0);
+        let mut links = Selector::new("a".to_string(), "links".to_string(), SelectorType::String);
+        links.follow = true;
+        links.max_links = Some(0);
+        let mut nested = links.clone();
+        nested.name = "nested".to_string();
+        nested.max_links = None;
+        nested.children = vec![alerted.clone()];
+        for _ in 0..MAX_FOLLOW_DEPTH {
+            let mut parent = nested.clone();
+            parent.children = vec![nested];
+            nested = parent;
+        }
+        let mut resource = Resource::new(
+            "not a url".to_string(),
+            vec![selector.clone(), selector, broken_selector, alerted],
+        );
+        resource.key_template = Some("{param}_{name}".to_string());
+        resource.next_page = Some("a[".to_string());
+        resource.max_pages = Some(0);
+        resource.accept_language = Some("en\r\nX-Evil: 1".to_string());
+        resource.headers = [
+            ("X Client".to_string(), "rvp".to_string()),
+            ("X-Token".to_string(), "a\r\nX-Evil: 1".to_string()),
+        ]
+        .into();
+        resource.refetch_on_miss = Some(true);
+        resource.sunset = NaiveDate::from_ymd_opt(2024, 6, 30);
+        resource.sitemap = Some(crate::structure::Sitemap {
+            filter: Some("[".to_string()),
+            limit: Some(0),
+        });
+        let mut followed = Resource::new("https://example.com".to_string(), vec![links, nested]);
+        followed.group = Some("missing".to_string());
+        let titles = Selector::new(
+            "$.items[*].title".to_string(),
+            "titles".to_string(),
+            SelectorType::String,
+        );
+        let mut feed = Resource::new("https://example.com/rss".to_string(), vec![titles]);
+        feed.kind = Some(ResourceKind::Feed);
+        feed.render = Some(true);
+        feed.method = Some(crate::fetcher::Method::Post);
+        feed.content_type = Some("application/json".to_string());
+        feed.headers = [("X-Client".to_string(), "rvp".to_string())].into();
+        feed.auth = Some(Auth::Bearer {
+            token: " ".to_string(),
+        });
+        let mut invalid = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![resource, followed, feed],
+        );
+        let computed = |name: &str, expr: &str| crate::structure::Computed {
+            name: name.to_string(),
+            expr: expr.to_string(),
+        };
+        invalid.computed = vec![
+            computed("change", "price - prev_price"),
+            computed("change", "price *"),
+            computed("", "1"),
+        ];
+        invalid.webhook = Some("hooks.example.com".to_string());
+        invalid.groups = vec![
+            Group::new("nasdaq".to_string()),
+            Group::new("nasdaq".to_string()),
+        ];
+
+        // The error of the CSS parser is its own text, only the prefix is ours
+        let css = "resource #1, selector \"price\": invalid CSS selector \"h1 >\": ";
+        let problems: Vec<String> = validate(&invalid)
+            .iter()
+            .map(|p| match p.to_string() {
+                problem if problem.starts_with(css) => css.to_string(),
+                problem => problem,
+            })
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                "webhook \"hooks.example.com\" is not a valid URL",
+                "computed \"change\": name is not unique",
+                "computed \"change\": Invalid expression \"price *\": unexpected end",
+                "computed \"\": name is empty",
+                "group \"nasdaq\": name is not unique",
+                "resource #1: invalid URL \"not a url\"",
+                "resource #1: key template \"{param}_{name}\" has unknown placeholders, supported: {selector}, {param}, {resource}, {host}",
+                "resource #1: key template uses {param}, but the resource has no `%%` or `%name%` placeholders",
+                "resource #1: key template has no {selector}, all values would get the same name",
+                "resource #1: invalid accept_language \"en\r\nX-Evil: 1\"",
+                "resource #1: invalid header name \"X Client\"",
+                "resource #1: invalid value of the header \"X-Token\"",
+                "resource #1: invalid next page selector \"a[\"",
+                "resource #1: max_pages is 0, at least the first page is grabbed",
+                "resource #1: next_page is set, but no selector is a list, only the first page is used",
+                "resource #1: invalid sitemap filter \"[\": regex parse error:\n    [\n    ^\nerror: unclosed character class",
+                "resource #1: sitemap limit is 0, no pages would be grabbed",
+                "resource #1: refetch_on_miss is set, but no selector is required, the page is never fetched again",
+                "resource #1: sunset or deprecation_note is set, but the resource is not deprecated",
+                "resource #1, selector \"title\": selector name is not unique",
+                css,
+                "resource #1, selector \"price\": invalid regex \"([0-9\": regex parse error:\n    ([0-9\n     ^\nerror: unclosed character class",
+                "resource #1, selector \"price\": invalid date format \"%Q\"",
+                "resource #1, selector \"price\": min 100 is greater than max 1, all values would be out of range",
+                "resource #1, selector \"price\": anomaly has neither z_score nor jump, nothing is flagged",
+                "resource #1, selector \"price\": anomaly window is 2, at least 3 previous values are needed",
+                "resource #1, selector \"status\": alert is set on a String selector, only Number values are checked",
+                "resource #1, selector \"status\": min or max is set on a String selector, only Number values are checked",
+                "resource #1, selector \"price\": alert \"change(cost, 1h) > 5%\" refers to \"cost\", which is not a selector of the resource",
+                "resource #2: group \"missing\" is not defined",
+                "resource #2, selector \"links\": follow is set, but the selector has no children",
+                "resource #2, selector \"links\": max_links is 0, no links would be followed",
+                "resource #2, selector \"nested\": child \"nested\": child \"nested\": child \"nested\": child \"status\": alert is set on a String selector, only Number values are checked",
+                "resource #2, selector \"nested\": child \"nested\": child \"nested\": child \"nested\": child \"status\": min or max is set on a String selector, only Number values are checked",
+                "resource #2, selector \"nested\": links are nested deeper than 3 levels, the deeper ones are not followed",
+                "resource #3: content_type is set without body",
+                "resource #3: render is set, but the browser only sends GET requests without a body",
+                "resource #3: auth token is empty",
+                "resource #3: render is set, but the browser doesn't send the auth",
+                "resource #3: render is set, but the browser doesn't send the headers",
+                "resource #3, selector \"titles\": path has the [*] wildcard, but the selector is not a list",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_past_sunsets() {
+        let mut retired = Resource::new("https://a.com".to_string(), vec![]);
+        retired.deprecated = true;
+        retired.sunset = NaiveDate::from_ymd_opt(2024, 6, 30);
+        let mut deprecated = Resource::new("https://b.com".to_string(), vec![]);
+        deprecated.deprecated = true;
+        let config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![deprecated, retired],
+        );
+
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap_or_default();
+        assert!(past_sunsets(&config, day(6, 30)).is_empty());
+        let problems: Vec<String> = past_sunsets(&config, day(7, 1))
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            problems,
+            vec!["resource #2: sunset on 2024-06-30 has passed, the resource should be removed"]
+        );
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let title = Selector::new("h1".to_string(), "title".to_string(), SelectorType::String);
+        let price = Selector::new("#p".to_string(), "price".to_string(), SelectorType::Number);
+        let other_price =
+            Selector::new("#p2".to_string(), "price".to_string(), SelectorType::Number);
+        let mut config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![
+                Resource::new(
+                    "https://example.com/%ticker%".to_string(),
+                    vec![title.clone(), price],
+                ),
+                Resource::new(
+                    "https://example.com/%ticker%".to_string(),
+                    vec![title.clone()],
+                ),
+                Resource::new(
+                    "https://example.com/%ticker%".to_string(),
+                    vec![other_price, title.clone()],
+                ),
+                Resource::new(
+                    "https://example.org/%ticker%".to_string(),
+                    vec![title.clone()],
+                ),
+            ],
+        );
+
+        let found = duplicates(&config);
+        let messages: Vec<String> = found.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "resource #2, selector \"title\": same path as in resource #1 with the same URL",
+                "resource #3, selector \"price\": same name as in resource #1 with the same URL, the values would mix",
+                "resource #3, selector \"title\": same path as in resource #1 with the same URL",
+            ]
+        );
+
+        let same_path: Vec<Duplicate> = found.into_iter().filter(|d| d.same_path).collect();
+        assert_eq!(remove_duplicates(&mut config, &same_path), 2);
+        assert_eq!(config.resources.len(), 3);
+        assert_eq!(config.resources[1].selectors[0].path, "#p2");
+        assert!(duplicates(&config).iter().all(|d| !d.same_path));
+
+        // Each `%%` resource gets its own value of `--params`
+        let config = Config::new(
+            "test".to_string(),
+            "".to_string(),
+            vec![
+                Resource::new("https://example.com/%%".to_string(), vec![title.clone()]),
+                Resource::new("https://example.com/%%".to_string(), vec![title]),
+            ],
+        );
+        assert!(duplicates(&config).is_empty());
+    }
+}